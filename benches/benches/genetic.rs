@@ -0,0 +1,86 @@
+//! Fitness evaluation, crossover and whole generations, at several numbers of cities. Criterion
+//! keeps the results of the last run, and reports how far each benchmark moved since.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use wasi_genetic::crossover::Crossover;
+use wasi_genetic::geo::DistanceMatrix;
+use wasi_genetic::{City, Metric, Path, RngAlgorithm, SeededRng, Simulation, TourType};
+
+const SIZES: [usize; 3] = [50, 200, 1000];
+
+/// `count` cities scattered over a square, the same ones every run.
+fn cities(count: usize) -> Vec<City> {
+    let mut rng = SeededRng::new(RngAlgorithm::ChaCha, count as u64);
+    (0..count).map(|_| City::new(rng.gen_range(0.0, 1000.0), rng.gen_range(0.0, 1000.0))).collect()
+}
+
+/// A random tour of `count` cities, as a population stores it.
+fn tour(count: usize, rng: &mut SeededRng) -> Vec<u16> {
+    let mut order: Vec<u16> = (0..count as u16).collect();
+    order.shuffle(rng);
+    order
+}
+
+fn fitness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fitness");
+    let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 1);
+    for &size in &SIZES {
+        let cities = cities(size);
+        let order = tour(size, &mut rng);
+        group.bench_with_input(BenchmarkId::new("measured", size), &order, |b, order| {
+            b.iter(|| Path::calculate_fitness(black_box(order), &cities, Metric::Euclidean, TourType::Closed))
+        });
+
+        let matrix = DistanceMatrix::new(&cities, Metric::Euclidean);
+        group.bench_with_input(BenchmarkId::new("matrix", size), &order, |b, order| {
+            b.iter(|| Path::matrix_fitness(black_box(order), &matrix, TourType::Closed))
+        });
+    }
+    group.finish();
+}
+
+fn crossover(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crossover");
+    let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 2);
+    for &crossover in &[Crossover::OnePoint, Crossover::Ordered] {
+        for &size in &SIZES {
+            let (mother, father) = (tour(size, &mut rng), tour(size, &mut rng));
+            let cuts = crossover.cuts(size, &mut rng);
+            let mut child = vec![0; size];
+            group.bench_function(BenchmarkId::new(crossover.to_string(), size), |b| {
+                b.iter(|| crossover.apply(black_box(&mother), black_box(&father), cuts, &mut child))
+            });
+        }
+    }
+    group.finish();
+}
+
+/// One generation of a population of 200 tours with the default rates, from selection to
+/// mutation, after the first has been made.
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    group.sample_size(20);
+    for &size in &SIZES {
+        let cities = cities(size);
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_batched(
+                || {
+                    let mut sim = Simulation::new(200, cities.clone(), 1, 0.4, 0.05, 0.3, SeededRng::new(RngAlgorithm::ChaCha, 3)).unwrap();
+                    sim.step();
+                    sim
+                },
+                |mut sim| {
+                    sim.step();
+                    sim
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fitness, crossover, generation);
+criterion_main!(benches);
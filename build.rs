@@ -0,0 +1,23 @@
+use std::fs;
+use std::process::Command;
+
+// Records the commit the binary was built from, for run manifests. Builds outside a git
+// checkout, e.g. from a source archive, simply go without.
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=WASI_GENETIC_GIT_HASH={}", hash.trim());
+    }
+
+    // Rebuild when HEAD moves, whether by a checkout or a commit to the current branch
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/packed-refs");
+    if let Some(head) = fs::read_to_string(".git/HEAD").ok().and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string())) {
+        println!("cargo:rerun-if-changed=.git/{}", head);
+    }
+}
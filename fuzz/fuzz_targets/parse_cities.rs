@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasi_genetic::input;
+
+fuzz_target!(|data: &[u8]| {
+    // Malformed input must come back as an error, never a panic
+    let _ = input::read_cities(data);
+    let _ = input::read_cities_lenient(data);
+});
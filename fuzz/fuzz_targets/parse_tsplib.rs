@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasi_genetic::tsplib;
+
+fuzz_target!(|data: &[u8]| {
+    // Whatever DIMENSION a file claims, a malformed one must come back as an error, never a
+    // panic or an allocation the size it claims
+    if let Ok(source) = std::str::from_utf8(data) {
+        let _ = tsplib::parse(source);
+    }
+});
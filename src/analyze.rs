@@ -0,0 +1,223 @@
+//! Fitness-landscape analysis: how an instance looks to the search before any run, and which
+//! settings that suggests.
+//!
+//! Two kinds of samples are taken. A random walk of swaps, the mutation the runs use, measures
+//! how rugged the landscape is: the autocorrelation of the lengths one swap apart, and the
+//! correlation length, roughly how many swaps it takes for lengths to stop resembling each
+//! other. 2-opt descents from random tours find local optima, whose count of distinct ones
+//! estimates how many there are, and whose fitness-distance correlation (FDC), between their
+//! lengths and their distance in edges from the best of them, tells whether good tours lie
+//! close together (a "big valley") or are scattered.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::diff;
+use crate::geo::Metric;
+use crate::input::City;
+use crate::polish;
+use crate::required::RequiredEdges;
+use crate::tour::TourType;
+use crate::{Error, Path};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Landscape {
+    pub cities: usize,
+    /// Swaps in the random walk.
+    pub walk: usize,
+    /// Autocorrelation of the walk's lengths one swap apart.
+    pub autocorrelation: f64,
+    /// How many swaps apart lengths stop being correlated, by -1 / ln |autocorrelation|.
+    pub correlation_length: f64,
+    pub random_mean: f64,
+    /// 2-opt descents from random tours.
+    pub descents: usize,
+    /// How many different local optima they ended at.
+    pub distinct_optima: usize,
+    pub best_optimum: f64,
+    pub mean_optimum: f64,
+    /// Fitness-distance correlation of the local optima; NaN when there's no spread to correlate.
+    pub fdc: f64,
+}
+
+/// Analyses the landscape of `cities` with a random walk of `walk` swaps and `descents` 2-opt
+/// descents from random tours, of the type `tour`. It takes at least 2 cities.
+pub fn analyze<R: Rng + ?Sized>(cities: &[City], metric: Metric, tour: TourType, walk: usize, descents: usize, rng: &mut R) -> Result<Landscape, Error> {
+    if cities.len() < 2 {
+        return Err(Error::Problem(format!("{} cities have no landscape to analyse, it takes at least 2", cities.len())));
+    }
+    let length = |order: &[usize]| 1.0 / Path::calculate_fitness(order, cities, metric, tour);
+    let random_tour = |rng: &mut R| {
+        let mut order: Vec<usize> = (0..cities.len()).collect();
+        order.shuffle(rng);
+        order
+    };
+
+    let mut order = random_tour(rng);
+    let mut lengths = vec![length(&order)];
+    for _ in 0..walk {
+        let (a, b) = (rng.gen_range(0, order.len()), rng.gen_range(0, order.len()));
+        order.swap(a, b);
+        lengths.push(length(&order));
+    }
+    let autocorrelation = autocorrelation(&lengths);
+
+    let no_edges = RequiredEdges::default();
+    let mut optima: Vec<(Vec<usize>, f64)> = Vec::new();
+    let mut random_total = 0.0;
+    for _ in 0..descents {
+        let mut order = random_tour(rng);
+        random_total += length(&order);
+        polish::two_opt(&mut order, 0, &no_edges, false, tour, usize::MAX, |a, b| metric.distance(&cities[a], &cities[b]));
+        // A path and its reverse are the same tour, and so are a closed tour's rotations
+        if tour == TourType::Closed {
+            let first = order.iter().position(|&city| city == 0).unwrap_or(0);
+            order.rotate_left(first);
+            if order.last() < order.get(1) {
+                order[1..].reverse();
+            }
+        } else if order.last() < order.first() {
+            order.reverse();
+        }
+        let optimum = length(&order);
+        optima.push((order, optimum));
+    }
+
+    let distinct: HashSet<&Vec<usize>> = optima.iter().map(|(order, _)| order).collect();
+    let best = optima.iter().min_by(|a, b| a.1.total_cmp(&b.1));
+    let fdc = best.map_or(f64::NAN, |(best, _)| {
+        let best_edges = diff::edges(best, tour);
+        let distances: Vec<f64> = optima.iter()
+            .map(|(order, _)| (best_edges.len() - diff::edges(order, tour).intersection(&best_edges).count()) as f64)
+            .collect();
+        let optimum_lengths: Vec<f64> = optima.iter().map(|(_, length)| *length).collect();
+        correlation(&optimum_lengths, &distances)
+    });
+
+    Ok(Landscape {
+        cities: cities.len(),
+        walk,
+        autocorrelation,
+        correlation_length: -1.0 / autocorrelation.abs().ln(),
+        random_mean: random_total / descents as f64,
+        descents,
+        distinct_optima: distinct.len(),
+        best_optimum: best.map_or(f64::NAN, |(_, length)| *length),
+        mean_optimum: optima.iter().map(|(_, length)| length).sum::<f64>() / descents as f64,
+        fdc,
+    })
+}
+
+/// Autocorrelation of `series` at a lag of one.
+fn autocorrelation(series: &[f64]) -> f64 {
+    let mean = series.iter().sum::<f64>() / series.len() as f64;
+    let variance: f64 = series.iter().map(|x| (x - mean).powi(2)).sum();
+    let covariance: f64 = series.windows(2).map(|pair| (pair[0] - mean) * (pair[1] - mean)).sum();
+    covariance / variance
+}
+
+/// Pearson correlation of `xs` and `ys`.
+fn correlation(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let (mean_x, mean_y) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+    let covariance: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let spread = |values: &[f64], mean: f64| values.iter().map(|v| (v - mean).powi(2)).sum::<f64>().sqrt();
+    covariance / (spread(xs, mean_x) * spread(ys, mean_y))
+}
+
+impl Landscape {
+    /// What the statistics suggest about the operators and parameters to use.
+    pub fn suggestions(&self) -> Vec<String> {
+        let mut suggestions = Vec::new();
+
+        if self.fdc >= 0.5 {
+            suggestions.push("good tours lie close together (a big valley), so recombining them pays off: \
+                favour crossover and elitism, e.g. a survival rate of 0.3 to 0.5, and finish with --polish".to_string());
+        } else if self.fdc < 0.15 || self.fdc.is_nan() {
+            suggestions.push("a tour's length says little about how close it is to the best ones: keep the \
+                population diverse with a larger population, a higher mutation rate, --diversity-floor or islands".to_string());
+        } else {
+            suggestions.push("good tours are only loosely clustered: the usual rates, around 0.4 crossover, \
+                0.1 mutation and 0.3 survival, are a sound start".to_string());
+        }
+
+        // A swap changes four legs, so on a smooth landscape lengths stay correlated for about n/4 swaps
+        if self.correlation_length < self.cities as f64 / 8.0 {
+            suggestions.push(format!(
+                "the landscape is rugged, lengths stop resembling each other after {:.1} swaps: keep the mutation \
+                 rate low, 0.05 or less, and leave the fine-tuning to --polish",
+                self.correlation_length
+            ));
+        } else {
+            suggestions.push(format!(
+                "the landscape is smooth, lengths stay correlated for {:.1} swaps: mutation makes steady progress \
+                 at rates around 0.1",
+                self.correlation_length
+            ));
+        }
+
+        if self.descents > 1 && self.distinct_optima == self.descents {
+            suggestions.push("every descent found a different local optimum, so there are many: a larger \
+                population helps keep from settling on one too early".to_string());
+        } else if self.distinct_optima * 2 <= self.descents {
+            suggestions.push("descents often end at the same local optima, so there are few: a smaller \
+                population running for more generations is enough".to_string());
+        }
+
+        suggestions
+    }
+}
+
+impl fmt::Display for Landscape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "cities                 {}", self.cities)?;
+        writeln!(f, "random tours           {:.3} on average", self.random_mean)?;
+        writeln!(f, "walk autocorrelation   {:.3} over {} swaps", self.autocorrelation, self.walk)?;
+        writeln!(f, "correlation length     {:.1} swaps", self.correlation_length)?;
+        writeln!(
+            f,
+            "local optima           {} distinct of {} 2-opt descents ({:.0}%)",
+            self.distinct_optima, self.descents, self.distinct_optima as f64 / self.descents as f64 * 100.0
+        )?;
+        writeln!(f, "optimum lengths        {:.3} best, {:.3} on average", self.best_optimum, self.mean_optimum)?;
+        if self.fdc.is_nan() {
+            writeln!(f, "fitness-distance corr. n/a, the optima don't differ")?;
+        } else {
+            writeln!(f, "fitness-distance corr. {:.3}", self.fdc)?;
+        }
+
+        writeln!(f, "suggestions:")?;
+        for suggestion in self.suggestions() {
+            writeln!(f, "  - {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{RngAlgorithm, SeededRng};
+    use crate::testing::twelve_cities;
+
+    #[test]
+    fn landscape_analysis_finds_the_single_optimum_of_a_line() {
+        let line: Vec<City> = (0..10).map(|i| City::new(i as f64, 0.0)).collect();
+        let landscape = analyze(&line, Metric::Euclidean, TourType::Open, 200, 20, &mut SeededRng::new(RngAlgorithm::ChaCha, 42)).unwrap();
+        assert_eq!((landscape.distinct_optima, landscape.best_optimum, landscape.mean_optimum), (1, 9.0, 9.0));
+        assert!(landscape.fdc.is_nan());
+        assert!(landscape.autocorrelation > 0.0 && landscape.autocorrelation < 1.0);
+        assert!(landscape.random_mean > 9.0);
+        assert_eq!(landscape.suggestions().len(), 3);
+
+        let landscape = analyze(&twelve_cities(), Metric::Euclidean, TourType::Closed, 200, 20, &mut SeededRng::new(RngAlgorithm::ChaCha, 42)).unwrap();
+        assert!(landscape.best_optimum <= landscape.mean_optimum && landscape.mean_optimum < landscape.random_mean);
+        assert!((-1.0..=1.0).contains(&landscape.fdc));
+
+        let one = [City::new(0.0, 0.0)];
+        assert!(matches!(analyze(&one, Metric::Euclidean, TourType::Closed, 200, 20, &mut SeededRng::new(RngAlgorithm::ChaCha, 42)), Err(Error::Problem(_))));
+    }
+}
@@ -0,0 +1,283 @@
+//! Zones tours should keep out of, such as lakes, restricted areas or toll zones, read from the
+//! polygons of a GeoJSON file. A leg whose straight line enters a zone costs a penalty on top of
+//! its length. As a hard constraint the penalty is more than any tour's length, so a tour
+//! entering fewer zones is always fitter than one entering more, however long it is.
+//!
+//! Zones are in the same coordinates as the cities: longitudes and latitudes for geographic
+//! cities, which GeoJSON expects, or plain x and y otherwise. Legs are straight lines in those
+//! coordinates, which is close enough to the great circle for legs of up to a few hundred km.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path as FilePath;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::evaluator::Evaluator;
+use crate::geo::Metric;
+use crate::input::City;
+use crate::projection::Projection;
+use crate::tour::TourType;
+
+/// What entering a zone costs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Penalty {
+    /// More than any tour is long.
+    Hard,
+    /// This much length.
+    Length(f64),
+}
+
+impl fmt::Display for Penalty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Penalty::Hard => write!(f, "hard"),
+            Penalty::Length(length) => write!(f, "{}", length),
+        }
+    }
+}
+
+impl FromStr for Penalty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Penalty, String> {
+        if s == "hard" {
+            return Ok(Penalty::Hard);
+        }
+        s.parse().ok()
+            .filter(|length: &f64| length.is_finite() && *length >= 0.0)
+            .map(Penalty::Length)
+            .ok_or_else(|| format!("{:?} isn't hard or a length to add per zone entered", s))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Zone {
+    /// The outline first, then any holes.
+    rings: Vec<Vec<City>>,
+    min: City,
+    max: City,
+}
+
+impl Zone {
+    fn new(rings: Vec<Vec<City>>) -> Zone {
+        let points = || rings.iter().flatten();
+        let min = City::new(points().map(|p| p.x).fold(f64::INFINITY, f64::min), points().map(|p| p.y).fold(f64::INFINITY, f64::min));
+        let max = City::new(points().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max), points().map(|p| p.y).fold(f64::NEG_INFINITY, f64::max));
+        Zone { rings, min, max }
+    }
+
+    /// Whether the straight line from `a` to `b` enters the zone, touching included.
+    fn entered_by(&self, a: &City, b: &City) -> bool {
+        if a.x.max(b.x) < self.min.x || a.x.min(b.x) > self.max.x || a.y.max(b.y) < self.min.y || a.y.min(b.y) > self.max.y {
+            return false;
+        }
+
+        self.contains(a) || self.contains(b) || self.rings.iter()
+            .flat_map(|ring| ring.windows(2))
+            .any(|edge| intersect(a, b, &edge[0], &edge[1]))
+    }
+
+    /// Whether `point` is inside the outline and outside the holes, by counting the ring edges
+    /// a ray from it crosses.
+    fn contains(&self, point: &City) -> bool {
+        let crossed = self.rings.iter()
+            .flat_map(|ring| ring.windows(2))
+            .filter(|edge| {
+                let (p, q) = (&edge[0], &edge[1]);
+                (p.y > point.y) != (q.y > point.y) && point.x < p.x + (point.y - p.y) / (q.y - p.y) * (q.x - p.x)
+            })
+            .count();
+        crossed % 2 == 1
+    }
+}
+
+/// Which side of the line through `p` and `q` `r` is on, or 0 if on it.
+fn orientation(p: &City, q: &City, r: &City) -> i8 {
+    let cross = (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+    if cross > 0.0 {
+        1
+    } else if cross < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Whether `r`, on the line through `p` and `q`, lies between them.
+fn within(p: &City, q: &City, r: &City) -> bool {
+    r.x >= p.x.min(q.x) && r.x <= p.x.max(q.x) && r.y >= p.y.min(q.y) && r.y <= p.y.max(q.y)
+}
+
+/// Whether the segments `a`-`b` and `c`-`d` meet.
+fn intersect(a: &City, b: &City, c: &City, d: &City) -> bool {
+    let (o1, o2, o3, o4) = (orientation(a, b, c), orientation(a, b, d), orientation(c, d, a), orientation(c, d, b));
+    (o1 != o2 && o3 != o4)
+        || (o1 == 0 && within(a, b, c))
+        || (o2 == 0 && within(a, b, d))
+        || (o3 == 0 && within(c, d, a))
+        || (o4 == 0 && within(c, d, b))
+}
+
+#[derive(Clone, Debug)]
+pub struct Barriers {
+    zones: Vec<Zone>,
+}
+
+impl Barriers {
+    /// Reads the zones of a GeoJSON file: the polygons and multipolygons of its geometries,
+    /// features and collections of either.
+    pub fn read(path: &FilePath) -> Result<Barriers, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let geojson: Value = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        Barriers::from_geojson(&geojson)
+    }
+
+    pub fn from_geojson(geojson: &Value) -> Result<Barriers, String> {
+        let mut zones = Vec::new();
+        collect(geojson, &mut zones)?;
+        Ok(Barriers { zones })
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    /// The zones projected with `projection`, resolved for the cities.
+    pub fn project(self, projection: Projection) -> Result<Barriers, String> {
+        let zones = self.zones.into_iter()
+            .map(|zone| {
+                let rings = zone.rings.iter().map(|ring| projection.project(ring)).collect::<Result<_, _>>()?;
+                Ok(Zone::new(rings))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Barriers { zones })
+    }
+
+    /// How many zones the straight line from `a` to `b` enters.
+    pub fn crossings(&self, a: &City, b: &City) -> usize {
+        self.zones.iter().filter(|zone| zone.entered_by(a, b)).count()
+    }
+
+    /// How many zones the legs of `order` enter, counting a zone once per leg entering it.
+    pub fn tour_crossings(&self, order: &[usize], cities: &[City], tour: TourType) -> usize {
+        tour.legs(order).map(|(a, b)| self.crossings(&cities[a], &cities[b])).sum()
+    }
+}
+
+fn collect(value: &Value, zones: &mut Vec<Zone>) -> Result<(), String> {
+    let members = |key: &str| value[key].as_array().ok_or_else(|| format!("a {} without {}", value["type"], key));
+    match value["type"].as_str() {
+        Some("FeatureCollection") => members("features")?.iter().try_for_each(|feature| collect(feature, zones)),
+        Some("GeometryCollection") => members("geometries")?.iter().try_for_each(|geometry| collect(geometry, zones)),
+        // Features may leave their geometry out
+        Some("Feature") if value["geometry"].is_null() => Ok(()),
+        Some("Feature") => collect(&value["geometry"], zones),
+        Some("Polygon") => {
+            zones.push(polygon(&value["coordinates"])?);
+            Ok(())
+        }
+        Some("MultiPolygon") => {
+            for coordinates in members("coordinates")? {
+                zones.push(polygon(coordinates)?);
+            }
+            Ok(())
+        }
+        Some(other) => Err(format!("{} isn't a zone, only polygons and multipolygons are", other)),
+        None => Err("GeoJSON objects need a type".to_string()),
+    }
+}
+
+fn polygon(coordinates: &Value) -> Result<Zone, String> {
+    let rings = coordinates.as_array().filter(|rings| !rings.is_empty()).ok_or("a polygon without rings")?;
+    let rings = rings.iter()
+        .map(|ring| {
+            let positions = ring.as_array().filter(|ring| ring.len() >= 4).ok_or("a polygon ring of fewer than 4 positions")?;
+            positions.iter()
+                .map(|position| match position.as_array().map(|p| (p.first().and_then(Value::as_f64), p.get(1).and_then(Value::as_f64))) {
+                    Some((Some(x), Some(y))) => Ok(City::new(x, y)),
+                    _ => Err(format!("{} isn't a position", position)),
+                })
+                .collect()
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Zone::new(rings))
+}
+
+/// Scores tours by their length plus the penalties for the zones they enter, the higher the
+/// fitness the shorter, as the built-in fitness does.
+pub struct Avoid {
+    cities: Vec<City>,
+    metric: Metric,
+    tour: TourType,
+    barriers: Barriers,
+    penalty: f64,
+}
+
+impl Avoid {
+    pub fn new(cities: Vec<City>, metric: Metric, tour: TourType, barriers: Barriers, penalty: Penalty) -> Avoid {
+        let penalty = match penalty {
+            Penalty::Length(length) => length,
+            // No leg is longer than twice the farthest city from the first, so no tour is
+            // longer than that for every leg
+            Penalty::Hard => {
+                let farthest = cities.iter().map(|city| metric.distance(&cities[0], city)).fold(0.0, f64::max);
+                (2.0 * farthest * cities.len() as f64).max(1.0)
+            }
+        };
+        Avoid { cities, metric, tour, barriers, penalty }
+    }
+}
+
+impl Evaluator for Avoid {
+    fn evaluate(&mut self, orders: &[&[usize]]) -> Result<Vec<f64>, Box<dyn Error>> {
+        Ok(orders.iter().map(|order| {
+            let cost: f64 = self.tour.legs(order)
+                .map(|(a, b)| {
+                    let (a, b) = (&self.cities[a], &self.cities[b]);
+                    self.metric.distance(a, b) + self.barriers.crossings(a, b) as f64 * self.penalty
+                })
+                .sum();
+            if cost == 0.0 { f64::MAX } else { 1.0 / cost }
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Path;
+
+    #[test]
+    fn legs_into_avoided_zones_are_penalised() {
+        let lake = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "geometry": { "type": "Polygon", "coordinates": [[[1, -1], [2, -1], [2, 1], [1, 1], [1, -1]]] },
+            }],
+        });
+        let barriers = Barriers::from_geojson(&lake).unwrap();
+        assert_eq!(barriers.len(), 1);
+        let (west, east, north) = (City::new(0.0, 0.0), City::new(3.0, 0.0), City::new(1.5, 3.0));
+        assert_eq!(barriers.crossings(&west, &east), 1);
+        assert_eq!(barriers.crossings(&west, &north), 0);
+        assert_eq!(barriers.crossings(&City::new(1.5, 0.0), &north), 1);
+        assert!(Barriers::from_geojson(&serde_json::json!({ "type": "Point", "coordinates": [0, 0] })).is_err());
+
+        let cities = vec![west, east, north];
+        // Closed, either order of the three cities is the same tour
+        let mut hard = Avoid::new(cities.clone(), Metric::Euclidean, TourType::Open, barriers.clone(), Penalty::Hard);
+        let fitness = hard.evaluate(&[&[0, 1, 2], &[0, 2, 1]]).unwrap();
+        assert!(fitness[1] > fitness[0]);
+        let mut soft = Avoid::new(cities.clone(), Metric::Euclidean, TourType::Open, barriers, Penalty::Length(0.5));
+        let crossing = 1.0 / soft.evaluate(&[&[0, 1, 2]]).unwrap()[0];
+        assert!((crossing - (1.0 / Path::calculate_fitness(&[0usize, 1, 2], &cities, Metric::Euclidean, TourType::Open) + 0.5)).abs() < 1e-9);
+    }
+}
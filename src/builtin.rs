@@ -0,0 +1,183 @@
+//! Classic TSPLIB instances built into the binary, to try the solver without a data file, and
+//! the files instances are otherwise read from.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::diagnostics;
+use crate::events::Notice;
+use crate::geo::{DistanceMatrix, Metric};
+use crate::input::{self, City};
+
+const BUILTIN: &[(&str, &str)] = &[
+    ("berlin52", include_str!("instances/berlin52.csv")),
+    ("eil101", include_str!("instances/eil101.csv")),
+];
+
+/// Where the cities to visit come from.
+pub enum Instance {
+    Builtin { name: &'static str, csv: &'static str },
+    /// A csv file.
+    File(PathBuf),
+    /// A TSPLIB `.tsp` file.
+    Tsplib(PathBuf),
+    /// A csv file of the distances between cities rather than where they are.
+    Matrix(PathBuf),
+    /// A csv piped to stdin, given as `-`.
+    Stdin,
+}
+
+/// The format of a file of cities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Tsplib,
+    /// A distance matrix, as csv.
+    Matrix,
+}
+
+impl Instance {
+    /// The file at `path`, as TSPLIB if it ends in .tsp or .atsp, otherwise as csv, or stdin
+    /// if the path is `-`.
+    pub fn file(path: PathBuf) -> Instance {
+        if path.as_os_str() == "-" {
+            return Instance::Stdin;
+        }
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("tsp") || extension.eq_ignore_ascii_case("atsp") => Instance::Tsplib(path),
+            _ => Instance::File(path),
+        }
+    }
+
+    /// The same instance, with a file read as `format` whatever its extension.
+    pub fn read_as(self, format: Format) -> Instance {
+        match (self, format) {
+            (Instance::File(path) | Instance::Tsplib(path) | Instance::Matrix(path), Format::Csv) => Instance::File(path),
+            (Instance::File(path) | Instance::Tsplib(path) | Instance::Matrix(path), Format::Tsplib) => Instance::Tsplib(path),
+            (Instance::File(path) | Instance::Tsplib(path) | Instance::Matrix(path), Format::Matrix) => Instance::Matrix(path),
+            (builtin, _) => builtin,
+        }
+    }
+
+    /// Reads the instance's cities. When `lenient`, rows of a csv file that can't be read are
+    /// skipped, with a warning about them returned beside the cities.
+    pub fn cities(&self, lenient: bool) -> miette::Result<(Vec<City>, Vec<Notice>)> {
+        match self {
+            Instance::Builtin { csv, .. } => Ok((input::read_cities(csv.as_bytes()).expect("built-in instances are valid"), Vec::new())),
+            Instance::File(path) => diagnostics::load_cities(path, lenient),
+            Instance::Tsplib(path) => diagnostics::load_tsplib(path).map(|problem| (problem.cities, Vec::new())),
+            Instance::Matrix(path) => diagnostics::load_matrix(path).map(|distances| (input::placeholders(distances.len()), Vec::new())),
+            Instance::Stdin => diagnostics::load_stdin_cities(lenient),
+        }
+    }
+
+    /// The distances between the cities, if the instance gives them rather than measuring them
+    /// between coordinates.
+    pub fn distances(&self) -> Option<DistanceMatrix> {
+        match self {
+            Instance::Tsplib(path) => diagnostics::load_tsplib(path).ok().and_then(|problem| problem.distances),
+            Instance::Matrix(path) => diagnostics::load_matrix(path).ok(),
+            _ => None,
+        }
+    }
+
+    /// How the instance says to measure distances, if it does, as TSPLIB files do.
+    pub fn metric(&self) -> Option<Metric> {
+        match self {
+            Instance::Tsplib(path) => diagnostics::load_tsplib(path).ok().map(|problem| problem.metric),
+            _ => None,
+        }
+    }
+
+    /// The instance's raw contents.
+    pub fn contents(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Instance::Builtin { csv, .. } => Ok(csv.as_bytes().to_vec()),
+            Instance::File(path) | Instance::Tsplib(path) | Instance::Matrix(path) => std::fs::read(path),
+            Instance::Stdin => input::stdin().map(|source| source.as_bytes().to_vec()),
+        }
+    }
+
+    /// The instance's name: the built-in's, or the file name without its extension.
+    pub fn name(&self) -> String {
+        match self {
+            Instance::Builtin { name, .. } => name.to_string(),
+            Instance::File(path) | Instance::Tsplib(path) | Instance::Matrix(path) => {
+                path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+            }
+            Instance::Stdin => "stdin".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instance::Builtin { name, .. } => write!(f, "builtin:{}", name),
+            Instance::File(path) | Instance::Tsplib(path) | Instance::Matrix(path) => write!(f, "{}", path.display()),
+            Instance::Stdin => f.write_str("-"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "tsplib" => Ok(Format::Tsplib),
+            "matrix" => Ok(Format::Matrix),
+            _ => Err(format!("unknown format {:?}, expected csv, tsplib or matrix", s)),
+        }
+    }
+}
+
+/// Parses `builtin:<name>` into one of the built-in instances, `-` into stdin and anything else
+/// into a path, read as TSPLIB if it ends in .tsp or .atsp.
+pub fn parse_instance(s: &str) -> Result<Instance, String> {
+    let wanted = match s.strip_prefix("builtin:") {
+        Some(wanted) => wanted,
+        None => return Ok(Instance::file(PathBuf::from(s))),
+    };
+
+    BUILTIN.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+        .map(|&(name, csv)| Instance::Builtin { name, csv })
+        .ok_or_else(|| {
+            let names: Vec<&str> = BUILTIN.iter().map(|(name, _)| *name).collect();
+            format!("there's no built-in instance {:?}, only {}", wanted, names.join(", "))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{optimum, rng};
+
+    #[test]
+    fn builtin_instances_load_as_the_known_instances() {
+        for &(name, _) in BUILTIN {
+            let cities = match parse_instance(&format!("builtin:{}", name)) {
+                Ok(Instance::Builtin { csv, .. }) => input::read_cities(csv.as_bytes()).unwrap(),
+                _ => panic!("{} isn't built in", name),
+            };
+            assert!(optimum::known(name, cities.len(), Metric::Euclidean).is_some(), "{} has {} cities", name, cities.len());
+            let mut places: Vec<(u64, u64)> = cities.iter().map(|city| (city.x.to_bits(), city.y.to_bits())).collect();
+            places.sort_unstable();
+            places.dedup();
+            assert_eq!(places.len(), cities.len(), "{} has cities in the same place", name);
+        }
+    }
+
+    #[test]
+    fn a_dash_reads_the_cities_from_stdin() {
+        for instance in [parse_instance("-").unwrap(), Instance::file(PathBuf::from("-"))] {
+            assert!(matches!(instance, Instance::Stdin));
+            assert_eq!((instance.to_string(), instance.name()), ("-".to_string(), "stdin".to_string()));
+        }
+        assert!(matches!(Instance::file(PathBuf::from("./-")), Instance::File(_)));
+        assert_ne!(rng::random_seed(), rng::random_seed());
+    }
+}
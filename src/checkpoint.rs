@@ -0,0 +1,100 @@
+//! Checkpoints of a run's population, for resuming long runs.
+//!
+//! A checkpoint is a header line naming the format and its version, followed by the state as
+//! JSON:
+//!
+//! ```text
+//! wasi-genetic checkpoint 2
+//! {"generation":120,"seed":42,"rng":"chacha","state":8417390211,"mutation_rate":0.05,"cities":52,"orders":[[0,3,...],...]}
+//! ```
+//!
+//! The version is read before anything else, so a checkpoint written by a newer, incompatible
+//! build is rejected with a clear message instead of failing to parse. When the format changes,
+//! bump `VERSION` and add a step to `upgrade` that turns the previous version's JSON into the
+//! new one, so checkpoints from long-running experiments keep loading after an upgrade.
+
+use std::fs;
+use std::path::Path as FilePath;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+pub const VERSION: u32 = 2;
+const MAGIC: &str = "wasi-genetic checkpoint";
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Checkpoint {
+    /// Generations evolved so far.
+    pub generation: usize,
+    /// The seed the run started from.
+    pub seed: u64,
+    pub rng: String,
+    /// What the generator was reseeded with when the checkpoint was taken, to carry on from.
+    pub state: u64,
+    /// The rate adaptive mutation had got to.
+    pub mutation_rate: Option<f64>,
+    pub cities: usize,
+    pub orders: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a wasi-genetic checkpoint")]
+    NotACheckpoint,
+    #[error("written by a newer version of wasi-genetic (checkpoint version {0}, this build reads up to {VERSION})")]
+    TooNew(u32),
+    #[error("damaged checkpoint: {0}")]
+    Damaged(#[from] serde_json::Error),
+}
+
+/// Writes `checkpoint` to `path`, replacing it only once the new one is complete.
+pub fn save(path: &FilePath, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+    let mut contents = format!("{} {}\n", MAGIC, VERSION);
+    contents.push_str(&serde_json::to_string(checkpoint)?);
+    contents.push('\n');
+
+    let partial = path.with_extension("partial");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+pub fn load(path: &FilePath) -> Result<Checkpoint, CheckpointError> {
+    let contents = fs::read_to_string(path)?;
+    let (header, body) = contents.split_once('\n').ok_or(CheckpointError::NotACheckpoint)?;
+
+    let version: u32 = header.strip_prefix(MAGIC)
+        .and_then(|version| version.trim().parse().ok())
+        .ok_or(CheckpointError::NotACheckpoint)?;
+    if version > VERSION {
+        return Err(CheckpointError::TooNew(version));
+    }
+
+    let body = upgrade(version, serde_json::from_str(body)?)?;
+    Ok(serde_json::from_value(body)?)
+}
+
+/// Brings the JSON of a checkpoint written as `version` up to the current version, one version
+/// at a time.
+fn upgrade(version: u32, mut body: Value) -> Result<Value, CheckpointError> {
+    match version {
+        VERSION => Ok(body),
+        // Version 1 had no generator state; runs resumed from one were seeded with the seed
+        // plus the generation
+        1 => {
+            let state = body["seed"].as_u64().zip(body["generation"].as_u64())
+                .map(|(seed, generation)| seed.wrapping_add(generation));
+            if let Some(fields) = body.as_object_mut() {
+                fields.insert("state".to_string(), state.into());
+                fields.insert("mutation_rate".to_string(), Value::Null);
+            }
+            upgrade(2, body)
+        }
+        // There was no version 0
+        _ => Err(CheckpointError::NotACheckpoint),
+    }
+}
@@ -0,0 +1,275 @@
+//! Coordinator/worker mode for spreading one run over several machines.
+//!
+//! Each worker evolves its own island from its own random seed population. The run is split into
+//! rounds: the coordinator asks every worker to evolve for a round, collects their elite tours,
+//! and hands the best of them to every worker at the start of the next round. Workers that stop
+//! answering are dropped, and workers can join or rejoin between rounds.
+//!
+//! The protocol is line based:
+//!
+//! ```text
+//! coordinator -> worker   PROBLEM <generations> <population> <crossover> <mutation> <survival> <migrants> [<metric> [<tour type>]]
+//!                         CITIES <x>,<y>;<x>,<y>;...
+//!                         EVOLVE <order>;<order>;...     (orders are comma separated indices)
+//! worker -> coordinator   ELITES <order>;<order>;...
+//! coordinator -> worker   DONE
+//! ```
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use crate::events::Notice;
+use crate::geo::Metric;
+use crate::rng::{random_seed, RngAlgorithm, SeededRng};
+use crate::tour::TourType;
+use crate::{City, Path, Simulation};
+
+const REJOIN_DELAY: Duration = Duration::from_secs(5);
+
+pub struct Problem {
+    pub cities: Vec<City>,
+    pub metric: Metric,
+    pub tour_type: TourType,
+    pub rounds: usize,
+    pub generations: usize,
+    pub population_size: usize,
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+    pub survival_rate: f64,
+    pub migrants: usize,
+}
+
+struct Peer {
+    name: String,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Peer {
+    fn send(&mut self, line: &str) -> std::io::Result<()> {
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn receive(&mut self) -> std::io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "disconnected"));
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+/// Accepts workers on `listen` and runs `problem` across them, returning the best path found.
+/// A worker that takes longer than `timeout` to finish a round is considered lost. How the run
+/// goes and the workers that come and go are told to `on_notice`.
+pub fn coordinate(listen: &str, problem: &Problem, timeout: Duration, on_notice: impl Fn(&Notice)) -> std::io::Result<Path> {
+    let listener = TcpListener::bind(listen)?;
+    let joins = accept_workers(listener)?;
+    on_notice(&Notice::Info(format!("coordinating on {}", listen)));
+
+    let mut workers: Vec<Peer> = Vec::new();
+    let mut pool: Vec<Path> = Vec::new();
+    let mut round = 0;
+
+    while round < problem.rounds {
+        if workers.is_empty() {
+            on_notice(&Notice::Info("waiting for workers".to_string()));
+            if let Ok(stream) = joins.recv() {
+                admit(stream, problem, timeout, &mut workers, &on_notice);
+            }
+        }
+        while let Ok(stream) = joins.try_recv() {
+            admit(stream, problem, timeout, &mut workers, &on_notice);
+        }
+
+        let immigrants = format!("EVOLVE {}", encode_orders(pool.iter().map(|p| &p.order[..])));
+        workers.retain_mut(|w| match w.send(&immigrants) {
+            Ok(()) => true,
+            Err(e) => {
+                on_notice(&Notice::Warning(format!("lost worker {}: {}", w.name, e)));
+                false
+            }
+        });
+
+        let mut elites = Vec::new();
+        workers.retain_mut(|w| match w.receive().and_then(|reply| parse_elites(&reply)) {
+            Ok(orders) => {
+                elites.extend(orders);
+                true
+            }
+            Err(e) => {
+                on_notice(&Notice::Warning(format!("lost worker {}: {}", w.name, e)));
+                false
+            }
+        });
+
+        // A round only counts once some worker finished it
+        if elites.is_empty() {
+            continue;
+        }
+
+        pool.extend(elites.into_iter()
+            .filter(|order| Path::is_tour(order, problem.cities.len()))
+            .map(|order| Path { fitness: Path::calculate_fitness(&order, &problem.cities, problem.metric, problem.tour_type), order }));
+        pool.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        pool.dedup_by(|a, b| a.order == b.order);
+        pool.truncate(problem.migrants.max(1));
+
+        round += 1;
+        on_notice(&Notice::Info(format!(
+            "round {}/{}: best length {} from {} workers", round, problem.rounds, 1.0 / pool[0].fitness, workers.len()
+        )));
+    }
+
+    for w in workers.iter_mut() {
+        let _ = w.send("DONE");
+    }
+
+    Ok(pool.swap_remove(0))
+}
+
+fn accept_workers(listener: TcpListener) -> std::io::Result<Receiver<TcpStream>> {
+    let (sender, joins) = channel();
+    thread::Builder::new().name("coordinator".to_string()).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if sender.send(stream).is_err() {
+                break;
+            }
+        }
+    })?;
+
+    Ok(joins)
+}
+
+fn admit(stream: TcpStream, problem: &Problem, timeout: Duration, workers: &mut Vec<Peer>, on_notice: &dyn Fn(&Notice)) {
+    let name = stream.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "?".to_string());
+
+    let result = (|| {
+        stream.set_read_timeout(Some(timeout))?;
+        let mut worker = Peer { name: name.clone(), reader: BufReader::new(stream.try_clone()?), writer: stream };
+
+        worker.send(&format!(
+            "PROBLEM {} {} {} {} {} {} {} {}",
+            problem.generations,
+            problem.population_size,
+            problem.crossover_rate,
+            problem.mutation_rate,
+            problem.survival_rate,
+            problem.migrants,
+            problem.metric,
+            problem.tour_type
+        ))?;
+        let cities: Vec<String> = problem.cities.iter().map(|c| format!("{},{}", c.x, c.y)).collect();
+        worker.send(&format!("CITIES {}", cities.join(";")))?;
+
+        Ok::<_, std::io::Error>(worker)
+    })();
+
+    match result {
+        Ok(worker) => {
+            on_notice(&Notice::Info(format!("worker {} joined", name)));
+            workers.push(worker);
+        }
+        Err(e) => on_notice(&Notice::Warning(format!("worker {} could not join: {}", name, e))),
+    }
+}
+
+/// Works for the coordinator at `addr` until it finishes the run, rejoining if the connection
+/// is lost, which is told to `on_notice` along with each joining. The island draws from
+/// `algorithm`, seeded with `seed` or at random.
+pub fn work(addr: &str, algorithm: RngAlgorithm, seed: Option<u64>, on_notice: impl Fn(&Notice)) {
+    loop {
+        match session(addr, algorithm, seed, &on_notice) {
+            Ok(()) => return,
+            Err(e) => {
+                on_notice(&Notice::Warning(format!("lost the coordinator at {} ({}), rejoining in {:?}", addr, e, REJOIN_DELAY)));
+                thread::sleep(REJOIN_DELAY);
+            }
+        }
+    }
+}
+
+fn session(addr: &str, algorithm: RngAlgorithm, seed: Option<u64>, on_notice: &dyn Fn(&Notice)) -> std::io::Result<()> {
+    let stream = TcpStream::connect(addr)?;
+    let mut coordinator = Peer { name: addr.to_string(), reader: BufReader::new(stream.try_clone()?), writer: stream };
+
+    let problem = coordinator.receive()?;
+    let params: Vec<&str> = problem.split_whitespace().collect();
+    // Coordinators from before the metric was sent only ever measured straight lines, and ones
+    // from before the tour type was sent only ever scored open paths
+    if !(7..=9).contains(&params.len()) || params[0] != "PROBLEM" {
+        return Err(malformed(&problem));
+    }
+    let generations = parse(params[1])?;
+    let population_size = parse(params[2])?;
+    let crossover_rate = parse(params[3])?;
+    let mutation_rate = parse(params[4])?;
+    let survival_rate = parse(params[5])?;
+    let migrants = parse(params[6])?;
+    let metric = params.get(7).map_or(Ok(Metric::Euclidean), |metric| parse(metric))?;
+    let tour_type = params.get(8).map_or(Ok(TourType::Open), |tour_type| parse(tour_type))?;
+
+    let cities_line = coordinator.receive()?;
+    let cities = cities_line.strip_prefix("CITIES ")
+        .ok_or_else(|| malformed(&cities_line))?
+        .split(';')
+        .map(|city| {
+            let (x, y) = city.split_once(',').ok_or_else(|| malformed(city))?;
+            Ok(City::new(parse(x)?, parse(y)?))
+        })
+        .collect::<std::io::Result<Vec<City>>>()?;
+
+    let rng = SeededRng::new(algorithm, seed.unwrap_or_else(random_seed));
+    on_notice(&Notice::Info(format!("joined {} for {} cities with seed {}", addr, cities.len(), rng.seed())));
+    let mut sim = Simulation::new(population_size, cities, generations, crossover_rate, mutation_rate, survival_rate, rng)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    sim.set_metric(metric);
+    sim.set_tour_type(tour_type);
+
+    loop {
+        let command = coordinator.receive()?;
+        if command == "DONE" {
+            return Ok(());
+        }
+
+        let immigrants = command.strip_prefix("EVOLVE").ok_or_else(|| malformed(&command))?;
+        let invalid = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        sim.immigrate(decode_orders(immigrants)?).map_err(invalid)?;
+        sim.solve(|_| ControlFlow::Continue(())).map_err(invalid)?;
+
+        let elites = sim.elites(migrants).map_err(invalid)?;
+        coordinator.send(&format!("ELITES {}", encode_orders(elites.iter().map(|o| &o[..]))))?;
+    }
+}
+
+fn encode_orders<'a>(orders: impl Iterator<Item = &'a [usize]>) -> String {
+    orders
+        .map(|order| order.iter().map(|o| o.to_string()).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn decode_orders(s: &str) -> std::io::Result<Vec<Vec<usize>>> {
+    s.split(';')
+        .map(str::trim)
+        .filter(|order| !order.is_empty())
+        .map(|order| order.split(',').map(parse).collect())
+        .collect()
+}
+
+fn parse_elites(reply: &str) -> std::io::Result<Vec<Vec<usize>>> {
+    decode_orders(reply.strip_prefix("ELITES").ok_or_else(|| malformed(reply))?)
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> std::io::Result<T> {
+    s.trim().parse().map_err(|_| malformed(s))
+}
+
+fn malformed(s: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("malformed message {:?}", s))
+}
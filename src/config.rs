@@ -0,0 +1,139 @@
+//! Run settings read from a TOML file, for `--config`. Every key is a command-line option's
+//! name, in kebab or snake case (`tour-type` or `tour_type`), and the cities the command line
+//! takes as an argument are `csv`. The GA parameters that used to be arguments too can still be
+//! given as `population_size` and `generations`, for `population` and `iterations`:
+//!
+//! ```toml
+//! population = 500
+//! iterations = 5000
+//! crossover_rate = 0.4
+//! mutation_rate = 0.001
+//! survival_rate = 0.3
+//! instance = "builtin:berlin52"
+//! seed = 7
+//! crossover = "ox"
+//! polish = true
+//! out = ["svg:route.svg", "stats:log.csv"]
+//! ```
+//!
+//! A switch such as `polish` is true or false, an option that can be given more than once such
+//! as `out` takes an array, and any other option a string or number, as it would be written on
+//! the command line.
+
+use std::ops::Range;
+use std::path::Path;
+
+use toml_edit::{Document, Item, Value as TomlValue};
+
+/// The settings taken as arguments rather than options.
+pub const ARGUMENTS: &[&str] = &["csv"];
+
+/// Keys of the arguments that are options now, and the options they set.
+const RENAMED: &[(&str, &str)] = &[("population_size", "population"), ("generations", "iterations")];
+
+/// One key of a config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Setting {
+    /// In snake case, e.g. `tour_type` for `--tour-type`.
+    pub key: String,
+    pub value: SettingValue,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SettingValue {
+    /// Whether a switch is on.
+    Switch(bool),
+    /// The values an option is given, once each.
+    Values(Vec<String>),
+}
+
+impl Setting {
+    /// The setting as command-line options: `--key=value` for each value, or `--key` for a
+    /// switch that's on.
+    pub fn args(&self) -> Vec<String> {
+        let option = format!("--{}", self.key.replace('_', "-"));
+        match &self.value {
+            SettingValue::Switch(true) => vec![option],
+            SettingValue::Switch(false) => Vec::new(),
+            SettingValue::Values(values) => values.iter().map(|value| format!("{}={}", option, value)).collect(),
+        }
+    }
+
+    /// The value of a setting that's an argument, or of an option given once.
+    pub fn single(&self) -> Option<&str> {
+        match &self.value {
+            SettingValue::Values(values) if values.len() == 1 => Some(&values[0]),
+            _ => None,
+        }
+    }
+}
+
+/// Why a config file can't be read, and where in it, as a byte range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Option<Range<usize>>,
+}
+
+/// Reads the settings of a config file.
+pub fn parse(source: &str) -> Result<Vec<Setting>, ParseError> {
+    let document = Document::parse(source).map_err(|e| ParseError { message: e.message().to_string(), span: e.span() })?;
+
+    document.iter()
+        .map(|(key, item)| {
+            let error = |message: String| ParseError { message, span: item.span() };
+            let key = key.replace('-', "_");
+            let key = RENAMED.iter().find(|&&(old, _)| old == key).map_or(key, |&(_, new)| new.to_string());
+            if key == "config" {
+                return Err(error("a config file can't name another".to_string()));
+            }
+            let value = match item {
+                Item::Value(TomlValue::Boolean(switch)) => SettingValue::Switch(*switch.value()),
+                Item::Value(TomlValue::Array(array)) => SettingValue::Values(array.iter().map(scalar).collect::<Option<_>>()
+                    .ok_or_else(|| error(format!("{} can only list strings and numbers", key)))?),
+                Item::Value(value) => {
+                    SettingValue::Values(vec![scalar(value).ok_or_else(|| error(format!("{} can't be a {}", key, value.type_name())))?])
+                }
+                _ => return Err(error(format!("{} is a table, but settings go at the top level", key))),
+            };
+            Ok(Setting { key, value })
+        })
+        .collect()
+}
+
+/// Reads the settings of the config file at `path`.
+pub fn load(path: &Path) -> miette::Result<Vec<Setting>> {
+    crate::diagnostics::load_config(path)
+}
+
+fn scalar(value: &TomlValue) -> Option<String> {
+    match value {
+        TomlValue::String(s) => Some(s.value().clone()),
+        TomlValue::Integer(i) => Some(i.value().to_string()),
+        TomlValue::Float(f) => Some(f.value().to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_files_are_read_as_command_line_options() {
+        let source = "population_size = 200\ntour-type = \"open\"\npolish = true\nverify = false\nmutation_rate = 0.05\n\
+                      out = [\"svg:a.svg\", \"stats:b.csv\"]\n";
+        let settings = parse(source).unwrap();
+        let args: Vec<String> = settings.iter().flat_map(Setting::args).collect();
+        assert_eq!(args, ["--population=200", "--tour-type=open", "--polish", "--mutation-rate=0.05", "--out=svg:a.svg", "--out=stats:b.csv"]);
+        assert_eq!((settings[0].key.as_str(), settings[0].single()), ("population", Some("200")));
+        assert_eq!(settings[1].key, "tour_type");
+
+        let error = parse("seed = 1\n[ga]\nseed = 2\n").unwrap_err();
+        assert_eq!(error.message, "ga is a table, but settings go at the top level");
+        assert_eq!(error.span, Some(9..13));
+        assert_eq!(parse("start = 2024-01-01\n").unwrap_err().message, "start can't be a datetime");
+        assert!(parse("config = \"other.toml\"\n").is_err());
+        assert!(parse("seed = \n").is_err());
+    }
+}
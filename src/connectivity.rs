@@ -0,0 +1,140 @@
+//! Cost matrices that leave legs out, for `--unreachable`: which cities can be reached from which
+//! by the legs that have a cost, and whether a tour of all of them can be made of those legs
+//! alone. If it can't, every tour takes a leg there's no way to take, and rather than solve for
+//! a length that's infinite whatever the order, the run names the groups of cities that can't
+//! be joined up, or takes the fewest such legs it can if told to.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::geo::DistanceMatrix;
+use crate::tour::TourType;
+
+// Groups of cities listed by name before the rest are only counted
+const LISTED_COMPONENTS: usize = 10;
+const LISTED_CITIES: usize = 10;
+
+/// What to do when no tour can be made of legs that have a cost.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unreachable {
+    /// Stop, listing the groups of cities that can't be joined up.
+    #[default]
+    Error,
+    /// Solve anyway, every leg without a cost costing a penalty, so the best tour takes as few
+    /// of them as it can.
+    Penalty,
+}
+
+impl FromStr for Unreachable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Unreachable, String> {
+        match s {
+            "error" => Ok(Unreachable::Error),
+            "penalty" => Ok(Unreachable::Penalty),
+            _ => Err(format!("unknown handling of unreachable cities {:?}, expected error or penalty", s)),
+        }
+    }
+}
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Unreachable::Error => "error",
+            Unreachable::Penalty => "penalty",
+        })
+    }
+}
+
+/// The strongly connected components of the cities of `matrix` by the legs that have a cost:
+/// groups in which every city can be reached from every other. Each is in the order of its
+/// cities' indices, and the groups in an order a tour could visit them in, one that never has
+/// a leg with a cost from a later group to an earlier one.
+pub fn components(matrix: &DistanceMatrix) -> Vec<Vec<usize>> {
+    let count = matrix.len();
+    let leg = |a: usize, b: usize| a != b && matrix.get(a, b).is_finite();
+
+    // Kosaraju's algorithm: the order the cities are finished in following the legs forwards...
+    let mut finished = Vec::with_capacity(count);
+    let mut seen = vec![false; count];
+    for start in 0..count {
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut stack = vec![(start, 0)];
+        while let Some(&(city, next)) = stack.last() {
+            let top = stack.len() - 1;
+            match (next..count).find(|&b| !seen[b] && leg(city, b)) {
+                Some(b) => {
+                    stack[top].1 = b + 1;
+                    seen[b] = true;
+                    stack.push((b, 0));
+                }
+                None => {
+                    finished.push(city);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    // ...then, from the last finished, the cities that reach each one, following them backwards
+    let mut grouped = vec![false; count];
+    let mut components = Vec::new();
+    for &start in finished.iter().rev() {
+        if grouped[start] {
+            continue;
+        }
+        grouped[start] = true;
+        let (mut component, mut stack) = (vec![start], vec![start]);
+        while let Some(city) = stack.pop() {
+            for (a, grouped) in grouped.iter_mut().enumerate() {
+                if !*grouped && leg(a, city) {
+                    *grouped = true;
+                    component.push(a);
+                    stack.push(a);
+                }
+            }
+        }
+        component.sort_unstable();
+        components.push(component);
+    }
+    components
+}
+
+/// The groups of cities of `matrix` that keep a tour of `tour_type` from being made of legs
+/// with a cost, or `None` if they don't. A closed tour needs every city reachable from every
+/// other, and an open one a leg from each group to the next. That's not always enough, so the
+/// best tour may still take a leg without a cost, which a penalty keeps to as few as it can.
+pub fn unreachable(matrix: &DistanceMatrix, tour_type: TourType) -> Option<Components> {
+    let components = components(matrix);
+    let joined = match tour_type {
+        TourType::Closed => components.len() <= 1,
+        TourType::Open => components.windows(2).all(|pair| {
+            pair[0].iter().any(|&a| pair[1].iter().any(|&b| matrix.get(a, b).is_finite()))
+        }),
+    };
+    if joined { None } else { Some(Components(components)) }
+}
+
+/// Groups of cities that can't be joined up, listed as `{0, 1, 2}, {3}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Components(pub Vec<Vec<usize>>);
+
+impl fmt::Display for Components {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups: Vec<String> = self.0.iter().take(LISTED_COMPONENTS).map(|component| {
+            let mut cities: Vec<String> = component.iter().take(LISTED_CITIES).map(usize::to_string).collect();
+            if component.len() > LISTED_CITIES {
+                cities.push(format!("and {} more", component.len() - LISTED_CITIES));
+            }
+            format!("{{{}}}", cities.join(", "))
+        }).collect();
+        write!(f, "{}", groups.join(", "))?;
+        if self.0.len() > LISTED_COMPONENTS {
+            write!(f, " and {} more groups", self.0.len() - LISTED_COMPONENTS)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! How two parent tours are recombined into a child. Either way the child is a tour of the
+//! same cities, and a prefix the parents share stays in place.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use crate::population::Gene;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Crossover {
+    /// The mother's cities up to a random point, then the rest in the father's order.
+    #[default]
+    OnePoint,
+    /// OX1: the mother's cities between two random points, in place, and the rest in the
+    /// father's order around them. The rest fills the places before the segment and then the
+    /// ones after it, rather than wrapping round from the end of the segment as in the usual
+    /// form, so that a prefix stays at the start.
+    Ordered,
+}
+
+impl Crossover {
+    /// Draws where to cut parents of `len` cities: the segment of the mother the child takes.
+    pub fn cuts<R: Rng + ?Sized>(self, len: usize, rng: &mut R) -> (usize, usize) {
+        let points = Uniform::new(0, len);
+        match self {
+            Crossover::OnePoint => (0, points.sample(rng)),
+            Crossover::Ordered => {
+                let (a, b) = (points.sample(rng), points.sample(rng));
+                (a.min(b), a.max(b) + 1)
+            }
+        }
+    }
+
+    /// Writes the child of `mother` and `father` cut at `cuts`, as drawn by `cuts`, to `child`.
+    /// A one-point cut is an ordered one whose segment starts the tour, so either way it's bred
+    /// the same.
+    pub fn apply<G: Gene>(self, mother: &[G], father: &[G], (start, end): (usize, usize), child: &mut [G]) {
+        let mut taken = vec![false; mother.len()];
+        for &city in &mother[start..end] {
+            taken[city.index()] = true;
+        }
+
+        let mut rest = father.iter().copied().filter(|city| !taken[city.index()]);
+        for (slot, city) in child[..start].iter_mut().zip(rest.by_ref()) {
+            *slot = city;
+        }
+        child[start..end].copy_from_slice(&mother[start..end]);
+        for (slot, city) in child[end..].iter_mut().zip(rest) {
+            *slot = city;
+        }
+    }
+}
+
+impl FromStr for Crossover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Crossover, String> {
+        match s {
+            "one-point" => Ok(Crossover::OnePoint),
+            "ox" => Ok(Crossover::Ordered),
+            _ => Err(format!("unknown crossover {:?}, expected one-point or ox", s)),
+        }
+    }
+}
+
+impl fmt::Display for Crossover {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Crossover::OnePoint => "one-point",
+            Crossover::Ordered => "ox",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::testing::{is_permutation, permutation};
+    use crate::Path;
+
+    proptest! {
+        #[test]
+        fn crossover_produces_a_permutation(
+            (mother, father) in permutation(50).prop_flat_map(|m| {
+                let len = m.len();
+                (Just(m), Just((0..len).collect::<Vec<usize>>()).prop_shuffle())
+            })
+        ) {
+            let child = Path::crossover(&mother, &father, &mut rand::thread_rng());
+            prop_assert!(is_permutation(&child, mother.len()), "{:?}", child);
+        }
+
+        #[test]
+        fn ordered_crossover_keeps_a_shared_prefix(
+            (prefix, mother, father) in permutation(50).prop_flat_map(|m| {
+                let len = m.len();
+                (0..len, Just(m), Just((0..len).collect::<Vec<usize>>()).prop_shuffle())
+            })
+        ) {
+            let (shared, mut father) = (&mother[..prefix], father);
+            father.retain(|city| !shared.contains(city));
+            father.splice(0..0, shared.iter().copied());
+
+            let cuts = Crossover::Ordered.cuts(mother.len(), &mut rand::thread_rng());
+            let mut child = vec![0; mother.len()];
+            Crossover::Ordered.apply(&mother, &father, cuts, &mut child);
+            prop_assert!(is_permutation(&child, mother.len()), "{:?}", child);
+            prop_assert!(child.starts_with(shared), "{:?} lost the prefix {:?}", child, shared);
+            prop_assert_eq!(&child[cuts.0..cuts.1], &mother[cuts.0..cuts.1]);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Built-in web page that plots convergence, draws the current best route and offers start/stop
+//! controls for the run.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use serde_json::json;
+
+use crate::{City, Path};
+
+const PAGE: &str = include_str!("dashboard.html");
+
+pub static DASHBOARD: Dashboard = Dashboard::new();
+
+pub struct Dashboard {
+    state: Mutex<State>,
+    start: Condvar,
+}
+
+struct State {
+    cities: Vec<(f64, f64)>,
+    history: Vec<f64>,
+    best: Vec<usize>,
+    running: bool,
+    stop_requested: bool,
+    start_requested: bool,
+}
+
+impl Dashboard {
+    const fn new() -> Dashboard {
+        Dashboard {
+            state: Mutex::new(State {
+                cities: Vec::new(),
+                history: Vec::new(),
+                best: Vec::new(),
+                running: false,
+                stop_requested: false,
+                start_requested: false,
+            }),
+            start: Condvar::new(),
+        }
+    }
+
+    pub fn begin_run(&self, cities: &[City]) {
+        let mut state = self.state.lock().unwrap();
+        state.cities = cities.iter().map(|c| (c.x, c.y)).collect();
+        state.history.clear();
+        state.best.clear();
+        state.running = true;
+        state.stop_requested = false;
+    }
+
+    /// Records the best path after a generation; breaks once the page asked the run to stop.
+    pub fn record_generation(&self, fittest: &Path) -> ControlFlow<()> {
+        let mut state = self.state.lock().unwrap();
+        state.history.push(1.0 / fittest.fitness);
+        if state.best != fittest.order {
+            state.best = fittest.order.clone();
+        }
+
+        if state.stop_requested {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    pub fn end_run(&self) {
+        self.state.lock().unwrap().running = false;
+    }
+
+    /// Blocks until the page asks for another run.
+    pub fn wait_for_start(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.start_requested {
+            state = self.start.wait(state).unwrap();
+        }
+        state.start_requested = false;
+    }
+
+    fn render_state(&self) -> String {
+        let state = self.state.lock().unwrap();
+        json!({
+            "running": state.running,
+            "cities": state.cities,
+            "history": state.history,
+            "best": state.best,
+        }).to_string()
+    }
+
+    fn request_start(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.running {
+            state.start_requested = true;
+            self.start.notify_all();
+        }
+    }
+
+    fn request_stop(&self) {
+        self.state.lock().unwrap().stop_requested = true;
+    }
+}
+
+/// Serves the dashboard on `addr` from a background thread.
+pub fn serve(addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+
+    thread::Builder::new().name("dashboard".to_string()).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = respond(&DASHBOARD, stream);
+        }
+    })?;
+
+    Ok(())
+}
+
+fn respond(dashboard: &Dashboard, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let mut fields = request_line.split_whitespace();
+    let method = fields.next().unwrap_or("");
+    let path = fields.next().unwrap_or("");
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/") => ("200 OK", "text/html; charset=utf-8", PAGE.to_string()),
+        ("GET", "/state") => ("200 OK", "application/json", dashboard.render_state()),
+        ("POST", "/start") => {
+            dashboard.request_start();
+            ("204 No Content", "text/plain", String::new())
+        }
+        ("POST", "/stop") => {
+            dashboard.request_stop();
+            ("204 No Content", "text/plain", String::new())
+        }
+        _ => ("404 Not Found", "text/plain", String::new()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::Duration;
+
+    /// Sends `request` to `dashboard` over a local connection and returns the response.
+    fn request(dashboard: &Dashboard, request: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::scope(|scope| {
+            let client = scope.spawn(|| {
+                let mut stream = TcpStream::connect(addr).unwrap();
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            });
+            let (stream, _) = listener.accept().unwrap();
+            respond(dashboard, stream).unwrap();
+            client.join().unwrap()
+        })
+    }
+
+    fn body(response: &str) -> serde_json::Value {
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn the_state_follows_the_run() {
+        let dashboard = Dashboard::new();
+        let state = body(&request(&dashboard, "GET /state HTTP/1.1\r\n\r\n"));
+        assert_eq!(state, json!({ "running": false, "cities": [], "history": [], "best": [] }));
+
+        dashboard.begin_run(&[City::new(0.0, 0.0), City::new(3.0, 4.0)]);
+        let flow = dashboard.record_generation(&Path { fitness: 0.1, order: vec![1, 0] });
+        assert_eq!(flow, ControlFlow::Continue(()));
+        let response = request(&dashboard, "GET /state HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n"), "{}", response);
+        assert_eq!(body(&response), json!({ "running": true, "cities": [[0.0, 0.0], [3.0, 4.0]], "history": [10.0], "best": [1, 0] }));
+
+        dashboard.end_run();
+        assert_eq!(body(&request(&dashboard, "GET /state HTTP/1.1\r\n\r\n"))["running"], false);
+    }
+
+    #[test]
+    fn stop_ends_the_run_at_the_next_generation() {
+        let dashboard = Dashboard::new();
+        dashboard.begin_run(&[City::new(0.0, 0.0)]);
+        let response = request(&dashboard, "POST /stop HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"), "{}", response);
+        assert_eq!(dashboard.record_generation(&Path { fitness: 1.0, order: vec![0] }), ControlFlow::Break(()));
+
+        // A new run starts without the old run's stop
+        dashboard.begin_run(&[City::new(0.0, 0.0)]);
+        assert_eq!(dashboard.record_generation(&Path { fitness: 1.0, order: vec![0] }), ControlFlow::Continue(()));
+    }
+
+    #[test]
+    fn start_only_starts_a_run_once_the_last_one_ended() {
+        let dashboard = Dashboard::new();
+        dashboard.begin_run(&[City::new(0.0, 0.0)]);
+        request(&dashboard, "POST /start HTTP/1.1\r\n\r\n");
+        assert!(!dashboard.state.lock().unwrap().start_requested);
+
+        dashboard.end_run();
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| dashboard.wait_for_start());
+            thread::sleep(Duration::from_millis(20));
+            assert!(!waiting.is_finished());
+            let response = request(&dashboard, "POST /start HTTP/1.1\r\n\r\n");
+            assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"), "{}", response);
+            waiting.join().unwrap();
+        });
+        assert!(!dashboard.state.lock().unwrap().start_requested);
+    }
+
+    #[test]
+    fn the_page_is_served_and_anything_else_is_not_found() {
+        let dashboard = Dashboard::new();
+        let response = request(&dashboard, "GET / HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/html"), "{}", response);
+        assert!(response.ends_with(PAGE));
+        for unknown in ["GET /stop HTTP/1.1\r\n\r\n", "POST /state HTTP/1.1\r\n\r\n", "GET /nope HTTP/1.1\r\n\r\n"] {
+            assert!(request(&dashboard, unknown).starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", unknown);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! What becomes of a tour that's already in the population, for `--dedup`. Populations soon
+//! fill up with copies of the fittest tour, each one a wasted place; replacing the copies every
+//! generation keeps the search spread out without relying on the few weak survivors alone.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dedup {
+    /// A random tour takes the copy's place.
+    Random,
+    /// The copy is mutated, so it stays near the tour it copied.
+    Mutate,
+}
+
+impl FromStr for Dedup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Dedup, String> {
+        match s {
+            "random" => Ok(Dedup::Random),
+            "mutate" => Ok(Dedup::Mutate),
+            _ => Err(format!("unknown deduplication {:?}, expected random or mutate", s)),
+        }
+    }
+}
+
+impl fmt::Display for Dedup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Dedup::Random => "random",
+            Dedup::Mutate => "mutate",
+        })
+    }
+}
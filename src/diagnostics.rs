@@ -0,0 +1,222 @@
+//! Input errors rendered with the offending snippet, a label pointing at the problem and a hint.
+
+use std::fs;
+use std::path::Path;
+
+use csv::{DeserializeErrorKind, ErrorKind, Position};
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+use crate::config::{self, Setting};
+use crate::events::Notice;
+use crate::geo::DistanceMatrix;
+use crate::input::{self, City};
+use crate::tsplib::{self, Problem};
+
+#[derive(Debug, Diagnostic, Error)]
+#[error("could not read {name}")]
+#[diagnostic(code(input::unreadable))]
+pub struct UnreadableInput {
+    name: String,
+    #[source]
+    source: std::io::Error,
+}
+
+#[derive(Debug, Diagnostic, Error)]
+#[error("{message}")]
+#[diagnostic(code(input::csv))]
+pub struct CsvError {
+    message: String,
+    #[source_code]
+    src: NamedSource<String>,
+    #[label("{label}")]
+    span: SourceSpan,
+    label: String,
+    #[help]
+    help: Option<String>,
+}
+
+#[derive(Debug, Diagnostic, Error)]
+#[error("invalid TSPLIB file: {message}")]
+#[diagnostic(code(input::tsplib))]
+pub struct TsplibError {
+    message: String,
+    #[source_code]
+    src: NamedSource<String>,
+    #[label("here")]
+    span: SourceSpan,
+    #[help]
+    help: Option<String>,
+}
+
+#[derive(Debug, Diagnostic, Error)]
+#[error("invalid distance matrix {name}: {message}")]
+#[diagnostic(code(input::matrix), help("each line has the costs from one city to every city, as csv"))]
+pub struct MatrixError {
+    name: String,
+    message: String,
+}
+
+#[derive(Debug, Diagnostic, Error)]
+#[error("invalid config file: {message}")]
+#[diagnostic(code(input::config))]
+pub struct ConfigError {
+    message: String,
+    #[source_code]
+    src: NamedSource<String>,
+    #[label("here")]
+    span: SourceSpan,
+}
+
+/// How many skipped rows are listed before the rest are only counted.
+const LISTED_SKIPS: usize = 10;
+
+/// Reads and parses the cities in the csv file at `path`. When `lenient`, rows that can't be
+/// read are skipped instead of failing the whole file, with the warnings about them returned
+/// beside the cities.
+pub fn load_cities(path: &Path, lenient: bool) -> miette::Result<(Vec<City>, Vec<Notice>)> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|source| UnreadableInput { name: name.clone(), source })?;
+    parse_cities(name, source, lenient)
+}
+
+/// Reads and parses the cities in a csv on stdin, as `load_cities` does a file's. Stdin is
+/// read once, so later calls see the same cities.
+pub fn load_stdin_cities(lenient: bool) -> miette::Result<(Vec<City>, Vec<Notice>)> {
+    let source = input::stdin().map_err(|source| UnreadableInput { name: "stdin".to_string(), source })?;
+    parse_cities("stdin".to_string(), source.to_string(), lenient)
+}
+
+fn parse_cities(name: String, source: String, lenient: bool) -> miette::Result<(Vec<City>, Vec<Notice>)> {
+    if !lenient {
+        return match input::read_cities(source.as_bytes()) {
+            Ok(cities) => Ok((cities, Vec::new())),
+            Err(e) => Err(csv_error(&name, source, e).into()),
+        };
+    }
+
+    let (cities, skipped) = input::read_cities_lenient(source.as_bytes());
+    let mut warnings: Vec<Notice> = skipped.iter().take(LISTED_SKIPS).map(|error| {
+        let (pos, _, label, _) = describe(error);
+        Notice::Warning(match pos {
+            Some(pos) => format!("skipping line {} of {}: {}", pos.line(), name, label),
+            None => format!("skipping a row of {}: {}", name, label),
+        })
+    }).collect();
+    if skipped.len() > LISTED_SKIPS {
+        warnings.push(Notice::Warning(format!("skipping {} more rows of {}", skipped.len() - LISTED_SKIPS, name)));
+    }
+    if !skipped.is_empty() {
+        warnings.push(Notice::Warning(format!(
+            "dropped {} of {} rows of {}", skipped.len(), skipped.len() + cities.len(), name
+        )));
+    }
+
+    Ok((cities, warnings))
+}
+
+/// Reads and parses the TSPLIB file at `path`.
+pub fn load_tsplib(path: &Path) -> miette::Result<Problem> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|source| UnreadableInput { name: name.clone(), source })?;
+
+    tsplib::parse(&source).map_err(|e| {
+        let start: usize = source.lines().take(e.line.saturating_sub(1)).map(|line| line.len() + 1).sum();
+        let span = locate(&source, start, None);
+        TsplibError { message: e.message, src: NamedSource::new(name, source), span, help: e.help.map(str::to_string) }.into()
+    })
+}
+
+/// Reads and parses the distance matrix in the csv file at `path`.
+pub fn load_matrix(path: &Path) -> miette::Result<DistanceMatrix> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|source| UnreadableInput { name: name.clone(), source })?;
+
+    input::read_matrix(source.as_bytes()).map_err(|message| MatrixError { name, message }.into())
+}
+
+/// Reads and parses the config file at `path`.
+pub fn load_config(path: &Path) -> miette::Result<Vec<Setting>> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path)
+        .map_err(|source| UnreadableInput { name: name.clone(), source })?;
+
+    config::parse(&source).map_err(|e| {
+        let span = e.span.map_or_else(|| SourceSpan::from(0..0), SourceSpan::from);
+        ConfigError { message: e.message, src: NamedSource::new(name, source), span }.into()
+    })
+}
+
+fn csv_error(name: &str, source: String, error: csv::Error) -> CsvError {
+    let (pos, field, label, help) = describe(&error);
+
+    let header_problem = label.starts_with("missing field");
+    let (line, span) = match &pos {
+        // A missing column is the header's fault, not the first row's
+        Some(_) if header_problem => (1, locate(&source, 0, None)),
+        Some(pos) => (pos.line(), locate(&source, pos.byte() as usize, field)),
+        None => (0, SourceSpan::from(0..0)),
+    };
+
+    CsvError {
+        message: match line {
+            _ if header_problem => "invalid csv header".to_string(),
+            0 => "invalid csv".to_string(),
+            line => format!("invalid city on line {}", line),
+        },
+        src: NamedSource::new(name, source),
+        span,
+        label,
+        help,
+    }
+}
+
+/// Where `error` happened, the field at fault if known, what went wrong and how to fix it.
+fn describe(error: &csv::Error) -> (Option<Position>, Option<u64>, String, Option<String>) {
+    match error.kind() {
+        ErrorKind::Deserialize { pos, err } => {
+            let help = match err.kind() {
+                DeserializeErrorKind::ParseFloat(_) | DeserializeErrorKind::ParseInt(_) => {
+                    Some("coordinates must be plain numbers, e.g. 1.5 or -3".to_string())
+                }
+                DeserializeErrorKind::Message(m) if m.starts_with("missing field") => {
+                    Some("the first line must be a header naming the x and y columns".to_string())
+                }
+                DeserializeErrorKind::Message(m) if m.ends_with("is not a finite coordinate") => {
+                    Some("a city at an infinite or undefined position can't be reached from the others".to_string())
+                }
+                _ => None,
+            };
+            (pos.clone(), err.field(), err.kind().to_string(), help)
+        }
+        ErrorKind::UnequalLengths { pos, expected_len, len } => (
+            pos.clone(),
+            None,
+            format!("{} fields where the header has {}", len, expected_len),
+            Some("every row needs exactly one value per header column".to_string()),
+        ),
+        ErrorKind::Utf8 { pos, .. } => (pos.clone(), None, "not valid UTF-8".to_string(), None),
+        _ => (None, None, error.to_string(), None),
+    }
+}
+
+/// The span of field `field` (or the whole record when unknown) of the record starting at `start`.
+fn locate(source: &str, start: usize, field: Option<u64>) -> SourceSpan {
+    let start = start.min(source.len());
+    let record = source[start..].lines().next().unwrap_or("");
+
+    let mut offset = start;
+    if let Some(field) = field {
+        for (i, value) in record.split(',').enumerate() {
+            if i as u64 == field {
+                return SourceSpan::from(offset..offset + value.len());
+            }
+            offset += value.len() + 1;
+        }
+    }
+
+    SourceSpan::from(start..start + record.len())
+}
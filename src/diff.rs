@@ -0,0 +1,174 @@
+//! Comparison of two tours of the same cities.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path as FilePath;
+
+use crate::geo::Metric;
+use crate::input::City;
+use crate::tour::TourType;
+use crate::{Error, Path};
+
+/// Reads a tour from `path`: either the program's output, whose last `a->b->c` line is the
+/// solution, or city indices separated by commas, spaces or newlines (a JSON array works too).
+pub fn read_tour(path: &FilePath) -> Result<Vec<usize>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+    let tour = match contents.lines().rev().find(|line| line.contains("->")) {
+        Some(line) => line.split("->").map(str::trim).collect::<Vec<&str>>(),
+        None => contents.split(|c: char| c.is_whitespace() || c == ',' || c == '[' || c == ']')
+            .filter(|s| !s.is_empty())
+            .collect(),
+    };
+
+    tour.into_iter()
+        .map(|city| city.parse().map_err(|_| format!("{:?} isn't a city index", city)))
+        .collect()
+}
+
+/// Reads a tour from `path` as [`read_tour`] does, and checks that it visits each of `cities`
+/// cities once.
+pub fn read_tour_of(path: &FilePath, cities: usize) -> Result<Vec<usize>, Error> {
+    let tour = read_tour(path).map_err(|e| Error::Problem(format!("could not read the tour {}: {}", path.display(), e)))?;
+    if !Path::is_tour(&tour, cities) {
+        return Err(Error::Problem(format!("{} isn't a tour of the {} cities", path.display(), cities)));
+    }
+    Ok(tour)
+}
+
+/// Compares the tours at `a` and `b`, both of `cities`, measured with `metric`.
+pub fn diff(a: &FilePath, b: &FilePath, cities: &[City], metric: Metric, tour_type: TourType) -> Result<Comparison, Error> {
+    let (a, b) = (read_tour_of(a, cities.len())?, read_tour_of(b, cities.len())?);
+    Ok(compare(&a, &b, |tour| 1.0 / Path::calculate_fitness(tour, cities, metric, tour_type), tour_type))
+}
+
+/// How tour `b` differs from tour `a`: the difference in length, how many edges they share and
+/// the stretches of each that the other doesn't follow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub length_a: f64,
+    pub length_b: f64,
+    /// Edges of `a` that `b` takes too, and how many `a` has.
+    pub shared: usize,
+    pub edges: usize,
+    pub only_in_a: Vec<Vec<usize>>,
+    pub only_in_b: Vec<Vec<usize>>,
+}
+
+/// Compares tours `a` and `b`, both of the type `tour_type` and measured with `length`.
+pub fn compare(a: &[usize], b: &[usize], length: impl Fn(&[usize]) -> f64, tour_type: TourType) -> Comparison {
+    let (edges_a, edges_b) = (edges(a, tour_type), edges(b, tour_type));
+    Comparison {
+        length_a: length(a),
+        length_b: length(b),
+        shared: edges_a.intersection(&edges_b).count(),
+        edges: edges_a.len(),
+        only_in_a: segments(a, &edges_b, tour_type),
+        only_in_b: segments(b, &edges_a, tour_type),
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = (self.length_a, self.length_b);
+        writeln!(f, "A: {:.3}", a)?;
+        writeln!(f, "B: {:.3}", b)?;
+        writeln!(f, "difference: {:+.3} ({:+.2}%)", b - a, (b / a - 1.0) * 100.0)?;
+        writeln!(f, "shared edges: {} of {} ({:.2}%)", self.shared, self.edges, self.shared as f64 / self.edges as f64 * 100.0)?;
+
+        for (name, segments) in [("A", &self.only_in_a), ("B", &self.only_in_b)] {
+            if segments.is_empty() {
+                continue;
+            }
+
+            writeln!(f, "only in {}:", name)?;
+            for segment in segments {
+                let segment: Vec<String> = segment.iter().map(|city| city.to_string()).collect();
+                writeln!(f, "  {}", segment.join("->"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The legs of `tour`, each as (lower city, higher city) so direction doesn't matter, with the
+/// leg back to the start if it's closed.
+pub fn edges(tour: &[usize], tour_type: TourType) -> HashSet<(usize, usize)> {
+    tour_type.legs(tour).map(|(a, b)| (a.min(b), a.max(b))).collect()
+}
+
+/// The maximal stretches of `tour` made of legs missing from `other`, as the cities they visit.
+/// A stretch of a closed tour may go on past its last city to its first ones.
+pub fn segments(tour: &[usize], other: &HashSet<(usize, usize)>, tour_type: TourType) -> Vec<Vec<usize>> {
+    let mut segments = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+
+    for (a, b) in tour_type.legs(tour) {
+        if other.contains(&(a.min(b), a.max(b))) {
+            if !current.is_empty() {
+                segments.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if current.is_empty() {
+            current.push(a);
+        }
+        current.push(b);
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+
+    // A stretch through the leg back to the start carries on into one from the first city
+    let starts = segments.first().and_then(|segment| segment.first()) == tour.first();
+    let ends = segments.last().and_then(|segment| segment.last()) == tour.first();
+    if tour_type == TourType::Closed && segments.len() > 1 && starts && ends {
+        let first = segments.remove(0);
+        segments.last_mut().expect("more than one stretch").extend_from_slice(&first[1..]);
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_finds_the_stretches_only_one_tour_takes() {
+        let a = [0, 1, 2, 3, 4, 5];
+        let b = [0, 1, 3, 2, 4, 5];
+
+        let open = TourType::Open;
+        assert_eq!(segments(&a, &edges(&b, open), open), vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(segments(&b, &edges(&a, open), open), vec![vec![1, 3], vec![2, 4]]);
+        assert_eq!(segments(&a, &edges(&a, open), open), Vec::<Vec<usize>>::new());
+
+        // A closed tour's stretch can run on through the leg back to the start
+        let closed = TourType::Closed;
+        assert_eq!(segments(&a, &edges(&b, closed), closed), vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(segments(&a, &edges(&[1, 2, 3, 4, 5], open), closed), vec![vec![5, 0, 1]]);
+    }
+
+    #[test]
+    fn tours_read_from_files_are_checked_before_theyre_compared() {
+        let dir = std::env::temp_dir().join(format!("wasi-genetic-diff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b, short) = (dir.join("a"), dir.join("b"), dir.join("short"));
+        fs::write(&a, "0,1,2,3").unwrap();
+        fs::write(&b, "0->2->1->3\n").unwrap();
+        fs::write(&short, "0 1 2").unwrap();
+        let cities = [City::new(0.0, 0.0), City::new(1.0, 0.0), City::new(1.0, 1.0), City::new(0.0, 1.0)];
+
+        let comparison = diff(&a, &b, &cities, Metric::Euclidean, TourType::Closed).unwrap();
+        assert_eq!((comparison.length_a, comparison.shared, comparison.edges), (4.0, 2, 4));
+        assert_eq!(
+            diff(&a, &short, &cities, Metric::Euclidean, TourType::Closed),
+            Err(Error::Problem(format!("{} isn't a tour of the 4 cities", short.display())))
+        );
+        assert!(matches!(read_tour_of(&dir.join("missing"), 4), Err(Error::Problem(message)) if message.starts_with("could not read the tour")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+//! How varied a population still is.
+//!
+//! Every measure is a fraction between 0 and 1. A population that has converged on a single
+//! tour has a unique-tour ratio and a position entropy near 0 and an edge overlap near 1.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::population::{Population, Stored, Width};
+
+// Tours compared pairwise for the edge overlap, spread evenly over the population
+const OVERLAP_SAMPLE: usize = 16;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Diversity {
+    /// Distinct orders over the number of paths.
+    pub unique_ratio: f64,
+    /// Mean share of one tour's edges that another tour also uses, in either direction.
+    pub edge_overlap: f64,
+    /// Mean entropy of the cities found at each position of the orders, normalised so that 1
+    /// is as varied as the population's size allows.
+    pub position_entropy: f64,
+}
+
+/// Measures the diversity of `paths`, tours of `city_count` cities.
+pub fn measure(paths: &Population, city_count: usize) -> Diversity {
+    if paths.is_empty() || city_count == 0 {
+        return Diversity::default();
+    }
+
+    match paths.width() {
+        Width::U16 => measure_as::<u16>(paths, city_count),
+        Width::U32 => measure_as::<u32>(paths, city_count),
+    }
+}
+
+fn measure_as<G: Stored>(paths: &Population, city_count: usize) -> Diversity {
+    Diversity {
+        unique_ratio: unique_ratio_as::<G>(paths),
+        edge_overlap: edge_overlap::<G>(paths, city_count),
+        position_entropy: position_entropy::<G>(paths, city_count),
+    }
+}
+
+/// Distinct orders over the number of `paths`, the cheapest of the measures.
+pub fn unique_ratio(paths: &Population) -> f64 {
+    match paths.width() {
+        Width::U16 => unique_ratio_as::<u16>(paths),
+        Width::U32 => unique_ratio_as::<u32>(paths),
+    }
+}
+
+fn unique_ratio_as<G: Stored>(paths: &Population) -> f64 {
+    let unique: HashSet<&[G]> = paths.iter().map(|(order, _)| order).collect();
+    unique.len() as f64 / paths.len().max(1) as f64
+}
+
+fn edge_overlap<G: Stored>(paths: &Population, city_count: usize) -> f64 {
+    let sample = OVERLAP_SAMPLE.min(paths.len());
+    if sample < 2 {
+        return 1.0;
+    }
+
+    // Each sampled tour's neighbours of every city, to look edges up in constant time
+    let neighbours: Vec<Vec<(usize, usize)>> = (0..sample).map(|i| {
+        let order = paths.order::<G>(i * paths.len() / sample);
+        let mut neighbours = vec![(0, 0); city_count];
+        for (j, &city) in order.iter().enumerate() {
+            neighbours[city.index()] = (order[(j + city_count - 1) % city_count].index(), order[(j + 1) % city_count].index());
+        }
+        neighbours
+    }).collect();
+
+    let mut total = 0.0;
+    let mut pairs = 0;
+    for a in 0..sample {
+        for b in a + 1..sample {
+            let shared = (0..city_count)
+                .filter(|&city| {
+                    let next = neighbours[a][city].1;
+                    neighbours[b][city].0 == next || neighbours[b][city].1 == next
+                })
+                .count();
+            total += shared as f64 / city_count as f64;
+            pairs += 1;
+        }
+    }
+
+    total / pairs as f64
+}
+
+fn position_entropy<G: Stored>(paths: &Population, city_count: usize) -> f64 {
+    // The most different cities a position can hold is the smaller of the two
+    let max_entropy = (paths.len().min(city_count) as f64).ln();
+    if max_entropy == 0.0 {
+        return 0.0;
+    }
+
+    let mut counts = vec![0usize; city_count];
+    let mut total = 0.0;
+    for position in 0..city_count {
+        for (order, _) in paths.iter::<G>() {
+            counts[order[position].index()] += 1;
+        }
+
+        let mut entropy = 0.0;
+        for (order, _) in paths.iter::<G>() {
+            let count = std::mem::take(&mut counts[order[position].index()]);
+            if count > 0 {
+                let share = count as f64 / paths.len() as f64;
+                entropy -= share * share.ln();
+            }
+        }
+        total += entropy / max_entropy;
+    }
+
+    total / city_count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::population::Population;
+    use crate::rng::{RngAlgorithm, SeededRng};
+
+    #[test]
+    fn diversity_tells_a_converged_population_from_a_random_one() {
+        let mut converged = Population::new(10);
+        let mut random = Population::new(10);
+        for i in 0..20 {
+            let mut order: Vec<usize> = (0..10).collect();
+            converged.push(&order, 1.0);
+            order.shuffle(&mut SeededRng::new(RngAlgorithm::ChaCha, i));
+            random.push(&order, 1.0);
+        }
+
+        let converged = measure(&converged, 10);
+        assert_eq!(converged, Diversity { unique_ratio: 0.05, edge_overlap: 1.0, position_entropy: 0.0 });
+
+        let random = measure(&random, 10);
+        assert_eq!(random.unique_ratio, 1.0);
+        assert!(random.edge_overlap < 0.5, "{:?}", random);
+        assert!(random.position_entropy > 0.7, "{:?}", random);
+    }
+}
@@ -0,0 +1,99 @@
+//! Snapshots of the whole population, for analysing how it evolves offline. Each is a JSON file:
+//!
+//! ```text
+//! {"generation":500,"cities":52,"population":[{"order":[0,21,...],"fitness":0.000123},...]}
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde_json::json;
+
+use crate::Path;
+
+/// Where in a file name the generation goes.
+const GENERATION: &str = "{gen}";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PopulationDumps {
+    /// Generations between snapshots.
+    pub every: usize,
+    /// The file to write to, with `{gen}` standing for the generation.
+    template: String,
+}
+
+impl PopulationDumps {
+    /// Whether to take a snapshot after `generation` generations.
+    pub fn due(&self, generation: usize) -> bool {
+        generation.is_multiple_of(self.every)
+    }
+
+    /// The file the snapshot after `generation` generations goes in.
+    pub fn path(&self, generation: usize) -> PathBuf {
+        PathBuf::from(self.template.replace(GENERATION, &generation.to_string()))
+    }
+}
+
+impl fmt::Display for PopulationDumps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "every={}:{}", self.every, self.template)
+    }
+}
+
+impl FromStr for PopulationDumps {
+    type Err = String;
+
+    /// Parses `every=<generations>:<file>`, e.g. `every=500:pop_{gen}.json`.
+    fn from_str(s: &str) -> Result<PopulationDumps, String> {
+        let (every, template) = s.strip_prefix("every=")
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(_, template)| !template.is_empty())
+            .ok_or_else(|| format!("{:?} isn't every=<generations>:<file>, e.g. every=500:pop_{{gen}}.json", s))?;
+        let every = every.parse().ok()
+            .filter(|&every| every > 0)
+            .ok_or_else(|| format!("{:?} isn't a positive number of generations", every))?;
+
+        Ok(PopulationDumps { every, template: template.to_string() })
+    }
+}
+
+/// Writes the snapshot of `paths`, tours of `cities` cities, after `generation` generations.
+pub fn write(path: &std::path::Path, generation: usize, cities: usize, paths: &[Path]) -> std::io::Result<()> {
+    let population: Vec<_> = paths.iter().map(|p| json!({ "order": p.order, "fitness": p.fitness })).collect();
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &json!({ "generation": generation, "cities": cities, "population": population }))?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::testing::{simulation, twelve_cities};
+
+    #[test]
+    fn population_dumps_hold_every_path_at_their_interval() {
+        let dir = std::env::temp_dir().join(format!("wasi-genetic-dumps-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut sim = simulation(twelve_cities(), 40);
+        sim.set_population_dumps(format!("every=15:{}/pop_{{gen}}.json", dir.display()).parse().unwrap());
+        sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let mut dumps: Vec<String> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        dumps.sort();
+        assert_eq!(dumps, vec!["pop_15.json", "pop_30.json"]);
+
+        let dump: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("pop_30.json")).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(dump["generation"], 30);
+        assert_eq!(dump["population"].as_array().unwrap().len(), 30);
+        assert!("every=0:pop.json".parse::<PopulationDumps>().is_err());
+    }
+}
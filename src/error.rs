@@ -0,0 +1,33 @@
+//! What can go wrong setting up or running a simulation. Files that can't be read have errors of their own,
+//! rendered with the offending snippet, in `diagnostics`.
+
+use thiserror::Error;
+
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum Error {
+    /// A GA parameter no run can use.
+    #[error("invalid {name}: {reason}")]
+    Parameter { name: &'static str, reason: String },
+    /// Cities, distances, a prefix, required edges or a checkpoint that don't fit the problem.
+    #[error("{0}")]
+    Problem(String),
+    /// The threads to score and breed paths on couldn't be started.
+    #[error("could not start {count} threads: {reason}")]
+    Threads { count: usize, reason: String },
+    /// The fitness command or script couldn't score the paths it was given.
+    #[error("fitness evaluation failed: {0}")]
+    Evaluation(String),
+    /// The population spilled to disk couldn't be read back or written.
+    #[error("could not {action} the spilled population: {reason}")]
+    Spill { action: &'static str, reason: String },
+}
+
+impl Error {
+    pub(crate) fn spill_read(e: std::io::Error) -> Error {
+        Error::Spill { action: "read", reason: e.to_string() }
+    }
+
+    pub(crate) fn spill_write(e: std::io::Error) -> Error {
+        Error::Spill { action: "write", reason: e.to_string() }
+    }
+}
@@ -0,0 +1,147 @@
+//! Estimates of how much longer a run is worth continuing, from how fast its best tour has been
+//! improving.
+//!
+//! The run so far is split into windows of a quarter of its duration. The improvement in the
+//! latest window gives the current rate, for the time left to a target length. Comparing it with
+//! the window before gives how fast improvement is slowing down: assuming it keeps slowing at
+//! that pace, each window improving on the last by the same factor, the improvements left add up
+//! to a geometric series, which says when the run will stall and how short its tour will be by
+//! then.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+
+// A window improving the tour by less than this fraction of its length counts as stalled
+const STALLED: f64 = 1e-4;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Estimate {
+    /// Too early, or improvement isn't slowing down yet, to say anything.
+    Unknown,
+    /// Improvement has practically stopped.
+    Stalled,
+    /// Improvement should practically stop after `seconds`, with a tour of about `length`.
+    Stall { seconds: f64, length: f64 },
+    /// The target length is reached, or should be after `seconds` at the current rate.
+    Target { seconds: f64 },
+    /// The target length won't be reached at the current rate.
+    Unreachable,
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Estimate::Unknown => write!(f, "no estimate yet"),
+            Estimate::Stalled => write!(f, "stalled"),
+            Estimate::Stall { seconds, length } => {
+                write!(f, "stalls in about {:.0}s at about {:.3}", seconds, length)
+            }
+            Estimate::Target { seconds } if *seconds == 0.0 => write!(f, "target reached"),
+            Estimate::Target { seconds } => write!(f, "target in about {:.0}s", seconds),
+            Estimate::Unreachable => write!(f, "target out of reach at the current rate"),
+        }
+    }
+}
+
+/// The best length over the course of a run.
+pub struct Eta {
+    target: Option<f64>,
+    /// Seconds into the run and the best length from then on, one for each improvement.
+    improvements: Vec<(f64, f64)>,
+}
+
+impl Eta {
+    /// Estimates the time to stall, or with a `target`, the time to a tour that short.
+    pub fn new(target: Option<f64>) -> Eta {
+        Eta { target, improvements: Vec::new() }
+    }
+
+    /// Forgets the lengths of earlier runs.
+    pub fn reset(&mut self) {
+        self.improvements.clear();
+    }
+
+    /// Records the best length `elapsed` into the run.
+    pub fn record(&mut self, elapsed: Duration, length: f64) {
+        if self.improvements.last().is_none_or(|&(_, best)| length < best) {
+            self.improvements.push((elapsed.as_secs_f64(), length));
+        }
+    }
+
+    pub fn estimate(&self, elapsed: Duration) -> Estimate {
+        let now = elapsed.as_secs_f64();
+        let window = now / 4.0;
+        let (first, latest) = match (self.improvements.first(), self.improvements.last()) {
+            (Some(&(first, _)), Some(&(_, latest))) if window > 0.0 && now - 2.0 * window >= first => (first, latest),
+            _ => return self.target.map_or(Estimate::Unknown, |target| self.target_estimate(target, None)),
+        };
+        debug_assert!(first <= now);
+
+        let recent = self.length_at(now - window) - latest;
+        let before = self.length_at(now - 2.0 * window) - self.length_at(now - window);
+
+        if let Some(target) = self.target {
+            return self.target_estimate(target, Some(recent / window));
+        }
+        if recent < STALLED * latest {
+            return Estimate::Stalled;
+        }
+        if recent >= before {
+            return Estimate::Unknown;
+        }
+
+        let slowdown = recent / before;
+        let windows = (STALLED * latest / recent).ln() / slowdown.ln();
+        Estimate::Stall {
+            seconds: windows.max(0.0) * window,
+            length: latest - recent * slowdown / (1.0 - slowdown),
+        }
+    }
+
+    fn target_estimate(&self, target: f64, rate: Option<f64>) -> Estimate {
+        match (self.improvements.last(), rate) {
+            (Some(&(_, latest)), _) if latest <= target => Estimate::Target { seconds: 0.0 },
+            (Some(&(_, latest)), Some(rate)) if rate > 0.0 => Estimate::Target { seconds: (latest - target) / rate },
+            (_, Some(_)) => Estimate::Unreachable,
+            _ => Estimate::Unknown,
+        }
+    }
+
+    /// The best length `seconds` into the run.
+    fn length_at(&self, seconds: f64) -> f64 {
+        let after = self.improvements.partition_point(|&(at, _)| at <= seconds);
+        self.improvements[after.saturating_sub(1)].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_projects_where_slowing_improvement_stalls() {
+        let length = |seconds: f64| 100.0 + 100.0 * 0.5f64.powf(seconds);
+        let at = |tenths: u32| Duration::from_secs_f64(tenths as f64 / 10.0);
+
+        let mut eta = Eta::new(None);
+        for tenths in 0..=80 {
+            eta.record(at(tenths), length(tenths as f64 / 10.0));
+        }
+        match eta.estimate(at(80)) {
+            Estimate::Stall { seconds, length } => {
+                assert!((length - 100.0).abs() < 1e-6, "{}", length);
+                assert!(seconds > 0.0, "{}", seconds);
+            }
+            estimate => panic!("{:?}", estimate),
+        }
+
+        let mut eta = Eta::new(Some(100.2));
+        for tenths in 0..=80 {
+            eta.record(at(tenths), length(tenths as f64 / 10.0));
+        }
+        assert!(matches!(eta.estimate(at(80)), Estimate::Target { seconds } if seconds > 0.0));
+    }
+}
@@ -0,0 +1,213 @@
+//! Checking a tour that may come from anywhere, e.g. another solver, for `evaluate`: whether it
+//! visits every city once, and what it doesn't if it doesn't.
+
+use std::fmt;
+use std::path::{Path as FilePath, PathBuf};
+
+use crate::builtin::Instance;
+use crate::diff::{self, Comparison};
+use crate::geo::Metric;
+use crate::input::City;
+use crate::optimum;
+use crate::tour::TourType;
+use crate::{Error, Path};
+
+/// What keeps a sequence of city indices from being a tour of some cities.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Check {
+    /// Cities the tour never visits.
+    pub missing: Vec<usize>,
+    /// Cities the tour visits more than once, with how many times.
+    pub repeated: Vec<(usize, usize)>,
+    /// Indices in the tour that aren't cities.
+    pub unknown: Vec<usize>,
+}
+
+impl Check {
+    /// Checks `tour` against `cities` cities.
+    pub fn new(tour: &[usize], cities: usize) -> Check {
+        let mut visits = vec![0; cities];
+        let mut check = Check::default();
+        for &city in tour {
+            match visits.get_mut(city) {
+                Some(count) => *count += 1,
+                None => check.unknown.push(city),
+            }
+        }
+
+        for (city, &count) in visits.iter().enumerate() {
+            match count {
+                0 => check.missing.push(city),
+                1 => {}
+                _ => check.repeated.push((city, count)),
+            }
+        }
+        check
+    }
+
+    /// Whether it's a tour: every city once and nothing else.
+    pub fn is_tour(&self) -> bool {
+        self.missing.is_empty() && self.repeated.is_empty() && self.unknown.is_empty()
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |cities: &mut dyn Iterator<Item = String>| cities.collect::<Vec<String>>().join(", ");
+        if self.is_tour() {
+            return writeln!(f, "every city is visited once");
+        }
+        if !self.missing.is_empty() {
+            writeln!(f, "missing cities: {}", list(&mut self.missing.iter().map(usize::to_string)))?;
+        }
+        if !self.repeated.is_empty() {
+            let repeated = list(&mut self.repeated.iter().map(|(city, count)| format!("{} ({} times)", city, count)));
+            writeln!(f, "repeated cities: {}", repeated)?;
+        }
+        if !self.unknown.is_empty() {
+            writeln!(f, "not cities: {}", list(&mut self.unknown.iter().map(usize::to_string)))?;
+        }
+        Ok(())
+    }
+}
+
+/// What `evaluate` finds of a tour: whether it is one and, if everything it visits is a city,
+/// its length and how far that is from the optimum.
+#[derive(Debug, PartialEq)]
+pub struct Evaluation {
+    pub check: Check,
+    /// The length of the tour as given, unless it visits something that isn't a city.
+    pub length: Option<f64>,
+    /// The optimum to measure the tour against, if it's a tour and the optimum is known.
+    pub optimum: Option<f64>,
+}
+
+impl Evaluation {
+    /// How much longer than the optimum the tour is, as a share of it.
+    pub fn gap(&self) -> Option<f64> {
+        Some(self.length? / self.optimum? - 1.0)
+    }
+}
+
+/// Checks `tour` against `cities` cities and measures it with `length`, comparing it with
+/// `optimum` if it's a tour.
+pub fn evaluate(tour: &[usize], cities: usize, length: impl Fn(&[usize]) -> f64, optimum: Option<f64>) -> Evaluation {
+    let check = Check::new(tour, cities);
+    let length = if check.unknown.is_empty() { Some(length(tour)) } else { None };
+    let optimum = optimum.filter(|optimum| check.is_tour() && optimum.is_finite() && *optimum > 0.0);
+    Evaluation { check, length, optimum }
+}
+
+/// A tour checked by [`evaluate_file`] and, if it was given another tour to compare it with, how
+/// they differ.
+#[derive(Debug, PartialEq)]
+pub struct Evaluated {
+    pub evaluation: Evaluation,
+    pub against: Option<(PathBuf, Comparison)>,
+}
+
+/// Checks the tour at `path` against the `cities` of `instance`, measured as the instance says
+/// or with `metric`, and compares it with `optimum` or the instance's known one. If it is a
+/// tour, compares it with the one at `against` too.
+pub fn evaluate_file(
+    path: &FilePath,
+    against: Option<&FilePath>,
+    instance: &Instance,
+    cities: &[City],
+    metric: Metric,
+    tour_type: TourType,
+    optimum: Option<f64>,
+) -> Result<Evaluated, Error> {
+    let order = diff::read_tour(path).map_err(|e| Error::Problem(format!("could not read the tour {}: {}", path.display(), e)))?;
+    let distances = instance.distances();
+    let length = |tour: &[usize]| 1.0 / match &distances {
+        Some(distances) => Path::matrix_fitness(tour, distances, tour_type),
+        None => Path::calculate_fitness(tour, cities, metric, tour_type),
+    };
+
+    // The known optima are of closed tours measured as the instance says
+    let known = optimum::known(&instance.name(), cities.len(), metric)
+        .filter(|_| tour_type == TourType::Closed && distances.is_none());
+    let evaluation = evaluate(&order, cities.len(), length, optimum.or(known));
+    let against = match against {
+        Some(other) if evaluation.check.is_tour() => {
+            let other_order = diff::read_tour_of(other, cities.len())?;
+            Some((other.to_path_buf(), diff::compare(&other_order, &order, length, tour_type)))
+        }
+        _ => None,
+    };
+    Ok(Evaluated { evaluation, against })
+}
+
+impl fmt::Display for Evaluated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.evaluation)?;
+        if let Some((path, comparison)) = &self.against {
+            writeln!(f, "against {}:", path.display())?;
+            write!(f, "{}", comparison)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.check)?;
+        match self.length {
+            Some(length) if self.check.is_tour() => writeln!(f, "length: {:.3}", length)?,
+            Some(length) => writeln!(f, "length as given: {:.3}", length)?,
+            None => {}
+        }
+        if let (Some(gap), Some(optimum)) = (self.gap(), self.optimum) {
+            writeln!(f, "gap to optimum: {:+.2}% (optimum {})", gap * 100.0, optimum)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluation_names_what_keeps_a_sequence_from_being_a_tour() {
+        assert!(Check::new(&[2, 0, 3, 1], 4).is_tour());
+
+        let check = Check::new(&[2, 0, 2, 7, 2], 4);
+        assert_eq!((check.missing.clone(), check.repeated.clone(), check.unknown.clone()), (vec![1, 3], vec![(2, 3)], vec![7]));
+        assert!(!check.is_tour());
+        assert_eq!(check.to_string(), "missing cities: 1, 3\nrepeated cities: 2 (3 times)\nnot cities: 7\n");
+
+        let evaluation = evaluate(&[2, 0, 2, 7, 2], 4, |_| unreachable!("it can't be measured"), Some(10.0));
+        assert_eq!((evaluation.length, evaluation.optimum), (None, None));
+        let evaluation = evaluate(&[2, 0, 3, 1], 4, |tour| tour.len() as f64 * 3.0, Some(10.0));
+        assert_eq!(evaluation.to_string(), "every city is visited once\nlength: 12.000\ngap to optimum: +20.00% (optimum 10)\n");
+        assert_eq!(evaluate(&[2, 0, 3], 4, |_| 12.0, Some(10.0)).to_string(), "missing cities: 1\nlength as given: 12.000\n");
+    }
+
+    #[test]
+    fn a_tour_file_is_evaluated_and_compared_with_another_only_if_its_a_tour() {
+        let dir = std::env::temp_dir().join(format!("wasi-genetic-evaluate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (tour, other, short) = (dir.join("tour"), dir.join("other"), dir.join("short"));
+        std::fs::write(&tour, "0,2,1,3").unwrap();
+        std::fs::write(&other, "0,1,2,3").unwrap();
+        std::fs::write(&short, "0,1,2").unwrap();
+        let cities = [City::new(0.0, 0.0), City::new(1.0, 0.0), City::new(1.0, 1.0), City::new(0.0, 1.0)];
+        let instance = Instance::File(dir.join("square.csv"));
+        let evaluate = |path: &FilePath, against: Option<&FilePath>| {
+            evaluate_file(path, against, &instance, &cities, Metric::Euclidean, TourType::Closed, Some(4.0))
+        };
+
+        let evaluated = evaluate(&tour, Some(&other)).unwrap();
+        assert_eq!(evaluated.evaluation.optimum, Some(4.0));
+        let (path, comparison) = evaluated.against.as_ref().expect("a comparison");
+        assert_eq!((path, comparison.length_a), (&other, 4.0));
+        assert!(evaluated.to_string().contains(&format!("against {}:\nA: 4.000\n", other.display())));
+
+        let evaluated = evaluate(&short, Some(&other)).unwrap();
+        assert_eq!((evaluated.evaluation.check.missing.clone(), evaluated.against), (vec![3], None));
+        assert!(matches!(evaluate(&tour, Some(&short)), Err(Error::Problem(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! Fitness evaluation other than the built-in tour length.
+//!
+//! `FitnessCommand` delegates to an external process: candidate orders are written to the child's
+//! stdin as JSON lines (one array of city indices per line) and the child answers each with a
+//! line holding its fitness, higher being better. Orders go out in batches so the child can be a
+//! simple read-a-line, write-a-line loop.
+//!
+//! A tour the child doesn't answer within the timeout gets the worst possible fitness, and the
+//! child is restarted, since it may still answer later and fall out of step with the requests.
+
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use crate::metrics::METRICS;
+
+/// The fitness given to tours whose evaluation timed out.
+pub const WORST_FITNESS: f64 = 0.0;
+
+/// Scores batches of orders; higher fitness is better. Evaluators travel with the `Simulation`
+/// using them, so they have to be `Send` and `Sync` too.
+pub trait Evaluator: Send + Sync {
+    fn evaluate(&mut self, orders: &[&[usize]]) -> Result<Vec<f64>, Box<dyn Error>>;
+}
+
+pub struct FitnessCommand {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    // Only ever used through &mut self; the mutex just makes the command Sync
+    lines: Mutex<Receiver<std::io::Result<String>>>,
+    batch_size: usize,
+    timeout: Duration,
+}
+
+impl FitnessCommand {
+    /// Starts `command` through the shell, sending at most `batch_size` orders at a time and
+    /// waiting up to `timeout` for the answer to each.
+    pub fn spawn(command: &str, batch_size: usize, timeout: Duration) -> std::io::Result<FitnessCommand> {
+        let (child, stdin, lines) = FitnessCommand::start(command)?;
+
+        Ok(FitnessCommand {
+            command: command.to_string(),
+            child,
+            stdin,
+            lines: Mutex::new(lines),
+            batch_size: batch_size.max(1),
+            timeout,
+        })
+    }
+
+    fn start(command: &str) -> std::io::Result<(Child, ChildStdin, Receiver<std::io::Result<String>>)> {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command).stdin(Stdio::piped()).stdout(Stdio::piped());
+        // A group of its own lets `stop` reach whatever the shell starts, not just the shell
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+        let mut child = shell.spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // Pipes can't time out, so the replies are read on their own thread
+        let (sender, lines) = channel();
+        thread::Builder::new().name("fitness-cmd".to_string()).spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        })?;
+
+        Ok((child, stdin, lines))
+    }
+
+    fn stop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+
+    fn restart(&mut self) -> std::io::Result<()> {
+        self.stop();
+
+        let (child, stdin, lines) = FitnessCommand::start(&self.command)?;
+        self.child = child;
+        self.stdin = stdin;
+        self.lines = Mutex::new(lines);
+
+        Ok(())
+    }
+
+    fn evaluate_batches(&mut self, orders: &[&[usize]]) -> std::io::Result<Vec<f64>> {
+        let mut fitness = Vec::with_capacity(orders.len());
+
+        'batches: while fitness.len() < orders.len() {
+            let batch = &orders[fitness.len()..(fitness.len() + self.batch_size).min(orders.len())];
+
+            let mut request = String::new();
+            for order in batch {
+                let order: Vec<String> = order.iter().map(|o| o.to_string()).collect();
+                request.push_str(&format!("[{}]\n", order.join(",")));
+            }
+            self.stdin.write_all(request.as_bytes())?;
+            self.stdin.flush()?;
+
+            for _ in batch {
+                let lines = self.lines.get_mut().unwrap_or_else(PoisonError::into_inner);
+                let line = match lines.recv_timeout(self.timeout) {
+                    Ok(line) => line?,
+                    Err(RecvTimeoutError::Timeout) => {
+                        METRICS.record_timeout();
+                        fitness.push(WORST_FITNESS);
+                        // The rest of the batch goes to a fresh child
+                        self.restart()?;
+                        continue 'batches;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "fitness command exited"));
+                    }
+                };
+
+                let value = line.trim().parse().map_err(|_| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("expected a number, got {:?}", line))
+                })?;
+                fitness.push(value);
+            }
+        }
+
+        Ok(fitness)
+    }
+}
+
+impl Evaluator for FitnessCommand {
+    fn evaluate(&mut self, orders: &[&[usize]]) -> Result<Vec<f64>, Box<dyn Error>> {
+        Ok(self.evaluate_batches(orders)?)
+    }
+}
+
+impl Drop for FitnessCommand {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::ops::ControlFlow;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::testing::{simulation, twelve_cities};
+
+    /// Starts `command` with batches of two and a timeout of `millis`.
+    fn command(command: &str, millis: u64) -> FitnessCommand {
+        FitnessCommand::spawn(command, 2, Duration::from_millis(millis)).unwrap()
+    }
+
+    #[test]
+    fn each_order_goes_out_as_a_json_line_and_comes_back_as_a_number() {
+        // Answers with the length of the line it was sent
+        let mut lengths = command(r#"while read -r line; do echo "${#line}"; done"#, 5000);
+        let orders: [&[usize]; 3] = [&[0, 1, 2], &[10, 2], &[3]];
+        assert_eq!(lengths.evaluate(&orders).unwrap(), [7.0, 6.0, 3.0]);
+        assert_eq!(lengths.evaluate(&orders[..1]).unwrap(), [7.0]);
+    }
+
+    #[test]
+    fn a_reply_that_isnt_a_number_is_an_error() {
+        let mut evaluator = command("while read -r line; do echo short; done", 5000);
+        let e = evaluator.evaluate(&[&[0, 1]]).unwrap_err();
+        assert_eq!(e.to_string(), r#"expected a number, got "short""#);
+    }
+
+    #[test]
+    fn a_command_that_exits_is_an_error() {
+        let mut evaluator = command("read -r line; exit 0", 5000);
+        assert!(evaluator.evaluate(&[&[0, 1], &[1, 0]]).is_err());
+    }
+
+    #[test]
+    fn an_order_unanswered_in_time_gets_the_worst_fitness_and_a_fresh_child() {
+        // The first child never answers; the one started in its place answers everything
+        let marker = std::env::temp_dir().join(format!("wasi-genetic-evaluator-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let script = format!(
+            r#"if [ -e "{0}" ]; then while read -r line; do echo 1; done; else touch "{0}"; sleep 30; fi"#,
+            marker.display()
+        );
+        let mut evaluator = command(&script, 200);
+
+        let timeouts = METRICS.timeouts();
+        let started = Instant::now();
+        assert_eq!(evaluator.evaluate(&[&[0, 1], &[1, 0], &[0, 1]]).unwrap(), [WORST_FITNESS, 1.0, 1.0]);
+        assert!(started.elapsed() < Duration::from_secs(10), "the child that timed out was waited for");
+        assert!(METRICS.timeouts() > timeouts);
+        std::fs::remove_file(&marker).unwrap();
+    }
+
+    #[test]
+    fn a_failed_evaluation_ends_the_run_with_an_error() {
+        let mut sim = simulation(twelve_cities(), 10);
+        sim.set_evaluator(Box::new(command("exit 1", 5000))).unwrap();
+        assert!(matches!(sim.solve(|_| ControlFlow::Continue(())), Err(crate::Error::Evaluation(_))));
+    }
+}
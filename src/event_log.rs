@@ -0,0 +1,109 @@
+//! A JSON Lines log of a run's notable events, one object per line, for tools like jq.
+//!
+//! Every line has a `type` (`generation`, `new_best`, `stagnating`, `checkpoint`,
+//! `termination`, ...), the `time` it was written, in seconds since the Unix epoch, and the
+//! `generation` it happened in.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path as FilePath, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::events::Notice;
+
+pub struct EventLog {
+    path: PathBuf,
+    file: Option<File>,
+    /// The warning that the log stopped, until it's taken.
+    stopped: Option<Notice>,
+}
+
+impl EventLog {
+    /// Opens `path` to append events to, creating it if needed.
+    pub fn open(path: &FilePath) -> std::io::Result<EventLog> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(EventLog { path: path.to_path_buf(), file: Some(file), stopped: None })
+    }
+
+    /// A warning that writing to the log failed and nothing more will be logged, the first time
+    /// it's asked for after that happens.
+    pub fn take_warning(&mut self) -> Option<Notice> {
+        self.stopped.take()
+    }
+
+    /// Appends an event of `kind` in `generation` with the fields of `fields`, which must
+    /// serialise to an object. A `kind` field in `fields` is taken as the event's type instead.
+    /// After a write fails the log stops, leaving a warning for `take_warning`.
+    pub fn write<T: Serialize>(&mut self, kind: &str, generation: usize, fields: &T) {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => return,
+        };
+
+        let mut event = Map::new();
+        event.insert("type".to_string(), json!(kind));
+        event.insert("time".to_string(), json!(now()));
+        event.insert("generation".to_string(), json!(generation));
+        if let Ok(Value::Object(fields)) = serde_json::to_value(fields) {
+            for (key, value) in fields {
+                match key.as_str() {
+                    "kind" => event.insert("type".to_string(), value),
+                    _ => event.insert(key, value),
+                };
+            }
+        }
+
+        let mut line = Value::Object(event).to_string();
+        line.push('\n');
+        // One write per line, so a reader following the file never sees half an event
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            self.stopped = Some(Notice::Warning(format!(
+                "could not write to {}, no more events will be logged: {}", self.path.display(), e
+            )));
+            self.file = None;
+        }
+    }
+}
+
+fn now() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::testing::{simulation, twelve_cities};
+
+    #[test]
+    fn the_event_log_has_a_line_per_generation_and_ends_with_the_termination() {
+        let path = std::env::temp_dir().join(format!("wasi-genetic-test-{}.jsonl", std::process::id()));
+        let mut sim = simulation(twelve_cities(), 40);
+        sim.set_event_log(EventLog::open(&path).unwrap());
+        sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let events: Vec<serde_json::Value> = std::fs::read_to_string(&path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.iter().filter(|e| e["type"] == "generation").count(), 40);
+        assert_eq!(events.last().unwrap()["type"], "termination");
+        assert_eq!(events.last().unwrap()["generation"], 40);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn a_log_that_cant_be_written_stops_with_a_warning() {
+        let mut log = EventLog::open(FilePath::new("/dev/full")).unwrap();
+        log.write("generation", 1, &json!({}));
+        assert!(matches!(log.take_warning(), Some(Notice::Warning(_))));
+        log.write("generation", 2, &json!({}));
+        assert_eq!(log.take_warning(), None);
+    }
+}
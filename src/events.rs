@@ -0,0 +1,149 @@
+//! Detection of a run stagnating or losing its diversity, and of it recovering, along with
+//! other notable things that happen to a run.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::diversity::Diversity;
+
+// How far above the floor diversity has to climb back to count as recovered, so a population
+// hovering around the floor doesn't report a collapse every other generation
+const RECOVERY_MARGIN: f64 = 1.5;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    /// The best path hasn't improved for `generations` generations.
+    Stagnating { generations: usize },
+    /// The best path improved again after `generations` generations without.
+    Improving { generations: usize },
+    /// The position entropy fell below the floor.
+    DiversityCollapsed { position_entropy: f64 },
+    /// The position entropy climbed back well above the floor.
+    DiversityRecovered { position_entropy: f64 },
+    /// A tour dropped in as a hint joined the population.
+    HintTaken { file: String },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Stagnating { generations } => write!(f, "stagnating, no improvement for {} generations", generations),
+            Event::Improving { generations } => write!(f, "improving again after {} generations without", generations),
+            Event::DiversityCollapsed { position_entropy } => {
+                write!(f, "diversity collapsed, position entropy is down to {:.3}", position_entropy)
+            }
+            Event::DiversityRecovered { position_entropy } => {
+                write!(f, "diversity recovered, position entropy is back up to {:.3}", position_entropy)
+            }
+            Event::HintTaken { file } => write!(f, "took the hint {}", file),
+        }
+    }
+}
+
+/// Something a run says about itself as it goes that isn't a result, for `on_notice` to pass
+/// on to whoever is watching: the library prints nothing itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Notice {
+    /// Something the run carries on past without, such as a checkpoint it couldn't write.
+    Warning(String),
+    /// How the run is going, such as the answer to a REPL command.
+    Info(String),
+}
+
+impl fmt::Display for Notice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Notice::Warning(message) => write!(f, "warning: {}", message),
+            Notice::Info(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Watches a run generation by generation and reports the events above as they happen.
+pub struct Watch {
+    stagnation: usize,
+    diversity_floor: Option<f64>,
+    unimproved: usize,
+    collapsed: bool,
+}
+
+impl Watch {
+    /// Reports stagnation after `stagnation` generations without improvement and, if given a
+    /// floor, a collapse when the position entropy falls below it.
+    pub fn new(stagnation: usize, diversity_floor: Option<f64>) -> Watch {
+        Watch { stagnation: stagnation.max(1), diversity_floor, unimproved: 0, collapsed: false }
+    }
+
+    pub fn diversity_floor(&self) -> Option<f64> {
+        self.diversity_floor
+    }
+
+    /// Forgets what happened in earlier runs.
+    pub fn reset(&mut self) {
+        self.unimproved = 0;
+        self.collapsed = false;
+    }
+
+    /// Generations since the best path last improved.
+    pub fn unimproved(&self) -> usize {
+        self.unimproved
+    }
+
+    /// The events a generation caused, given whether it improved on the best path and, if a
+    /// floor is set, the population's diversity.
+    pub fn observe(&mut self, improved: bool, diversity: Option<Diversity>) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if improved {
+            if self.unimproved >= self.stagnation {
+                events.push(Event::Improving { generations: self.unimproved });
+            }
+            self.unimproved = 0;
+        } else {
+            self.unimproved += 1;
+            if self.unimproved == self.stagnation {
+                events.push(Event::Stagnating { generations: self.unimproved });
+            }
+        }
+
+        if let (Some(floor), Some(diversity)) = (self.diversity_floor, diversity) {
+            let position_entropy = diversity.position_entropy;
+            if !self.collapsed && position_entropy < floor {
+                self.collapsed = true;
+                events.push(Event::DiversityCollapsed { position_entropy });
+            } else if self.collapsed && position_entropy >= floor * RECOVERY_MARGIN {
+                self.collapsed = false;
+                events.push(Event::DiversityRecovered { position_entropy });
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stagnation_and_collapse_are_reported_once_until_they_end() {
+        let mut watch = Watch::new(3, Some(0.1));
+        let entropy = |position_entropy| Some(Diversity { position_entropy, ..Diversity::default() });
+
+        let events: Vec<Vec<Event>> = [(true, 0.5), (false, 0.05), (false, 0.05), (false, 0.12), (false, 0.2), (true, 0.2)]
+            .iter()
+            .map(|&(improved, position_entropy)| watch.observe(improved, entropy(position_entropy)))
+            .collect();
+
+        assert_eq!(events, vec![
+            vec![],
+            vec![Event::DiversityCollapsed { position_entropy: 0.05 }],
+            vec![],
+            vec![Event::Stagnating { generations: 3 }],
+            vec![Event::DiversityRecovered { position_entropy: 0.2 }],
+            vec![Event::Improving { generations: 4 }],
+        ]);
+    }
+}
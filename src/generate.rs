@@ -0,0 +1,138 @@
+//! Synthetic instances, for `generate`: cities scattered over a square, uniformly or in
+//! clusters, or laid out on a circle or a grid. The shortest tour of a circle goes round it, so
+//! its length is known and a run on one shows how far from the optimum the solver ends up.
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::input::City;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Anywhere in the square, with the same chance.
+    Uniform,
+    /// Around centres anywhere in the square, normally distributed.
+    Clustered,
+    /// Evenly spaced on the circle that fits in the square.
+    Circle,
+    /// On a square grid filling the square row by row, the last row partly if the cities don't
+    /// make up a whole one.
+    Grid,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Layout, String> {
+        match s {
+            "uniform" => Ok(Layout::Uniform),
+            "clustered" => Ok(Layout::Clustered),
+            "circle" => Ok(Layout::Circle),
+            "grid" => Ok(Layout::Grid),
+            _ => Err(format!("unknown layout {:?}, expected uniform, clustered, circle or grid", s)),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Layout::Uniform => "uniform",
+            Layout::Clustered => "clustered",
+            Layout::Circle => "circle",
+            Layout::Grid => "grid",
+        })
+    }
+}
+
+/// `count` cities laid out as `layout` over a square of side `size` with its corner at the
+/// origin, in `clusters` clusters if they're clustered. Circles and grids are shuffled, so the
+/// order of the cities doesn't give their shortest tour away.
+pub fn cities<R: Rng + ?Sized>(layout: Layout, count: usize, size: f64, clusters: usize, rng: &mut R) -> Vec<City> {
+    let mut cities: Vec<City> = match layout {
+        Layout::Uniform => (0..count).map(|_| City::new(rng.gen_range(0.0, size), rng.gen_range(0.0, size))).collect(),
+        Layout::Clustered => {
+            let centres: Vec<City> = (0..clusters.max(1)).map(|_| City::new(rng.gen_range(0.0, size), rng.gen_range(0.0, size))).collect();
+            let spread = size / 20.0;
+            (0..count).map(|_| {
+                let centre = centres.choose(rng).expect("there's a cluster");
+                // Box-Muller: a normally distributed distance in a uniformly distributed direction
+                let distance = spread * (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+                let angle = rng.gen_range(0.0, 2.0 * PI);
+                City::new(centre.x + distance * angle.cos(), centre.y + distance * angle.sin())
+            }).collect()
+        }
+        Layout::Circle => {
+            let radius = size / 2.0;
+            (0..count).map(|i| {
+                let angle = 2.0 * PI * i as f64 / count as f64;
+                City::new(radius + radius * angle.cos(), radius + radius * angle.sin())
+            }).collect()
+        }
+        Layout::Grid => {
+            let columns = (count as f64).sqrt().ceil().max(1.0) as usize;
+            let spacing = size / columns as f64;
+            (0..count).map(|i| City::new((i % columns) as f64 * spacing, (i / columns) as f64 * spacing)).collect()
+        }
+    };
+
+    if matches!(layout, Layout::Circle | Layout::Grid) {
+        cities.shuffle(rng);
+    }
+    cities
+}
+
+/// The length of the shortest closed tour of `count` cities laid out as `layout` over a square
+/// of side `size`, if it's known: round the circle, each leg a chord of it.
+pub fn optimum(layout: Layout, count: usize, size: f64) -> Option<f64> {
+    match layout {
+        Layout::Circle if count > 1 => Some(count as f64 * size * (PI / count as f64).sin()),
+        Layout::Circle => Some(0.0),
+        Layout::Uniform | Layout::Clustered | Layout::Grid => None,
+    }
+}
+
+/// Writes `cities` to `writer` as csv, with an x,y header as the program reads them.
+pub fn write<W: Write>(writer: W, cities: &[City]) -> std::io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["x", "y"])?;
+    for city in cities {
+        writer.write_record([city.x.to_string(), city.y.to_string()])?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Metric;
+    use crate::rng::{RngAlgorithm, SeededRng};
+    use crate::tour::TourType;
+    use crate::{input, Path};
+
+    #[test]
+    fn generated_instances_read_back_and_circles_are_as_short_as_said() {
+
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        for layout in [Layout::Uniform, Layout::Clustered, Layout::Circle, Layout::Grid] {
+            let cities = cities(layout, 30, 100.0, 3, &mut rng);
+            let mut csv = Vec::new();
+            write(&mut csv, &cities).unwrap();
+            let read = input::read_cities(&csv[..]).unwrap();
+            assert!(read.iter().map(|c| (c.x, c.y)).eq(cities.iter().map(|c| (c.x, c.y))), "{}", layout);
+        }
+
+        // Going round the circle in order of angle
+        let circle = cities(Layout::Circle, 30, 100.0, 1, &mut rng);
+        let angle = |city: usize| (circle[city].y - 50.0).atan2(circle[city].x - 50.0);
+        let mut order: Vec<usize> = (0..30).collect();
+        order.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+        let length = 1.0 / Path::calculate_fitness(&order, &circle, Metric::Euclidean, TourType::Closed);
+        assert!((length - optimum(Layout::Circle, 30, 100.0).unwrap()).abs() < 1e-9);
+    }
+}
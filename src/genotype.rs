@@ -0,0 +1,257 @@
+//! A genetic algorithm over any chromosome, not just tours. `Simulation` stays specialised to
+//! tours, with the features that only make sense for them; `Evolution` runs the same scheme of
+//! breeding pool, survivors and mutation on any `Genotype`.
+//!
+//! Three common representations come with it, each scored by an objective function of its
+//! genes: `Bits`, `Reals` and `Permutation`.
+
+use std::sync::Arc;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::{check_parameters, BreedingCounts, Error, Path};
+
+/// A chromosome: what a genetic algorithm breeds, mutates and selects by fitness.
+pub trait Genotype: Clone {
+    /// A child of `self` and `other`.
+    fn crossover<R: Rng + ?Sized>(&self, other: &Self, rng: &mut R) -> Self;
+
+    /// Makes a small random change.
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R);
+
+    /// How good the chromosome is; higher is better.
+    fn fitness(&self) -> f64;
+}
+
+/// Scores the genes of a chromosome; higher is better.
+pub type Objective<T> = Arc<dyn Fn(&[T]) -> f64 + Send + Sync>;
+
+/// Genes cut at the same random point of both parents, the child taking the first part from
+/// `mother` and the rest from `father`.
+fn one_point<T: Clone, R: Rng + ?Sized>(mother: &[T], father: &[T], rng: &mut R) -> Vec<T> {
+    let point = rng.gen_range(0, mother.len() + 1);
+    mother[..point].iter().chain(&father[point..]).cloned().collect()
+}
+
+/// A string of bits, e.g. for knapsack or feature selection. Mutation flips a bit.
+#[derive(Clone)]
+pub struct Bits {
+    pub genes: Vec<bool>,
+    objective: Objective<bool>,
+}
+
+impl Bits {
+    pub fn new(genes: Vec<bool>, objective: Objective<bool>) -> Bits {
+        Bits { genes, objective }
+    }
+
+    pub fn random<R: Rng + ?Sized>(len: usize, objective: Objective<bool>, rng: &mut R) -> Bits {
+        Bits::new((0..len).map(|_| rng.gen()).collect(), objective)
+    }
+}
+
+impl Genotype for Bits {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Bits, rng: &mut R) -> Bits {
+        Bits::new(one_point(&self.genes, &other.genes, rng), self.objective.clone())
+    }
+
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        if let Some(bit) = self.genes.choose_mut(rng) {
+            *bit = !*bit;
+        }
+    }
+
+    fn fitness(&self) -> f64 {
+        (self.objective)(&self.genes)
+    }
+}
+
+/// Real numbers within bounds, e.g. for tuning continuous parameters. Mutation moves one of
+/// them by up to `step` either way, staying within the bounds.
+#[derive(Clone)]
+pub struct Reals {
+    pub genes: Vec<f64>,
+    low: f64,
+    high: f64,
+    step: f64,
+    objective: Objective<f64>,
+}
+
+impl Reals {
+    pub fn new(genes: Vec<f64>, low: f64, high: f64, step: f64, objective: Objective<f64>) -> Reals {
+        assert!(low <= high, "the bounds {}..{} are the wrong way round", low, high);
+        Reals { genes, low, high, step, objective }
+    }
+
+    pub fn random<R: Rng + ?Sized>(len: usize, low: f64, high: f64, step: f64, objective: Objective<f64>, rng: &mut R) -> Reals {
+        let range = Uniform::new_inclusive(low, high);
+        Reals::new((0..len).map(|_| range.sample(rng)).collect(), low, high, step, objective)
+    }
+}
+
+impl Genotype for Reals {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Reals, rng: &mut R) -> Reals {
+        let genes = one_point(&self.genes, &other.genes, rng);
+        Reals::new(genes, self.low, self.high, self.step, self.objective.clone())
+    }
+
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let (low, high) = (self.low, self.high);
+        let step = Uniform::new_inclusive(-self.step, self.step);
+        if let Some(gene) = self.genes.choose_mut(rng) {
+            *gene = (*gene + step.sample(rng)).clamp(low, high);
+        }
+    }
+
+    fn fitness(&self) -> f64 {
+        (self.objective)(&self.genes)
+    }
+}
+
+/// An ordering of 0..n, e.g. for scheduling, bred and mutated as tours are.
+#[derive(Clone)]
+pub struct Permutation {
+    pub genes: Vec<usize>,
+    objective: Objective<usize>,
+}
+
+impl Permutation {
+    pub fn new(genes: Vec<usize>, objective: Objective<usize>) -> Permutation {
+        Permutation { genes, objective }
+    }
+
+    pub fn random<R: Rng + ?Sized>(len: usize, objective: Objective<usize>, rng: &mut R) -> Permutation {
+        let mut genes: Vec<usize> = (0..len).collect();
+        genes.shuffle(rng);
+        Permutation::new(genes, objective)
+    }
+}
+
+impl Genotype for Permutation {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Permutation, rng: &mut R) -> Permutation {
+        Permutation::new(Path::crossover(&self.genes, &other.genes, rng), self.objective.clone())
+    }
+
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        if self.genes.is_empty() {
+            return;
+        }
+        let (a, b) = (rng.gen_range(0, self.genes.len()), rng.gen_range(0, self.genes.len()));
+        self.genes.swap(a, b);
+    }
+
+    fn fitness(&self) -> f64 {
+        (self.objective)(&self.genes)
+    }
+}
+
+/// A population of genotypes evolving as a `Simulation`'s paths do: each generation the
+/// fittest `crossover_rate` of it breed, the fittest `survival_rate` of those and a couple of the
+/// weakest carry over unchanged, and every member may then mutate at `mutation_rate`.
+pub struct Evolution<G: Genotype> {
+    /// Fittest first, with their fitness.
+    population: Vec<(G, f64)>,
+    counts: BreedingCounts,
+    mutation_rate: f64,
+}
+
+impl<G: Genotype> Evolution<G> {
+    /// Evolves `population`, with the parameters `Simulation::new` takes, which are checked as
+    /// it checks them.
+    pub fn new(population: Vec<G>, crossover_rate: f64, mutation_rate: f64, survival_rate: f64) -> Result<Evolution<G>, Error> {
+        check_parameters(population.len(), crossover_rate, mutation_rate, survival_rate)?;
+        let counts = BreedingCounts::new(population.len(), crossover_rate, survival_rate);
+        let mut population: Vec<(G, f64)> = population.into_iter().map(|g| {
+            let fitness = g.fitness();
+            (g, fitness)
+        }).collect();
+        population.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        Ok(Evolution { population, counts, mutation_rate })
+    }
+
+    /// Breeds the next generation.
+    pub fn generation<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let BreedingCounts { breeding_count, surviving_parent_count, surviving_weak_count, offspring_count } = self.counts;
+        let breeders = &self.population[..breeding_count];
+        let partner = Uniform::new(0, breeders.len());
+
+        let offspring: Vec<G> = (0..offspring_count)
+            .map(|i| breeders[i % breeders.len()].0.crossover(&breeders[partner.sample(rng)].0, rng))
+            .collect();
+        let weakest = self.population.len() - surviving_weak_count;
+        let survivors = self.population[..surviving_parent_count].iter().chain(&self.population[weakest..]).cloned();
+
+        let mut next: Vec<(G, f64)> = offspring.into_iter()
+            .map(|g| {
+                let fitness = g.fitness();
+                (g, fitness)
+            })
+            .chain(survivors)
+            .collect();
+        for (g, fitness) in &mut next {
+            if rng.gen_bool(self.mutation_rate) {
+                g.mutate(rng);
+                *fitness = g.fitness();
+            }
+        }
+        next.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        self.population = next;
+    }
+
+    /// Breeds `generations` generations, returning the fittest genotype seen in any of them.
+    pub fn run<R: Rng + ?Sized>(&mut self, generations: usize, rng: &mut R) -> (G, f64) {
+        let mut fittest = self.population[0].clone();
+        for _ in 0..generations {
+            self.generation(rng);
+            if self.population[0].1 > fittest.1 {
+                fittest = self.population[0].clone();
+            }
+        }
+        fittest
+    }
+
+    /// The fittest genotype of the current generation, with its fitness.
+    pub fn fittest(&self) -> (&G, f64) {
+        let (g, fitness) = &self.population[0];
+        (g, *fitness)
+    }
+
+    /// The current generation, fittest first.
+    pub fn population(&self) -> impl Iterator<Item = &G> {
+        self.population.iter().map(|(g, _)| g)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{RngAlgorithm, SeededRng};
+
+    #[test]
+    fn evolution_improves_bits_reals_and_permutations() {
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let ones = Arc::new(|genes: &[bool]| genes.iter().filter(|&&bit| bit).count() as f64);
+        let population = (0..40).map(|_| Bits::random(32, ones.clone(), &mut rng)).collect();
+        let (bits, fitness) = Evolution::new(population, 0.4, 0.3, 0.3).unwrap().run(200, &mut rng);
+        assert_eq!(fitness, 32.0);
+        assert!(bits.genes.iter().all(|&bit| bit));
+
+        let sphere = Arc::new(|genes: &[f64]| -genes.iter().map(|x| x * x).sum::<f64>());
+        let population: Vec<Reals> = (0..40).map(|_| Reals::random(4, -5.0, 5.0, 0.5, sphere.clone(), &mut rng)).collect();
+        let start = population.iter().map(|g| g.fitness()).fold(f64::NEG_INFINITY, f64::max);
+        let (reals, fitness) = Evolution::new(population, 0.4, 0.3, 0.3).unwrap().run(200, &mut rng);
+        assert!(fitness > start && fitness > -0.5);
+        assert!(reals.genes.iter().all(|x| (-5.0..=5.0).contains(x)));
+
+        let sorted = Arc::new(|genes: &[usize]| -(genes.windows(2).filter(|pair| pair[0] > pair[1]).count() as f64));
+        let population = (0..40).map(|_| Permutation::random(8, sorted.clone(), &mut rng)).collect();
+        let mut evolution = Evolution::new(population, 0.4, 0.3, 0.3).unwrap();
+        let (permutation, _) = evolution.run(200, &mut rng);
+        assert!(Path::is_tour(&permutation.genes, 8));
+        assert_eq!(evolution.population().count(), 40);
+    }
+}
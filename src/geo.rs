@@ -0,0 +1,328 @@
+//! Distances between cities, and telling when their coordinates are longitudes and latitudes,
+//! which Euclidean distance gets wrong everywhere but the equator.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::input::City;
+
+/// The mean radius of the Earth, in kilometres.
+const EARTH_RADIUS: f64 = 6371.0;
+
+/// The radius of TSPLIB's idealised Earth, in kilometres.
+const TSPLIB_EARTH_RADIUS: f64 = 6378.388;
+
+// Header names taken to mean a column of longitudes or latitudes, compared in lowercase
+const LONGITUDE_COLUMNS: &[&str] = &["lon", "lng", "long", "longitude"];
+const LATITUDE_COLUMNS: &[&str] = &["lat", "latitude"];
+
+/// How the distance between two cities is measured.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// Straight lines on the plane, in the units of the coordinates.
+    #[default]
+    Euclidean,
+    /// Great circles between longitudes (`x`) and latitudes (`y`) in degrees, in kilometres.
+    Haversine,
+    /// TSPLIB's GEO distance: great circles as for haversine, but on TSPLIB's Earth, so the
+    /// lengths of GEO instances match the published ones.
+    Geo,
+    /// TSPLIB's ATT pseudo-Euclidean distance, a tenth of the squared straight line's root.
+    Att,
+}
+
+impl Metric {
+    /// Most memory worth precomputing distances in. Straight lines are so quick to measure that
+    /// looking them up only pays while the matrix stays in cache, up to about 1,000 cities;
+    /// great circles are slow enough that it pays up to about 5,800.
+    pub fn matrix_memory(self) -> usize {
+        match self {
+            Metric::Euclidean | Metric::Att => 8 << 20,
+            Metric::Haversine | Metric::Geo => 256 << 20,
+        }
+    }
+
+    pub fn distance(self, a: &City, b: &City) -> f64 {
+        match self {
+            // Squared by multiplying, as the legs measured in pairs are, rather than with powf,
+            // which libm doesn't always round the same
+            Metric::Euclidean => ((a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)).sqrt(),
+            Metric::Haversine => {
+                let (latitude_a, latitude_b) = (a.y.to_radians(), b.y.to_radians());
+                let half_chord = ((latitude_b - latitude_a) / 2.0).sin().powi(2)
+                    + latitude_a.cos() * latitude_b.cos() * ((b.x - a.x).to_radians() / 2.0).sin().powi(2);
+                2.0 * EARTH_RADIUS * half_chord.sqrt().min(1.0).asin()
+            }
+            Metric::Geo => {
+                let (latitude_a, latitude_b) = (a.y.to_radians(), b.y.to_radians());
+                let q1 = (a.x - b.x).to_radians().cos();
+                let (q2, q3) = ((latitude_a - latitude_b).cos(), (latitude_a + latitude_b).cos());
+                TSPLIB_EARTH_RADIUS * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).clamp(-1.0, 1.0).acos()
+            }
+            Metric::Att => (((a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)) / 10.0).sqrt(),
+        }
+    }
+}
+
+/// A way of measuring the distance between two cities, for measuring them some way `Metric`
+/// doesn't, e.g. by road or over the sphere in miles. Set one with
+/// `Simulation::set_distance_metric`.
+pub trait DistanceMetric: Send + Sync {
+    fn distance(&self, a: &City, b: &City) -> f64;
+
+    /// Most memory worth precomputing distances in, as for `Metric::matrix_memory`. By default
+    /// as much as for great circles, as a metric of its own is seldom quicker to measure.
+    fn matrix_memory(&self) -> usize {
+        256 << 20
+    }
+}
+
+impl DistanceMetric for Metric {
+    fn distance(&self, a: &City, b: &City) -> f64 {
+        Metric::distance(*self, a, b)
+    }
+
+    fn matrix_memory(&self) -> usize {
+        Metric::matrix_memory(*self)
+    }
+}
+
+/// How precisely a distance matrix keeps its distances.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    F64,
+    /// Half the memory, and half the cache, for distances good to about seven significant
+    /// digits, which the lengths of tours summed from them are off from the exact ones by
+    /// about as much.
+    F32,
+}
+
+impl Precision {
+    /// The memory a distance takes.
+    pub fn bytes(self) -> usize {
+        match self {
+            Precision::F64 => std::mem::size_of::<f64>(),
+            Precision::F32 => std::mem::size_of::<f32>(),
+        }
+    }
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Precision, String> {
+        match s {
+            "f64" => Ok(Precision::F64),
+            "f32" => Ok(Precision::F32),
+            _ => Err(format!("unknown precision {:?}, expected f64 or f32", s)),
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Precision::F64 => "f64",
+            Precision::F32 => "f32",
+        })
+    }
+}
+
+/// The distance between every pair of cities, measured once up front so tours can be scored
+/// by looking their legs up. It takes 8 bytes per pair, or 4 at `Precision::F32`, `bytes` of
+/// them in all.
+#[derive(Clone, Debug)]
+pub struct DistanceMatrix {
+    count: usize,
+    distances: Distances,
+}
+
+#[derive(Clone, Debug)]
+enum Distances {
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+}
+
+impl DistanceMatrix {
+    pub fn new(cities: &[City], metric: Metric) -> DistanceMatrix {
+        DistanceMatrix::measured(cities, &metric, Precision::F64)
+    }
+
+    /// The distances between `cities` measured with `metric`, kept at `precision`.
+    pub fn measured(cities: &[City], metric: &dyn DistanceMetric, precision: Precision) -> DistanceMatrix {
+        let distances = cities.iter().flat_map(|a| cities.iter().map(move |b| metric.distance(a, b)));
+        let distances = match precision {
+            Precision::F64 => Distances::F64(distances.collect()),
+            Precision::F32 => Distances::F32(distances.map(|d| d as f32).collect()),
+        };
+        DistanceMatrix { count: cities.len(), distances }
+    }
+
+    /// The same distances kept at `precision`. Narrowing them to `F32` rounds them for good.
+    pub fn with_precision(self, precision: Precision) -> DistanceMatrix {
+        let distances = match (self.distances, precision) {
+            (Distances::F64(distances), Precision::F32) => Distances::F32(distances.into_iter().map(|d| d as f32).collect()),
+            (Distances::F32(distances), Precision::F64) => Distances::F64(distances.into_iter().map(f64::from).collect()),
+            (distances, _) => distances,
+        };
+        DistanceMatrix { distances, ..self }
+    }
+
+    pub fn precision(&self) -> Precision {
+        match self.distances {
+            Distances::F64(_) => Precision::F64,
+            Distances::F32(_) => Precision::F32,
+        }
+    }
+
+    /// A matrix of distances given rather than measured, e.g. road travel times: `rows[a][b]` is
+    /// the cost of going from city `a` to city `b`. Costs can't be negative, and an infinite one
+    /// is a leg there's no way to take, which has to be given a cost with `with_penalty` before
+    /// tours are scored by the matrix.
+    pub fn from_rows(rows: Vec<Vec<f64>>) -> Result<DistanceMatrix, String> {
+        let count = rows.len();
+        let mut distances = Vec::with_capacity(count * count);
+        for (a, row) in rows.into_iter().enumerate() {
+            if row.len() != count {
+                return Err(format!("row {} has {} costs, not one for each of the {} cities", a + 1, row.len(), count));
+            }
+            if let Some(b) = row.iter().position(|cost| cost.is_nan() || *cost < 0.0) {
+                return Err(format!("the cost from city {} to city {} is {}, not a cost of at least 0 or inf for no way", a, b, row[b]));
+            }
+            distances.extend(row);
+        }
+
+        Ok(DistanceMatrix { count, distances: Distances::F64(distances) })
+    }
+
+    /// How many cities the matrix is of.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The memory a matrix of `count` cities takes at `precision`.
+    pub fn bytes(count: usize, precision: Precision) -> usize {
+        count.saturating_mul(count).saturating_mul(precision.bytes())
+    }
+
+    /// How many legs between two cities have no cost, there being no way to take them.
+    pub fn missing_legs(&self) -> usize {
+        (0..self.count).map(|a| (0..self.count).filter(|&b| a != b && self.get(a, b).is_infinite()).count()).sum()
+    }
+
+    /// The matrix with every leg that has no cost given the same `penalty`, the longest leg
+    /// that has one times the number of cities and one more, so that a tour that takes one
+    /// more of them is always longer than one that doesn't, however long its other legs.
+    pub fn with_penalty(&self) -> (DistanceMatrix, f64) {
+        let longest = (0..self.count)
+            .flat_map(|a| (0..self.count).map(move |b| (a, b)))
+            .map(|(a, b)| self.get(a, b))
+            .filter(|cost| cost.is_finite())
+            .fold(0.0, f64::max);
+        let penalty = longest * self.count as f64 + 1.0;
+        let rows = (0..self.count)
+            .map(|a| (0..self.count).map(|b| if self.get(a, b).is_infinite() { penalty } else { self.get(a, b) }).collect())
+            .collect();
+        let matrix = DistanceMatrix::from_rows(rows).expect("penalised costs are finite and not negative");
+        (matrix.with_precision(self.precision()), penalty)
+    }
+
+    /// Whether every distance is the same both ways.
+    pub fn is_symmetric(&self) -> bool {
+        (0..self.count).all(|a| (0..a).all(|b| self.get(a, b) == self.get(b, a)))
+    }
+
+    /// The distance from city `a` to city `b`, by index.
+    pub fn get(&self, a: usize, b: usize) -> f64 {
+        match &self.distances {
+            Distances::F64(distances) => distances[a * self.count + b],
+            Distances::F32(distances) => f64::from(distances[a * self.count + b]),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::Euclidean => write!(f, "euclidean"),
+            Metric::Haversine => write!(f, "haversine"),
+            Metric::Geo => write!(f, "geo"),
+            Metric::Att => write!(f, "att"),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Metric, String> {
+        match s {
+            "euclidean" => Ok(Metric::Euclidean),
+            "haversine" => Ok(Metric::Haversine),
+            "geo" => Ok(Metric::Geo),
+            "att" => Ok(Metric::Att),
+            _ => Err(format!("unknown metric {:?}, expected euclidean, haversine, geo or att", s)),
+        }
+    }
+}
+
+/// What the coordinates of a csv of cities look like.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coordinates {
+    Planar,
+    /// The columns are named as longitudes and latitudes.
+    Named,
+    /// The columns are `x` and `y`, but every city would be a valid longitude and latitude,
+    /// and not all of them on whole numbers as grids and test instances tend to be.
+    InRange,
+}
+
+/// Looks at the header of `csv` and the `cities` read from it.
+pub fn detect(csv: &str, cities: &[City]) -> Coordinates {
+    let named = csv::Reader::from_reader(csv.as_bytes())
+        .headers()
+        .map(|headers| {
+            let has = |names: &[&str]| headers.iter().any(|h| names.contains(&h.trim().to_lowercase().as_str()));
+            has(LONGITUDE_COLUMNS) && has(LATITUDE_COLUMNS)
+        })
+        .unwrap_or(false);
+    if named {
+        return Coordinates::Named;
+    }
+
+    let in_range = cities.iter().all(|c| c.x.abs() <= 180.0 && c.y.abs() <= 90.0);
+    let fractional = cities.iter().any(|c| c.x.fract() != 0.0 || c.y.fract() != 0.0);
+    if cities.len() > 1 && in_range && fractional {
+        Coordinates::InRange
+    } else {
+        Coordinates::Planar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input;
+
+    #[test]
+    fn longitudes_and_latitudes_are_detected_and_measured_on_the_globe() {
+        let csv = "Latitude,Longitude\n51.5074,-0.1278\n48.8566,2.3522\n";
+        let cities = input::read_cities(csv.as_bytes()).unwrap();
+        assert_eq!(detect(csv, &cities), Coordinates::Named);
+        // London to Paris
+        assert!((Metric::Haversine.distance(&cities[0], &cities[1]) - 343.6).abs() < 0.5);
+
+        let xy = "x,y\n-0.1278,51.5074\n2.3522,48.8566\n";
+        assert_eq!(detect(xy, &input::read_cities(xy.as_bytes()).unwrap()), Coordinates::InRange);
+        let grid = "x,y\n0,0\n3,4\n";
+        assert_eq!(detect(grid, &input::read_cities(grid.as_bytes()).unwrap()), Coordinates::Planar);
+    }
+}
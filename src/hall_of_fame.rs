@@ -0,0 +1,47 @@
+//! The best distinct tours of a whole run, for `--top-k`: alternatives to the best one that are
+//! nearly as short. Two paths are the same tour if they take the same legs, whichever city a
+//! closed tour starts from and, unless legs cost more one way than the other, whichever way
+//! round they go.
+
+use crate::population::Gene;
+use crate::tour::TourType;
+use crate::Path;
+
+pub struct HallOfFame {
+    size: usize,
+    /// Fittest first.
+    paths: Vec<Path>,
+    /// The tour each of `paths` is, as `TourType::canonical` writes it.
+    tours: Vec<Vec<usize>>,
+}
+
+impl HallOfFame {
+    /// Keeps the `size` fittest tours.
+    pub fn new(size: usize) -> HallOfFame {
+        HallOfFame { size, paths: Vec::with_capacity(size), tours: Vec::with_capacity(size) }
+    }
+
+    /// Takes the path of `order` and `fitness` in if it's fitter than the least fit kept, or
+    /// there's room for it, and isn't a tour already kept.
+    pub fn offer<G: Gene>(&mut self, order: &[G], fitness: f64, tour: TourType, asymmetric: bool) {
+        let full = self.paths.len() >= self.size;
+        if full && self.paths.last().is_none_or(|weakest| fitness <= weakest.fitness) {
+            return;
+        }
+        let canonical = tour.canonical(order, asymmetric);
+        if self.tours.contains(&canonical) {
+            return;
+        }
+
+        let at = self.paths.partition_point(|kept| kept.fitness >= fitness);
+        self.paths.insert(at, Path { fitness, order: order.iter().map(|city| city.index()).collect() });
+        self.tours.insert(at, canonical);
+        self.paths.truncate(self.size);
+        self.tours.truncate(self.size);
+    }
+
+    /// Fittest first.
+    pub fn paths(&self) -> &[Path] {
+        &self.paths
+    }
+}
@@ -0,0 +1,47 @@
+//! A drop directory for tours from outside the run, e.g. from a person or another solver,
+//! which are spliced into the population like migrants from another island.
+//!
+//! Every file dropped into the directory is read as a tour in any format `diff` reads and then
+//! removed. Files whose names start with a dot are left alone, so a tour can be written under
+//! a dot name and renamed into place once it's complete.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::diff;
+
+pub struct Hints {
+    dir: PathBuf,
+}
+
+impl Hints {
+    /// Watches `dir` for tours, creating it if needed.
+    pub fn watch(dir: PathBuf) -> io::Result<Hints> {
+        fs::create_dir_all(&dir)?;
+        Ok(Hints { dir })
+    }
+
+    /// The tours dropped since the last call, in file name order, or why each couldn't be read.
+    pub fn take(&self) -> Vec<(PathBuf, Result<Vec<usize>, String>)> {
+        let mut files: Vec<PathBuf> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries.flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+                .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+                .map(|entry| entry.path())
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        files.sort();
+
+        files.into_iter().map(|path| {
+            let tour = diff::read_tour(&path);
+            // Read once, whether or not it was a tour, so a bad file isn't complained about forever
+            let tour = match fs::remove_file(&path) {
+                Ok(()) => tour,
+                Err(e) => Err(format!("could not remove it after reading: {}", e)),
+            };
+            (path, tour)
+        }).collect()
+    }
+}
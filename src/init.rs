@@ -0,0 +1,153 @@
+//! How the initial population is made. Random tours are the default; the constructive
+//! heuristics build a share of them instead, tours far shorter than random ones for the search
+//! to start from. Nearest-neighbour tours start from different cities, and greedy ones after
+//! the first add edges in an order shuffled by a little noise, so that no two are alike.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+
+// Edges from each city to its nearest ones that greedy tours are built from; the fragments
+// they leave are joined nearest end first
+const CANDIDATES: usize = 10;
+
+// Greedy tours after the first weigh up edges as up to this much longer than they are
+const GREEDY_NOISE: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Init {
+    #[default]
+    Random,
+    /// From a city, on to the nearest one not yet visited, until all are.
+    NearestNeighbor,
+    /// The shortest edges that keep the tour a set of paths, joined up at the end.
+    Greedy,
+}
+
+impl FromStr for Init {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Init, String> {
+        match s {
+            "random" => Ok(Init::Random),
+            "nearest-neighbor" => Ok(Init::NearestNeighbor),
+            "greedy" => Ok(Init::Greedy),
+            _ => Err(format!("unknown initialisation {:?}, expected random, nearest-neighbor or greedy", s)),
+        }
+    }
+}
+
+impl fmt::Display for Init {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Init::Random => "random",
+            Init::NearestNeighbor => "nearest-neighbor",
+            Init::Greedy => "greedy",
+        })
+    }
+}
+
+/// `cities` in nearest-neighbour order from `first`, which is one of them.
+pub fn nearest_neighbor(first: usize, cities: &[usize], distance: impl Fn(usize, usize) -> f64) -> Vec<usize> {
+    let mut rest: Vec<usize> = cities.iter().copied().filter(|&city| city != first).collect();
+    let mut order = Vec::with_capacity(cities.len());
+    order.push(first);
+
+    while !rest.is_empty() {
+        let last = order[order.len() - 1];
+        let nearest = (0..rest.len())
+            .min_by(|&a, &b| distance(last, rest[a]).total_cmp(&distance(last, rest[b])))
+            .expect("there's a city left");
+        order.push(rest.swap_remove(nearest));
+    }
+
+    order
+}
+
+/// The edges from each of `cities` to its nearest others, with their lengths, for `greedy`.
+pub fn candidates(cities: &[usize], distance: impl Fn(usize, usize) -> f64) -> Vec<(usize, usize, f64)> {
+    let mut edges = Vec::with_capacity(cities.len() * CANDIDATES);
+    for &a in cities {
+        let mut others: Vec<(usize, f64)> = cities.iter().filter(|&&b| b != a).map(|&b| (b, distance(a, b))).collect();
+        if others.len() > CANDIDATES {
+            others.select_nth_unstable_by(CANDIDATES, |x, y| x.1.total_cmp(&y.1));
+            others.truncate(CANDIDATES);
+        }
+        edges.extend(others.into_iter().map(|(b, length)| (a, b, length)));
+    }
+    edges
+}
+
+/// `edges` made up to a tenth longer at random, for a greedy tour unlike the others.
+pub fn jitter<R: Rng + ?Sized>(edges: &[(usize, usize, f64)], rng: &mut R) -> Vec<(usize, usize, f64)> {
+    edges.iter().map(|&(a, b, length)| (a, b, length * (1.0 + GREEDY_NOISE * rng.gen::<f64>()))).collect()
+}
+
+/// A greedy tour of `cities`: `edges` are taken shortest first as long as no city gets more
+/// than two and they close no loop, and the paths they form are then joined, each to the
+/// nearest end of another, starting from the one nearest the city `after`, if any. If the
+/// `distance` is `asymmetric`, edges only go one way, and a path can only be entered at its
+/// start.
+pub fn greedy(
+    cities: &[usize],
+    mut edges: Vec<(usize, usize, f64)>,
+    asymmetric: bool,
+    after: Option<usize>,
+    distance: impl Fn(usize, usize) -> f64,
+) -> Vec<usize> {
+    let size = cities.iter().max().map_or(0, |&city| city + 1);
+    // The cities each one is joined to, only the next one if the edges go one way
+    let mut links: Vec<Vec<usize>> = vec![Vec::new(); size];
+    let mut entered = vec![false; size];
+    let mut paths: Vec<usize> = (0..size).collect();
+
+    edges.sort_by(|x, y| x.2.total_cmp(&y.2));
+    for (a, b, _) in edges {
+        let free = if asymmetric { links[a].is_empty() && !entered[b] } else { links[a].len() < 2 && links[b].len() < 2 };
+        let (path_a, path_b) = (find(&mut paths, a), find(&mut paths, b));
+        if !free || path_a == path_b {
+            continue;
+        }
+
+        paths[path_a] = path_b;
+        links[a].push(b);
+        if asymmetric {
+            entered[b] = true;
+        } else {
+            links[b].push(a);
+        }
+    }
+
+    let mut visited = vec![false; size];
+    let mut order = Vec::with_capacity(cities.len());
+    let mut last = after;
+    while order.len() < cities.len() {
+        let starts_path = |city: usize| !visited[city] && if asymmetric { !entered[city] } else { links[city].len() < 2 };
+        let mut city = cities.iter().copied()
+            .filter(|&city| starts_path(city))
+            .min_by(|&a, &b| last.map_or(Ordering::Equal, |last| distance(last, a).total_cmp(&distance(last, b))))
+            .expect("every path has an end");
+        loop {
+            visited[city] = true;
+            order.push(city);
+            match links[city].iter().find(|&&next| !visited[next]) {
+                Some(&next) => city = next,
+                None => break,
+            }
+        }
+        last = Some(city);
+    }
+
+    order
+}
+
+/// The path `city` is in, as one of its cities.
+fn find(paths: &mut [usize], mut city: usize) -> usize {
+    while paths[city] != city {
+        paths[city] = paths[paths[city]];
+        city = paths[city];
+    }
+    city
+}
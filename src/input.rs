@@ -0,0 +1,104 @@
+use std::io::{self, Read};
+use std::sync::OnceLock;
+
+use csv::{Reader, ReaderBuilder, StringRecord, Trim};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+use crate::geo::DistanceMatrix;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct City {
+    // Longitudes go in x and latitudes in y, named as in geo::detect
+    #[serde(deserialize_with = "finite", alias = "lon", alias = "lng", alias = "long", alias = "longitude")]
+    pub(crate) x: f64,
+    #[serde(deserialize_with = "finite", alias = "lat", alias = "latitude")]
+    pub(crate) y: f64,
+}
+
+impl City {
+    pub fn new(x: f64, y: f64) -> City {
+        City { x, y }
+    }
+}
+
+/// Rejects infinite and NaN coordinates: a city there is unreachable, since every tour through
+/// it would have an infinite or undefined length.
+fn finite<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let coordinate = f64::deserialize(deserializer)?;
+    if !coordinate.is_finite() {
+        return Err(D::Error::custom(format!("{} is not a finite coordinate", coordinate)));
+    }
+
+    Ok(coordinate)
+}
+
+/// Reads cities from csv with `x` and `y` columns, or longitude and latitude ones.
+pub fn read_cities<R: Read>(input: R) -> csv::Result<Vec<City>> {
+    reader(input).deserialize().collect()
+}
+
+/// Like `read_cities`, but skips rows that can't be read, returning their errors alongside the
+/// cities that could.
+pub fn read_cities_lenient<R: Read>(input: R) -> (Vec<City>, Vec<csv::Error>) {
+    let mut cities = Vec::new();
+    let mut skipped = Vec::new();
+
+    for row in reader(input).deserialize() {
+        match row {
+            Ok(city) => cities.push(city),
+            Err(e) => skipped.push(e),
+        }
+    }
+
+    (cities, skipped)
+}
+
+/// Reads a matrix of the costs of going from every city to every other, one row of csv per city
+/// with one cost per city, as in a distance matrix; a first row that isn't all numbers is taken
+/// for a header and skipped. A leg there's no way to take costs `inf`, or is left empty.
+pub fn read_matrix<R: Read>(input: R) -> Result<DistanceMatrix, String> {
+    let mut rows = Vec::new();
+    let records = ReaderBuilder::new().has_headers(false).flexible(true).trim(Trim::All).from_reader(input).into_records();
+    for (i, record) in records.enumerate() {
+        let record = record.map_err(|e| e.to_string())?;
+        let row: Result<Vec<f64>, _> = record.iter().map(|cost| if cost.is_empty() { Ok(f64::INFINITY) } else { cost.parse() }).collect();
+        match row {
+            Ok(row) => rows.push(row),
+            Err(_) if i == 0 => {}
+            Err(_) => return Err(format!("line {} isn't a row of costs", i + 1)),
+        }
+    }
+
+    DistanceMatrix::from_rows(rows)
+}
+
+/// Cities for an instance given only by the distances between them, placed along the x axis at
+/// their index, so drawings and routes of a tour still show its order.
+pub fn placeholders(count: usize) -> Vec<City> {
+    (0..count).map(|i| City::new(i as f64, 0.0)).collect()
+}
+
+/// Everything on stdin, read on first use, so the cities and a manifest's fingerprint of them
+/// both see the same input.
+pub fn stdin() -> io::Result<&'static str> {
+    static STDIN: OnceLock<String> = OnceLock::new();
+    if let Some(source) = STDIN.get() {
+        return Ok(source);
+    }
+
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    Ok(STDIN.get_or_init(|| source))
+}
+
+/// A csv reader that matches column names regardless of case and surrounding spaces.
+fn reader<R: Read>(input: R) -> Reader<R> {
+    let mut reader = Reader::from_reader(input);
+    if let Ok(headers) = reader.headers() {
+        let headers: StringRecord = headers.iter().map(|h| h.trim().to_lowercase()).collect();
+        reader.set_headers(headers);
+    }
+
+    reader
+}
@@ -0,0 +1,3054 @@
+//! A genetic algorithm for the travelling salesman problem, on tours that return to their
+//! start or, with [`TourType::Open`], paths that don't.
+//!
+//! A [`Simulation`] evolves a population of [`Path`]s through a list of [`City`]s: build one
+//! with `Simulation::new`, which fails with an [`Error`] on parameters no run can use, set any
+//! optional features with its `set_*` methods before the run, and `solve` it, which returns the
+//! fittest path found, or the [`Error`] that ended the run if its paths couldn't be scored.
+//!
+//! ```no_run
+//! use std::ops::ControlFlow;
+//! use wasi_genetic::{City, RngAlgorithm, SeededRng, Simulation};
+//!
+//! let cities = vec![City::new(0.0, 0.0), City::new(3.0, 4.0), City::new(6.0, 0.0)];
+//! let mut sim = Simulation::new(100, cities, 500, 0.4, 0.1, 0.3, SeededRng::new(RngAlgorithm::ChaCha, 7))?;
+//! let fittest = sim.solve(|_| ControlFlow::Continue(()))?;
+//! println!("{:?} is {} long", fittest.order(), fittest.length());
+//! # Ok::<(), wasi_genetic::Error>(())
+//! ```
+
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{ControlFlow, Range};
+use std::path::PathBuf;
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rand::distributions::{Distribution, Uniform};
+use serde::Serialize;
+use serde_json::json;
+// std's clock panics in the browser
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+pub mod analyze;
+pub mod barrier;
+pub mod builtin;
+pub mod checkpoint;
+pub mod cluster;
+pub mod config;
+pub mod connectivity;
+pub mod crossover;
+pub mod dedup;
+#[cfg(feature = "web-ui")]
+pub mod dashboard;
+mod diagnostics;
+pub mod diff;
+pub mod diversity;
+pub mod dump;
+pub mod error;
+pub mod eta;
+pub mod event_log;
+pub mod evaluate;
+pub mod evaluator;
+pub mod events;
+pub mod generate;
+pub mod genotype;
+pub mod geo;
+pub mod hall_of_fame;
+pub mod hints;
+pub mod init;
+pub mod input;
+pub mod local_search;
+pub mod manifest;
+pub mod metrics;
+pub mod migration;
+pub mod mutation;
+pub mod operators;
+pub mod optimum;
+pub mod output;
+mod polish;
+pub mod population;
+pub mod projection;
+pub mod regress;
+pub mod repl;
+mod required;
+pub mod restarts;
+pub mod route;
+pub mod rng;
+#[cfg(feature = "rhai")]
+pub mod script;
+pub mod selection;
+mod sha256;
+mod simd;
+pub mod solve;
+mod spill;
+pub mod statsd;
+pub mod summary;
+pub mod tour;
+pub mod trace;
+pub mod tsplib;
+pub mod tune;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "worker")]
+pub mod worker;
+
+use checkpoint::Checkpoint;
+use crossover::Crossover;
+use dedup::Dedup;
+use diversity::Diversity;
+use dump::PopulationDumps;
+pub use error::Error;
+use eta::{Estimate, Eta};
+use event_log::EventLog;
+use evaluator::Evaluator;
+use events::{Event, Notice, Watch};
+use hall_of_fame::HallOfFame;
+use hints::Hints;
+use init::Init;
+pub use input::City;
+use local_search::{LocalSearch, Target};
+use geo::{DistanceMatrix, DistanceMetric, Precision};
+pub use geo::Metric;
+use metrics::METRICS;
+use migration::Migration;
+use mutation::{Adaptive, Mutation, Swap};
+use operators::Operators;
+use population::{Gene, Population, Stored, Width};
+use repl::{Command, Repl};
+use required::RequiredEdges;
+pub use rng::{RngAlgorithm, SeededRng};
+use selection::{Selection, Truncation};
+pub use spill::parse_size;
+use spill::Spill;
+use summary::{Summary, Termination};
+pub use tour::TourType;
+use trace::{Span, Tracer};
+
+#[derive(Clone)]
+pub struct Path {
+    fitness: f64,
+    order: Vec<usize>
+}
+
+impl Path {
+    pub fn breed<R: Rng + ?Sized>(&self, other: &Path, city_list: &[City], metric: Metric, tour: TourType, rng: &mut R) -> Path {
+        let order = Path::crossover(&self.order, &other.order, rng);
+        let fitness = Path::calculate_fitness(&order, city_list, metric, tour);
+
+        Path { fitness, order }
+    }
+
+    fn crossover<R: Rng + ?Sized>(mother: &[usize], father: &[usize], rng: &mut R) -> Vec<usize> {
+        let crossover_point = Uniform::new(0, mother.len()).sample(rng);
+        Path::crossover_at(mother, father, crossover_point)
+    }
+
+    fn crossover_at(mother: &[usize], father: &[usize], crossover_point: usize) -> Vec<usize> {
+        let mother_dna = &mother[0..crossover_point];
+        let mut father_dna: Vec<usize> = father.iter().filter_map(|d| {
+            if !mother_dna.contains(d) {
+                return Some(*d)
+            }
+            None
+        }).collect();
+
+        let mut child = Vec::new();
+        child.extend_from_slice(mother_dna);
+        child.append(&mut father_dna);
+
+        child
+    }
+
+    /// Swaps two random cities, leaving the first `fixed` alone. The fitness is left stale for
+    /// the caller to re-evaluate.
+    pub fn mutate<R: Rng + ?Sized>(&mut self, fixed: usize, rng: &mut R) {
+        let fixed = fixed.min(self.order.len());
+        mutation::swap(&mut self.order[fixed..], rng);
+    }
+
+    /// Whether `order` visits each of `city_count` cities exactly once.
+    pub fn is_tour<G: Gene>(order: &[G], city_count: usize) -> bool {
+        let mut sorted: Vec<usize> = order.iter().map(|city| city.index()).collect();
+        sorted.sort_unstable();
+        sorted.into_iter().eq(0..city_count)
+    }
+
+    /// The cities in the order the path visits them.
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Higher is better: the inverse of the tour length, unless an evaluator scored it.
+    pub fn fitness(&self) -> f64 {
+        self.fitness
+    }
+
+    /// The tour length, or whatever an evaluator's fitness is the inverse of.
+    pub fn length(&self) -> f64 {
+        1.0 / self.fitness
+    }
+
+    /// The inverse of the length of `path` between `city_list` measured with `metric`, back to
+    /// its start if it's a closed `tour`.
+    pub fn calculate_fitness<G: Gene>(path: &[G], city_list: &[City], metric: Metric, tour: TourType) -> f64 {
+        Path::timed(|| Path::tour_fitness(path, city_list, metric, tour))
+    }
+
+    /// The same as `calculate_fitness` with the distances measured with a `DistanceMetric` of
+    /// the caller's own.
+    pub fn measured_fitness<G: Gene>(path: &[G], city_list: &[City], metric: &dyn DistanceMetric, tour: TourType) -> f64 {
+        Path::timed(|| Path::legs_fitness(path, tour, |a, b| metric.distance(&city_list[a], &city_list[b])))
+    }
+
+    /// The same as `calculate_fitness` with the distances looked up in `matrix`.
+    pub fn matrix_fitness<G: Gene>(path: &[G], matrix: &DistanceMatrix, tour: TourType) -> f64 {
+        Path::timed(|| Path::legs_fitness(path, tour, |a, b| matrix.get(a, b)))
+    }
+
+    fn timed(fitness: impl FnOnce() -> f64) -> f64 {
+        if !METRICS.enabled() {
+            return fitness();
+        }
+
+        let start = Instant::now();
+        let fitness = fitness();
+        METRICS.record_evaluation(start.elapsed());
+
+        fitness
+    }
+
+    fn tour_fitness<G: Gene>(path: &[G], city_list: &[City], metric: Metric, tour: TourType) -> f64 {
+        match simd::length(path, city_list, metric, tour) {
+            Some(cost) => Path::cost_fitness(cost),
+            None => Path::legs_fitness(path, tour, |a, b| metric.distance(&city_list[a], &city_list[b])),
+        }
+    }
+
+    fn legs_fitness<G: Gene>(path: &[G], tour: TourType, distance: impl Fn(usize, usize) -> f64) -> f64 {
+        let mut cost = 0.0;
+        for (a, b) in tour.legs(path) {
+            cost += distance(a, b);
+        }
+        Path::cost_fitness(cost)
+    }
+
+    fn cost_fitness(cost: f64) -> f64 {
+        // Tours of cities on the same spot, or with given costs of 0, cost nothing and can't be
+        // beaten
+        if cost == 0.0 {
+            return f64::MAX;
+        }
+
+        1.0 / cost
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_solution(f, self.fitness, &self.order)
+    }
+}
+
+/// How each generation is split between breeders and the paths carried over unchanged.
+#[derive(Clone, Copy)]
+struct BreedingCounts {
+    breeding_count: usize,
+    surviving_parent_count: usize,
+    surviving_weak_count: usize,
+    offspring_count: usize,
+}
+
+impl BreedingCounts {
+    /// Derives the counts for a population of `population_size` from rates `check_parameters`
+    /// accepts, so there's always someone to breed from. Survivors and offspring always add up
+    /// to exactly `population_size`.
+    fn new(population_size: usize, crossover_rate: f64, survival_rate: f64) -> BreedingCounts {
+        let breeding_count = (population_size as f64 * crossover_rate) as usize;
+
+        // Keep a few weak units for diversity, but leave room for at least one other path
+        let surviving_weak_count = 2.min(population_size.saturating_sub(1));
+        let counts = BreedingCounts {
+            breeding_count,
+            surviving_parent_count: 0,
+            surviving_weak_count,
+            offspring_count: population_size - surviving_weak_count,
+        };
+        counts.with_survival_rate(survival_rate)
+    }
+
+    /// The same counts, but with the fittest of the breeding pool surviving at `survival_rate`.
+    fn with_survival_rate(self, survival_rate: f64) -> BreedingCounts {
+        self.with_survivors((self.breeding_count as f64 * survival_rate) as usize)
+    }
+
+    /// The same counts, but with the `count` fittest surviving however many breed, as far as
+    /// there's room for them beside the weak survivors.
+    fn with_survivors(self, count: usize) -> BreedingCounts {
+        let population_size = self.population_size();
+        let surviving_parent_count = count.min(population_size - self.surviving_weak_count);
+        let offspring_count = population_size - self.surviving_weak_count - surviving_parent_count;
+
+        BreedingCounts { surviving_parent_count, offspring_count, ..self }
+    }
+
+    /// How many of the fittest a spilled population keeps in memory: the breeding pool, or the
+    /// survivors if there are more of them.
+    fn resident_fittest(&self) -> usize {
+        self.breeding_count.max(self.surviving_parent_count)
+    }
+
+    fn population_size(&self) -> usize {
+        self.surviving_parent_count + self.surviving_weak_count + self.offspring_count
+    }
+}
+
+/// A mutation the adaptive operators drew: the path it was made to by its index, the mutation
+/// by its place among them and the path's fitness before it.
+struct Tried {
+    index: usize,
+    operator: usize,
+    fitness: f64,
+}
+
+/// The fewest cities a `Simulation` takes: with fewer there's only the one tour, and nothing to
+/// breed or mutate.
+pub const MIN_CITIES: usize = 3;
+
+/// Checks the GA parameters `Simulation::new` takes, as it does, for callers that would rather
+/// turn them down before loading the cities. The rates are between 0 and 1, and the crossover
+/// rate of the population has to breed from at least one path.
+pub fn check_parameters(population_size: usize, crossover_rate: f64, mutation_rate: f64, survival_rate: f64) -> Result<(), Error> {
+    let rate = |name, rate: f64| {
+        if (0.0..=1.0).contains(&rate) {
+            Ok(())
+        } else {
+            Err(Error::Parameter { name, reason: format!("{} is not between 0 and 1", rate) })
+        }
+    };
+
+    if population_size == 0 {
+        return Err(Error::Parameter { name: "population size", reason: "a population of 0 can't evolve".to_string() });
+    }
+    rate("crossover rate", crossover_rate)?;
+    rate("mutation rate", mutation_rate)?;
+    rate("survival rate", survival_rate)?;
+    if population_size as f64 * crossover_rate < 1.0 {
+        return Err(Error::Parameter {
+            name: "crossover rate",
+            reason: format!(
+                "{} breeds from none of {} paths, it has to be at least {}",
+                crossover_rate, population_size, 1.0 / population_size as f64
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Parses a duration such as 30s, 500ms, 5m or 2h; a bare number is in seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let seconds = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(format!("{:?} isn't a duration like 30s, 500ms, 5m or 2h", s)),
+    };
+
+    number.trim().parse::<f64>().ok()
+        .and_then(|n| Duration::try_from_secs_f64(n * seconds).ok())
+        .ok_or_else(|| format!("{:?} isn't a duration like 30s, 500ms, 5m or 2h", s))
+}
+
+/// What `solve` tells its observer after each generation.
+pub struct Report<'a> {
+    pub generation: usize,
+    /// The best path found so far.
+    pub fittest: &'a Path,
+    /// Stagnation and diversity changes this generation brought about.
+    pub events: Vec<Event>,
+    /// When the run should stall, or reach its target length.
+    pub estimate: Estimate,
+    population: &'a Population,
+    city_count: usize,
+    diversity: OnceCell<Diversity>,
+}
+
+impl Report<'_> {
+    /// Diversity of the population this generation left, measured on first use. For a spilled
+    /// population, only of the paths kept in memory.
+    pub fn diversity(&self) -> Diversity {
+        *self.diversity.get_or_init(|| diversity::measure(self.population, self.city_count))
+    }
+
+    /// Mean fitness of the population this generation left, leaving out quarantined paths. For
+    /// a spilled population, only of the paths kept in memory.
+    pub fn mean_fitness(&self) -> f64 {
+        let scored: Vec<f64> = self.population.fitness_values().iter().copied().filter(|f| f.is_finite()).collect();
+        scored.iter().sum::<f64>() / scored.len().max(1) as f64
+    }
+
+    /// Lengths of the tours this generation left, leaving out quarantined paths. For a spilled
+    /// population, only of the paths kept in memory.
+    pub fn lengths(&self) -> impl Iterator<Item = f64> + '_ {
+        self.population.fitness_values().iter().filter(|&&f| f > 0.0).map(|f| 1.0 / f)
+    }
+
+    /// The spread of `lengths`.
+    pub fn population_stats(&self) -> PopulationStats {
+        PopulationStats::of(self.lengths())
+    }
+}
+
+/// How long the tours of a population are, leaving out quarantined ones.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct PopulationStats {
+    pub best: f64,
+    pub mean: f64,
+    pub worst: f64,
+    pub std_dev: f64,
+}
+
+impl PopulationStats {
+    /// The stats of `lengths`, which are all infinite or 0 if there are none.
+    pub fn of(lengths: impl Iterator<Item = f64>) -> PopulationStats {
+        let lengths: Vec<f64> = lengths.collect();
+        let count = lengths.len().max(1) as f64;
+        let mean = lengths.iter().sum::<f64>() / count;
+        let variance = lengths.iter().map(|length| (length - mean).powi(2)).sum::<f64>() / count;
+        PopulationStats {
+            best: lengths.iter().copied().fold(f64::INFINITY, f64::min),
+            mean,
+            worst: lengths.iter().copied().fold(0.0, f64::max),
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+/// What `run` found.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SolutionReport {
+    /// The cities in the order the best tour visits them.
+    pub best_tour: Vec<usize>,
+    pub length: f64,
+    pub fitness: f64,
+    pub generations_run: usize,
+    /// The best length after every generation, before any polishing.
+    pub history: Vec<f64>,
+    /// The hall of fame's tours with their lengths, fittest first, if one was kept.
+    pub hall_of_fame: Vec<(Vec<usize>, f64)>,
+}
+
+impl fmt::Display for SolutionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_solution(f, self.fitness, &self.best_tour)
+    }
+}
+
+/// The solution as the command line prints it, its fitness and then its cities in order.
+fn write_solution(f: &mut fmt::Formatter, fitness: f64, order: &[usize]) -> fmt::Result {
+    let order: Vec<String> = order.iter().map(|o| o.to_string()).collect();
+    write!(f, "Solution:\nFitness {}\n{}", fitness, order.join("->"))
+}
+
+/// A callback for `solve` chosen at run time.
+pub type OnGeneration<'a> = Box<dyn FnMut(&Report) -> ControlFlow<()> + 'a>;
+
+/// A callback kept by the simulation, for `on_generation`.
+pub type Observer = Box<dyn FnMut(&Report) -> ControlFlow<()> + Send + Sync>;
+
+/// The callback for `on_notice`.
+pub type NoticeHandler = Box<dyn Fn(&Notice) + Send + Sync>;
+
+// Generations without improvement before a run counts as stagnating, unless set otherwise
+const DEFAULT_STAGNATION: usize = 100;
+
+// Most paths bred, scored and spilled at once when the population doesn't fit in memory
+const MAX_SPILL_CHUNK: usize = 1024;
+
+// How often a paused run checks for SIGUSR1 to resume it
+const PAUSE_POLL: Duration = Duration::from_millis(200);
+
+/// A run of the genetic algorithm. It is `Send` and `Sync`, so it can be moved to another thread
+/// or a thread pool and shared behind a lock; all of its state, including the generator and any
+/// evaluator, travels with it.
+pub struct Simulation {
+    /// Created on first use. When spilled, only the fittest paths, as many as breed or survive,
+    /// followed by the weakest paths are kept here.
+    population: Population,
+    /// What the population was a generation ago, whose buffers the next one is bred into.
+    next: Population,
+    spill: Option<Spill>,
+    max_memory: Option<usize>,
+    /// Tours from a checkpoint, to start the population from instead of random ones.
+    restored: Vec<Vec<usize>>,
+    first_generation: usize,
+    /// Generations evolved so far by `solve` and `step`, counting those before a checkpoint
+    /// the run was resumed from.
+    generation: usize,
+    /// The best path found so far, once there's a population.
+    best: Option<Path>,
+    hall_of_fame: Option<HallOfFame>,
+    checkpoints: Option<(PathBuf, usize)>,
+    dumps: Option<PopulationDumps>,
+    hints: Option<Hints>,
+    repl: Option<Repl>,
+    /// Ends the run once the best path is this short.
+    stop_length: Option<f64>,
+    /// Ends the run once the best path hasn't improved for this many generations.
+    stagnation_limit: Option<usize>,
+    /// Ends the run once it has taken this long.
+    time_limit: Option<Duration>,
+    city_list: Vec<City>,
+    max_iterations: usize,
+    counts: BreedingCounts,
+    /// Whether the surviving parents are elites set by `set_elitism`, which never mutate.
+    elitism: bool,
+    selection: Box<dyn Selection>,
+    crossover: Crossover,
+    mutation: Box<dyn Mutation>,
+    mutation_rate: f64,
+    adaptive_mutation: Option<Adaptive>,
+    /// Every operator, each drawn as often as it has improved tours lately, instead of
+    /// `crossover` and `mutation`.
+    adaptive_operators: Option<Operators>,
+    local_search: Option<LocalSearch>,
+    dedup: Option<Dedup>,
+    init: Init,
+    /// The share of the initial population `init` builds, the rest being random.
+    init_fraction: f64,
+    tracer: Tracer,
+    migration: Option<Migration>,
+    evaluator: Option<Box<dyn Evaluator>>,
+    observers: Vec<Observer>,
+    on_notice: Option<NoticeHandler>,
+    watch: Watch,
+    optimum: Option<f64>,
+    eta: Eta,
+    event_log: Option<EventLog>,
+    polish: bool,
+    /// Cities every path starts with, in this order.
+    prefix: Vec<usize>,
+    required: RequiredEdges,
+    metric: Metric,
+    /// A metric set with `set_distance_metric`, measuring in place of `metric`.
+    distance_metric: Option<Box<dyn DistanceMetric>>,
+    /// The distances between cities, if they take no more than `matrix_memory`, or by default
+    /// as much as `Metric::matrix_memory` says is worth it, or if they were given.
+    matrix: Option<DistanceMatrix>,
+    matrix_memory: Option<usize>,
+    precision: Precision,
+    /// Whether `matrix` holds distances given rather than measured, which stay whatever the metric.
+    distances_given: bool,
+    /// Whether going from one city to another may cost something else than coming back.
+    asymmetric: bool,
+    tour_type: TourType,
+    quarantined: usize,
+    evaluations: usize,
+    summary: Option<Summary>,
+    verify: bool,
+    /// Where paths are scored and bred, rather than rayon's global pool.
+    #[cfg(feature = "parallel")]
+    threads: Option<rayon::ThreadPool>,
+    rng: SeededRng,
+}
+
+// Keeps Simulation embeddable in multithreaded programs
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Simulation>();
+};
+
+impl Simulation {
+    pub fn new(
+        population_size: usize,
+        cities: Vec<City>,
+        max_iterations: usize,
+        crossover_rate: f64,
+        mutation_rate: f64,
+        survival_rate: f64,
+        rng: SeededRng,
+    ) -> Result<Simulation, Error> {
+        check_parameters(population_size, crossover_rate, mutation_rate, survival_rate)?;
+        if cities.len() < MIN_CITIES {
+            return Err(Error::Problem(format!("a tour needs at least {} cities, but there are {}", MIN_CITIES, cities.len())));
+        }
+
+        let mut sim = Simulation {
+            population: Population::new(cities.len()),
+            next: Population::new(cities.len()),
+            spill: None,
+            max_memory: None,
+            restored: Vec::new(),
+            first_generation: 0,
+            generation: 0,
+            best: None,
+            hall_of_fame: None,
+            checkpoints: None,
+            dumps: None,
+            hints: None,
+            repl: None,
+            stop_length: None,
+            stagnation_limit: None,
+            time_limit: None,
+            city_list: cities,
+            max_iterations,
+            counts: BreedingCounts::new(population_size, crossover_rate, survival_rate),
+            elitism: false,
+            selection: Box::new(Truncation),
+            crossover: Crossover::default(),
+            mutation: Box::new(Swap),
+            mutation_rate,
+            adaptive_mutation: None,
+            adaptive_operators: None,
+            local_search: None,
+            dedup: None,
+            init: Init::default(),
+            init_fraction: 0.0,
+            tracer: Tracer::disabled(),
+            migration: None,
+            evaluator: None,
+            observers: Vec::new(),
+            on_notice: None,
+            watch: Watch::new(DEFAULT_STAGNATION, None),
+            optimum: None,
+            eta: Eta::new(None),
+            event_log: None,
+            polish: false,
+            prefix: Vec::new(),
+            required: RequiredEdges::default(),
+            metric: Metric::default(),
+            distance_metric: None,
+            matrix: None,
+            matrix_memory: None,
+            precision: Precision::default(),
+            distances_given: false,
+            asymmetric: false,
+            tour_type: TourType::default(),
+            quarantined: 0,
+            evaluations: 0,
+            summary: None,
+            verify: false,
+            #[cfg(feature = "parallel")]
+            threads: None,
+            rng,
+        };
+        sim.measure_distances();
+        Ok(sim)
+    }
+
+    /// The seed all of the run's randomness derives from; passing a generator of the same
+    /// algorithm and seed to `new` again with the same arguments reproduces the run.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    pub fn cities(&self) -> &[City] {
+        &self.city_list
+    }
+
+    pub fn population_size(&self) -> usize {
+        self.counts.population_size()
+    }
+
+    /// Paths picked to breed each generation.
+    pub fn breeding_count(&self) -> usize {
+        self.counts.breeding_count
+    }
+
+    pub fn mutation_rate(&self) -> f64 {
+        self.mutation_rate
+    }
+
+    /// Generations the run goes on for, unless it's stopped early.
+    pub fn generations(&self) -> usize {
+        self.max_iterations
+    }
+
+    /// Raises the mutation rate while the population's share of distinct tours is below the
+    /// threshold of `adaptive` and lowers it while it isn't, within its bounds, starting from
+    /// the rate given to `new` moved into them.
+    pub fn set_adaptive_mutation(&mut self, adaptive: Adaptive) {
+        self.mutation_rate = adaptive.clamp(self.mutation_rate);
+        self.adaptive_mutation = Some(adaptive);
+    }
+
+    /// Builds `fraction` of the initial population, between 0 and 1, with `init` instead of at
+    /// random, for the run to start from shorter tours.
+    pub fn set_init(&mut self, init: Init, fraction: f64) -> Result<(), Error> {
+        debug_assert!(self.population.is_empty(), "the population already exists");
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(Error::Parameter { name: "initialisation fraction", reason: format!("{} is not between 0 and 1", fraction) });
+        }
+        self.init = init;
+        self.init_fraction = fraction;
+        Ok(())
+    }
+
+    /// Replaces tours that are already in the population after every generation, as `dedup`
+    /// says. A spilled population is left as it is.
+    pub fn set_dedup(&mut self, dedup: Dedup) {
+        self.dedup = Some(dedup);
+    }
+
+    /// Improves the tours `search` targets with it every generation, after they're mutated.
+    pub fn set_local_search(&mut self, search: LocalSearch) {
+        self.local_search = Some(search);
+    }
+
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = tracer;
+    }
+
+    /// Scores paths with `evaluator` instead of the tour length, re-scoring the current
+    /// population with it.
+    pub fn set_evaluator(&mut self, evaluator: Box<dyn Evaluator>) -> Result<(), Error> {
+        self.evaluator = Some(evaluator);
+
+        if let Some(mut spill) = self.spill.take() {
+            let len = spill.len();
+            for start in (0..len).step_by(spill.chunk_size()) {
+                let paths = (start..(start + spill.chunk_size()).min(len))
+                    .map(|i| spill.read(i))
+                    .collect::<std::io::Result<Vec<Path>>>()
+                    .map_err(Error::spill_read)?;
+                let orders: Vec<&[usize]> = paths.iter().map(|p| &p.order[..]).collect();
+                let fitness = self.evaluate(&orders)?;
+                for (i, (p, fitness)) in (start..).zip(paths.into_iter().zip(fitness)) {
+                    spill.replace(i, &Path { fitness, order: p.order })
+                        .map_err(Error::spill_write)?;
+                }
+            }
+            self.spill = Some(spill);
+            return self.reload_spilled();
+        }
+
+        let mut population = std::mem::take(&mut self.population);
+        let all: Vec<usize> = (0..population.len()).collect();
+        let rescored = self.rescore(&mut population, &all);
+        self.population = population;
+        rescored
+    }
+
+    pub fn set_migration(&mut self, migration: Migration) {
+        self.migration = Some(migration);
+    }
+
+    /// Reports a `Stagnating` event after `stagnation` generations without improvement and,
+    /// with a `diversity_floor`, a `DiversityCollapsed` event when the population's position
+    /// entropy falls below it, which means measuring it every generation.
+    pub fn set_watch(&mut self, stagnation: usize, diversity_floor: Option<f64>) {
+        self.watch = Watch::new(stagnation, diversity_floor);
+    }
+
+    /// Ends the run early once the best path hasn't improved for `generations` generations.
+    pub fn set_stagnation_limit(&mut self, generations: usize) {
+        self.stagnation_limit = Some(generations.max(1));
+    }
+
+    /// Ends the run after the first generation to finish `limit` or more after it started,
+    /// with the best path found by then.
+    pub fn set_time_limit(&mut self, limit: Duration) {
+        self.time_limit = Some(limit);
+    }
+
+    /// The length of the optimal tour, so the summary can report how far from it the run ended.
+    pub fn set_optimum(&mut self, length: f64) {
+        self.optimum = Some(length);
+    }
+
+    /// Estimates when the run will reach a tour of `length` instead of when it will stall.
+    pub fn set_target(&mut self, length: f64) {
+        self.eta = Eta::new(Some(length));
+    }
+
+    /// Logs each generation, new best path, stagnation and diversity event, checkpoint and the
+    /// end of the run to `log`.
+    pub fn set_event_log(&mut self, log: EventLog) {
+        self.event_log = Some(log);
+    }
+
+    /// Keeps the `size` fittest distinct tours seen over the whole run, for `hall_of_fame`.
+    pub fn set_hall_of_fame(&mut self, size: usize) {
+        self.hall_of_fame = Some(HallOfFame::new(size));
+    }
+
+    /// The fittest distinct tours seen so far, fittest first, if `set_hall_of_fame` was called.
+    pub fn hall_of_fame(&self) -> &[Path] {
+        self.hall_of_fame.as_ref().map_or(&[], HallOfFame::paths)
+    }
+
+    /// Runs the best path to a 2-opt local optimum once the run is over. With an evaluator,
+    /// the polished path is only kept if the evaluator agrees it's fitter.
+    pub fn set_polish(&mut self, polish: bool) {
+        self.polish = polish;
+    }
+
+    /// Carries exactly the `count` fittest paths over to every next generation, unmutated,
+    /// instead of the share of the breeding pool the survival rate makes. Has to be set before
+    /// the population is created by the first generation.
+    pub fn set_elitism(&mut self, count: usize) {
+        debug_assert!(self.population.is_empty(), "the population already exists");
+        self.counts = self.counts.with_survivors(count);
+        if self.counts.surviving_parent_count < count {
+            self.notify(Notice::Warning(format!(
+                "only {} of {} paths can be elites, keeping that many",
+                self.counts.surviving_parent_count, self.counts.population_size()
+            )));
+        }
+        self.elitism = true;
+    }
+
+    /// Picks the breeding pool with `selection` rather than taking the fittest outright.
+    pub fn set_selection(&mut self, selection: Box<dyn Selection>) {
+        self.selection = selection;
+    }
+
+    /// Recombines parents with `crossover` rather than at a single point.
+    pub fn set_crossover(&mut self, crossover: Crossover) {
+        self.crossover = crossover;
+    }
+
+    /// Mutates paths with `mutation` rather than by swapping two cities.
+    pub fn set_mutation(&mut self, mutation: Box<dyn Mutation>) {
+        self.mutation = mutation;
+    }
+
+    /// Breeds and mutates with every crossover and mutation in place of the ones set, drawing
+    /// each by how much it has improved the tours it was used on lately. The chances start
+    /// even, and a resumed run starts them over.
+    pub fn set_adaptive_operators(&mut self) {
+        self.adaptive_operators = Some(Operators::all());
+    }
+
+    /// The operators and their chances, if they adapt.
+    pub fn adaptive_operators(&self) -> Option<&Operators> {
+        self.adaptive_operators.as_ref()
+    }
+
+    /// Scores and breeds paths on `count` threads of the run's own, rather than on as many as
+    /// there are cores. The run is the same either way.
+    #[cfg(feature = "parallel")]
+    pub fn set_threads(&mut self, count: usize) -> Result<(), Error> {
+        let threads = rayon::ThreadPoolBuilder::new()
+            .num_threads(count)
+            .thread_name(|i| format!("simulation-{}", i))
+            .build()
+            .map_err(|e| Error::Threads { count, reason: e.to_string() })?;
+        self.threads = Some(threads);
+        Ok(())
+    }
+
+    /// Measures the distances between cities with `metric` rather than as straight lines.
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+        self.distance_metric = None;
+        self.measure_distances();
+    }
+
+    /// Measures the distances between cities with a metric of the caller's own, in place of
+    /// any `Metric`, until `set_metric` is called again.
+    pub fn set_distance_metric(&mut self, metric: Box<dyn DistanceMetric>) {
+        self.distance_metric = Some(metric);
+        self.measure_distances();
+    }
+
+    fn measure(&self) -> &dyn DistanceMetric {
+        self.distance_metric.as_deref().unwrap_or(&self.metric)
+    }
+
+    /// Precomputes the distances between cities only if they take no more than `bytes` of
+    /// memory, rather than as much as pays off for the metric; bigger instances measure them leg
+    /// by leg as tours are scored. 0 always measures them on the fly. The run is the same either
+    /// way.
+    pub fn set_matrix_memory(&mut self, bytes: usize) {
+        self.matrix_memory = Some(bytes);
+        self.measure_distances();
+    }
+
+    /// Keeps the distances between cities, measured or given, at `precision`. `F32` halves the
+    /// memory a matrix takes, so that one of more cities fits in the memory allowed, at the cost
+    /// of lengths good to about seven significant digits.
+    pub fn set_distance_precision(&mut self, precision: Precision) {
+        self.precision = precision;
+        match self.matrix.take() {
+            Some(matrix) if self.distances_given => self.matrix = Some(matrix.with_precision(precision)),
+            _ => self.measure_distances(),
+        }
+    }
+
+    /// Scores tours by the given `distances` between cities, e.g. road travel times or tolls,
+    /// instead of measuring them between coordinates. The cost of a leg is that from the city it
+    /// leaves to the one it reaches, which needn't be the same the other way round.
+    pub fn set_distances(&mut self, distances: DistanceMatrix) -> Result<(), Error> {
+        if distances.len() != self.city_list.len() {
+            return Err(Error::Problem(format!("the distances are between {} cities, not {}", distances.len(), self.city_list.len())));
+        }
+        if distances.missing_legs() > 0 {
+            return Err(Error::Problem(format!("{} legs have no cost, and need one from with_penalty", distances.missing_legs())));
+        }
+        self.asymmetric |= !distances.is_symmetric();
+        self.matrix = Some(distances.with_precision(self.precision));
+        self.distances_given = true;
+        Ok(())
+    }
+
+    /// Treats the cost of a leg as depending on its direction, as for one-way streets, even if
+    /// no distances given so far do. Given distances that aren't symmetric make the run
+    /// asymmetric anyway. Only the polish takes it into account; tours are always scored in
+    /// the direction they go.
+    pub fn set_asymmetric(&mut self, asymmetric: bool) {
+        self.asymmetric = asymmetric;
+    }
+
+    pub fn asymmetric(&self) -> bool {
+        self.asymmetric
+    }
+
+    /// Scores tours as open paths that end at their last city, rather than as closed ones that
+    /// go back to their first. Has to be set before the run, as the population is scored
+    /// whichever way it's set when it's created.
+    pub fn set_tour_type(&mut self, tour_type: TourType) {
+        self.tour_type = tour_type;
+    }
+
+    pub fn tour_type(&self) -> TourType {
+        self.tour_type
+    }
+
+    /// The distance from city `a` to city `b`, by index.
+    pub fn distance(&self, a: usize, b: usize) -> f64 {
+        match &self.matrix {
+            Some(matrix) => matrix.get(a, b),
+            None => self.measure().distance(&self.city_list[a], &self.city_list[b]),
+        }
+    }
+
+    /// The distances between cities, if they were given with `set_distances`.
+    pub fn distances(&self) -> Option<&DistanceMatrix> {
+        self.matrix.as_ref().filter(|_| self.distances_given)
+    }
+
+    fn measure_distances(&mut self) {
+        if self.distances_given {
+            return;
+        }
+        let limit = self.matrix_memory.unwrap_or_else(|| self.measure().matrix_memory());
+        let fits = limit > 0 && DistanceMatrix::bytes(self.city_list.len(), self.precision) <= limit;
+        self.matrix = if fits { Some(DistanceMatrix::measured(&self.city_list, self.measure(), self.precision)) } else { None };
+    }
+
+    /// Makes every path start with the cities of `prefix`, in that order, e.g. stops already
+    /// committed to, and evolves only the rest of the tour. Has to be set before the population
+    /// is created by the first generation.
+    pub fn set_prefix(&mut self, prefix: Vec<usize>) -> Result<(), Error> {
+        debug_assert!(self.population.is_empty(), "the population already exists");
+        let mut seen = vec![false; self.city_list.len()];
+        for &city in &prefix {
+            match seen.get_mut(city) {
+                None => return Err(Error::Problem(format!("there's no city {}, only {}", city, self.city_list.len()))),
+                Some(true) => return Err(Error::Problem(format!("city {} is in the prefix twice", city))),
+                Some(seen) => *seen = true,
+            }
+        }
+
+        self.required = RequiredEdges::new(self.required.edges().to_vec(), self.city_list.len(), &prefix).map_err(Error::Problem)?;
+        self.prefix = prefix;
+        Ok(())
+    }
+
+    /// Makes every path take each of `edges`, in one direction or the other. Has to be set
+    /// before the population is created by the first generation.
+    pub fn set_required_edges(&mut self, edges: Vec<(usize, usize)>) -> Result<(), Error> {
+        debug_assert!(self.population.is_empty(), "the population already exists");
+        self.required = RequiredEdges::new(edges, self.city_list.len(), &self.prefix).map_err(Error::Problem)?;
+        Ok(())
+    }
+
+    /// Keeps the population within about `bytes` of memory: if it needs more, only the breeding
+    /// pool and the few weakest paths stay in memory and the rest is spilled to a temporary file
+    /// between generations. The run is otherwise the same. Has to be set before the population
+    /// is created by the first generation.
+    pub fn set_max_memory(&mut self, bytes: usize) {
+        debug_assert!(self.population.is_empty(), "the population already exists");
+        self.max_memory = Some(bytes);
+    }
+
+    /// Writes a checkpoint to `path` every `interval` generations and when the run ends.
+    pub fn set_checkpoints(&mut self, path: PathBuf, interval: usize) {
+        self.checkpoints = Some((path, interval.max(1)));
+    }
+
+    /// Writes a snapshot of the whole population, orders and fitness, as `dumps` says.
+    pub fn set_population_dumps(&mut self, dumps: PopulationDumps) {
+        self.dumps = Some(dumps);
+    }
+
+    fn dump_population(&mut self, generation: usize) -> Result<(), Error> {
+        let path = match &self.dumps {
+            Some(dumps) => dumps.path(generation),
+            None => return Ok(()),
+        };
+
+        let paths: Vec<Path> = (0..self.path_count()).map(|i| self.path_at(i)).collect::<Result<_, _>>()?;
+        match dump::write(&path, generation, self.city_list.len(), &paths) {
+            Ok(()) => if let Some(log) = &mut self.event_log {
+                log.write("population_dump", generation, &json!({ "path": path }));
+            },
+            Err(e) => self.notify(Notice::Warning(format!("could not write the population to {}: {}", path.display(), e))),
+        }
+        Ok(())
+    }
+
+    /// Continues the run saved in `checkpoint`: the population starts from its tours and the
+    /// generations it already evolved count towards `max_iterations`. The generator carries on
+    /// from where it was, and so does the rate of adaptive mutation if it's set first, so the
+    /// run goes on as the one that took the checkpoint did.
+    pub fn resume(&mut self, checkpoint: Checkpoint) -> Result<(), Error> {
+        if checkpoint.cities != self.city_list.len() {
+            return Err(Error::Problem(format!(
+                "the checkpoint is for {} cities, not {}", checkpoint.cities, self.city_list.len()
+            )));
+        }
+        let algorithm: RngAlgorithm = checkpoint.rng.parse().map_err(Error::Problem)?;
+
+        let city_count = self.city_list.len();
+        self.restored = checkpoint.orders.into_iter().filter(|order| Path::is_tour(order, city_count)).collect();
+        self.first_generation = checkpoint.generation;
+        self.generation = checkpoint.generation;
+        self.best = None;
+        self.rng = SeededRng::resumed(algorithm, checkpoint.seed, checkpoint.state);
+        if let (Some(adaptive), Some(rate)) = (self.adaptive_mutation, checkpoint.mutation_rate) {
+            self.mutation_rate = adaptive.clamp(rate);
+        }
+        self.population.clear();
+        self.spill = None;
+
+        Ok(())
+    }
+
+    /// The state of the run after `generation` generations. The generator is reseeded so that
+    /// its state can be saved, which makes the run go on differently than it would have without
+    /// the checkpoint, though the same as when it's resumed.
+    pub fn checkpoint(&mut self, generation: usize) -> Result<Checkpoint, Error> {
+        self.populate()?;
+
+        Ok(Checkpoint {
+            generation,
+            seed: self.rng.seed(),
+            rng: self.rng.algorithm().to_string(),
+            state: self.rng.reseed(),
+            mutation_rate: self.adaptive_mutation.map(|_| self.mutation_rate),
+            cities: self.city_list.len(),
+            orders: (0..self.path_count()).map(|i| self.path_at(i).map(|p| p.order)).collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn save_checkpoint(&mut self, generation: usize) -> Result<(), Error> {
+        let checkpoint = self.checkpoint(generation)?;
+        if let Some((path, _)) = &self.checkpoints {
+            match checkpoint::save(path, &checkpoint) {
+                Ok(()) => if let Some(log) = &mut self.event_log {
+                    log.write("checkpoint", generation, &json!({ "path": path }));
+                },
+                Err(e) => self.notify(Notice::Warning(format!("could not write the checkpoint {}: {}", path.display(), e))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the population's invariants after every generation, panicking on the first one
+    /// broken. Slow, but catches operators that corrupt tours or leave fitness stale.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// Solves, and returns the best tour found with the best length after every generation.
+    /// Prints nothing; the command line reports on the run with a callback of its own.
+    pub fn run(&mut self) -> Result<SolutionReport, Error> {
+        self.run_with(|_| ControlFlow::Continue(()))
+    }
+
+    /// Like `run`, calling `on_generation` after each generation as `solve` does.
+    pub fn run_with<F: FnMut(&Report) -> ControlFlow<()>>(&mut self, mut on_generation: F) -> Result<SolutionReport, Error> {
+        let mut history = Vec::new();
+        let solved = self.solve(|report| {
+            history.push(1.0 / report.fittest.fitness);
+            on_generation(report)
+        });
+        self.tracer.shutdown();
+        self.pass_on_warnings();
+        let fittest = solved?;
+
+        Ok(SolutionReport {
+            length: 1.0 / fittest.fitness,
+            fitness: fittest.fitness,
+            best_tour: fittest.order,
+            generations_run: self.summary.as_ref().map_or(0, |summary| summary.generations),
+            history,
+            hall_of_fame: self.hall_of_fame().iter().map(|path| (path.order.clone(), path.length())).collect(),
+        })
+    }
+
+    /// How many paths have had a NaN or infinite fitness, and were given the worst instead.
+    pub fn quarantined(&self) -> usize {
+        self.quarantined
+    }
+
+    /// Evolves the population by one generation, creating it first if there's none yet, for
+    /// programs that run the loop themselves, e.g. to draw the best tour as it improves. Unlike
+    /// `solve`, it prints nothing, writes no checkpoints or logs and leaves stopping to the
+    /// caller, whatever `max_iterations` is.
+    pub fn step(&mut self) -> Result<(), Error> {
+        self.populate()?;
+        if self.best.is_none() {
+            let fittest = self.find_fittest();
+            self.record_best(&fittest);
+            self.enter_hall_of_fame();
+        }
+        let span = self.tracer.start("generation", None);
+        let evolved = self.evolve(self.generation, &span);
+        self.tracer.end(span);
+        evolved.map(|_| ())
+    }
+
+    /// The best path found so far, by `solve` or `step`, or `None` before either has made a
+    /// population.
+    pub fn best(&self) -> Option<&Path> {
+        self.best.as_ref()
+    }
+
+    /// How many generations `solve` and `step` have evolved so far, counting those before a
+    /// checkpoint the run was resumed from.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// The spread of tour lengths in the current population, or `None` before there is one. For
+    /// a spilled population, only of the paths kept in memory.
+    pub fn population_stats(&self) -> Option<PopulationStats> {
+        if self.population.is_empty() {
+            return None;
+        }
+        Some(PopulationStats::of(self.population.fitness_values().iter().filter(|&&f| f > 0.0).map(|f| 1.0 / f)))
+    }
+
+    /// Statistics on the last call to `solve`.
+    pub fn summary(&self) -> Option<&Summary> {
+        self.summary.as_ref()
+    }
+
+    /// Evolves the population for `max_iterations` generations, calling `on_generation` with
+    /// a report on each one. Returning `ControlFlow::Break` from it ends the run early.
+    pub fn solve<F: FnMut(&Report) -> ControlFlow<()>>(&mut self, mut on_generation: F) -> Result<Path, Error> {
+        let started = Instant::now();
+        let evaluations = self.evaluations;
+        self.populate()?;
+        self.watch.reset();
+        self.eta.reset();
+
+        let mut run_span = self.tracer.start("run", None);
+        run_span.set_attribute("population_size", self.counts.population_size() as f64);
+        run_span.set_attribute("cities", self.city_list.len() as f64);
+
+        let mut fittest = self.find_fittest();
+        self.record_best(&fittest);
+        self.enter_hall_of_fame();
+        self.eta.record(started.elapsed(), 1.0 / fittest.fitness);
+        let mut summary = Summary {
+            generations: 0,
+            termination: Termination::Completed,
+            evaluations: 0,
+            wall_time: Duration::default(),
+            initial_length: 1.0 / fittest.fitness,
+            final_length: 1.0 / fittest.fitness,
+            best_generation: None,
+            best_found_after: started.elapsed(),
+            optimum: self.optimum,
+            polished_from: None,
+            required_edges: None,
+        };
+
+        // The REPL can move the last generation while the run goes on
+        for generation in self.first_generation.. {
+            if generation >= self.max_iterations {
+                break;
+            }
+            let mut generation_span = self.tracer.start("generation", Some(&run_span));
+            let (challenger, hints) = match self.evolve(generation, &generation_span) {
+                Ok(evolved) => evolved,
+                Err(e) => {
+                    self.tracer.end(generation_span);
+                    self.tracer.end(run_span);
+                    return Err(e);
+                }
+            };
+
+            let improved = challenger.fitness > fittest.fitness;
+            if improved {
+                fittest = challenger;
+                summary.best_generation = Some(generation);
+                summary.best_found_after = started.elapsed();
+            }
+            summary.generations += 1;
+
+            self.eta.record(started.elapsed(), 1.0 / fittest.fitness);
+            let mut report = Report {
+                generation,
+                fittest: &fittest,
+                events: Vec::new(),
+                estimate: self.eta.estimate(started.elapsed()),
+                population: &self.population,
+                city_count: self.city_list.len(),
+                diversity: OnceCell::new(),
+            };
+            METRICS.record_generation(1.0 / fittest.fitness);
+
+            generation_span.set_attribute("generation", generation as f64);
+            generation_span.set_attribute("best_length", 1.0 / fittest.fitness);
+            // Measuring diversity takes about as long as breeding, so only when someone's watching
+            if METRICS.enabled() || self.tracer.enabled() {
+                let diversity = report.diversity();
+                METRICS.record_diversity(&diversity);
+                generation_span.set_attribute("unique_ratio", diversity.unique_ratio);
+                generation_span.set_attribute("edge_overlap", diversity.edge_overlap);
+                generation_span.set_attribute("position_entropy", diversity.position_entropy);
+            }
+            self.tracer.end(generation_span);
+
+            report.events = self.watch.observe(improved, self.watch.diversity_floor().map(|_| report.diversity()));
+            report.events.extend(hints);
+            if let Some(log) = &mut self.event_log {
+                if improved {
+                    log.write("new_best", generation, &json!({ "length": 1.0 / fittest.fitness }));
+                }
+                for event in &report.events {
+                    log.write("event", generation, event);
+                }
+                log.write("generation", generation, &json!({
+                    "best_length": 1.0 / fittest.fitness,
+                    "mutation_rate": self.mutation_rate,
+                    "diversity": report.diversity.get(),
+                    "estimate": report.estimate,
+                }));
+            }
+            let mut stop = false;
+            for observer in &mut self.observers {
+                stop |= observer(&report).is_break();
+            }
+            stop |= on_generation(&report).is_break();
+            let estimate = report.estimate;
+            stop |= self.control(generation, &fittest, &estimate);
+            if let Some(length) = self.stop_length.filter(|&length| 1.0 / fittest.fitness <= length) {
+                summary.termination = Termination::TargetReached { length, generation };
+                stop = true;
+            } else if stop {
+                summary.termination = Termination::Stopped;
+            } else if let Some(limit) = self.time_limit.filter(|&limit| started.elapsed() >= limit) {
+                summary.termination = Termination::TimeLimit(limit);
+                stop = true;
+            } else if self.stagnation_limit.is_some_and(|limit| self.watch.unimproved() >= limit) {
+                summary.termination = Termination::Stagnated(self.watch.unimproved());
+                stop = true;
+            }
+
+            let last = stop || generation + 1 == self.max_iterations;
+            if self.checkpoints.as_ref().is_some_and(|(_, interval)| last || (generation + 1).is_multiple_of(*interval)) {
+                self.save_checkpoint(generation + 1)?;
+            }
+            if self.dumps.as_ref().is_some_and(|dumps| dumps.due(generation + 1)) {
+                self.dump_population(generation + 1)?;
+            }
+            self.pass_on_warnings();
+
+            if stop {
+                break;
+            }
+        }
+
+        if self.polish {
+            let polish_span = self.tracer.start("polish", Some(&run_span));
+            summary.polished_from = Some(1.0 / fittest.fitness);
+            fittest = self.polished(fittest)?;
+            self.record_best(&fittest);
+            self.tracer.end(polish_span);
+        }
+
+        run_span.set_attribute("best_length", 1.0 / fittest.fitness);
+        self.tracer.end(run_span);
+        self.finish_summary(summary, started, evaluations, &fittest);
+        self.pass_on_warnings();
+
+        Ok(fittest)
+    }
+
+    /// Breeds generation `generation` and does what comes after it: migration, taking in hints,
+    /// verifying and adapting the mutation rate. Returns the fittest path of the new population,
+    /// and the events the hints brought.
+    fn evolve(&mut self, generation: usize, span: &Span) -> Result<(Path, Vec<Event>), Error> {
+        self.selection.set_progress(self.generation as f64 / self.max_iterations.max(1) as f64);
+        self.generate_next_generation(span)?;
+        if let Some(dedup) = self.dedup.filter(|_| self.spill.is_none()) {
+            self.deduplicate(dedup)?;
+        }
+        self.generation += 1;
+        let fittest = self.find_fittest();
+        self.record_best(&fittest);
+        self.enter_hall_of_fame();
+
+        if self.migration.as_ref().is_some_and(|m| m.due(generation)) {
+            self.migrate()?;
+        }
+        let hints = self.take_hints()?;
+
+        if self.verify {
+            self.verify_generation(generation)?;
+        }
+        if let Some(adaptive) = self.adaptive_mutation {
+            self.mutation_rate = adaptive.adjust(self.mutation_rate, diversity::unique_ratio(&self.population));
+        }
+        if let Some(operators) = &mut self.adaptive_operators {
+            operators.adapt();
+        }
+
+        Ok((fittest, hints))
+    }
+
+    fn record_best(&mut self, path: &Path) {
+        if self.best.as_ref().is_none_or(|best| path.fitness > best.fitness) {
+            self.best = Some(path.clone());
+        }
+        if let Some(hall) = &mut self.hall_of_fame {
+            hall.offer(&path.order, path.fitness, self.tour_type, self.asymmetric);
+        }
+    }
+
+    /// Offers every path of the population to the hall of fame, if one is kept.
+    fn enter_hall_of_fame(&mut self) {
+        match self.population.width() {
+            Width::U16 => self.enter_hall_of_fame_as::<u16>(),
+            Width::U32 => self.enter_hall_of_fame_as::<u32>(),
+        }
+    }
+
+    fn enter_hall_of_fame_as<G: Stored>(&mut self) {
+        if let Some(hall) = &mut self.hall_of_fame {
+            for (order, fitness) in self.population.iter::<G>() {
+                hall.offer(order, fitness, self.tour_type, self.asymmetric);
+            }
+        }
+    }
+
+    /// Completes the summary of a run and logs its end.
+    fn finish_summary(&mut self, mut summary: Summary, started: Instant, evaluations: usize, fittest: &Path) {
+        summary.evaluations = self.evaluations - evaluations;
+        summary.wall_time = started.elapsed();
+        summary.final_length = 1.0 / fittest.fitness;
+        if !self.required.is_empty() {
+            let required = self.required.edges().len();
+            summary.required_edges = Some((required - self.required.missing(&fittest.order).len(), required));
+        }
+
+        if let Some(log) = &mut self.event_log {
+            log.write("termination", self.first_generation + summary.generations, &json!({
+                "reason": summary.termination.reason(),
+                "summary": summary.to_json(),
+            }));
+        }
+        self.summary = Some(summary);
+    }
+
+    fn polished(&mut self, fittest: Path) -> Result<Path, Error> {
+        let mut order = fittest.order.clone();
+        polish::two_opt(&mut order, self.prefix.len(), &self.required, self.asymmetric, self.tour_type, usize::MAX, |a, b| self.distance(a, b));
+        let fitness = self.evaluate(&[&order])?[0];
+
+        if fitness > fittest.fitness {
+            Ok(Path { fitness, order })
+        } else {
+            Ok(fittest)
+        }
+    }
+
+    fn find_fittest(&self) -> Path {
+        self.population.path(self.population.fittest())
+    }
+
+    /// Swaps the fittest paths for ones published by other islands, which replace the weakest.
+    fn migrate(&mut self) -> Result<(), Error> {
+        let migrants = match &self.migration {
+            Some(migration) => migration.migrants(),
+            None => return Ok(()),
+        };
+
+        let emigrants = self.elites(migrants)?;
+        let emigrants: Vec<&[usize]> = emigrants.iter().map(|o| &o[..]).collect();
+
+        let exchange = self.migration.as_mut().map(|migration| migration.exchange(&emigrants));
+        let immigrants = match exchange.expect("migration is set") {
+            Ok(immigrants) => immigrants,
+            Err(e) => {
+                self.notify(Notice::Warning(format!("migration failed: {}", e)));
+                return Ok(());
+            }
+        };
+
+        self.immigrate(immigrants)
+    }
+
+    /// Takes commands from stdin after every generation, to inspect the run and change its
+    /// parameters while it goes on.
+    pub fn set_repl(&mut self, repl: Repl) {
+        self.repl = Some(repl);
+    }
+
+    /// Calls `observer` after every generation `solve` runs, this run and any after it, e.g. to
+    /// log or plot the run whichever way it's started. It sees the same `Report` as `solve`'s
+    /// own callback, just before it, and returning `ControlFlow::Break` from either ends the
+    /// run. Observers are called in the order they were added.
+    pub fn on_generation(&mut self, observer: impl FnMut(&Report) -> ControlFlow<()> + Send + Sync + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Calls `on_notice` with the warnings and answers to REPL commands the run has for whoever
+    /// is watching it, which otherwise go unsaid. Set it first to hear about the `set_*` calls.
+    pub fn on_notice(&mut self, on_notice: impl Fn(&Notice) + Send + Sync + 'static) {
+        self.on_notice = Some(Box::new(on_notice));
+    }
+
+    fn notify(&self, notice: Notice) {
+        if let Some(on_notice) = &self.on_notice {
+            on_notice(&notice);
+        }
+    }
+
+    /// Passes on the warnings the event log and tracer have left: that the log stopped, and of
+    /// spans that couldn't be exported.
+    fn pass_on_warnings(&mut self) {
+        let stopped = self.event_log.as_mut().and_then(EventLog::take_warning);
+        for notice in stopped.into_iter().chain(self.tracer.take_warnings()) {
+            self.notify(notice);
+        }
+    }
+
+    /// Ends the run once the best path is no longer than `length`.
+    pub fn set_stop_length(&mut self, length: f64) {
+        self.stop_length = Some(length);
+    }
+
+    /// How the run stands, for the REPL's `stats`.
+    fn stats(&self, generation: usize, fittest: &Path, estimate: &Estimate) -> String {
+        let counts = &self.counts;
+        format!(
+            "generation {}: best {:.3}, position entropy {:.3}, {}\n\
+             mutation {}, {} of {} breeders survive, {} of {} generations, {} evaluations",
+            generation,
+            1.0 / fittest.fitness,
+            diversity::measure(&self.population, self.city_list.len()).position_entropy,
+            estimate,
+            self.mutation_rate,
+            counts.surviving_parent_count,
+            counts.breeding_count,
+            generation + 1,
+            self.max_iterations,
+            self.evaluations
+        )
+    }
+
+    /// Carries out the REPL's commands after `generation`, returning whether to stop. While
+    /// paused, by `pause` or SIGUSR1, waits here for commands until resumed.
+    fn control(&mut self, generation: usize, fittest: &Path, estimate: &Estimate) -> bool {
+        let mut stop = false;
+        let mut paused = false;
+        let mut paused_at = None;
+        loop {
+            if repl::pause_signalled() {
+                paused = !paused;
+            }
+            let commands = match &self.repl {
+                Some(repl) if paused => repl.wait(PAUSE_POLL),
+                Some(repl) => repl.commands(),
+                None => {
+                    if paused {
+                        std::thread::sleep(PAUSE_POLL);
+                    }
+                    Vec::new()
+                }
+            };
+            for command in commands {
+                let command = match command {
+                    Ok(command) => command,
+                    Err(e) => {
+                        self.notify(Notice::Info(e));
+                        continue;
+                    }
+                };
+                match command {
+                    Command::Stats => self.notify(Notice::Info(self.stats(generation, fittest, estimate))),
+                    Command::Help => self.notify(Notice::Info(repl::HELP.to_string())),
+                    Command::Pause => paused = true,
+                    Command::Resume => paused = false,
+                    Command::Export(path) => self.notify(match repl::export(&path, fittest) {
+                        Ok(()) => Notice::Info(format!("wrote the best tour, of length {:.3}, to {}", fittest.length(), path.display())),
+                        Err(e) => Notice::Warning(format!("could not write {}: {}", path.display(), e)),
+                    }),
+                    Command::Stop => {
+                        stop = true;
+                        paused = false;
+                    }
+                    command => self.tune(command, generation),
+                }
+            }
+
+            match (paused, paused_at) {
+                (true, None) => {
+                    self.notify(Notice::Info(format!(
+                        "paused, type resume or send SIGUSR1 again to go on\n{}",
+                        self.stats(generation, fittest, estimate)
+                    )));
+                    if let Some(log) = &mut self.event_log {
+                        log.write("paused", generation, &json!({}));
+                    }
+                    paused_at = Some(Instant::now());
+                }
+                (false, Some(since)) => {
+                    let seconds = since.elapsed().as_secs_f64();
+                    self.notify(Notice::Info(format!("resumed after {:.1}s", seconds)));
+                    if let Some(log) = &mut self.event_log {
+                        log.write("resumed", generation, &json!({ "seconds": seconds }));
+                    }
+                    break;
+                }
+                (false, None) => break,
+                (true, Some(_)) => (),
+            }
+        }
+        stop
+    }
+
+    /// Applies a parameter change typed into the REPL after `generation`.
+    fn tune(&mut self, command: Command, generation: usize) {
+        let (parameter, value) = match command {
+            Command::Mutation(rate) => {
+                self.mutation_rate = rate;
+                self.notify(Notice::Info(format!("mutation rate is now {}", rate)));
+                ("mutation_rate", json!(rate))
+            }
+            Command::Elitism(rate) => {
+                self.counts = self.counts.with_survival_rate(rate);
+                self.elitism = false;
+                self.notify(Notice::Info(format!(
+                    "{} of {} breeders now survive",
+                    self.counts.surviving_parent_count, self.counts.breeding_count
+                )));
+                ("survival_rate", json!(rate))
+            }
+            Command::Generations(count) => {
+                self.max_iterations = count.max(generation + 1);
+                self.notify(Notice::Info(format!("ending after generation {}", self.max_iterations)));
+                ("generations", json!(self.max_iterations))
+            }
+            Command::StopAt(length) => {
+                self.stop_length = Some(length);
+                self.notify(Notice::Info(format!("ending once the best tour is {} or shorter", length)));
+                ("stop_length", json!(length))
+            }
+            _ => return,
+        };
+        if let Some(log) = &mut self.event_log {
+            log.write("tuned", generation, &json!({ "parameter": parameter, "value": value }));
+        }
+    }
+
+    /// Splices the tours dropped into `hints` into the population after every generation, as
+    /// `immigrate` does.
+    pub fn set_hints(&mut self, hints: Hints) {
+        self.hints = Some(hints);
+    }
+
+    /// Takes the tours dropped in as hints, returning an event for each that joined the population.
+    fn take_hints(&mut self) -> Result<Vec<Event>, Error> {
+        let dropped = match &self.hints {
+            Some(hints) => hints.take(),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut taken = Vec::new();
+        let mut events = Vec::new();
+        for (path, tour) in dropped {
+            match tour {
+                Ok(order) if self.fits(&order) => {
+                    events.push(Event::HintTaken { file: path.display().to_string() });
+                    taken.push(order);
+                }
+                Ok(_) => self.notify(Notice::Warning(format!(
+                    "ignoring the hint {}, which isn't a tour of the {} cities starting with the prefix",
+                    path.display(), self.city_list.len()
+                ))),
+                Err(e) => self.notify(Notice::Warning(format!("ignoring the hint {}: {}", path.display(), e))),
+            }
+        }
+        if !taken.is_empty() {
+            self.immigrate(taken)?;
+        }
+
+        Ok(events)
+    }
+
+    /// Replaces the weakest paths with `orders`, skipping any that aren't tours of these cities
+    /// starting with the prefix and repairing any missing required edges.
+    pub fn immigrate(&mut self, orders: Vec<Vec<usize>>) -> Result<(), Error> {
+        self.populate()?;
+
+        let mut valid: Vec<Vec<usize>> = orders.into_iter()
+            .filter(|order| self.fits(order))
+            .take(self.counts.population_size())
+            .collect();
+        for order in &mut valid {
+            self.required.repair(order);
+        }
+        let orders: Vec<&[usize]> = valid.iter().map(|o| &o[..]).collect();
+        let fitness = self.evaluate(&orders)?;
+
+        if let Some(spill) = &mut self.spill {
+            let ranked = spill.ranked();
+            for (&i, (order, fitness)) in ranked.iter().rev().zip(valid.into_iter().zip(fitness)) {
+                spill.replace(i, &Path { fitness, order })
+                    .map_err(Error::spill_write)?;
+            }
+            return self.reload_spilled();
+        }
+
+        self.population.sort(&mut self.next);
+        let weakest = (0..self.population.len()).rev();
+        for (i, (order, fitness)) in weakest.zip(valid.iter().zip(fitness)) {
+            self.population.replace(i, order, fitness);
+        }
+        Ok(())
+    }
+
+    /// The orders of the `count` fittest paths, fittest first.
+    pub fn elites(&mut self, count: usize) -> Result<Vec<Vec<usize>>, Error> {
+        self.populate()?;
+
+        if let Some(spill) = &mut self.spill {
+            return spill.ranked().into_iter()
+                .take(count)
+                .map(|i| spill.read(i).map(|p| p.order))
+                .collect::<std::io::Result<_>>()
+                .map_err(Error::spill_read);
+        }
+
+        self.population.sort(&mut self.next);
+        Ok((0..count.min(self.population.len())).map(|i| self.population.path(i).order).collect())
+    }
+
+    /// Panics unless the population is still the configured size, every path is a tour of all
+    /// the cities that keeps to the prefix and required edges, and a sample of fitness values match a fresh evaluation.
+    fn verify_generation(&mut self, generation: usize) -> Result<(), Error> {
+        const SAMPLE_SIZE: usize = 8;
+
+        let expected = self.counts.population_size();
+        let count = self.path_count();
+        assert_eq!(count, expected, "verify: generation {} has {} paths instead of {}", generation, count, expected);
+
+        let city_count = self.city_list.len();
+        for i in 0..count {
+            let p = self.path_at(i)?;
+            assert!(
+                p.order.len() == city_count && Path::is_tour(&p.order, city_count),
+                "verify: generation {} has {:?}, which isn't a tour of {} cities", generation, p.order, city_count
+            );
+            assert!(
+                p.order.starts_with(&self.prefix),
+                "verify: generation {} has {:?}, which doesn't start with {:?}", generation, p.order, self.prefix
+            );
+            let missing = self.required.missing(&p.order);
+            assert!(
+                missing.is_empty(),
+                "verify: generation {} has {:?}, which is missing the required edges {:?}", generation, p.order, missing
+            );
+        }
+
+        // Sampling with a generator of its own keeps verified runs identical to unverified ones
+        let mut sampler = SmallRng::seed_from_u64(rng::random_seed());
+        let sample: Vec<Path> = rand::seq::index::sample(&mut sampler, count, SAMPLE_SIZE.min(count))
+            .into_iter()
+            .map(|i| self.path_at(i))
+            .collect::<Result<_, _>>()?;
+        let (quarantined, evaluations) = (self.quarantined, self.evaluations);
+        let orders: Vec<&[usize]> = sample.iter().map(|p| &p.order[..]).collect();
+        let fresh = self.evaluate(&orders)?;
+        (self.quarantined, self.evaluations) = (quarantined, evaluations);
+
+        for (p, fresh) in sample.iter().zip(fresh) {
+            let stale = (p.fitness - fresh).abs() > 1e-9 * fresh.abs().max(1.0);
+            assert!(
+                !stale,
+                "verify: generation {} has {:?} with fitness {} but it evaluates to {}", generation, p.order, p.fitness, fresh
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether `order` is a tour of these cities starting with the prefix.
+    fn fits(&self, order: &[usize]) -> bool {
+        Path::is_tour(order, self.city_list.len()) && order.starts_with(&self.prefix)
+    }
+
+    fn path_count(&self) -> usize {
+        self.spill.as_ref().map_or(self.population.len(), Spill::len)
+    }
+
+    fn path_at(&mut self, index: usize) -> Result<Path, Error> {
+        match &mut self.spill {
+            Some(spill) => spill.read(index).map_err(Error::spill_read),
+            None => Ok(self.population.path(index)),
+        }
+    }
+
+    fn evaluate<G: Gene>(&mut self, orders: &[&[G]]) -> Result<Vec<f64>, Error> {
+        self.evaluations += orders.len();
+        let fitness = match &mut self.evaluator {
+            Some(evaluator) => {
+                let owned: Vec<Vec<usize>> = orders.iter().map(|order| order.iter().map(|city| city.index()).collect()).collect();
+                evaluator.evaluate(&owned.iter().map(|order| &order[..]).collect::<Vec<_>>())
+                    .map_err(|e| Error::Evaluation(e.to_string()))?
+            }
+            None => match &self.matrix {
+                Some(matrix) => self.map(orders.len(), |i| Path::matrix_fitness(orders[i], matrix, self.tour_type)),
+                None => match &self.distance_metric {
+                    Some(metric) => self.map(orders.len(), |i| Path::measured_fitness(orders[i], &self.city_list, &**metric, self.tour_type)),
+                    None => self.map(orders.len(), |i| Path::calculate_fitness(orders[i], &self.city_list, self.metric, self.tour_type)),
+                },
+            },
+        };
+
+        Ok(self.quarantine(orders, fitness))
+    }
+
+    /// `f` of 0..count, in order. With the `parallel` feature the calls are spread over threads,
+    /// so they mustn't draw random numbers.
+    #[cfg(feature = "parallel")]
+    fn map<T: Send>(&self, count: usize, f: impl Fn(usize) -> T + Send + Sync) -> Vec<T> {
+        let map = || (0..count).into_par_iter().map(&f).collect();
+        match &self.threads {
+            Some(threads) => threads.install(map),
+            None => map(),
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn map<T: Send>(&self, count: usize, f: impl Fn(usize) -> T + Send + Sync) -> Vec<T> {
+        (0..count).map(f).collect()
+    }
+
+    /// Calls `f` with the index and order of each path whose orders `genes` holds one after
+    /// another, to write them in place. Spread over threads as `map` is.
+    #[cfg(feature = "parallel")]
+    fn fill<G: Gene>(&self, genes: &mut [G], f: impl Fn(usize, &mut [G]) + Send + Sync) {
+        let mut fill = || genes.par_chunks_mut(self.city_list.len()).enumerate().for_each(|(i, order)| f(i, order));
+        match &self.threads {
+            Some(threads) => threads.install(fill),
+            None => fill(),
+        }
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn fill<G: Gene>(&self, genes: &mut [G], f: impl Fn(usize, &mut [G]) + Send + Sync) {
+        genes.chunks_mut(self.city_list.len()).enumerate().for_each(|(i, order)| f(i, order));
+    }
+
+    /// Gives paths with a NaN or infinite fitness the worst possible fitness instead, so they
+    /// can't win or upset the ordering of the population.
+    fn quarantine<G: Gene>(&mut self, orders: &[&[G]], mut fitness: Vec<f64>) -> Vec<f64> {
+        for (order, f) in orders.iter().zip(fitness.iter_mut()) {
+            if f.is_finite() {
+                continue;
+            }
+
+            if self.quarantined == 0 {
+                let order: Vec<String> = order.iter().map(|o| o.to_string()).collect();
+                self.notify(Notice::Warning(format!("quarantining {} with fitness {}", order.join("->"), f)));
+            }
+            self.quarantined += 1;
+            *f = 0.0;
+        }
+
+        fitness
+    }
+
+    /// Scores the paths of `paths` at `indices`.
+    fn rescore(&mut self, paths: &mut Population, indices: &[usize]) -> Result<(), Error> {
+        let fitness = match paths.width() {
+            Width::U16 => self.evaluate(&indices.iter().map(|&i| paths.order::<u16>(i)).collect::<Vec<_>>())?,
+            Width::U32 => self.evaluate(&indices.iter().map(|&i| paths.order::<u32>(i)).collect::<Vec<_>>())?,
+        };
+        for (&i, fitness) in indices.iter().zip(fitness) {
+            paths.set_fitness(i, fitness);
+        }
+        Ok(())
+    }
+
+    /// Mutates the path at `index` of `paths`, working out its new fitness from the legs the
+    /// mutation changed rather than from the whole tour where it can: where paths are scored by
+    /// their length, needn't be repaired and this one has a length to change. Returns whether
+    /// the path still needs scoring. A mutation the adaptive operators draw is noted in `tried`,
+    /// to be credited with `credit_mutations` once the path's scored.
+    fn mutate_path<G: Stored>(&mut self, paths: &mut Population, index: usize, tried: &mut Vec<Tried>) -> bool {
+        let offset = self.prefix.len();
+        let fitness = paths.fitness(index);
+        let order = paths.order_mut::<G>(index);
+        let mutation = match &self.adaptive_operators {
+            Some(operators) => {
+                let operator = operators.mutation.pick(&mut self.rng);
+                tried.push(Tried { index, operator, fitness });
+                &*operators.mutations[operator]
+            }
+            None => &*self.mutation,
+        };
+        let change = match mutation.draw(order.len() - offset, &mut self.rng) {
+            Some(change) => change,
+            None => return false,
+        };
+        if self.evaluator.is_some() || !self.required.is_empty() || !(fitness > 0.0 && fitness < f64::MAX) {
+            change.apply(&mut order[offset..], &mut self.rng);
+            self.required.repair(order);
+            return true;
+        }
+
+        let legs = change.legs(offset, order.len(), self.tour_type == TourType::Closed, self.asymmetric);
+        let before = self.legs_length(order, &legs);
+        change.apply(&mut order[offset..], &mut self.rng);
+        let after = self.legs_length(order, &legs);
+
+        // Unchanged legs leave the fitness as it was, rather than a rounding away from it
+        if after != before {
+            let cost = 1.0 / fitness + (after - before);
+            if cost > 0.0 {
+                paths.set_fitness(index, 1.0 / cost);
+            } else {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Credits the mutations `tried` on `paths` with how much fitter they left them.
+    fn credit_mutations(&mut self, paths: &Population, tried: &[Tried]) {
+        if let Some(operators) = &mut self.adaptive_operators {
+            for tried in tried {
+                operators.credit_mutation(tried.operator, tried.fitness, paths.fitness(tried.index));
+            }
+        }
+    }
+
+    /// The crossover to breed a child with: one the adaptive operators draw, by its place among
+    /// them, or `None` for the one that's set.
+    fn pick_crossover(&mut self) -> Option<usize> {
+        match &self.adaptive_operators {
+            Some(operators) => Some(operators.crossover.pick(&mut self.rng)),
+            None => None,
+        }
+    }
+
+    /// The crossover picked as `operator`.
+    fn crossover_for(&self, operator: Option<usize>) -> Crossover {
+        match (&self.adaptive_operators, operator) {
+            (Some(operators), Some(operator)) => operators.crossovers[operator],
+            _ => self.crossover,
+        }
+    }
+
+    /// The length of the `legs` of `order`, each by the position it leaves from.
+    fn legs_length<G: Gene>(&self, order: &[G], legs: &[usize]) -> f64 {
+        let mut length = 0.0;
+        for &leg in legs {
+            length += self.distance(order[leg].index(), order[(leg + 1) % order.len()].index());
+        }
+        length
+    }
+
+    fn generate_next_generation(&mut self, parent: &Span) -> Result<(), Error> {
+        self.populate()?;
+        match (self.population.width(), self.spill.is_some()) {
+            (Width::U16, false) => self.breed::<u16>(parent),
+            (Width::U32, false) => self.breed::<u32>(parent),
+            (Width::U16, true) => self.breed_spilled::<u16>(parent),
+            (Width::U32, true) => self.breed_spilled::<u32>(parent),
+        }
+    }
+
+    /// Breeds the next generation of a population kept in memory, with the city indices it
+    /// stores.
+    fn breed<G: Stored>(&mut self, parent: &Span) -> Result<(), Error> {
+        let selection_span = self.tracer.start("selection", Some(parent));
+        self.population.sort(&mut self.next);
+
+        let BreedingCounts { breeding_count, surviving_parent_count, surviving_weak_count, offspring_count } = self.counts;
+
+        let picks = self.selection.select(self.population.fitness_values(), breeding_count, &mut self.rng);
+        self.tracer.end(selection_span);
+
+        let crossover_span = self.tracer.start("crossover", Some(parent));
+        let pcnt_range = Uniform::new(0, picks.len());
+        let pairings: Vec<(usize, Option<usize>, (usize, usize))> = (0..offspring_count)
+            .map(|_| {
+                let rs = pcnt_range.sample(&mut self.rng);
+                let operator = self.pick_crossover();
+                (rs, operator, self.crossover_for(operator).cuts(self.city_list.len(), &mut self.rng))
+            })
+            .collect();
+
+        let mut next_generation = std::mem::take(&mut self.next);
+        next_generation.clear();
+        next_generation.extend_from(&self.population, 0..surviving_parent_count);
+        next_generation.grow(offspring_count);
+        let offspring = surviving_parent_count..surviving_parent_count + offspring_count;
+        self.fill(next_generation.orders_mut::<G>(offspring.clone()), |i, child| {
+            let (rs, operator, cuts) = pairings[i];
+            let mother = self.population.order(picks[i % picks.len()]);
+            let father = self.population.order(picks[rs]);
+            self.crossover_for(operator).apply(mother, father, cuts, child);
+            self.required.repair(child);
+        });
+        self.tracer.end(crossover_span);
+
+        let fitness_span = self.tracer.start("fitness", Some(parent));
+        self.rescore(&mut next_generation, &offspring.collect::<Vec<usize>>())?;
+        if let Some(operators) = &mut self.adaptive_operators {
+            for (i, &(rs, operator, _)) in pairings.iter().enumerate() {
+                let parent = self.population.fitness(picks[i % picks.len()]).max(self.population.fitness(picks[rs]));
+                operators.credit_crossover(operator, parent, next_generation.fitness(surviving_parent_count + i));
+            }
+        }
+        self.tracer.end(fitness_span);
+
+        // Add a few weak units to keep the genetic diversity
+        next_generation.extend_from(&self.population, (self.population.len() - surviving_weak_count)..self.population.len());
+        debug_assert_eq!(next_generation.len(), self.population.len());
+
+        let mutation_span = self.tracer.start("mutation", Some(parent));
+        let (mut mutated, mut tried) = (Vec::new(), Vec::new());
+        let elites = if self.elitism { surviving_parent_count } else { 0 };
+        for i in elites..next_generation.len() {
+            if self.rng.gen_bool(self.mutation_rate) && self.mutate_path::<G>(&mut next_generation, i, &mut tried) {
+                mutated.push(i);
+            }
+        }
+        self.rescore(&mut next_generation, &mutated)?;
+        self.credit_mutations(&next_generation, &tried);
+        self.tracer.end(mutation_span);
+
+        if let Some(search) = self.local_search {
+            let targets = match search.target {
+                Target::Offspring => surviving_parent_count..surviving_parent_count + offspring_count,
+                Target::Elite => 0..surviving_parent_count,
+            };
+            let local_search_span = self.tracer.start("local_search", Some(parent));
+            self.improve(&mut next_generation, targets)?;
+            self.tracer.end(local_search_span);
+        }
+
+        self.next = std::mem::replace(&mut self.population, next_generation);
+        Ok(())
+    }
+
+    /// Replaces every path that's the same tour as one before it in the population, the elites
+    /// coming first, with a random tour or a mutated copy, and rescores them.
+    fn deduplicate(&mut self, dedup: Dedup) -> Result<(), Error> {
+        match self.population.width() {
+            Width::U16 => self.deduplicate_as::<u16>(dedup),
+            Width::U32 => self.deduplicate_as::<u32>(dedup),
+        }
+    }
+
+    fn deduplicate_as<G: Stored>(&mut self, dedup: Dedup) -> Result<(), Error> {
+        let mut population = std::mem::take(&mut self.population);
+        let mut seen = HashSet::with_capacity(population.len());
+        let (mut replaced, mut tried) = (Vec::new(), Vec::new());
+        for i in 0..population.len() {
+            if seen.insert(self.tour_type.canonical(population.order::<G>(i), self.asymmetric)) {
+                continue;
+            }
+
+            let stale = match dedup {
+                Dedup::Random => {
+                    let order = population.order_mut::<G>(i);
+                    order[self.prefix.len()..].shuffle(&mut self.rng);
+                    self.required.repair(order);
+                    true
+                }
+                Dedup::Mutate => self.mutate_path::<G>(&mut population, i, &mut tried),
+            };
+            if stale {
+                replaced.push(i);
+            }
+        }
+
+        let rescored = self.rescore(&mut population, &replaced);
+        self.credit_mutations(&population, &tried);
+        self.population = population;
+        rescored
+    }
+
+    /// Runs the local search, if there is one, on the paths in `range`, rescoring the ones it
+    /// changes.
+    fn improve(&mut self, paths: &mut Population, range: Range<usize>) -> Result<(), Error> {
+        let search = match self.local_search {
+            Some(search) => search,
+            None => return Ok(()),
+        };
+
+        let improved = self.map(range.len(), |i| {
+            let path = paths.path(range.start + i);
+            let mut order = path.order.clone();
+            search.improve(&mut order, self.prefix.len(), &self.required, self.asymmetric, self.tour_type, |a, b| self.distance(a, b));
+            Some((range.start + i, order)).filter(|(_, order)| *order != path.order)
+        });
+        let changed: Vec<(usize, Vec<usize>)> = improved.into_iter().flatten().collect();
+        let orders: Vec<&[usize]> = changed.iter().map(|(_, order)| &order[..]).collect();
+        let fitness = self.evaluate(&orders)?;
+        for ((i, order), fitness) in changed.iter().zip(fitness) {
+            paths.replace(*i, order, fitness);
+        }
+        Ok(())
+    }
+
+    /// Breeds the next generation of a spilled population. It draws the same random numbers in
+    /// the same order as `breed`, so the run doesn't change, but offspring
+    /// are bred, mutated, scored and spilled a chunk at a time.
+    fn breed_spilled<G: Stored>(&mut self, parent: &Span) -> Result<(), Error> {
+        let mut spill = self.spill.take().expect("the population is spilled");
+        let BreedingCounts { breeding_count, surviving_parent_count, surviving_weak_count, offspring_count } = self.counts;
+
+        // What's in memory is already the fittest, at least as many as breed, and then the
+        // weakest. The pool is read from them unless the selection picks others.
+        let selection_span = self.tracer.start("selection", Some(parent));
+        let ranked = spill.ranked();
+        let fitness: Vec<f64> = ranked.iter().map(|&i| spill.fitness(i)).collect();
+        let picks = self.selection.select(&fitness, breeding_count, &mut self.rng);
+        let pool: Option<Population> = if picks.iter().copied().eq(0..breeding_count) {
+            None
+        } else {
+            let mut pool = Population::new(self.city_list.len());
+            for &rank in &picks {
+                if rank < breeding_count {
+                    pool.extend_from(&self.population, rank..rank + 1);
+                } else {
+                    let path = spill.read(ranked[rank]).map_err(Error::spill_read)?;
+                    pool.push(&path.order, path.fitness);
+                }
+            }
+            Some(pool)
+        };
+        self.tracer.end(selection_span);
+
+        let crossover_span = self.tracer.start("crossover", Some(parent));
+        let pcnt_range = Uniform::new(0, breeding_count);
+        let pairings: Vec<(usize, Option<usize>, (usize, usize))> = (0..offspring_count)
+            .map(|_| {
+                let rs = pcnt_range.sample(&mut self.rng);
+                let operator = self.pick_crossover();
+                (rs, operator, self.crossover_for(operator).cuts(self.city_list.len(), &mut self.rng))
+            })
+            .collect();
+
+        spill.clear();
+        let mut survivors = Population::new(self.city_list.len());
+        survivors.extend_from(&self.population, 0..surviving_parent_count);
+        let mut weakest = Population::new(self.city_list.len());
+        weakest.extend_from(&self.population, (self.population.len() - surviving_weak_count)..self.population.len());
+
+        let improve_elite = self.local_search.is_some_and(|search| search.target == Target::Elite);
+        let improve_offspring = self.local_search.is_some_and(|search| search.target == Target::Offspring);
+        if self.elitism {
+            if improve_elite {
+                self.improve(&mut survivors, 0..surviving_parent_count)?;
+            }
+            spill.push(&survivors).map_err(Error::spill_write)?;
+        } else {
+            self.mutate_and_spill::<G>(&mut spill, &mut survivors, improve_elite)?;
+        }
+        // Each chunk of offspring is bred into the buffer of the one before
+        let mut children = survivors;
+        for (start, chunk) in (0..).step_by(spill.chunk_size()).zip(pairings.chunks(spill.chunk_size())) {
+            let parents = pool.as_ref().unwrap_or(&self.population);
+            children.clear();
+            children.grow(chunk.len());
+            self.fill(children.orders_mut::<G>(0..chunk.len()), |j, child| {
+                let (rs, operator, cuts) = chunk[j];
+                let mother = parents.order((start + j) % breeding_count);
+                let father = parents.order(rs);
+                self.crossover_for(operator).apply(mother, father, cuts, child);
+                self.required.repair(child);
+            });
+            self.rescore(&mut children, &(0..chunk.len()).collect::<Vec<usize>>())?;
+            if let Some(operators) = &mut self.adaptive_operators {
+                let parents = pool.as_ref().unwrap_or(&self.population);
+                for (j, &(rs, operator, _)) in chunk.iter().enumerate() {
+                    let parent = parents.fitness((start + j) % breeding_count).max(parents.fitness(rs));
+                    operators.credit_crossover(operator, parent, children.fitness(j));
+                }
+            }
+            self.mutate_and_spill::<G>(&mut spill, &mut children, improve_offspring)?;
+        }
+        self.mutate_and_spill::<G>(&mut spill, &mut weakest, false)?;
+        self.tracer.end(crossover_span);
+
+        let ranking_span = self.tracer.start("selection", Some(parent));
+        self.spill = Some(spill);
+        let reloaded = self.reload_spilled();
+        self.tracer.end(ranking_span);
+        reloaded
+    }
+
+    /// Mutates `paths` as `breed` does, rescoring the mutated ones that need it, runs the local
+    /// search on them if they're to be `improved` and spills them.
+    fn mutate_and_spill<G: Stored>(&mut self, spill: &mut Spill, paths: &mut Population, improved: bool) -> Result<(), Error> {
+        let (mut rescored, mut tried) = (Vec::new(), Vec::new());
+        for i in 0..paths.len() {
+            if self.rng.gen_bool(self.mutation_rate) && self.mutate_path::<G>(paths, i, &mut tried) {
+                rescored.push(i);
+            }
+        }
+
+        self.rescore(paths, &rescored)?;
+        self.credit_mutations(paths, &tried);
+        if improved {
+            self.improve(paths, 0..paths.len())?;
+        }
+
+        spill.push(paths).map_err(Error::spill_write)?;
+        Ok(())
+    }
+
+    /// Creates the initial population on first use, spilling it if it doesn't fit in memory.
+    fn populate(&mut self) -> Result<(), Error> {
+        if !self.population.is_empty() {
+            return Ok(());
+        }
+
+        let population_size = self.counts.population_size();
+        let chunk_size = match self.spill_chunk_size() {
+            Some(chunk_size) => match Spill::create(self.city_list.len(), Width::of(self.city_list.len()), chunk_size) {
+                Ok(spill) => {
+                    self.notify(Notice::Info(format!("spilling the population to {}", spill.path().display())));
+                    self.spill = Some(spill);
+                    chunk_size
+                }
+                Err(e) => {
+                    self.notify(Notice::Warning(format!(
+                        "could not create a file to spill the population to, keeping it in memory: {}", e
+                    )));
+                    population_size
+                }
+            },
+            None => population_size,
+        };
+
+        // The prefix first, then every other city
+        let base_list: Vec<usize> = self.prefix.iter().copied()
+            .chain((0..self.city_list.len()).filter(|city| !self.prefix.contains(city)))
+            .collect();
+        let mut restored: Vec<Vec<usize>> = std::mem::take(&mut self.restored);
+        restored.retain(|order| order.starts_with(&self.prefix));
+        let built = (population_size as f64 * self.init_fraction).round() as usize;
+        let mut built = self.build(&base_list, built.min(population_size.saturating_sub(restored.len()))).into_iter();
+        let mut restored = restored.into_iter();
+        for start in (0..population_size).step_by(chunk_size) {
+            let orders: Vec<Vec<usize>> = (start..(start + chunk_size).min(population_size)).map(|_| {
+                let mut order = restored.next().or_else(|| built.next()).unwrap_or_else(|| {
+                    let mut p = base_list.clone();
+                    p[self.prefix.len()..].shuffle(&mut self.rng);
+                    p
+                });
+                self.required.repair(&mut order);
+                order
+            }).collect();
+            let order_refs: Vec<&[usize]> = orders.iter().map(|o| &o[..]).collect();
+            let fitness = self.evaluate(&order_refs)?;
+
+            match &mut self.spill {
+                Some(spill) => {
+                    let mut paths = Population::new(self.city_list.len());
+                    for (order, fitness) in orders.iter().zip(fitness) {
+                        paths.push(order, fitness);
+                    }
+                    spill.push(&paths).map_err(Error::spill_write)?;
+                }
+                None => for (order, fitness) in orders.iter().zip(fitness) {
+                    self.population.push(order, fitness);
+                },
+            }
+        }
+
+        self.reload_spilled()
+    }
+
+    /// Up to `count` tours built with `init`, each starting with the prefix and then the other
+    /// cities of `base_list` in the order it puts them in.
+    fn build(&mut self, base_list: &[usize], count: usize) -> Vec<Vec<usize>> {
+        let (prefix, free) = base_list.split_at(self.prefix.len());
+        let tour = |rest: Vec<usize>| prefix.iter().copied().chain(rest).collect::<Vec<usize>>();
+        match self.init {
+            _ if count == 0 || free.is_empty() => Vec::new(),
+            Init::Random => Vec::new(),
+            Init::NearestNeighbor => {
+                let firsts: Vec<usize> = free.choose_multiple(&mut self.rng, count).copied().collect();
+                self.map(firsts.len(), |i| tour(init::nearest_neighbor(firsts[i], free, |a, b| self.distance(a, b))))
+            }
+            Init::Greedy => {
+                let edges = init::candidates(free, |a, b| self.distance(a, b));
+                (0..count).map(|i| {
+                    let edges = if i == 0 { edges.clone() } else { init::jitter(&edges, &mut self.rng) };
+                    tour(init::greedy(free, edges, self.asymmetric, prefix.last().copied(), |a, b| self.distance(a, b)))
+                }).collect()
+            }
+        }
+    }
+
+    /// How many paths to handle at a time if the population needs spilling to stay within
+    /// `max_memory`, or `None` if it can stay in memory.
+    fn spill_chunk_size(&self) -> Option<usize> {
+        let max_memory = self.max_memory?;
+        let population_size = self.counts.population_size();
+        let path_size = std::mem::size_of::<f64>() + self.city_list.len() * Width::of(self.city_list.len()).bytes();
+        // The next generation is bred beside the one it replaces
+        if population_size.saturating_mul(2 * path_size) <= max_memory {
+            return None;
+        }
+
+        let resident = self.counts.resident_fittest() + self.counts.surviving_weak_count;
+        if resident >= population_size {
+            self.notify(Notice::Warning(format!(
+                "the whole population breeds, so none of it can be spilled to stay within {} bytes", max_memory
+            )));
+            return None;
+        }
+
+        let spare = (max_memory / path_size).saturating_sub(resident);
+        if spare == 0 {
+            self.notify(Notice::Warning(format!(
+                "the breeding pool alone needs about {} bytes, more than the {} allowed",
+                resident * path_size, max_memory
+            )));
+        }
+
+        Some(spare.clamp(1, MAX_SPILL_CHUNK))
+    }
+
+    /// Reloads the part of a spilled population kept in memory: the fittest, as many as breed
+    /// or survive, fittest first, followed by the weakest paths.
+    fn reload_spilled(&mut self) -> Result<(), Error> {
+        let spill = match &mut self.spill {
+            Some(spill) => spill,
+            None => return Ok(()),
+        };
+
+        let ranked = spill.ranked();
+        let weakest = ranked.len() - self.counts.surviving_weak_count;
+        self.population.clear();
+        for &i in ranked[..self.counts.resident_fittest()].iter().chain(&ranked[weakest..]) {
+            let path = spill.read(i).map_err(Error::spill_read)?;
+            self.population.push(&path.order, path.fitness);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use testing::{is_permutation, simulation, twelve_cities};
+
+    fn cities(max_len: usize) -> impl Strategy<Value = Vec<City>> {
+        prop::collection::vec((-1e3..1e3f64, -1e3..1e3f64), 3..max_len)
+            .prop_map(|coords| coords.into_iter().map(|(x, y)| City::new(x, y)).collect())
+    }
+
+    #[test]
+    fn the_same_seed_reproduces_a_run() {
+        let solve = |algorithm| Simulation::new(30, twelve_cities(), 40, 0.4, 0.05, 0.3, SeededRng::new(algorithm, 42)).unwrap()
+            .solve(|_| ControlFlow::Continue(()))
+            .unwrap()
+            .order;
+
+        for algorithm in [RngAlgorithm::ChaCha, RngAlgorithm::Pcg64, RngAlgorithm::Small] {
+            assert_eq!(solve(algorithm), solve(algorithm));
+        }
+    }
+
+    #[test]
+    fn unreachable_cities_are_rejected_or_cost_a_penalty() {
+        for city in ["inf,1", "1,-inf", "NaN,1"] {
+            let csv = format!("x,y\n0,0\n{}\n2,2\n", city);
+            assert!(input::read_cities(csv.as_bytes()).is_err(), "{}", city);
+        }
+
+        // 3 can only be left for 4, and 4 can't be left at all
+        let matrix = input::read_matrix("0,1,2,inf,inf\n1,0,1,,inf\n2,1,0,inf,inf\n1,inf,inf,0,3\ninf,inf,inf,inf,0\n".as_bytes()).unwrap();
+        assert_eq!(matrix.missing_legs(), 12);
+        let components = connectivity::components(&matrix);
+        assert_eq!(components, vec![vec![3], vec![4], vec![0, 1, 2]]);
+        assert_eq!(connectivity::unreachable(&matrix, TourType::Closed).unwrap().to_string(), "{3}, {4}, {0, 1, 2}");
+        assert!(connectivity::unreachable(&matrix, TourType::Open).is_some());
+        let matrix = input::read_matrix("0,1,2,inf,inf\n1,0,1,,5\n2,1,0,inf,inf\n1,inf,inf,0,3\ninf,inf,inf,inf,0\n".as_bytes()).unwrap();
+        assert_eq!(connectivity::unreachable(&matrix, TourType::Open), None);
+        assert!(connectivity::unreachable(&matrix, TourType::Closed).is_some());
+
+        let mut sim = simulation(input::placeholders(5), 40);
+        assert!(sim.set_distances(matrix.clone()).is_err());
+        let (penalised, penalty) = matrix.with_penalty();
+        assert_eq!((penalty, penalised.get(0, 3), penalised.get(1, 4)), (26.0, 26.0, 5.0));
+        sim.set_distances(penalised).unwrap();
+        sim.set_tour_type(TourType::Open);
+        assert_eq!(sim.solve(|_| ControlFlow::Continue(())).unwrap().order(), &[3, 0, 2, 1, 4]);
+    }
+
+    #[test]
+    fn checkpoints_load_back_unless_they_are_from_a_newer_version() {
+        let path = std::env::temp_dir().join(format!("wasi-genetic-test-{}.checkpoint", std::process::id()));
+        let mut sim = simulation(twelve_cities(), 40);
+        let checkpoint = sim.checkpoint(0).unwrap();
+
+        checkpoint::save(&path, &checkpoint).unwrap();
+        assert_eq!(checkpoint::load(&path).unwrap(), checkpoint);
+
+        let version = |version| format!(" {}\n", version);
+        let first = std::fs::read_to_string(&path).unwrap()
+            .replacen(&version(checkpoint::VERSION), &version(1), 1)
+            .replacen(&format!(",\"state\":{}", checkpoint.state), "", 1)
+            .replacen(",\"mutation_rate\":null", "", 1);
+        std::fs::write(&path, first).unwrap();
+        let upgraded = checkpoint::load(&path).unwrap();
+        assert_eq!(upgraded.state, 42);
+        assert_eq!(upgraded.orders, checkpoint.orders);
+
+        let newer = std::fs::read_to_string(&path).unwrap().replacen(&version(1), &version(checkpoint::VERSION + 1), 1);
+        std::fs::write(&path, newer).unwrap();
+        let error = checkpoint::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(error, checkpoint::CheckpointError::TooNew(_)), "{}", error);
+    }
+
+    #[test]
+    fn a_resumed_run_goes_on_as_the_one_that_took_the_checkpoint() {
+        let path = std::env::temp_dir().join(format!("wasi-genetic-test-{}-resumed.checkpoint", std::process::id()));
+        let resumable = |generations| {
+            let mut sim = simulation(twelve_cities(), generations);
+            sim.set_adaptive_mutation("0.01:0.5".parse().unwrap());
+            sim
+        };
+
+        let mut whole = resumable(40);
+        whole.set_checkpoints(path.clone(), 20);
+        let fittest = whole.solve(|_| ControlFlow::Continue(())).unwrap().order;
+
+        let mut interrupted = resumable(20);
+        interrupted.set_checkpoints(path.clone(), 100);
+        interrupted.solve(|_| ControlFlow::Continue(())).unwrap();
+        let mut resumed = resumable(40);
+        resumed.resume(checkpoint::load(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.solve(|_| ControlFlow::Continue(())).unwrap().order, fittest);
+        assert_eq!(resumed.mutation_rate(), whole.mutation_rate());
+        assert_eq!(resumed.checkpoint(40).unwrap().orders, whole.checkpoint(40).unwrap().orders);
+    }
+
+    #[test]
+    fn spilling_does_not_change_a_run() {
+        let solve = |max_memory| {
+            let mut sim = simulation(twelve_cities(), 40);
+            sim.set_max_memory(max_memory);
+            sim.set_verify(true);
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap().order;
+            (fittest, sim.elites(30).unwrap())
+        };
+
+        assert_eq!(solve(1 << 10), solve(usize::MAX));
+    }
+
+    #[test]
+    fn a_spill_that_cant_be_written_ends_the_run_with_an_error() {
+        let mut sim = simulation(twelve_cities(), 40);
+        sim.set_max_memory(1 << 10);
+        sim.step().unwrap();
+        sim.spill.as_mut().expect("the population is spilled").make_read_only().unwrap();
+
+        let Err(e) = sim.solve(|_| ControlFlow::Continue(())) else { panic!("the run went on without its spill") };
+        assert!(matches!(e, Error::Spill { action: "write", .. }), "{}", e);
+    }
+
+    #[test]
+    fn local_search_takes_offspring_to_a_local_optimum() {
+        // Any tour of points on a circle that doesn't cross itself goes round it, so every 2-opt
+        // optimum is the shortest tour
+        let cities = || -> Vec<City> {
+            (0..40).map(|i| {
+                let angle = (i * 17 % 40) as f64 * std::f64::consts::TAU / 40.0;
+                City::new(angle.cos(), angle.sin())
+            }).collect()
+        };
+        let shortest = 40.0 * 2.0 * (std::f64::consts::PI / 40.0).sin();
+        let solve = |search: Option<LocalSearch>, max_memory| {
+            let mut sim = simulation(cities(), 10);
+            sim.set_max_memory(max_memory);
+            if let Some(search) = search {
+                sim.set_local_search(search);
+            }
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+            (fittest.length(), sim.elites(30).unwrap())
+        };
+
+        assert!(solve(None, usize::MAX).0 > shortest + 1.0);
+        let search = LocalSearch { kind: local_search::Kind::TwoOpt, target: local_search::Target::Offspring, budget: usize::MAX };
+        assert!((solve(Some(search), usize::MAX).0 - shortest).abs() < 1e-9);
+        let three_opt = LocalSearch { kind: local_search::Kind::ThreeOpt, ..search };
+        assert!((solve(Some(three_opt), usize::MAX).0 - shortest).abs() < 1e-9);
+
+        let elite = LocalSearch { target: local_search::Target::Elite, budget: 200, ..search };
+        assert_eq!(solve(Some(elite), 1 << 10), solve(Some(elite), usize::MAX));
+    }
+
+    #[test]
+    fn constructive_tours_start_the_run_shorter() {
+        // Two rows of cities further apart than the cities in them, which both heuristics go
+        // along one and back along the other
+        let cities: Vec<City> = (0..40).map(|i| City::new((i % 20) as f64, (i / 20) as f64 * 1.5)).collect();
+        let distance = |a: usize, b: usize| Metric::Euclidean.distance(&cities[a], &cities[b]);
+        let all: Vec<usize> = (0..40).collect();
+        for order in [init::nearest_neighbor(0, &all, distance), init::greedy(&all, init::candidates(&all, distance), false, None, distance)] {
+            assert!(Path::is_tour(&order, 40));
+            assert!((1.0 / Path::tour_fitness(&order, &cities, Metric::Euclidean, TourType::Closed) - 41.0).abs() < 1e-9, "{:?}", order);
+        }
+        let directed = init::greedy(&all, init::candidates(&all, distance), true, Some(0), distance);
+        assert!(Path::is_tour(&directed, 40));
+
+        let initial_length = |init, prefix: Vec<usize>| {
+            let mut sim = simulation(cities.clone(), 1);
+            sim.set_prefix(prefix.clone()).unwrap();
+            sim.set_init(init, 0.2).unwrap();
+            sim.set_verify(true);
+            sim.solve(|_| ControlFlow::Continue(())).unwrap();
+            let with_prefix = sim.elites(30).unwrap().into_iter().filter(|order| order.starts_with(&prefix)).count();
+            assert_eq!(with_prefix, 30);
+            sim.summary().unwrap().initial_length
+        };
+        let random = initial_length(Init::Random, Vec::new());
+        for init in [Init::NearestNeighbor, Init::Greedy] {
+            assert!(initial_length(init, Vec::new()) < random / 2.0);
+            assert!(initial_length(init, vec![5, 30]) < random / 2.0);
+        }
+    }
+
+    #[test]
+    fn verifying_does_not_change_a_run() {
+        let solve = |verify| {
+            let mut sim = simulation(twelve_cities(), 40);
+            sim.set_verify(verify);
+            sim.solve(|_| ControlFlow::Continue(())).unwrap().order
+        };
+
+        assert_eq!(solve(true), solve(false));
+    }
+
+    #[test]
+    fn a_flat_population_sorts_its_paths_fittest_first_in_place() {
+        let mut population = Population::new(4);
+        population.push(&[0usize, 1, 2, 3], 0.5);
+        population.push(&[3u32, 2, 1, 0], 0.75);
+        population.push(&[1usize, 0, 3, 2], 0.5);
+        population.push(&[2u32, 3, 0, 1], 1.0);
+
+        let mut scratch = Population::new(4);
+        population.sort(&mut scratch);
+        assert_eq!(population.fitness_values(), [1.0, 0.75, 0.5, 0.5]);
+        assert_eq!(population.order::<u16>(0), [2, 3, 0, 1]);
+        assert_eq!(population.path(2).order(), [0, 1, 2, 3], "equal fitness keeps its order");
+        assert_eq!(population.path(3).order(), [1, 0, 3, 2]);
+
+        population.replace(3, &[3usize, 1, 2, 0], 2.0);
+        assert_eq!((population.fittest(), population.order::<u16>(3)), (3, &[3, 1, 2, 0][..]));
+        population.clear();
+        population.grow(2);
+        assert_eq!((population.len(), population.orders_mut::<u16>(0..2).len()), (2, 8));
+    }
+
+    #[test]
+    fn compact_indices_and_f32_distances_keep_lengths_to_seven_digits() {
+        assert_eq!(Population::new(12).width(), Width::U16);
+        assert_eq!(Population::new(1 << 16).width(), Width::U16);
+        assert_eq!(Population::new((1 << 16) + 1).width(), Width::U32);
+
+        let cities = twelve_cities();
+        let mut sim = simulation(cities.clone(), 40);
+        sim.set_distance_precision(Precision::F32);
+        assert_eq!(sim.matrix.as_ref().map(DistanceMatrix::precision), Some(Precision::F32));
+
+        let report = sim.run().unwrap();
+        let exact = 1.0 / Path::calculate_fitness(&report.best_tour, &cities, Metric::Euclidean, TourType::Closed);
+        assert!((report.length - exact).abs() <= 1e-6 * exact, "{} scored as {}", exact, report.length);
+    }
+
+    #[test]
+    fn a_simulation_that_cant_run_is_an_error() {
+        let rng = || SeededRng::new(RngAlgorithm::ChaCha, 42);
+        assert!(matches!(Simulation::new(0, twelve_cities(), 40, 0.4, 0.05, 0.3, rng()), Err(Error::Parameter { name: "population size", .. })));
+        assert!(matches!(Simulation::new(30, Vec::new(), 40, 0.4, 0.05, 0.3, rng()), Err(Error::Problem(_))));
+        assert!(matches!(Simulation::new(30, twelve_cities()[..2].to_vec(), 40, 0.4, 0.05, 0.3, rng()), Err(Error::Problem(_))));
+        assert_eq!(
+            Simulation::new(30, twelve_cities(), 40, 0.4, 1.5, 0.3, rng()).err(),
+            Some(Error::Parameter { name: "mutation rate", reason: "1.5 is not between 0 and 1".to_string() })
+        );
+        assert!(matches!(Simulation::new(30, twelve_cities(), 40, 0.0, 0.05, 0.3, rng()), Err(Error::Parameter { name: "crossover rate", .. })));
+        assert!(matches!(Simulation::new(30, twelve_cities(), 40, 0.4, 0.05, f64::NAN, rng()), Err(Error::Parameter { name: "survival rate", .. })));
+
+        let mut sim = Simulation::new(30, twelve_cities(), 40, 0.4, 0.05, 0.3, rng()).unwrap();
+        assert_eq!(sim.set_prefix(vec![12]), Err(Error::Problem("there's no city 12, only 12".to_string())));
+    }
+
+    #[test]
+    fn every_tour_keeps_the_prefix() {
+        let mut sim = simulation(twelve_cities(), 40);
+        assert!(sim.set_prefix(vec![3, 3]).is_err());
+        assert!(sim.set_prefix(vec![12]).is_err());
+        sim.set_prefix(vec![7, 0, 4]).unwrap();
+        sim.set_polish(true);
+        sim.set_verify(true);
+
+        let fittest = sim.solve(|report| {
+            assert!(report.fittest.order.starts_with(&[7, 0, 4]));
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert!(fittest.order.starts_with(&[7, 0, 4]));
+        assert!(Path::is_tour(&fittest.order, 12));
+    }
+
+    #[test]
+    fn every_tour_takes_the_required_edges() {
+        let mut sim = simulation(twelve_cities(), 40);
+        assert!(sim.set_required_edges(vec![(1, 2), (2, 3), (3, 1)]).is_err());
+        assert!(sim.set_required_edges(vec![(1, 2), (1, 3), (1, 4)]).is_err());
+        sim.set_prefix(vec![0, 5]).unwrap();
+        assert!(sim.set_required_edges(vec![(5, 9)]).is_err());
+        sim.set_required_edges(vec![(0, 5), (9, 2), (2, 11), (4, 7)]).unwrap();
+        sim.set_polish(true);
+        sim.set_verify(true);
+
+        let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+        assert!(fittest.order.starts_with(&[0, 5]));
+        assert_eq!(sim.summary().unwrap().required_edges, Some((4, 4)));
+    }
+
+    #[test]
+    fn hints_join_the_population_once() {
+        let dir = std::env::temp_dir().join(format!("wasi-genetic-hints-{}", std::process::id()));
+        let cities: Vec<City> = (0..12).map(|i| City::new(i as f64, (i % 2) as f64)).collect();
+        let mut sim = simulation(cities.clone(), 5);
+        sim.set_hints(Hints::watch(dir.clone()).unwrap());
+        let straight: Vec<String> = (0..12).map(|i| i.to_string()).collect();
+        std::fs::write(dir.join("straight"), straight.join(",")).unwrap();
+        std::fs::write(dir.join("short"), "0,1,2").unwrap();
+        std::fs::write(dir.join(".partial"), "0,1").unwrap();
+
+        let mut taken = Vec::new();
+        let fittest = sim.solve(|report| {
+            taken.extend(report.events.iter().filter(|e| matches!(e, Event::HintTaken { .. })).cloned());
+            ControlFlow::Continue(())
+        }).unwrap();
+        let left: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(taken, vec![Event::HintTaken { file: dir.join("straight").display().to_string() }]);
+        assert_eq!(left, vec![".partial"]);
+        let hinted = 1.0 / Path::calculate_fitness(&(0..12).collect::<Vec<usize>>(), &cities, Metric::Euclidean, TourType::Closed);
+        assert!(1.0 / fittest.fitness <= hinted + 1e-9);
+    }
+
+    #[test]
+    fn a_paused_run_takes_commands_until_resumed() {
+        let file = std::env::temp_dir().join(format!("wasi-genetic-export-{}", std::process::id()));
+        let commands = format!("pause\nexport {}\nmutation 0.2\nresume\nstop\n", file.display());
+        let mut sim = simulation(twelve_cities(), 1_000_000);
+        sim.set_repl(Repl::reading(std::io::Cursor::new(commands)).unwrap());
+
+        let mut generations = 0;
+        sim.run_with(|_| {
+            generations += 1;
+            ControlFlow::Continue(())
+        }).unwrap();
+        let exported = diff::read_tour(&file);
+        std::fs::remove_file(&file).unwrap();
+
+        assert!(generations < 1_000_000);
+        assert!(Path::is_tour(&exported.unwrap(), 12));
+        assert_eq!(sim.mutation_rate, 0.2);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn threads_do_not_change_a_run() {
+        let solve = |threads| {
+            let mut sim = simulation(twelve_cities(), 40);
+            sim.set_crossover(Crossover::Ordered);
+            sim.set_threads(threads).unwrap();
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap().order;
+            (fittest, sim.elites(30).unwrap())
+        };
+
+        assert_eq!(solve(1), solve(4));
+    }
+
+    #[test]
+    fn stepping_a_run_goes_as_solving_it_does() {
+        let mut solved = simulation(twelve_cities(), 40);
+        let fittest = solved.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let mut stepped = simulation(twelve_cities(), 40);
+        assert!(stepped.best().is_none() && stepped.population_stats().is_none());
+        for _ in 0..40 {
+            stepped.step().unwrap();
+        }
+
+        assert_eq!((stepped.generation(), solved.generation()), (40, 40));
+        let best = |sim: &Simulation| sim.best().map(|best| (best.order.clone(), best.fitness));
+        assert_eq!(best(&stepped), Some((fittest.order.clone(), fittest.fitness)));
+        assert_eq!(best(&solved), best(&stepped));
+        assert_eq!(stepped.elites(30).unwrap(), solved.elites(30).unwrap());
+        let stats = stepped.population_stats().unwrap();
+        assert!(fittest.length() <= stats.best && stats.best <= stats.mean && stats.mean <= stats.worst && stats.std_dev >= 0.0);
+    }
+
+    #[test]
+    fn observers_see_every_generation_and_can_end_the_run() {
+        use std::sync::{Arc, Mutex};
+
+        let mut sim = simulation(twelve_cities(), 40);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        sim.on_generation(move |report| {
+            log.lock().unwrap().push((report.generation, report.population_stats().mean));
+            ControlFlow::Continue(())
+        });
+        sim.on_generation(|report| if report.generation == 9 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) });
+
+        let mut called = 0;
+        sim.solve(|_| {
+            called += 1;
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(called, 10);
+        assert_eq!(sim.summary().unwrap().termination, Termination::Stopped);
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.iter().map(|&(generation, _)| generation).collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert!(seen.iter().all(|&(_, mean)| mean.is_finite() && mean > 0.0));
+    }
+
+    #[test]
+    fn a_run_reports_what_it_found() {
+        let mut sim = simulation(twelve_cities(), 40);
+        let solution = sim.run().unwrap();
+
+        assert!(Path::is_tour(&solution.best_tour, 12));
+        assert_eq!((solution.generations_run, solution.history.len()), (40, 40));
+        assert!(solution.history.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(solution.history.last(), Some(&solution.length));
+        assert_eq!(solution.fitness, 1.0 / solution.length);
+        assert_eq!(sim.best().map(|best| best.order()), Some(&solution.best_tour[..]));
+    }
+
+    #[test]
+    fn the_hall_of_fame_keeps_the_best_distinct_tours() {
+        assert_eq!(TourType::Closed.canonical(&[3usize, 1, 0, 2], false), [0, 1, 3, 2]);
+        assert_eq!(TourType::Closed.canonical(&[2usize, 0, 1, 3], false), [0, 1, 3, 2]);
+        assert_eq!(TourType::Closed.canonical(&[2usize, 0, 1, 3], true), [0, 1, 3, 2]);
+        assert_eq!(TourType::Closed.canonical(&[3usize, 1, 0, 2], true), [0, 2, 3, 1]);
+        assert_eq!(TourType::Open.canonical(&[3usize, 1, 0, 2], false), [2, 0, 1, 3]);
+
+        let mut sim = simulation(twelve_cities(), 40);
+        sim.set_hall_of_fame(5);
+        let solution = sim.run().unwrap();
+
+        let hall = sim.hall_of_fame();
+        assert_eq!(hall.len(), 5);
+        assert_eq!(hall[0].order(), &solution.best_tour[..]);
+        assert!(hall.windows(2).all(|pair| pair[0].fitness >= pair[1].fitness));
+        let tours: HashSet<Vec<usize>> = hall.iter().map(|path| TourType::Closed.canonical(path.order(), false)).collect();
+        assert_eq!(tours.len(), 5);
+        assert_eq!(solution.hall_of_fame.len(), 5);
+    }
+
+    #[test]
+    fn dedup_leaves_no_tour_in_the_population_twice() {
+        let distinct = |dedup: Option<Dedup>| {
+            let mut sim = simulation(twelve_cities(), 40);
+            if let Some(dedup) = dedup {
+                sim.set_dedup(dedup);
+            }
+            sim.set_verify(true);
+            for _ in 0..40 {
+                sim.step().unwrap();
+            }
+            sim.population.iter::<u16>().map(|(order, _)| TourType::Closed.canonical(order, false)).collect::<HashSet<_>>().len()
+        };
+
+        assert_eq!(distinct(Some(Dedup::Random)), 30);
+        assert!(distinct(Some(Dedup::Mutate)) > distinct(None));
+    }
+
+    #[test]
+    fn the_distance_matrix_does_not_change_a_run() {
+        let solve = |matrix_memory| {
+            let mut sim = simulation(twelve_cities(), 40);
+            sim.set_metric(Metric::Haversine);
+            sim.set_matrix_memory(matrix_memory);
+            assert_eq!(sim.matrix.is_some(), matrix_memory > 0);
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+            (fittest.order, fittest.fitness)
+        };
+
+        assert_eq!(solve(0), solve(usize::MAX));
+    }
+
+    #[test]
+    fn tours_are_measured_with_a_distance_metric_of_the_callers_own() {
+        struct Manhattan;
+        impl geo::DistanceMetric for Manhattan {
+            fn distance(&self, a: &City, b: &City) -> f64 {
+                (a.x - b.x).abs() + (a.y - b.y).abs()
+            }
+        }
+
+        let cities = twelve_cities();
+        let solve = |matrix_memory| {
+            let mut sim = simulation(cities.clone(), 40);
+            sim.set_distance_metric(Box::new(Manhattan));
+            sim.set_matrix_memory(matrix_memory);
+            assert_eq!(sim.distance(0, 1), 5.0);
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+            (fittest.order, fittest.fitness)
+        };
+
+        let (order, fitness) = solve(0);
+        let length: f64 = TourType::Closed.legs(&order).map(|(a, b)| Manhattan.distance(&cities[a], &cities[b])).sum();
+        assert!((1.0 / fitness - length).abs() < 1e-9);
+        assert_eq!(solve(usize::MAX), (order, fitness));
+    }
+
+    #[test]
+    fn notices_go_to_on_notice_rather_than_being_printed() {
+        let mut sim = simulation(twelve_cities(), 40);
+        let notices = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let heard = notices.clone();
+        sim.on_notice(move |notice| heard.lock().unwrap().push(notice.clone()));
+        sim.set_elitism(100);
+
+        assert_eq!(*notices.lock().unwrap(), [Notice::Warning("only 28 of 30 paths can be elites, keeping that many".to_string())]);
+        assert_eq!(notices.lock().unwrap()[0].to_string(), "warning: only 28 of 30 paths can be elites, keeping that many");
+    }
+
+    #[test]
+    fn elites_survive_unchanged_whether_spilled_or_not() {
+        let solve = |max_memory| {
+            // More elites than breeders, and every other path mutating
+            let mut sim = Simulation::new(30, twelve_cities(), 40, 0.2, 1.0, 0.3, SeededRng::new(RngAlgorithm::ChaCha, 42)).unwrap();
+            sim.set_elitism(8);
+            sim.set_max_memory(max_memory);
+            sim.set_verify(true);
+            let mut elites = sim.elites(8).unwrap();
+            for _ in 0..10 {
+                sim.max_iterations = 1;
+                sim.solve(|_| ControlFlow::Continue(())).unwrap();
+                let next = sim.elites(30).unwrap();
+                assert!(elites.iter().all(|elite| next.contains(elite)), "an elite was lost or mutated");
+                elites = next[..8].to_vec();
+            }
+            elites
+        };
+
+        assert_eq!(solve(1 << 10), solve(usize::MAX));
+    }
+
+    #[test]
+    fn a_stagnating_run_ends_early_and_says_why() {
+        // Three cities on a line are solved by the initial population
+        let cities = vec![City::new(0.0, 0.0), City::new(1.0, 0.0), City::new(2.0, 0.0)];
+        let mut sim = Simulation::new(10, cities, 1000, 0.4, 0.1, 0.3, SeededRng::new(RngAlgorithm::ChaCha, 42)).unwrap();
+        sim.set_stagnation_limit(25);
+        sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let summary = sim.summary().unwrap();
+        assert_eq!((summary.generations, summary.termination), (25, Termination::Stagnated(25)));
+        assert_eq!(summary.to_json()["termination"], "stagnated");
+    }
+
+    #[test]
+    fn a_run_ends_at_the_target_length() {
+        let mut sim = simulation(twelve_cities(), 1000);
+        let target = 45.0;
+        sim.set_stop_length(target);
+        let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let summary = sim.summary().unwrap();
+        assert!(fittest.length() <= target);
+        assert_eq!(summary.termination, Termination::TargetReached { length: target, generation: summary.generations - 1 });
+        assert!(summary.generations < 1000);
+    }
+
+    #[test]
+    fn the_mean_fitness_is_no_better_than_the_best() {
+        let mut sim = simulation(twelve_cities(), 40);
+        let mut means = Vec::new();
+        sim.solve(|report| {
+            means.push((report.mean_fitness(), report.fittest.fitness));
+            ControlFlow::Continue(())
+        }).unwrap();
+
+        assert_eq!(means.len(), 40);
+        assert!(means.iter().all(|&(mean, best)| mean > 0.0 && mean <= best));
+    }
+
+    #[test]
+    fn a_run_ends_at_its_time_limit() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5m"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 2h "), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("10"), Ok(Duration::from_secs(10)));
+        assert!(parse_duration("-1s").is_err() && parse_duration("5 days").is_err() && parse_duration("s").is_err());
+
+        let mut sim = simulation(twelve_cities(), usize::MAX);
+        sim.set_time_limit(Duration::from_millis(50));
+        sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let summary = sim.summary().unwrap();
+        assert_eq!(summary.termination, Termination::TimeLimit(Duration::from_millis(50)));
+        assert!(summary.wall_time >= Duration::from_millis(50) && summary.wall_time < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn tours_are_scored_by_given_distances() {
+        // Going up the cities costs 1 a leg and coming down 10, and the coordinates say nothing
+        let costs: Vec<Vec<f64>> = (0..6).map(|a| (0..6).map(|b| if b > a { 1.0 } else if b < a { 10.0 } else { 0.0 }).collect()).collect();
+        let csv: String = costs.iter().map(|row| row.iter().map(f64::to_string).collect::<Vec<_>>().join(",") + "\n").collect();
+        let distances = input::read_matrix(format!("a,b,c,d,e,f\n{}", csv).as_bytes()).unwrap();
+        assert_eq!((distances.get(0, 1), distances.get(1, 0)), (1.0, 10.0));
+        assert!(input::read_matrix("1,2\n3\n".as_bytes()).is_err());
+
+        let mut sim = simulation(input::placeholders(6), 40);
+        sim.set_distances(distances).unwrap();
+        sim.set_tour_type(TourType::Open);
+        sim.set_polish(true);
+        let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+        assert_eq!(fittest.order(), &[0, 1, 2, 3, 4, 5]);
+        assert_eq!(fittest.length(), 5.0);
+        assert_eq!(sim.distance(5, 0), 10.0);
+        assert!(sim.set_distances(input::read_matrix("0,1\n1,0\n".as_bytes()).unwrap()).is_err());
+
+        let outcome = output::Outcome {
+            fittest: &fittest,
+            cities: sim.cities(),
+            given: sim.cities(),
+            metric: Metric::Euclidean,
+            tour_type: TourType::Open,
+            distances: sim.distances(),
+            summary: None,
+            hall_of_fame: &[],
+        };
+        assert_eq!(outcome.result(42)["tour_length"], json!(5.0));
+
+        let mut sim = simulation(input::placeholders(3), 10);
+        sim.set_distances(input::read_matrix("0,4,9\n1,0,2\n9,9,0\n".as_bytes()).unwrap()).unwrap();
+        assert!(sim.asymmetric());
+    }
+
+    #[test]
+    fn closed_tours_pay_for_the_leg_back_to_the_start() {
+        let line: Vec<City> = (0..10).map(|i| City::new(i as f64, 0.0)).collect();
+        let solve = |tour_type: TourType| {
+            let mut sim = simulation(line.clone(), 40);
+            sim.set_tour_type(tour_type);
+            sim.set_polish(true);
+            sim.solve(|_| ControlFlow::Continue(())).unwrap().length()
+        };
+        assert_eq!(solve(TourType::Closed), 18.0);
+        assert_eq!(solve(TourType::Open), 9.0);
+
+        assert_eq!(TourType::default(), TourType::Closed);
+        assert_eq!("open".parse::<TourType>().map(|tour_type| tour_type.to_string()), Ok("open".to_string()));
+        assert!("round".parse::<TourType>().is_err());
+        assert_eq!(TourType::Closed.legs(&[2usize, 0, 1]).collect::<Vec<_>>(), vec![(2, 0), (0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn adaptive_mutation_rises_as_tours_converge_and_stays_in_bounds() {
+        let adaptive: Adaptive = "0.1:0.5".parse().unwrap();
+        let mut sim = Simulation::new(30, twelve_cities(), 40, 0.4, 0.9, 0.3, SeededRng::new(RngAlgorithm::ChaCha, 42)).unwrap();
+        sim.set_adaptive_mutation(Adaptive { threshold: 1.0, ..adaptive });
+        assert_eq!(sim.mutation_rate(), 0.5);
+        // Short of every tour being distinct the rate rises, and a bred population has duplicates
+        sim.mutation_rate = 0.1;
+        sim.solve(|_| ControlFlow::Continue(())).unwrap();
+        assert_eq!(sim.mutation_rate(), 0.5);
+
+        sim.set_adaptive_mutation(Adaptive { threshold: 0.0, ..adaptive });
+        sim.solve(|_| ControlFlow::Continue(())).unwrap();
+        assert_eq!(sim.mutation_rate(), 0.1);
+    }
+
+    #[test]
+    fn spilling_does_not_change_adaptive_operators() {
+        let solve = |max_memory| {
+            let mut sim = simulation(twelve_cities(), 40);
+            sim.set_max_memory(max_memory);
+            sim.set_adaptive_operators();
+            sim.set_verify(true);
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap().order;
+            (fittest, sim.elites(30).unwrap(), sim.adaptive_operators().unwrap().to_string())
+        };
+        let (fittest, elites, chances) = solve(usize::MAX);
+        assert_ne!(chances, "one-point 0.50, ox 0.50; swap 0.25, inversion 0.25, scramble 0.25, insertion 0.25");
+        assert_eq!(solve(1 << 10), (fittest, elites, chances));
+    }
+
+    proptest! {
+
+        #[test]
+        fn fitness_is_the_reciprocal_of_the_tour_length(cities in cities(30)) {
+            let order: Vec<usize> = (0..cities.len()).collect();
+            let leg = |a: &City, b: &City| ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+            let length: f64 = cities.windows(2).map(|pair| leg(&pair[0], &pair[1])).sum();
+            let back = leg(&cities[cities.len() - 1], &cities[0]);
+            prop_assume!(length > 0.0);
+
+            let open = Path::calculate_fitness(&order, &cities, Metric::Euclidean, TourType::Open);
+            prop_assert!((open - 1.0 / length).abs() <= 1e-9 * open.abs());
+            let closed = Path::calculate_fitness(&order, &cities, Metric::Euclidean, TourType::Closed);
+            prop_assert!((closed - 1.0 / (length + back)).abs() <= 1e-9 * closed.abs());
+        }
+
+        #[test]
+        fn paired_legs_measure_a_tour_to_the_last_bit(cities in cities(30), closed in any::<bool>(), seed in any::<u64>()) {
+            let tour = if closed { TourType::Closed } else { TourType::Open };
+            let mut order: Vec<u16> = (0..cities.len() as u16).collect();
+            order.shuffle(&mut SmallRng::seed_from_u64(seed));
+
+            for metric in [Metric::Euclidean, Metric::Att] {
+                let one_by_one = tour.legs(&order).fold(0.0, |cost, (a, b)| cost + metric.distance(&cities[a], &cities[b]));
+                prop_assert_eq!(simd::length(&order, &cities, metric, tour), Some(one_by_one));
+            }
+            prop_assert_eq!(simd::length(&order, &cities, Metric::Haversine, tour), None);
+        }
+
+        #[test]
+        fn polishing_keeps_a_tour_and_never_lengthens_it(cities in cities(30), closed in any::<bool>()) {
+            let tour = if closed { TourType::Closed } else { TourType::Open };
+            let mut order: Vec<usize> = (0..cities.len()).rev().collect();
+            let before = Path::tour_fitness(&order, &cities, Metric::Euclidean, tour);
+
+            polish::two_opt(&mut order, 0, &RequiredEdges::default(), false, tour, usize::MAX, |a, b| Metric::Euclidean.distance(&cities[a], &cities[b]));
+            prop_assert!(Path::is_tour(&order, cities.len()));
+            prop_assert!(Path::tour_fitness(&order, &cities, Metric::Euclidean, tour) >= before * (1.0 - 1e-12));
+        }
+
+        #[test]
+        fn polishing_never_lengthens_an_asymmetric_tour(costs in prop::collection::vec(0.0..100.0f64, 20 * 20), closed in any::<bool>()) {
+            let tour = if closed { TourType::Closed } else { TourType::Open };
+            let distances = DistanceMatrix::from_rows(costs.chunks(20).map(<[f64]>::to_vec).collect()).unwrap();
+            prop_assume!(!distances.is_symmetric());
+            let mut order: Vec<usize> = (0..20).collect();
+            let before = Path::matrix_fitness(&order, &distances, tour);
+
+            polish::two_opt(&mut order, 0, &RequiredEdges::default(), true, tour, usize::MAX, |a, b| distances.get(a, b));
+            prop_assert!(Path::is_tour(&order, 20));
+            prop_assert!(Path::matrix_fitness(&order, &distances, tour) >= before * (1.0 - 1e-12));
+        }
+
+        #[test]
+        fn local_search_keeps_a_tour_its_prefix_and_required_edges_and_never_lengthens_it(
+            costs in prop::collection::vec(0.0..100.0f64, 15 * 15),
+            symmetric in any::<bool>(),
+            closed in any::<bool>(),
+            fixed in 0..4usize,
+            kind in prop_oneof![Just(local_search::Kind::TwoOpt), Just(local_search::Kind::OrOpt), Just(local_search::Kind::ThreeOpt)],
+            budget in prop_oneof![1..200usize, Just(usize::MAX)],
+        ) {
+            let tour = if closed { TourType::Closed } else { TourType::Open };
+            let cost = |a: usize, b: usize| if symmetric { costs[a.min(b) * 15 + a.max(b)] } else { costs[a * 15 + b] };
+            let distances = DistanceMatrix::from_rows((0..15).map(|a| (0..15).map(|b| cost(a, b)).collect()).collect()).unwrap();
+            let mut order: Vec<usize> = (0..15).collect();
+            let required = RequiredEdges::new(vec![(6, 7)], 15, &order[..fixed]).unwrap();
+            let before = Path::matrix_fitness(&order, &distances, tour);
+
+            let search = LocalSearch { kind, target: local_search::Target::Offspring, budget };
+            let moves = search.improve(&mut order, fixed, &required, !distances.is_symmetric(), tour, |a, b| distances.get(a, b));
+            prop_assert!(moves <= budget);
+            prop_assert!(Path::is_tour(&order, 15));
+            prop_assert!(order.iter().take(fixed).copied().eq(0..fixed));
+            prop_assert!(required.missing(&order).is_empty());
+            prop_assert!(Path::matrix_fitness(&order, &distances, tour) >= before * (1.0 - 1e-12));
+        }
+
+        #[test]
+        fn breeding_counts_always_fill_the_population(
+            population_size in 1..100_000usize,
+            crossover_rate in 0.0..=1.0f64,
+            survival_rate in 0.0..=1.0f64,
+        ) {
+            prop_assume!(check_parameters(population_size, crossover_rate, 0.0, survival_rate).is_ok());
+            let counts = BreedingCounts::new(population_size, crossover_rate, survival_rate);
+
+            prop_assert!(counts.breeding_count >= 1 && counts.breeding_count <= population_size);
+            prop_assert!(counts.surviving_parent_count <= counts.breeding_count);
+            prop_assert_eq!(
+                counts.surviving_parent_count + counts.offspring_count + counts.surviving_weak_count,
+                population_size
+            );
+        }
+
+        #[test]
+        fn generations_keep_valid_tours_with_accurate_fitness(
+            cities in cities(20),
+            population_size in 1..40usize,
+            crossover_rate in 0.0..=1.0f64,
+            mutation_rate in 0.0..=1.0f64,
+            survival_rate in 0.0..=1.0f64,
+            seed in any::<u64>(),
+        ) {
+            prop_assume!(population_size as f64 * crossover_rate >= 1.0);
+            let city_count = cities.len();
+            let mut sim = Simulation::new(
+                population_size, cities, 1, crossover_rate, mutation_rate, survival_rate,
+                SeededRng::new(RngAlgorithm::ChaCha, seed),
+            ).unwrap();
+            let span = sim.tracer.start("test", None);
+
+            for _ in 0..5 {
+                sim.generate_next_generation(&span).unwrap();
+
+                prop_assert_eq!(sim.population.len(), population_size);
+                for (order, fitness) in sim.population.iter::<u16>() {
+                    prop_assert!(is_permutation(order, city_count), "{:?}", order);
+                    // Mutated paths are rescored from the legs that changed, which rounds differently
+                    let fresh = Path::calculate_fitness(order, &sim.city_list, Metric::Euclidean, sim.tour_type);
+                    prop_assert!((fitness - fresh).abs() <= 1e-12 * fresh, "{} for {:?} of fitness {}", fitness, order, fresh);
+                }
+            }
+        }
+    }
+}
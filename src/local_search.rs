@@ -0,0 +1,106 @@
+//! Local search on the tours of every generation, which makes the genetic algorithm a memetic
+//! one: crossover and mutation move the search around, and local search takes each tour it's
+//! given down to the nearest local optimum, or as near as its budget allows.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::polish;
+use crate::required::RequiredEdges;
+use crate::tour::TourType;
+
+/// Moves weighed up on each tour, unless set otherwise.
+pub const DEFAULT_BUDGET: usize = 5000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Reverses stretches of the tour, as `--polish` does.
+    TwoOpt,
+    /// Moves stretches of up to three cities elsewhere.
+    OrOpt,
+    /// 2-opt moves, and then cuts the tour in three places and joins the pieces up another way.
+    ThreeOpt,
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        match s {
+            "2opt" => Ok(Kind::TwoOpt),
+            "or-opt" => Ok(Kind::OrOpt),
+            "3opt" => Ok(Kind::ThreeOpt),
+            _ => Err(format!("unknown local search {:?}, expected 2opt, or-opt or 3opt", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::TwoOpt => "2opt",
+            Kind::OrOpt => "or-opt",
+            Kind::ThreeOpt => "3opt",
+        })
+    }
+}
+
+/// Which tours of a generation are improved.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Target {
+    /// The children bred this generation.
+    #[default]
+    Offspring,
+    /// The breeders that survive into the next generation.
+    Elite,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        match s {
+            "offspring" => Ok(Target::Offspring),
+            "elite" => Ok(Target::Elite),
+            _ => Err(format!("unknown local search target {:?}, expected offspring or elite", s)),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Target::Offspring => "offspring",
+            Target::Elite => "elite",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalSearch {
+    pub kind: Kind,
+    pub target: Target,
+    /// Moves weighed up on each tour before the search gives up on it, which trades how short
+    /// the tours get for how long the search takes.
+    pub budget: usize,
+}
+
+impl LocalSearch {
+    /// Improves `order`, leaving its first `fixed` cities and `required` edges in place, and
+    /// returns how many moves it weighed up.
+    pub(crate) fn improve(
+        &self,
+        order: &mut [usize],
+        fixed: usize,
+        required: &RequiredEdges,
+        asymmetric: bool,
+        tour: TourType,
+        distance: impl Fn(usize, usize) -> f64,
+    ) -> usize {
+        match self.kind {
+            Kind::TwoOpt => polish::two_opt(order, fixed, required, asymmetric, tour, self.budget, distance),
+            Kind::OrOpt => polish::or_opt(order, fixed, required, tour, self.budget, distance),
+            Kind::ThreeOpt => polish::three_opt(order, fixed, required, asymmetric, tour, self.budget, distance),
+        }
+    }
+}
@@ -1,232 +1,1567 @@
+// The run manifest's parameters are one json! literal, deeper than the default limit allows
+#![recursion_limit = "256"]
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::ops::ControlFlow;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
-use csv::Reader;
-use rand::{thread_rng, Rng};
-use rand::seq::SliceRandom;
-use rand::distributions::{Distribution, Uniform};
-use serde::Deserialize;
+use serde_json::json;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
-#[derive(Deserialize)]
-pub struct City {
-    x: f64,
-    y: f64,
+use wasi_genetic::barrier::{Avoid, Barriers, Penalty};
+use wasi_genetic::builtin::{self, Instance};
+use wasi_genetic::config::{self, Setting};
+use wasi_genetic::crossover::Crossover;
+use wasi_genetic::dedup::Dedup;
+use wasi_genetic::dump::PopulationDumps;
+use wasi_genetic::event_log::EventLog;
+use wasi_genetic::evaluator::FitnessCommand;
+use wasi_genetic::generate::{self, Layout};
+use wasi_genetic::connectivity::{self, Unreachable};
+use wasi_genetic::geo::{self, Coordinates, DistanceMatrix, Metric, Precision};
+use wasi_genetic::hints::Hints;
+use wasi_genetic::init::Init;
+use wasi_genetic::local_search::{self, LocalSearch};
+use wasi_genetic::manifest::Manifest;
+use wasi_genetic::migration::Migration;
+use wasi_genetic::output::{self, Format, Outcome, Outputs, Target};
+use wasi_genetic::projection::Projection;
+use wasi_genetic::repl::{self, Repl};
+use wasi_genetic::restarts::Restarts;
+use wasi_genetic::rng::{random_seed, RngAlgorithm, SeededRng};
+use wasi_genetic::tune::Search;
+use wasi_genetic::{analyze, checkpoint, cluster, diff, evaluate, metrics, mutation, optimum, regress, selection, solve, statsd, tune};
+use wasi_genetic::events::Notice;
+use wasi_genetic::{check_parameters, City, OnGeneration, Path, Report, Simulation, TourType, MIN_CITIES};
+#[cfg(feature = "web-ui")]
+use wasi_genetic::dashboard;
+#[cfg(feature = "rhai")]
+use wasi_genetic::script;
+#[cfg(feature = "otel")]
+use wasi_genetic::trace::Tracer;
+#[cfg(feature = "worker")]
+use wasi_genetic::worker;
+
+/// Prints a line about how a run is going: to stdout, or to stderr once `reserve_stdout` has
+/// been called.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if stdout_reserved() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+/// Sends what runs `say` about themselves to stderr from now on, and leaves out the solution,
+/// so stdout carries nothing but the output format's for another program to read.
+fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+fn stdout_reserved() -> bool {
+    STDOUT_RESERVED.load(Ordering::Relaxed)
 }
 
-impl City {
-    pub fn new(x: f64, y: f64) -> City {
-        City { x, y }
+/// Prints what a run has to say about itself: warnings to stderr, the rest as `say!` does.
+fn tell(notice: &Notice) {
+    match notice {
+        Notice::Warning(_) => eprintln!("{}", notice),
+        Notice::Info(_) => say!("{}", notice),
     }
 }
 
-#[derive(Clone)]
-pub struct Path {
-    fitness: f64,
-    order: Vec<usize>
+#[derive(StructOpt)]
+#[structopt()]
+struct Opt {
+    /// Generations to evolve the population for
+    #[structopt(long = "iterations", default_value = "1000")]
+    iterations: usize,
+    /// Paths in the population
+    #[structopt(long = "population", default_value = "500")]
+    population: usize,
+    /// Share of the population, the fittest, that breeds each generation
+    #[structopt(long = "crossover-rate", default_value = "0.4")]
+    crossover_rate: f64,
+    /// Chance of each path mutating each generation
+    #[structopt(long = "mutation-rate", default_value = "0.05")]
+    mutation_rate: f64,
+    /// Share of the breeders that carry over unchanged to the next generation
+    #[structopt(long = "survival-rate", default_value = "0.3")]
+    survival_rate: f64,
+    /// Cities to visit: a csv file, or - to read them from stdin
+    #[structopt(name = "csv", parse(from_os_str))]
+    csv: Option<PathBuf>,
+    #[structopt(subcommand)]
+    cmd: Option<Cmd>,
+    /// Cities to visit instead of <csv>: a csv or TSPLIB .tsp file, or one built into the program (builtin:berlin52 or builtin:eil101)
+    #[structopt(long = "instance", parse(try_from_str = "builtin::parse_instance"), conflicts_with = "csv")]
+    instance: Option<Instance>,
+    /// Read the settings of the run from a TOML file, one key per option or argument, e.g.
+    /// mutation_rate = 0.01 or crossover = "ox"; anything also given on the command line is
+    /// taken from there instead
+    #[structopt(long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Take the cost of a leg to depend on its direction, e.g. for one-way streets; it does
+    /// anyway when given distances aren't symmetric
+    #[structopt(long = "asymmetric")]
+    asymmetric: bool,
+    /// Whether tours go back to where they started: closed, paying for the leg from the last
+    /// city back to the first as the travelling salesman does, or open paths that end at their
+    /// last city
+    #[structopt(long = "tour-type", default_value = "closed")]
+    tour_type: TourType,
+    /// Read the cities as csv, tsplib or matrix (a csv of the costs between them), whatever the
+    /// file's extension; files ending in .tsp are read as TSPLIB, and any others as csv, by default
+    #[structopt(long = "format")]
+    format: Option<builtin::Format>,
+    /// What to do when a matrix leaves out so many legs (inf or empty costs) that no tour can
+    /// do without them: error (the default) stops, listing the groups of cities that can't be
+    /// joined up, and penalty solves anyway, taking as few of them as it can
+    #[structopt(long = "unreachable", default_value = "error")]
+    unreachable: Unreachable,
+    /// Seed for the random number generator, to reproduce an earlier run
+    #[structopt(long = "seed")]
+    seed: Option<u64>,
+    /// Skip rows of the csv that can't be read, with a warning for each, instead of stopping at
+    /// the first of them as a run does by default
+    #[structopt(long = "lenient")]
+    lenient: bool,
+    /// Keep the population within this much memory (e.g. 512M or 2G) by spilling all but the breeding pool to a temporary file
+    #[structopt(long = "max-memory", parse(try_from_str = "wasi_genetic::parse_size"))]
+    max_memory: Option<usize>,
+    /// Precompute the distances between cities only if they fit in this much memory (e.g. 64M or
+    /// 1G; 8M for euclidean and 256M for haversine by default), otherwise measure legs as tours
+    /// are scored; 0 never precomputes them
+    #[structopt(long = "matrix-memory", parse(try_from_str = "wasi_genetic::parse_size"))]
+    matrix_memory: Option<usize>,
+    /// Keep the distances between cities as f64 or as f32, which takes half the memory for
+    /// lengths good to about seven significant digits
+    #[structopt(long = "distance-precision", default_value = "f64")]
+    distance_precision: Precision,
+    /// Append a JSON object per generation, new best tour, stagnation, checkpoint and the end of the run to this file
+    #[structopt(long = "events", parse(from_os_str))]
+    events: Option<PathBuf>,
+    /// Write a manifest of the run to this file: version, parameters, seed, input fingerprint, times and result
+    #[structopt(long = "manifest", parse(from_os_str))]
+    manifest: Option<PathBuf>,
+    /// Write the best tour leg by leg to this file, with each leg's distance and the distance so
+    /// far: as JSON if it ends in .json, otherwise as csv. Short for --out route:<file>
+    #[structopt(long = "route", parse(from_os_str))]
+    route: Option<PathBuf>,
+    /// Write the best, mean and worst tour length of every generation, their standard deviation
+    /// and the seconds elapsed to this csv, to plot how the run converged. Short for --out history:<file>
+    #[structopt(long = "history", parse(from_os_str))]
+    history: Option<PathBuf>,
+    /// Write results as kind:path, where kind is json (the solution), svg (a drawing of it),
+    /// stats (best length per generation, as csv), history (as --history) or route (as --route).
+    /// Can be given more than once
+    #[structopt(long = "out", number_of_values = 1)]
+    out: Vec<Target>,
+    /// Print the result as text, or as json or geojson (the best tour as a LineString), a single
+    /// object on stdout with everything else on stderr
+    #[structopt(long = "output-format", default_value = "text")]
+    output_format: Format,
+    /// Write the best tour's cities in order with their coordinates to this file: as a GeoJSON
+    /// LineString with --output-format geojson or if it ends in .geojson, otherwise as csv
+    #[structopt(long = "output-file", parse(from_os_str))]
+    output_file: Option<PathBuf>,
+    /// Run as under WASI, which is the default when built for wasm32-wasi: read the cities as csv
+    /// from stdin unless given others and print the result as JSON
+    #[structopt(long = "wasi")]
+    wasi: bool,
+    /// Read commands from stdin while the run goes on, to see how it's doing and change the
+    /// mutation rate, elitism or when to stop (type help for the list)
+    #[structopt(long = "repl")]
+    repl: bool,
+    /// Watch this directory for tours dropped in while the run goes on, and splice them into
+    /// the population. Files starting with a dot are ignored until renamed
+    #[structopt(long = "hints", parse(from_os_str))]
+    hints: Option<PathBuf>,
+    /// Write the whole population, orders and fitness, to a JSON file every so many generations,
+    /// as every=<generations>:<file> with {gen} in the file name standing for the generation
+    #[structopt(long = "dump-population")]
+    dump_population: Option<PopulationDumps>,
+    /// Save the population, generator and generation to this file every --checkpoint-interval
+    /// generations and at the end of the run
+    #[structopt(long = "checkpoint", raw(visible_alias = r#""checkpoint-file""#), parse(from_os_str))]
+    checkpoint: Option<PathBuf>,
+    /// Generations between checkpoints
+    #[structopt(long = "checkpoint-interval", raw(visible_alias = r#""checkpoint-every""#), default_value = "100")]
+    checkpoint_interval: usize,
+    /// Continue the run saved in this checkpoint where it left off
+    #[structopt(long = "resume", parse(from_os_str))]
+    resume: Option<PathBuf>,
+    /// Solve the problem this many times from different seeds, --seed and the ones after it if
+    /// given, and report the best, mean and spread of the lengths found with the best tour. The
+    /// runs go on as many threads at a time as there are cores
+    #[structopt(
+        long = "restarts",
+        raw(conflicts_with_all = r#"&["repl", "resume", "checkpoint", "events", "dump_population", "hints", "manifest", "out", "history", "route", "output_file", "migration", "fitness_cmd", "top_k"]"#)
+    )]
+    restarts: Option<usize>,
+    /// Keep this many of the shortest distinct tours seen over the whole run and print them after
+    /// the solution, for alternatives to the best one
+    #[structopt(long = "top-k")]
+    top_k: Option<usize>,
+    /// Check after every generation that the population is intact and its fitness up to date
+    #[structopt(long = "verify")]
+    verify: bool,
+    /// How to measure distances: euclidean, or haversine for longitudes (x) and latitudes (y)
+    /// in degrees, or TSPLIB's geo or att. Haversine is the default for csvs with lat and lon
+    /// columns, and TSPLIB files say which to use
+    #[structopt(long = "metric", raw(visible_alias = r#""distance""#))]
+    metric: Option<Metric>,
+    /// Project longitudes (x) and latitudes (y) onto the plane before solving, measuring in
+    /// metres: utm (picking the zone), web-mercator or an EPSG code such as EPSG:32633
+    #[structopt(long = "projection", conflicts_with = "metric")]
+    projection: Option<Projection>,
+    /// Cities every tour has to start with, in order, as comma-separated indices (e.g. 0,4,2)
+    #[structopt(long = "prefix", parse(try_from_str = "parse_cities"))]
+    prefix: Option<Vec<usize>>,
+    /// An edge every tour has to take, in either direction, as two city indices (e.g. 3-17).
+    /// Can be given more than once
+    #[structopt(long = "require-edge", parse(try_from_str = "parse_edge"), number_of_values = 1)]
+    require_edge: Vec<(usize, usize)>,
+    /// GeoJSON polygons for tours to keep out of, e.g. lakes or restricted areas, in the
+    /// cities' coordinates
+    #[structopt(long = "avoid", parse(from_os_str), conflicts_with = "fitness_cmd")]
+    avoid: Option<PathBuf>,
+    /// What a leg entering an avoided zone costs: hard, to enter one only when there's no way
+    /// around, or a length to add to the tour
+    #[structopt(long = "avoid-penalty", default_value = "hard")]
+    avoid_penalty: Penalty,
+    /// How to build --init-fraction of the initial tours instead of at random:
+    /// nearest-neighbor (from a different city each) or greedy (shortest edges first)
+    #[structopt(long = "init", default_value = "random")]
+    init: Init,
+    /// Share of the initial population (0 to 1) that --init builds
+    #[structopt(long = "init-fraction", default_value = "0.1")]
+    init_fraction: f64,
+    /// Carry exactly this many of the fittest tours over to every generation, never mutated,
+    /// instead of --survival-rate of the breeders
+    #[structopt(long = "elitism")]
+    elitism: Option<usize>,
+    /// How to recombine parent tours: one-point (the default) or ox (ordered crossover, which
+    /// keeps more of both parents' order)
+    #[structopt(long = "crossover", default_value = "one-point")]
+    crossover: Crossover,
+    /// How to mutate a tour: swap (two cities, the default), inversion (reverse the cities
+    /// between two, which untangles crossing legs), scramble (shuffle them) or insertion (move
+    /// a city elsewhere)
+    #[structopt(long = "mutation", default_value = "swap")]
+    mutation: mutation::Kind,
+    /// Adapt the mutation rate to the population's diversity between these bounds (e.g.
+    /// 0.01:0.3), raising it while fewer than --adaptive-threshold of the tours are distinct
+    /// and lowering it otherwise
+    #[structopt(long = "adaptive-mutation")]
+    adaptive_mutation: Option<mutation::Adaptive>,
+    /// Share of distinct tours (0 to 1) below which --adaptive-mutation raises the rate
+    #[structopt(long = "adaptive-threshold", default_value = "0.5")]
+    adaptive_threshold: f64,
+    /// Breed and mutate with every crossover and mutation rather than --crossover and
+    /// --mutation, drawing each more often the more it has improved tours lately
+    #[structopt(long = "adaptive-operators")]
+    adaptive_operators: bool,
+    /// How to pick the tours that breed: truncation (the fittest, the default), tournament
+    /// (the fittest of --tournament-size tours drawn at random, once per breeder), roulette
+    /// (drawn with chances in proportion to their fitness), sus (the same chances, drawn with
+    /// one spin of evenly spaced pointers), rank (drawn with chances that fall in a straight
+    /// line from the fittest to the least fit) or boltzmann (drawn with chances that fall
+    /// exponentially with how much less fit they are than the fittest, more steeply as the
+    /// temperature cools from --temp-start to --temp-end)
+    #[structopt(long = "selection", default_value = "truncation")]
+    selection: selection::Kind,
+    /// Tours drawn for each tournament of --selection tournament; the more, the harder the
+    /// pressure towards the fittest
+    #[structopt(long = "tournament-size", default_value = "2")]
+    tournament_size: usize,
+    /// How many times the average chance the fittest tour has of breeding with --selection
+    /// rank, from 1 (every tour as likely) to 2 (the least fit never breeds)
+    #[structopt(long = "selection-pressure", default_value = "1.5")]
+    selection_pressure: f64,
+    /// The temperature of --selection boltzmann at the start of the run; the hotter, the
+    /// more nearly every tour is as likely to breed
+    #[structopt(long = "temp-start", default_value = "1")]
+    temp_start: f64,
+    /// The temperature of --selection boltzmann at the end of the run, which it cools to
+    /// geometrically
+    #[structopt(long = "temp-end", default_value = "0.01")]
+    temp_end: f64,
+    /// Run the best tour to a 2-opt local optimum before printing it
+    #[structopt(long = "polish")]
+    polish: bool,
+    /// Replace tours that are already in the population after every generation, to keep it
+    /// diverse: with random ones, or with mutated copies (mutate)
+    #[structopt(long = "dedup")]
+    dedup: Option<Dedup>,
+    /// Improve tours with local search every generation, making the run a memetic algorithm:
+    /// 2opt (reverse stretches of the tour, as --polish does), or-opt (move stretches of up to
+    /// three cities elsewhere) or 3opt (2opt, then cut the tour in three and rejoin the pieces)
+    #[structopt(long = "local-search")]
+    local_search: Option<local_search::Kind>,
+    /// Which tours --local-search improves: offspring (the children bred each generation, the
+    /// default) or elite (the breeders that survive)
+    #[structopt(long = "local-search-on", default_value = "offspring")]
+    local_search_on: local_search::Target,
+    /// Moves --local-search evaluates on each tour before giving up on it
+    #[structopt(long = "local-search-budget", default_value = "5000")]
+    local_search_budget: usize,
+    /// Report the run as stagnating after this many generations without a better tour
+    #[structopt(long = "stagnation", default_value = "100")]
+    stagnation: usize,
+    /// Print the generation, best and mean fitness and diversity every this many generations
+    #[structopt(long = "report-every")]
+    report_every: Option<usize>,
+    /// End the run after this long (e.g. 30s, 500ms or 5m) with the best tour found so far
+    #[structopt(long = "time-limit", parse(try_from_str = "wasi_genetic::parse_duration"))]
+    time_limit: Option<Duration>,
+    /// End the run once the best tour hasn't improved for this many generations
+    #[structopt(long = "stagnation-limit")]
+    stagnation_limit: Option<usize>,
+    /// Report a diversity collapse when the population's position entropy (0 to 1) falls below this
+    #[structopt(long = "diversity-floor")]
+    diversity_floor: Option<f64>,
+    /// End the run as soon as a tour this short is found, and estimate when that will be
+    /// instead of when the run stalls
+    #[structopt(long = "target-length")]
+    target_length: Option<f64>,
+    /// Length of the optimal tour, to report the gap to it; known TSPLIB instances are recognised by file name
+    #[structopt(long = "optimum")]
+    optimum: Option<f64>,
+    /// Random number generator: chacha (portable, the default), pcg64 or small (fastest)
+    #[structopt(long = "rng", default_value = "chacha")]
+    rng: RngAlgorithm,
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9898) while solving
+    #[structopt(long = "metrics-addr")]
+    metrics_addr: Option<String>,
+    /// Send best-length and generation-rate gauges to this StatsD agent (e.g. 127.0.0.1:8125)
+    #[structopt(long = "statsd")]
+    statsd: Option<String>,
+    /// Exchange elite tours with other instances through this Redis server (e.g. redis://localhost:6379)
+    #[structopt(long = "migration")]
+    migration: Option<String>,
+    /// Redis stream the islands exchange tours through
+    #[structopt(long = "migration-key", default_value = "wasi-genetic:migrants")]
+    migration_key: String,
+    /// Generations between migrations
+    #[structopt(long = "migration-interval", default_value = "50")]
+    migration_interval: usize,
+    /// Number of tours each migration sends
+    #[structopt(long = "migrants", default_value = "4")]
+    migrants: usize,
+    /// Score tours with this command: it reads JSON arrays of city indices, one per line, and answers each with a fitness (higher is better)
+    #[structopt(long = "fitness-cmd")]
+    fitness_cmd: Option<String>,
+    /// Number of tours sent to the fitness command at once
+    #[structopt(long = "fitness-batch", default_value = "64")]
+    fitness_batch: usize,
+    /// Seconds the fitness command or script may take to score a single tour; tours that take longer get the worst fitness
+    #[structopt(long = "fitness-timeout", default_value = "10")]
+    fitness_timeout: f64,
+    /// Rhai script defining fitness(order, cities) and/or on_generation(generation, best_length, order)
+    #[cfg(feature = "rhai")]
+    #[structopt(long = "script", parse(from_os_str))]
+    script: Option<PathBuf>,
+    /// Export run, generation and phase spans to this OTLP/HTTP collector (e.g. http://localhost:4318)
+    #[cfg(feature = "otel")]
+    #[structopt(long = "otlp-endpoint")]
+    otlp_endpoint: Option<String>,
+    /// Solve jobs from this NATS server (e.g. nats://localhost:4222) instead of the csv; the
+    /// cities, generations, population and rates become defaults for jobs that leave them out
+    #[cfg(feature = "worker")]
+    #[structopt(long = "worker")]
+    worker: Option<String>,
+    /// Subject the worker takes jobs from
+    #[cfg(feature = "worker")]
+    #[structopt(long = "subject", default_value = "wasi-genetic.jobs")]
+    subject: String,
+    /// Serve a dashboard with convergence, route and start/stop controls on this address (e.g. 127.0.0.1:8080)
+    #[cfg(feature = "web-ui")]
+    #[structopt(long = "ui-addr")]
+    ui_addr: Option<String>,
+    /// Threads to score and breed tours on; as many as there are cores by default
+    #[cfg(feature = "parallel")]
+    #[structopt(long = "threads")]
+    threads: Option<usize>,
 }
 
-impl Path {
-    pub fn breed(&self, other: &Path, city_list: &Vec<City>) -> Path {
-        let order = Path::crossover(&self.order, &other.order);
-        let fitness = Path::calculate_fitness(&order, city_list);
+#[derive(StructOpt)]
+enum Cmd {
+    /// Solve an instance, as the program does without a subcommand: `solve [OPTIONS] <csv>` is
+    /// the same as `[OPTIONS] <csv>`
+    #[structopt(
+        name = "solve",
+        raw(settings = "&[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen, AppSettings::DisableHelpFlags, AppSettings::DisableVersion]")
+    )]
+    Solve {
+        /// The program's options and arguments
+        #[structopt(parse(from_os_str))]
+        args: Vec<OsString>,
+    },
+    /// Spread the run given by the other options over workers that join this address
+    #[structopt(name = "coordinator")]
+    Coordinator {
+        /// Address to accept workers on (e.g. 0.0.0.0:7878)
+        #[structopt(long = "listen")]
+        listen: String,
+        /// Generations each worker evolves between exchanges with the coordinator
+        #[structopt(long = "round-generations", default_value = "50")]
+        round_generations: usize,
+        /// Seconds a worker may take to finish a round before it's considered lost
+        #[structopt(long = "worker-timeout", default_value = "60")]
+        worker_timeout: u64,
+    },
+    /// Evolve an island for the coordinator at this address (e.g. 10.0.0.1:7878)
+    #[structopt(name = "worker")]
+    Worker {
+        coordinator: String,
+    },
+    /// Solve the built-in reference instances from fixed seeds and fail if the median tour
+    /// lengths got worse than the baseline
+    #[structopt(name = "regress")]
+    Regress {
+        /// JSON file of median tour lengths to compare with
+        #[structopt(long = "baseline", parse(from_os_str))]
+        baseline: PathBuf,
+        /// How much longer, as a fraction, a median tour may get before it counts as a regression
+        #[structopt(long = "tolerance", default_value = "0.02")]
+        tolerance: f64,
+        /// Write the current results to the baseline instead of comparing with it
+        #[structopt(long = "update")]
+        update: bool,
+    },
+    /// Compare two tours of the same cities: their lengths, shared edges and where they differ
+    #[structopt(name = "diff")]
+    Diff {
+        /// A tour: the program's output or a list of city indices
+        #[structopt(parse(from_os_str))]
+        tour_a: PathBuf,
+        /// The tour to compare with the first
+        #[structopt(parse(from_os_str))]
+        tour_b: PathBuf,
+        /// The cities the tours visit: a csv file or builtin:<name>
+        #[structopt(long = "instance", parse(try_from_str = "builtin::parse_instance"))]
+        instance: Instance,
+    },
+    /// Check a tour of an instance, e.g. another solver's: that it visits every city once, how
+    /// long it is and, given another tour, how they differ
+    #[structopt(name = "evaluate")]
+    Evaluate {
+        /// The tour: the program's output or a list of city indices
+        #[structopt(parse(from_os_str))]
+        tour: PathBuf,
+        /// The cities the tour visits: a csv, TSPLIB or distance matrix file, or builtin:<name>
+        #[structopt(long = "instance", parse(try_from_str = "builtin::parse_instance"))]
+        instance: Instance,
+        /// A tour of the same cities to compare it with, as diff does
+        #[structopt(long = "against", parse(from_os_str))]
+        against: Option<PathBuf>,
+    },
+    /// Search for the population size, rates and operators that solve an instance best, with the
+    /// other options as given, and write the best configuration found as a --config file
+    #[structopt(name = "tune")]
+    Tune {
+        /// The cities to tune for, in place of <csv>
+        #[structopt(long = "instance", parse(try_from_str = "builtin::parse_instance"))]
+        instance: Option<Instance>,
+        /// How to pick the configurations to try: random, or grid for every combination in turn
+        #[structopt(long = "search", default_value = "random")]
+        search: Search,
+        /// Configurations to try at most (default 20 for a random search, the whole grid for a
+        /// grid search)
+        #[structopt(long = "trials")]
+        trials: Option<usize>,
+        /// Runs of each configuration, from consecutive seeds starting at --seed, the same for
+        /// every configuration
+        #[structopt(long = "seeds", default_value = "3")]
+        seeds: usize,
+        /// How long the whole search may take (e.g. 30s, 500ms or 5m)
+        #[structopt(long = "budget", default_value = "60s", parse(try_from_str = "wasi_genetic::parse_duration"))]
+        budget: Duration,
+        /// Population sizes to try (default 100,200,500)
+        #[structopt(long = "populations", raw(use_delimiter = "true"))]
+        populations: Vec<usize>,
+        /// Crossover rates to try (default 0.2,0.4,0.6)
+        #[structopt(long = "crossover-rates", raw(use_delimiter = "true"))]
+        crossover_rates: Vec<f64>,
+        /// Mutation rates to try (default 0.01,0.05,0.1,0.2)
+        #[structopt(long = "mutation-rates", raw(use_delimiter = "true"))]
+        mutation_rates: Vec<f64>,
+        /// Survival rates to try (default 0.1,0.3,0.5)
+        #[structopt(long = "survival-rates", raw(use_delimiter = "true"))]
+        survival_rates: Vec<f64>,
+        /// Crossovers to try (default all of them)
+        #[structopt(long = "crossovers", raw(use_delimiter = "true"))]
+        crossovers: Vec<Crossover>,
+        /// Mutations to try (default all of them)
+        #[structopt(long = "mutations", raw(use_delimiter = "true"))]
+        mutations: Vec<mutation::Kind>,
+        /// File to write the best configuration to as TOML, or stdout if there's none
+        #[structopt(parse(from_os_str))]
+        config: Option<PathBuf>,
+    },
+    /// Sample the fitness landscape of an instance, with a random walk and 2-opt descents from
+    /// random tours, and suggest settings to solve it with
+    #[structopt(name = "analyze")]
+    Analyze {
+        /// The cities to analyse: a csv file or builtin:<name>
+        #[structopt(long = "instance", parse(try_from_str = "builtin::parse_instance"))]
+        instance: Instance,
+        /// Swaps in the random walk
+        #[structopt(long = "walk", default_value = "1000")]
+        walk: usize,
+        /// 2-opt descents from random tours
+        #[structopt(long = "descents", default_value = "100")]
+        descents: usize,
+    },
+    /// Write a synthetic instance to try the solver on: cities scattered uniformly or in
+    /// clusters, or laid out on a circle or a grid
+    #[structopt(name = "generate")]
+    Generate {
+        /// How the cities are laid out: uniform, clustered, circle (whose shortest tour is known)
+        /// or grid
+        #[structopt(long = "layout", default_value = "uniform")]
+        layout: Layout,
+        /// Cities to generate
+        #[structopt(long = "cities", default_value = "100")]
+        cities: usize,
+        /// Side of the square the cities are laid out over
+        #[structopt(long = "size", default_value = "1000")]
+        size: f64,
+        /// Clusters the cities of a clustered layout are spread over
+        #[structopt(long = "clusters", default_value = "5")]
+        clusters: usize,
+        /// File to write the cities to as csv, or stdout if there's none
+        #[structopt(parse(from_os_str))]
+        csv: Option<PathBuf>,
+    },
+}
 
-        Path { fitness, order }
-    }
+impl Opt {
+    /// Fails on the first option given a value no run can take, of those clap can't check
+    /// itself, as `check_parameters` does for the GA parameters.
+    fn validate(&self) -> Result<(), wasi_genetic::Error> {
+        check_parameters(self.population, self.crossover_rate, self.mutation_rate, self.survival_rate)?;
+        if let Some(option) = self.restarts.and(restarts_conflict(self)) {
+            return Err(wasi_genetic::Error::Parameter { name: "--restarts", reason: format!("can't be combined with {}", option) });
+        }
+
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        let reads_stdin = match &self.instance {
+            Some(instance) => matches!(instance, Instance::Stdin),
+            None => self.csv.as_ref().is_some_and(|csv| csv.as_os_str() == "-"),
+        };
+        #[cfg(feature = "parallel")]
+        let no_threads = self.threads == Some(0);
+        #[cfg(not(feature = "parallel"))]
+        let no_threads = false;
+        let mut checks = vec![
+            (Duration::try_from_secs_f64(self.fitness_timeout).is_err(), "--fitness-timeout", "must be a number of seconds"),
+            (self.tournament_size == 0, "--tournament-size", "must be at least 1"),
+            (!(1.0..=2.0).contains(&self.selection_pressure), "--selection-pressure", "must be between 1 and 2"),
+            (!positive(self.temp_start), "--temp-start", "must be a positive number"),
+            (!positive(self.temp_end), "--temp-end", "must be a positive number"),
+            (!(0.0..=1.0).contains(&self.init_fraction), "--init-fraction", "must be between 0 and 1"),
+            (self.adaptive_mutation.is_some() && !(0.0..=1.0).contains(&self.adaptive_threshold), "--adaptive-threshold", "must be between 0 and 1"),
+            (no_threads, "--threads", "must be at least 1"),
+            (self.local_search.is_some() && self.local_search_budget == 0, "--local-search-budget", "must be at least 1 move"),
+            (self.report_every == Some(0), "--report-every", "must be at least 1 generation"),
+            (self.stagnation_limit == Some(0), "--stagnation-limit", "must be at least 1 generation"),
+            (self.target_length.is_some_and(|length| !positive(length)), "--target-length", "must be a positive tour length"),
+            (self.optimum.is_some_and(|optimum| !positive(optimum)), "--optimum", "must be a positive tour length"),
+            (self.top_k == Some(0), "--top-k", "must be at least 1 tour"),
+            (self.restarts == Some(0), "--restarts", "must be at least 1 run"),
+            (reads_stdin && self.repl, "--repl", "reads commands from stdin, so the cities have to come from a file"),
+        ];
+        match &self.cmd {
+            Some(Cmd::Coordinator { .. }) => checks.push((self.avoid.is_some(), "--avoid", "only applies to runs solved here, a coordinator's workers measure plain lengths")),
+            Some(Cmd::Analyze { walk, descents, .. }) => checks.extend([
+                (*walk < 2, "--walk", "must be at least 2 swaps"),
+                (*descents == 0, "--descents", "must be at least 1"),
+            ]),
+            Some(Cmd::Generate { cities, size, clusters, .. }) => checks.extend([
+                (*cities == 0, "--cities", "must be at least 1"),
+                (*clusters == 0, "--clusters", "must be at least 1"),
+                (!positive(*size), "--size", "must be a positive number"),
+            ]),
+            Some(Cmd::Tune { seeds, trials, budget, .. }) => checks.extend([
+                (*seeds == 0, "--seeds", "must be at least 1"),
+                (*trials == Some(0), "--trials", "must be at least 1"),
+                (budget.is_zero(), "--budget", "must be longer than nothing"),
+            ]),
+            _ => {}
+        }
+        if let Some(&(_, name, reason)) = checks.iter().find(|(failed, ..)| *failed) {
+            return Err(wasi_genetic::Error::Parameter { name, reason: reason.to_string() });
+        }
 
-    fn crossover(mother: &Vec<usize>, father: &Vec<usize>) -> Vec<usize> {
-        let mut rng = thread_rng();
-        let crossover_point = Uniform::new(0, mother.len()).sample(&mut rng);
+        match self.tuning_space() {
+            Some(space) => space.check(),
+            None => Ok(()),
+        }
+    }
 
-        let mother_dna = &mother[0..crossover_point];
-        let mut father_dna: Vec<usize> = father.iter().filter_map(|d| {
-            if !mother_dna.contains(d) {
-                return Some(*d)
+    /// The values `tune` tries, if that's the command.
+    fn tuning_space(&self) -> Option<tune::Space> {
+        match &self.cmd {
+            Some(Cmd::Tune { populations, crossover_rates, mutation_rates, survival_rates, crossovers, mutations, .. }) => {
+                Some(tune::Space::given(populations, crossover_rates, mutation_rates, survival_rates, crossovers, mutations))
             }
-            None
-        }).collect();
+            _ => None,
+        }
+    }
+}
 
-        let mut child = Vec::new();
-        child.extend_from_slice(mother_dna);
-        child.append(&mut father_dna);
+/// Parses comma-separated city indices.
+fn parse_cities(s: &str) -> Result<Vec<usize>, String> {
+    s.split(',')
+        .map(|city| city.trim().parse().map_err(|_| format!("{:?} isn't a city index", city)))
+        .collect()
+}
 
-        child
+/// Parses an edge between two cities, e.g. `3-17`.
+fn parse_edge(s: &str) -> Result<(usize, usize), String> {
+    let (a, b) = s.split_once('-').ok_or_else(|| format!("{:?} isn't two cities joined by -", s))?;
+    let city = |c: &str| c.trim().parse().map_err(|_| format!("{:?} isn't a city index", c));
+    Ok((city(a)?, city(b)?))
+}
+
+/// Reads the zones to avoid from `path`, projected with `projection` if the cities are, and
+/// exits if they can't be read.
+fn read_barriers(path: &std::path::Path, projection: Option<Projection>) -> Barriers {
+    let barriers = Barriers::read(path).and_then(|barriers| match projection {
+        Some(projection) => barriers.project(projection),
+        None => Ok(barriers),
+    });
+    match barriers {
+        Ok(barriers) => {
+            say!("avoiding {} zones from {}", barriers.len(), path.display());
+            barriers
+        }
+        Err(e) => {
+            eprintln!("could not read {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn watch_hints(dir: &std::path::Path) -> Hints {
+    match Hints::watch(dir.to_path_buf()) {
+        Ok(hints) => {
+            say!("taking hints dropped into {}", dir.display());
+            hints
+        }
+        Err(e) => {
+            eprintln!("could not watch {} for hints: {}", dir.display(), e);
+            std::process::exit(1);
+        }
     }
+}
 
-    pub fn mutate(&mut self, city_list: &Vec<City>) {
-        let mut rng = thread_rng();
-        let point_one = Uniform::new(0, self.order.len()).sample(&mut rng);
-        let point_two = Uniform::new(0, self.order.len()).sample(&mut rng);
+/// Gives the legs `distances` leave out a cost, the penalty it returns, if there are any. If no
+/// tour can do without them, the run stops there with the groups of cities that can't be joined
+/// up, unless `--unreachable` says to solve anyway.
+fn cost_missing_legs(distances: DistanceMatrix, instance: &Instance, opts: &Opt) -> (DistanceMatrix, Option<f64>) {
+    let missing = distances.missing_legs();
+    if missing == 0 {
+        return (distances, None);
+    }
 
-        self.order.swap(point_one, point_two);
-        self.fitness = Path::calculate_fitness(&self.order, &city_list);
+    if let Some(components) = connectivity::unreachable(&distances, opts.tour_type) {
+        if opts.unreachable == Unreachable::Error {
+            eprintln!(
+                "no tour of {} can do without the legs it leaves out: its cities only join up in the groups {} \
+                 (pass --unreachable penalty to solve anyway)",
+                instance, components
+            );
+            std::process::exit(1);
+        }
+        eprintln!("warning: no tour of {} can do without the legs it leaves out, its cities only join up in the groups {}", instance, components);
     }
+    let (distances, penalty) = distances.with_penalty();
+    say!("{} leaves out {} legs, each costing a penalty of {}", instance, missing, penalty);
+    (distances, Some(penalty))
+}
 
-    pub fn calculate_fitness(path: &Vec<usize>, city_list: &Vec<City>) -> f64 {
-        let path_length = city_list.len();
-        let mut cost = 0.0;
-        for i in 0..path_length - 1 {
-            let a = &city_list[path[i]];
-            let b = &city_list[path[i + 1]];
-            cost = cost + ((a.x - b.x).powf(2.0) + (a.y - b.y).powf(2.0)).sqrt();
+/// Projects the `cities` of `instance` onto the plane with `projection`, if one was given, and
+/// picks the metric to measure the distances between them with.
+fn prepare_cities(
+    projection: Option<Projection>,
+    metric: Option<Metric>,
+    instance: &Instance,
+    cities: Vec<City>,
+) -> (Vec<City>, Metric) {
+    let projection = match projection {
+        Some(projection) => projection,
+        None => {
+            let metric = choose_metric(metric, instance, &cities);
+            return (cities, metric);
         }
+    };
 
-        1.0 / cost
+    if let Some(notice) = projection.distortion(&cities) {
+        tell(&notice);
+    }
+    match projection.project(&cities) {
+        Ok(projected) => {
+            say!("projecting {} onto {}, lengths are in metres", instance, projection);
+            (projected, Metric::Euclidean)
+        }
+        Err(e) => {
+            eprintln!("could not project {}: {}", instance, e);
+            std::process::exit(1);
+        }
     }
 }
 
-pub struct Simulation {
-    population: Vec<Path>,
-    city_list: Vec<City>,
-    max_iterations: usize,
-    crossover_rate: f64,
-    mutation_rate: f64,
-    survival_rate: f64,
+/// The metric to measure the distances between the `cities` of `instance` with: `metric` if
+/// one was given, otherwise haversine if the instance's columns are named as longitudes and
+/// latitudes. Warns when they're called x and y but look like longitudes and latitudes anyway.
+fn choose_metric(metric: Option<Metric>, instance: &Instance, cities: &[City]) -> Metric {
+    if let Some(metric) = metric.or_else(|| instance.metric()) {
+        return metric;
+    }
+
+    let contents = instance.contents().unwrap_or_default();
+    match geo::detect(&String::from_utf8_lossy(&contents), cities) {
+        Coordinates::Planar => Metric::Euclidean,
+        Coordinates::Named => {
+            eprintln!(
+                "note: {} has longitude and latitude columns, measuring great-circle distances in kilometres \
+                 (pass --metric euclidean for straight lines)",
+                instance
+            );
+            Metric::Haversine
+        }
+        Coordinates::InRange => {
+            eprintln!(
+                "warning: every city in {} could be a longitude (x) and latitude (y), but distances are \
+                 measured as straight lines in degrees, which is wrong away from the equator \
+                 (pass --metric haversine for great-circle distances)",
+                instance
+            );
+            Metric::Euclidean
+        }
+    }
 }
 
-impl Simulation {
-    pub fn new(
-        population_size: usize,
-        cities: Vec<City>,
-        max_iterations: usize,
-        crossover_rate: f64,
-        mutation_rate: f64,
-        survival_rate: f64,
-    ) -> Simulation {
-        Simulation {
-            population: Simulation::initial_population(&cities, population_size),
-            city_list: cities,
-            max_iterations,
-            crossover_rate,
-            mutation_rate,
-            survival_rate,
+// How often a run reports how it's going
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What a run says about itself as it goes: its events, the generation, best and mean fitness
+/// and diversity every `--report-every` generations, and the best length and estimate every
+/// `PROGRESS_INTERVAL`.
+struct Progress {
+    every: Option<usize>,
+    last: Instant,
+}
+
+impl Progress {
+    fn new(every: Option<usize>) -> Progress {
+        Progress { every, last: Instant::now() }
+    }
+
+    fn generation(&mut self, report: &Report) {
+        for event in &report.events {
+            say!("generation {}: {}", report.generation, event);
+        }
+        if self.every.is_some_and(|every| report.generation.is_multiple_of(every)) {
+            let diversity = report.diversity();
+            say!(
+                "generation {}: best fitness {:.6e}, mean fitness {:.6e}, unique {:.3}, edge overlap {:.3}, position entropy {:.3}",
+                report.generation, report.fittest.fitness(), report.mean_fitness(),
+                diversity.unique_ratio, diversity.edge_overlap, diversity.position_entropy
+            );
+        }
+        if self.last.elapsed() >= PROGRESS_INTERVAL {
+            self.last = Instant::now();
+            say!("generation {}: best {:.3}, {}", report.generation, report.fittest.length(), report.estimate);
+        }
+    }
+}
+
+fn print_hall_of_fame(paths: &[Path]) {
+    if paths.is_empty() {
+        return;
+    }
+
+    println!("Hall of fame:");
+    for (rank, path) in paths.iter().enumerate() {
+        let order: Vec<String> = path.order().iter().map(|o| o.to_string()).collect();
+        println!("{:>3}. {:.3}  {}", rank + 1, path.length(), order.join("->"));
+    }
+}
+
+/// An option given with --restarts that it can't be combined with, of those clap can't check:
+/// ones only built with some features, and the ones that aren't a run's.
+fn restarts_conflict(opts: &Opt) -> Option<&'static str> {
+    if matches!(opts.cmd, Some(Cmd::Coordinator { .. })) {
+        return Some("coordinator");
+    }
+    if opts.output_format == Format::Geojson {
+        return Some("--output-format geojson");
+    }
+    #[cfg(feature = "rhai")]
+    {
+        if opts.script.is_some() {
+            return Some("--script");
+        }
+    }
+    #[cfg(feature = "otel")]
+    {
+        if opts.otlp_endpoint.is_some() {
+            return Some("--otlp-endpoint");
+        }
+    }
+    #[cfg(feature = "worker")]
+    {
+        if opts.worker.is_some() {
+            return Some("--worker");
+        }
+    }
+    #[cfg(feature = "web-ui")]
+    {
+        if opts.ui_addr.is_some() {
+            return Some("--ui-addr");
+        }
+    }
+    None
+}
+
+/// Exits on a run that can't be set up as given, as on any other invalid argument.
+fn invalid(e: &wasi_genetic::Error) -> ! {
+    structopt::clap::Error::with_description(&e.to_string(), structopt::clap::ErrorKind::InvalidValue).exit()
+}
+
+/// The cities of `instance`, with a warning for each row skipped when `lenient`. Exits if they
+/// can't be read.
+fn read_cities(instance: &Instance, lenient: bool) -> Vec<City> {
+    match instance.cities(lenient) {
+        Ok((cities, skipped)) => {
+            skipped.iter().for_each(tell);
+            cities
+        }
+        Err(report) => {
+            eprintln!("{:?}", report);
+            std::process::exit(1);
         }
     }
+}
+
+/// Exits with `e`, for a run that failed after it started.
+fn failed(e: &wasi_genetic::Error) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1)
+}
+
+/// Options that can't be given together, so a config file's one gives way to the other on the
+/// command line.
+const CONFLICTS: &[(&str, &str)] = &[("instance", "csv"), ("metric", "projection"), ("avoid", "fitness_cmd")];
+
+/// The command line, with `solve` left out: what follows it are the program's own options and
+/// arguments, after any that come before it.
+fn command_line() -> Vec<OsString> {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    while let Some(Cmd::Solve { args: solve }) = Opt::from_clap(&Opt::clap().get_matches_from(&args)).cmd {
+        let at = args.len() - solve.len() - 1;
+        args.truncate(at);
+        args.extend(solve);
+    }
+    args
+}
+
+/// The command line, with whatever it leaves out taken from the `--config` file if it names one.
+fn configured_opts() -> Opt {
+    let mut args = command_line();
+    let matches = Opt::clap().get_matches_from(&args);
+    let path = match matches.value_of_os("config") {
+        Some(path) => PathBuf::from(path),
+        None => return Opt::from_clap(&matches),
+    };
+    let settings = config::load(&path).unwrap_or_else(|report| {
+        eprintln!("{:?}", report);
+        std::process::exit(1);
+    });
+
+    let given = |key: &str| {
+        let conflicting = CONFLICTS.iter().filter_map(|&(a, b)| if a == key { Some(b) } else if b == key { Some(a) } else { None });
+        std::iter::once(key).chain(conflicting).any(|key| matches.occurrences_of(key) > 0)
+    };
+    // Ahead of the command line's own, so they're the program's options rather than a subcommand's
+    let options: Vec<OsString> = settings.iter()
+        .filter(|setting| !config::ARGUMENTS.contains(&setting.key.as_str()) && !given(&setting.key))
+        .flat_map(Setting::args)
+        .map(OsString::from)
+        .collect();
+    args.splice(1..1, options);
+    let matches = Opt::clap().get_matches_from_safe(args).unwrap_or_else(|e| {
+        eprintln!("{}
+(with the settings of {})", e.message, path.display());
+        std::process::exit(1);
+    });
+
+    let mut opts = Opt::from_clap(&matches);
+    if opts.instance.is_none() {
+        opts.csv = opts.csv.or_else(|| config_argument(&settings, "csv", &path));
+    }
+    opts
+}
+
+/// The value the config file at `path` gives the argument `name`, if it gives one.
+fn config_argument<T: FromStr>(settings: &[Setting], name: &str, path: &std::path::Path) -> Option<T> {
+    let setting = settings.iter().find(|setting| setting.key == name)?;
+    let message = match setting.single() {
+        Some(value) => match value.parse() {
+            Ok(value) => return Some(value),
+            Err(_) => format!("{:?} isn't a valid {} in {}", value, name, path.display()),
+        },
+        None => format!("{} in {} takes a single value", name, path.display()),
+    };
+    structopt::clap::Error::with_description(&message, structopt::clap::ErrorKind::InvalidValue).exit()
+}
+
+/// Fills in what a run under WASI, which sees no files unless the runtime maps a directory in,
+/// needs to go without arguments: the cities from stdin and the result as JSON on stdout.
+fn wasi_defaults(opts: &mut Opt) {
+    if opts.cmd.is_some() {
+        return;
+    }
 
-    pub fn run(&mut self) -> () {
-        let mut fittest = self.find_fittest();
-        println!("starting iterations");
+    if opts.instance.is_none() && opts.csv.is_none() {
+        opts.instance = Some(Instance::Stdin);
+    }
+    if opts.output_format == Format::Text {
+        opts.output_format = Format::Json;
+    }
+}
+
+fn main() {
+    let mut opts = configured_opts();
+    if opts.wasi || cfg!(target_os = "wasi") {
+        wasi_defaults(&mut opts);
+    }
+    if opts.output_format != Format::Text {
+        reserve_stdout();
+    }
+    opts.validate().unwrap_or_else(|e| invalid(&e));
 
-        for _ in 0..self.max_iterations {
-            self.generate_next_generation();
+    if let Some(Cmd::Worker { coordinator }) = &opts.cmd {
+        cluster::work(coordinator, opts.rng, opts.seed, tell);
+        return;
+    }
 
-            let challenger = self.find_fittest();
-            if challenger.fitness > fittest.fitness {
-                fittest = challenger;
+    if let Some(Cmd::Regress { baseline, tolerance, update }) = &opts.cmd {
+        let result = if *update {
+            regress::update(baseline).map(|baseline| (baseline.to_string(), true))
+        } else {
+            regress::check(baseline, *tolerance).map(|comparison| (comparison.to_string(), comparison.passed()))
+        };
+        match result {
+            Ok((lines, passed)) => {
+                println!("{}", lines);
+                if !passed {
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Err(e) => {
+                eprintln!("could not use the baseline {}: {}", baseline.display(), e);
+                std::process::exit(1);
             }
         }
+    }
+
+    if let Some(Cmd::Diff { tour_a, tour_b, instance }) = &opts.cmd {
+        let cities = read_cities(instance, opts.lenient);
+        let projection = opts.projection.map(|projection| projection.resolve(&cities));
+        let (cities, metric) = prepare_cities(projection, opts.metric, instance, cities);
+        print!("{}", diff::diff(tour_a, tour_b, &cities, metric, opts.tour_type).unwrap_or_else(|e| failed(&e)));
+        return;
+    }
 
-        let order: Vec<String> = fittest.order.iter().map(|o| o.to_string()).collect();
+    if let Some(Cmd::Evaluate { tour, instance, against }) = &opts.cmd {
+        let cities = read_cities(instance, opts.lenient);
+        let projection = opts.projection.map(|projection| projection.resolve(&cities));
+        let (cities, metric) = prepare_cities(projection, opts.metric, instance, cities);
+        let evaluated = evaluate::evaluate_file(tour, against.as_deref(), instance, &cities, metric, opts.tour_type, opts.optimum)
+            .unwrap_or_else(|e| failed(&e));
+        print!("{}", evaluated);
+        if !evaluated.evaluation.check.is_tour() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(Cmd::Analyze { instance, walk, descents }) = &opts.cmd {
+        let cities = read_cities(instance, opts.lenient);
+        let projection = opts.projection.map(|projection| projection.resolve(&cities));
+        let (cities, metric) = prepare_cities(projection, opts.metric, instance, cities);
+        let mut rng = SeededRng::new(opts.rng, opts.seed.unwrap_or_else(random_seed));
+        println!("seed {} ({})", rng.seed(), rng.algorithm());
+        let landscape = analyze::analyze(&cities, metric, opts.tour_type, *walk, *descents, &mut rng).unwrap_or_else(|e| failed(&e));
+        print!("{}", landscape);
+        return;
+    }
+
+    if let Some(Cmd::Generate { layout, cities, size, clusters, csv }) = &opts.cmd {
+        let mut rng = SeededRng::new(opts.rng, opts.seed.unwrap_or_else(random_seed));
+        eprintln!("seed {} ({})", rng.seed(), rng.algorithm());
+        let cities = generate::cities(*layout, *cities, *size, *clusters, &mut rng);
+        let written = match csv {
+            Some(path) => File::create(path).and_then(|file| generate::write(file, &cities)),
+            None => generate::write(std::io::stdout().lock(), &cities),
+        };
+        if let Err(e) = written {
+            eprintln!("could not write the cities: {}", e);
+            std::process::exit(1);
+        }
+        if let Some(optimum) = generate::optimum(*layout, cities.len(), *size) {
+            eprintln!("the shortest closed tour is {} long; pass --optimum {} to see how close a run gets", optimum, optimum);
+        }
+        return;
+    }
 
-        println!("Solution:");
-        println!("Fitness {}", fittest.fitness);
-        println!("{}", order.join("->"));
+    if let Some(Cmd::Tune { instance, .. }) = &mut opts.cmd {
+        opts.instance = instance.take().or(opts.instance.take());
     }
 
-    fn find_fittest(&self) -> Path {
-        let mut fittest = &self.population[0];
+    // Only the worker, the regression gate, diff, evaluate, analyze and generate can go without a problem to solve
+    let instance = match opts.instance.take().or_else(|| opts.csv.take().map(Instance::file)) {
+        Some(instance) => match opts.format {
+            Some(format) => instance.read_as(format),
+            None => instance,
+        },
+        None => structopt::clap::Error::with_description(
+            "<csv> is required (or --instance in place of it)",
+            structopt::clap::ErrorKind::MissingRequiredArgument,
+        ).exit(),
+    };
 
-        for i in 1..self.population.len() {
-            let p = &self.population[i];
-            if p.fitness > fittest.fitness {
-                fittest = p;
+    let (generations, population_size) = (opts.iterations, opts.population);
+    let (crossover_rate, mutation_rate, survival_rate) = (opts.crossover_rate, opts.mutation_rate, opts.survival_rate);
+
+    if let Some(addr) = &opts.metrics_addr {
+        match metrics::serve(addr) {
+            Ok(()) => say!("serving metrics on http://{}/metrics", addr),
+            Err(e) => eprintln!("could not serve metrics on {}: {}", addr, e),
+        }
+    }
+
+    if let Some(addr) = &opts.statsd {
+        if let Err(e) = statsd::start(addr) {
+            eprintln!("could not send statsd metrics to {}: {}", addr, e);
+        }
+    }
+
+    let cities = read_cities(&instance, opts.lenient);
+
+    if cities.len() < MIN_CITIES {
+        eprintln!("{} has {} cities, but a tour needs at least {}", instance, cities.len(), MIN_CITIES);
+        std::process::exit(1);
+    }
+    let (distances, penalty) = match instance.distances() {
+        Some(distances) => {
+            let (distances, penalty) = cost_missing_legs(distances, &instance, &opts);
+            (Some(distances), penalty)
+        }
+        None => (None, None),
+    };
+    if distances.is_some() && (opts.projection.is_some() || opts.avoid.is_some() || matches!(opts.cmd, Some(Cmd::Coordinator { .. }))) {
+        structopt::clap::Error::with_description(
+            &format!("{} gives the distances between cities, not where they are, which --projection, --avoid and coordinators need", instance),
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ).exit()
+    }
+    let projection = opts.projection.map(|projection| projection.resolve(&cities));
+    // Routes show the cities as given, even when they're solved projected
+    let given = cities.clone();
+    let (cities, metric) = prepare_cities(projection, opts.metric, &instance, cities);
+
+    if let Some(Cmd::Coordinator { listen, round_generations, worker_timeout }) = &opts.cmd {
+        let round_generations = (*round_generations).max(1);
+        let problem = cluster::Problem {
+            cities,
+            metric,
+            tour_type: opts.tour_type,
+            rounds: (generations / round_generations).max(1),
+            generations: round_generations,
+            population_size,
+            crossover_rate,
+            mutation_rate,
+            survival_rate,
+            migrants: opts.migrants,
+        };
+        match cluster::coordinate(listen, &problem, Duration::from_secs(*worker_timeout), tell) {
+            Ok(fittest) => println!("{}", fittest),
+            Err(e) => {
+                eprintln!("could not coordinate on {}: {}", listen, e);
+                std::process::exit(1);
             }
         }
+        return;
+    }
 
-        return fittest.clone();
+    #[cfg(feature = "worker")]
+    {
+        if let Some(url) = &opts.worker {
+            if distances.is_some() {
+                eprintln!("{} gives the distances between cities, which workers can't take yet", instance);
+                std::process::exit(1);
+            }
+            let defaults = worker::Defaults {
+                cities,
+                metric,
+                tour_type: opts.tour_type,
+                iterations: generations,
+                population_size,
+                crossover_rate,
+                mutation_rate,
+                survival_rate,
+            };
+            if let Err(e) = worker::run(url, &opts.subject, defaults, tell) {
+                eprintln!("worker stopped: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
     }
 
-    fn generate_next_generation(&mut self) {
-        self.population.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
+    // Checked by validate
+    let fitness_timeout = Duration::from_secs_f64(opts.fitness_timeout);
 
-        let breeding_count = (self.population.len() as f64 * self.crossover_rate) as usize;
-        let surviving_parent_count = (breeding_count as f64 * self.survival_rate) as usize;
-        let surviving_weak_count = 2;
+    if distances.as_ref().is_some_and(|distances| !distances.is_symmetric()) && !opts.asymmetric {
+        eprintln!("note: the costs of {} differ by direction, solving it as asymmetric", instance);
+    }
+    let instance_name = instance.name();
+    // The known optima are of closed tours measured as the instance says, avoiding nothing
+    let known = optimum::known(&instance_name, cities.len(), metric)
+        .filter(|_| opts.tour_type == TourType::Closed && opts.avoid.is_none() && distances.is_none());
+    if let (Some(optimum), None) = (known, opts.optimum) {
+        say!("{} is a known instance with an optimal tour of {}", instance_name, optimum);
+    }
+    let barriers = opts.avoid.as_ref().map(|path| read_barriers(path, projection));
 
-        let mut breeding_population = Vec::new();
-        breeding_population.extend_from_slice(&self.population[0..breeding_count]);
+    // A simulation of the problem with every option that applies to each run of it
+    let build_with = |seed, config: tune::Config| {
+        let mut sim = Simulation::new(
+            config.population,
+            cities.clone(),
+            generations,
+            config.crossover_rate,
+            config.mutation_rate,
+            config.survival_rate,
+            SeededRng::new(opts.rng, seed),
+        ).unwrap_or_else(|e| invalid(&e));
+        sim.on_notice(tell);
+        if let Some(count) = opts.elitism {
+            sim.set_elitism(count);
+        }
+        sim.set_selection(opts.selection.build(opts.tournament_size, opts.selection_pressure, (opts.temp_start, opts.temp_end)));
+        sim.set_crossover(config.crossover);
+        sim.set_mutation(config.mutation.build());
+        sim.set_init(opts.init, opts.init_fraction).unwrap_or_else(|e| invalid(&e));
+        if let Some(mut adaptive) = opts.adaptive_mutation {
+            adaptive.threshold = opts.adaptive_threshold;
+            sim.set_adaptive_mutation(adaptive);
+        }
+        if opts.adaptive_operators {
+            sim.set_adaptive_operators();
+        }
+        #[cfg(feature = "parallel")]
+        {
+            if let Some(count) = opts.threads {
+                if let Err(e) = sim.set_threads(count) {
+                    eprintln!("could not start {} threads: {}", count, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if let Some(size) = opts.top_k {
+            sim.set_hall_of_fame(size);
+        }
+        if let Some(dedup) = opts.dedup {
+            sim.set_dedup(dedup);
+        }
+        sim.set_verify(opts.verify);
+        sim.set_polish(opts.polish);
+        if let Some(kind) = opts.local_search {
+            sim.set_local_search(LocalSearch { kind, target: opts.local_search_on, budget: opts.local_search_budget });
+        }
+        sim.set_metric(metric);
+        sim.set_tour_type(opts.tour_type);
+        if let Some(distances) = &distances {
+            sim.set_distances(distances.clone()).expect("the distances are between the instance's cities");
+        }
+        if opts.asymmetric {
+            sim.set_asymmetric(true);
+        }
+        if let Some(prefix) = opts.prefix.clone() {
+            if let Err(e) = sim.set_prefix(prefix) {
+                eprintln!("invalid --prefix: {}", e);
+                std::process::exit(1);
+            }
+        }
+        if let Err(e) = sim.set_required_edges(opts.require_edge.clone()) {
+            eprintln!("invalid --require-edge: {}", e);
+            std::process::exit(1);
+        }
+        sim.set_watch(opts.stagnation, opts.diversity_floor);
+        if let Some(limit) = opts.time_limit {
+            sim.set_time_limit(limit);
+        }
+        if let Some(generations) = opts.stagnation_limit {
+            sim.set_stagnation_limit(generations);
+        }
+        if let Some(length) = opts.target_length {
+            sim.set_target(length);
+            sim.set_stop_length(length);
+        }
+        if let Some(optimum) = opts.optimum.or(known) {
+            sim.set_optimum(optimum);
+        }
+        if let Some(bytes) = opts.max_memory {
+            sim.set_max_memory(bytes);
+        }
+        if let Some(bytes) = opts.matrix_memory {
+            sim.set_matrix_memory(bytes);
+        }
+        sim.set_distance_precision(opts.distance_precision);
+        if let Some(barriers) = &barriers {
+            sim.set_evaluator(Box::new(Avoid::new(sim.cities().to_vec(), metric, opts.tour_type, barriers.clone(), opts.avoid_penalty)))
+                .unwrap_or_else(|e| failed(&e));
+        }
+        sim
+    };
+    let configured = tune::Config {
+        population: population_size,
+        crossover_rate,
+        mutation_rate,
+        survival_rate,
+        crossover: opts.crossover,
+        mutation: opts.mutation,
+    };
+    let build = |seed| build_with(seed, configured);
 
-        let mut offspring = Vec::new();
-        let mut rng = thread_rng();
-        let pcnt_range = Uniform::new(0, breeding_population.len());
+    #[cfg(feature = "web-ui")]
+    {
+        if let Some(addr) = &opts.ui_addr {
+            use dashboard::DASHBOARD;
 
-        for i in 0..(self.population.len() - surviving_parent_count - surviving_weak_count) {
-            let rs = pcnt_range.sample(&mut rng);
-            offspring.push(
-                breeding_population[i % breeding_population.len()].breed(
-                    &breeding_population[rs],
-                    &self.city_list
-                )
-            );
+            if let Err(e) = dashboard::serve(addr) {
+                eprintln!("could not serve the dashboard on {}: {}", addr, e);
+                std::process::exit(1);
+            }
+            say!("serving the dashboard on http://{}/", addr);
+
+            let mut seed = opts.seed.unwrap_or_else(random_seed);
+            loop {
+                DASHBOARD.begin_run(&cities);
+                let mut sim = build(seed);
+                if let Some(dir) = &opts.hints {
+                    sim.set_hints(watch_hints(dir));
+                }
+                say!("seed {} ({})", sim.seed(), opts.rng);
+                sim.solve(|report| DASHBOARD.record_generation(report.fittest)).unwrap_or_else(|e| failed(&e));
+                DASHBOARD.end_run();
+                DASHBOARD.wait_for_start();
+                seed = random_seed();
+            }
         }
+    }
 
-        let mut next_generation = Vec::new();
-        next_generation.extend_from_slice(&self.population[0..surviving_parent_count]);
-        next_generation.append(&mut offspring);
-        // Add a few weak units to keep the genetic diversity
-        next_generation.extend_from_slice(
-            &self.population[(self.population.len() - surviving_weak_count)..self.population.len()]
-        );
+    if let Some(Cmd::Tune { search, trials, seeds, budget, config, .. }) = &opts.cmd {
+        let space = opts.tuning_space().expect("tuning");
+        let first = opts.seed.unwrap_or_else(random_seed);
+        let seeds = tune::seeds(first, *seeds);
+        let trials = trials.unwrap_or_else(|| search.trials(&space));
+        say!("tuning on {} with a {} search of {} configurations at most in {:?}, from seeds {} ({})",
+            instance, search, trials, budget, seeds.iter().map(u64::to_string).collect::<Vec<_>>().join(", "), opts.rng);
 
-        for p in 0..next_generation.len() {
-            if thread_rng().gen_bool(self.mutation_rate) {
-                next_generation[p].mutate(&self.city_list);
+        let mut rng = SeededRng::new(opts.rng, first);
+        let tuning = tune::run(&space, *search, trials, &seeds, *budget, &mut rng, opts.time_limit, build_with);
+        let toml = match tuning.config_file(instance, generations) {
+            Some(toml) => toml,
+            None => {
+                eprintln!("no configuration finished its runs within the budget of {:?}", budget);
+                std::process::exit(1);
             }
+        };
+        say!("{}", tuning);
+
+        let written = match config {
+            Some(path) => std::fs::write(path, toml),
+            None => std::io::Write::write_all(&mut std::io::stdout(), toml.as_bytes()),
+        };
+        if let Err(e) = written {
+            eprintln!("could not write the configuration: {}", e);
+            std::process::exit(1);
         }
+        return;
+    }
 
-        self.population = next_generation;
+    if let Some(count) = opts.restarts {
+        let seeds = Restarts::seeds(opts.seed, count);
+        say!("solving {} times from seeds {} ({})", count, seeds.iter().map(u64::to_string).collect::<Vec<_>>().join(", "), opts.rng);
+        let restarts = Restarts::run(&seeds, build).unwrap_or_else(|e| failed(&e));
+        if !stdout_reserved() {
+            println!("{}", restarts.best().report);
+        }
+        say!("{}", restarts);
+        if opts.output_format == Format::Json {
+            println!("{}", restarts.result());
+        }
+        return;
     }
 
-    fn initial_population(city_list: &Vec<City>, population_count: usize) -> Vec<Path> {
-        let base_list: Vec<usize> = (0..city_list.len()).collect();
-        let mut population = Vec::new();
+    let mut sim = build(opts.seed.unwrap_or_else(random_seed));
+    if let Some(path) = &opts.resume {
+        let resumed = checkpoint::load(path).map_err(|e| e.to_string()).and_then(|c| {
+            let generation = c.generation;
+            sim.resume(c).map(|()| generation).map_err(|e| e.to_string())
+        });
+        match resumed {
+            Ok(generation) => say!("resuming {} after generation {}", path.display(), generation),
+            Err(e) => {
+                eprintln!("could not resume {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let manifest = opts.manifest.as_ref().map(|_| {
+        #[allow(unused_mut)] // Only extended with feature-specific options
+        let mut parameters = json!({
+            "population_size": sim.population_size(),
+            "generations": sim.generations(),
+            "crossover_rate": crossover_rate,
+            "mutation_rate": sim.mutation_rate(),
+            "survival_rate": survival_rate,
+            "breeding_count": sim.breeding_count(),
+            "rng": opts.rng.to_string(),
+            "config": opts.config,
+            "lenient": opts.lenient,
+            "max_memory": opts.max_memory,
+            "matrix_memory": opts.matrix_memory.unwrap_or_else(|| metric.matrix_memory()),
+            "distance_precision": opts.distance_precision.to_string(),
+            "checkpoint": opts.checkpoint,
+            "checkpoint_interval": opts.checkpoint_interval,
+            "hints": opts.hints,
+            "dump_population": opts.dump_population.as_ref().map(PopulationDumps::to_string),
+            "resume": opts.resume,
+            "top_k": opts.top_k,
+            "verify": opts.verify,
+            "polish": opts.polish,
+            "dedup": opts.dedup.map(|dedup| dedup.to_string()),
+            "local_search": opts.local_search.map(|kind| kind.to_string()),
+            "local_search_on": opts.local_search_on.to_string(),
+            "local_search_budget": opts.local_search_budget,
+            "metric": metric.to_string(),
+            "asymmetric": sim.asymmetric(),
+            "tour_type": opts.tour_type.to_string(),
+            "unreachable": opts.unreachable.to_string(),
+            "projection": projection.map(|projection| projection.to_string()),
+            "prefix": opts.prefix,
+            "required_edges": opts.require_edge,
+            "avoid": opts.avoid,
+            "avoid_penalty": opts.avoid_penalty.to_string(),
+            "init": opts.init.to_string(),
+            "init_fraction": opts.init_fraction,
+            "elitism": opts.elitism,
+            "crossover": opts.crossover.to_string(),
+            "mutation": opts.mutation.to_string(),
+            "adaptive_mutation": opts.adaptive_mutation.map(|adaptive| adaptive.to_string()),
+            "adaptive_threshold": opts.adaptive_threshold,
+            "adaptive_operators": opts.adaptive_operators,
+            "selection": opts.selection.to_string(),
+            "tournament_size": opts.tournament_size,
+            "selection_pressure": opts.selection_pressure,
+            "temp_start": opts.temp_start,
+            "temp_end": opts.temp_end,
+            "report_every": opts.report_every,
+            "stagnation": opts.stagnation,
+            "stagnation_limit": opts.stagnation_limit,
+            "time_limit": opts.time_limit.map(|limit| limit.as_secs_f64()),
+            "diversity_floor": opts.diversity_floor,
+            "target_length": opts.target_length,
+            "optimum": opts.optimum.or(known),
+            "fitness_cmd": opts.fitness_cmd,
+            "fitness_batch": opts.fitness_batch,
+            "fitness_timeout": opts.fitness_timeout,
+            "migration": opts.migration,
+            "migration_key": opts.migration_key,
+            "migration_interval": opts.migration_interval,
+            "migrants": opts.migrants,
+        });
+        #[cfg(feature = "rhai")]
+        {
+            parameters["script"] = json!(opts.script);
+        }
+        #[cfg(feature = "parallel")]
+        {
+            parameters["threads"] = json!(opts.threads);
+        }
+        let input = instance.contents().unwrap_or_else(|e| {
+            eprintln!("could not read {} to fingerprint it: {}", instance, e);
+            std::process::exit(1);
+        });
+        Manifest::start(parameters, sim.seed(), &instance.to_string(), &input)
+    });
 
-        for _ in 0..population_count {
-            let mut p = base_list.clone();
-            let mut rng = thread_rng();
-            p.shuffle(&mut rng);
-            let fitness = Path::calculate_fitness(&p, city_list);
+    if let Some(path) = &opts.events {
+        match EventLog::open(path) {
+            Ok(log) => sim.set_event_log(log),
+            Err(e) => {
+                eprintln!("could not open {} to log events to: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = opts.checkpoint.take() {
+        sim.set_checkpoints(path, opts.checkpoint_interval);
+    }
+    if let Some(dumps) = opts.dump_population.take() {
+        sim.set_population_dumps(dumps);
+    }
+    if let Some(dir) = &opts.hints {
+        sim.set_hints(watch_hints(dir));
+    }
+    if opts.repl {
+        match Repl::stdin() {
+            Ok(repl) => {
+                say!("reading commands from stdin, type help for the list");
+                sim.set_repl(repl);
+            }
+            Err(e) => eprintln!("could not read commands from stdin: {}", e),
+        }
+    }
+    #[cfg(unix)]
+    repl::pause_on_signal();
 
-            population.push(Path { fitness, order: p });
+    if let Some(command) = &opts.fitness_cmd {
+        match FitnessCommand::spawn(command, opts.fitness_batch, fitness_timeout) {
+            Ok(command) => sim.set_evaluator(Box::new(command)).unwrap_or_else(|e| failed(&e)),
+            Err(e) => {
+                eprintln!("could not start fitness command {:?}: {}", command, e);
+                std::process::exit(1);
+            }
         }
+    }
 
-        population
+    if let Some(url) = &opts.migration {
+        match Migration::connect(url, &opts.migration_key, opts.migration_interval, opts.migrants) {
+            Ok(migration) => sim.set_migration(migration),
+            Err(e) => eprintln!("could not connect to {} for migration: {}", url, e),
+        }
     }
-}
 
-#[derive(StructOpt)]
-#[structopt()]
-struct Opt {
-    #[structopt(name = "iterations")]
-    iterations: usize,
-    #[structopt(name = "pop_size")]
-    population_size: usize,
-    #[structopt(name = "crossover_rate")]
-    crossover_rate: f64,
-    #[structopt(name = "mutation_rate")]
-    mutation_rate: f64,
-    #[structopt(name = "survival_rate")]
-    survival_rate: f64,
-    #[structopt(name = "csv", parse(from_os_str))]
-    csv: PathBuf,
-}
+    #[cfg(feature = "otel")]
+    {
+        if let Some(endpoint) = &opts.otlp_endpoint {
+            sim.set_tracer(Tracer::otlp(endpoint).unwrap_or_else(|e| invalid(&e)));
+        }
+    }
 
-fn main() {
-    let opts = Opt::from_args();
-    let mut reader = Reader::from_path(opts.csv).unwrap();
-    let cities: Vec<City> = reader.deserialize()
-        .map(|r| {
-            let result: City = r.unwrap();
-            result
-        })
-        .collect();
+    let mut targets = opts.out.clone();
+    targets.extend(opts.history.clone().map(|path| Target { kind: output::Kind::History, path }));
+    targets.extend(opts.route.clone().map(|path| Target { kind: output::Kind::Route, path }));
+    targets.extend(opts.output_file.clone().map(|path| {
+        let geojson = opts.output_format == Format::Geojson
+            || path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("geojson"));
+        Target { kind: if geojson { output::Kind::Geojson } else { output::Kind::Tour }, path }
+    }));
+    let mut outputs = Outputs::open(&targets).unwrap_or_else(|(path, e)| {
+        eprintln!("could not create {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+
+    #[allow(unused_mut)] // Only replaced by a script's hook
+    let mut on_generation: OnGeneration = Box::new(|_| ControlFlow::Continue(()));
+    #[cfg(feature = "rhai")]
+    {
+        if let Some(path) = opts.script.take() {
+            let mut script = match script::Script::load(path.clone(), sim.cities(), fitness_timeout) {
+                Ok(script) => script,
+                Err(e) => {
+                    eprintln!("could not load {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+
+            if script.defines("fitness") && barriers.is_some() {
+                eprintln!("{} defines fitness, which can't be combined with --avoid", path.display());
+                std::process::exit(1);
+            }
+            if script.defines("fitness") {
+                sim.set_evaluator(Box::new(script.clone())).unwrap_or_else(|e| failed(&e));
+            }
+            if script.defines("on_generation") {
+                on_generation = Box::new(move |report| {
+                    script.on_generation(report.generation, report.fittest).unwrap_or_else(|e| {
+                        tell(&Notice::Warning(format!("on_generation failed: {}", e)));
+                        ControlFlow::Continue(())
+                    })
+                });
+            }
+        }
+    }
+
+    say!("seed {} ({})", sim.seed(), opts.rng);
+    say!("starting iterations");
+    let mut progress = Progress::new(opts.report_every);
+    let solved = solve::solve(&mut sim, penalty, barriers.as_ref(), |report| {
+        progress.generation(report);
+        outputs.generation(report).iter().for_each(tell);
+        on_generation(report)
+    }).unwrap_or_else(|e| failed(&e));
+    let notices = solved.notices();
+    let fittest = solved.fittest;
+    if !stdout_reserved() {
+        println!("{}", fittest);
+        print_hall_of_fame(sim.hall_of_fame());
+    }
+    if let Some(summary) = sim.summary() {
+        say!("{}", summary);
+    }
+    if let Some(operators) = sim.adaptive_operators() {
+        say!("operator chances: {}", operators);
+    }
+    notices.iter().for_each(tell);
 
-    let mut sim = Simulation::new(
-        opts.iterations,
-        cities,
-        opts.population_size,
-        opts.crossover_rate,
-        opts.mutation_rate,
-        opts.survival_rate,
-    );
-    sim.run();
+    let outcome = Outcome {
+        fittest: &fittest,
+        cities: sim.cities(),
+        given: &given,
+        metric,
+        tour_type: opts.tour_type,
+        distances: sim.distances(),
+        summary: sim.summary(),
+        hall_of_fame: sim.hall_of_fame(),
+    };
+    match opts.output_format {
+        Format::Text => {}
+        Format::Json => println!("{}", outcome.result(sim.seed())),
+        Format::Geojson => println!("{}", outcome.geojson()),
+    }
+    let failed = outputs.finish(&outcome);
+    for (path, e) in &failed {
+        eprintln!("could not write {}: {}", path.display(), e);
+    }
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+
+    if let (Some(manifest), Some(path)) = (manifest, &opts.manifest) {
+        if let Err(e) = manifest.write(path, &fittest, sim.summary()) {
+            eprintln!("could not write the manifest {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
 }
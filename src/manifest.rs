@@ -0,0 +1,82 @@
+//! A manifest recording everything needed to audit or reproduce a run: the build, the
+//! parameters as resolved, the seed, a fingerprint of the input and the result.
+
+use std::fs;
+use std::path::Path as FilePath;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::sha256;
+use crate::summary::Summary;
+use crate::Path;
+
+pub struct Manifest {
+    started: SystemTime,
+    parameters: Value,
+    seed: u64,
+    input: Value,
+}
+
+impl Manifest {
+    /// Starts the manifest of a run with `parameters` from `seed`, solving `instance`, whose
+    /// contents are `input`.
+    pub fn start(parameters: Value, seed: u64, instance: &str, input: &[u8]) -> Manifest {
+        Manifest {
+            started: SystemTime::now(),
+            parameters,
+            seed,
+            input: json!({ "instance": instance, "sha256": sha256::hex_digest(input) }),
+        }
+    }
+
+    /// Writes the manifest of the run that found `fittest` to `path`.
+    pub fn write(&self, path: &FilePath, fittest: &Path, summary: Option<&Summary>) -> std::io::Result<()> {
+        let manifest = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "git_hash": option_env!("WASI_GENETIC_GIT_HASH"),
+            "command_line": std::env::args().collect::<Vec<String>>(),
+            "parameters": self.parameters,
+            "seed": self.seed,
+            "input": self.input,
+            "started_at": timestamp(self.started),
+            "ended_at": timestamp(SystemTime::now()),
+            "result": {
+                "length": 1.0 / fittest.fitness,
+                "order": fittest.order,
+                "summary": summary.map(Summary::to_json),
+            },
+        });
+
+        fs::write(path, serde_json::to_string_pretty(&manifest)? + "\n")
+    }
+}
+
+/// `time` in UTC as RFC 3339, e.g. `2026-10-15T09:30:00.250Z`.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, seconds_of_day) = ((seconds / 86400) as i64, seconds % 86400);
+
+    // Days since the epoch to a civil date, after Howard Hinnant's days_from_civil inverse
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
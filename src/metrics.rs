@@ -0,0 +1,272 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::diversity::Diversity;
+
+// Upper bounds (in seconds) of the fitness evaluation latency buckets
+const LATENCY_BUCKETS: [f64; 8] = [1e-6, 5e-6, 1e-5, 5e-5, 1e-4, 5e-4, 1e-3, 1e-2];
+
+// The best length before any generation: none, as far as anyone watching is concerned
+const NO_LENGTH: u64 = f64::INFINITY.to_bits();
+
+pub static METRICS: Metrics = Metrics::new();
+
+pub struct Metrics {
+    enabled: AtomicBool,
+    generations: AtomicU64,
+    evaluations: AtomicU64,
+    timeouts: AtomicU64,
+    job_queue_depth: AtomicU64,
+    best_length: AtomicU64,
+    unique_ratio: AtomicU64,
+    edge_overlap: AtomicU64,
+    position_entropy: AtomicU64,
+    latency_buckets: [AtomicU64; 8],
+    latency_sum_ns: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) const fn new() -> Metrics {
+        Metrics {
+            enabled: AtomicBool::new(false),
+            generations: AtomicU64::new(0),
+            evaluations: AtomicU64::new(0),
+            timeouts: AtomicU64::new(0),
+            job_queue_depth: AtomicU64::new(0),
+            best_length: AtomicU64::new(NO_LENGTH),
+            unique_ratio: AtomicU64::new(0),
+            edge_overlap: AtomicU64::new(0),
+            position_entropy: AtomicU64::new(0),
+            latency_buckets: [
+                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+                AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0),
+            ],
+            latency_sum_ns: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Turns on the measurements only taken for someone watching, e.g. evaluation latency.
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn generations(&self) -> u64 {
+        self.generations.load(Ordering::Relaxed)
+    }
+
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Jobs a worker has taken and not yet replied to.
+    pub fn job_queue_depth(&self) -> u64 {
+        self.job_queue_depth.load(Ordering::Relaxed)
+    }
+
+    /// The shortest tour any run has found, over every run in the process, once there's been a
+    /// generation.
+    pub fn best_length(&self) -> Option<f64> {
+        Some(f64::from_bits(self.best_length.load(Ordering::Relaxed))).filter(|length| length.is_finite())
+    }
+
+    /// The diversity of the latest generation.
+    pub fn diversity(&self) -> Diversity {
+        Diversity {
+            unique_ratio: f64::from_bits(self.unique_ratio.load(Ordering::Relaxed)),
+            edge_overlap: f64::from_bits(self.edge_overlap.load(Ordering::Relaxed)),
+            position_entropy: f64::from_bits(self.position_entropy.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn record_generation(&self, best_length: f64) {
+        self.generations.fetch_add(1, Ordering::Relaxed);
+        // Lengths are never negative, so their bits order as they do
+        self.best_length.fetch_min(best_length.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn record_diversity(&self, diversity: &Diversity) {
+        self.unique_ratio.store(diversity.unique_ratio.to_bits(), Ordering::Relaxed);
+        self.edge_overlap.store(diversity.edge_overlap.to_bits(), Ordering::Relaxed);
+        self.position_entropy.store(diversity.position_entropy.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn record_timeout(&self) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_job_taken(&self) {
+        self.job_queue_depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_job_done(&self) {
+        self.job_queue_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn record_evaluation(&self, elapsed: Duration) {
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_ns.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+
+        let seconds = elapsed.as_secs_f64();
+        if let Some(b) = LATENCY_BUCKETS.iter().position(|upper| seconds <= *upper) {
+            self.latency_buckets[b].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn render(&self) -> String {
+        let evaluations = self.evaluations.load(Ordering::Relaxed);
+        let mut out = String::new();
+
+        out.push_str("# HELP wasi_genetic_generations_total Generations evolved.\n");
+        out.push_str("# TYPE wasi_genetic_generations_total counter\n");
+        out.push_str(&format!("wasi_genetic_generations_total {}\n", self.generations()));
+
+        out.push_str("# HELP wasi_genetic_evaluations_total Fitness evaluations performed.\n");
+        out.push_str("# TYPE wasi_genetic_evaluations_total counter\n");
+        out.push_str(&format!("wasi_genetic_evaluations_total {}\n", evaluations));
+
+        out.push_str("# HELP wasi_genetic_evaluation_timeouts_total Fitness evaluations that timed out and got the worst fitness.\n");
+        out.push_str("# TYPE wasi_genetic_evaluation_timeouts_total counter\n");
+        out.push_str(&format!("wasi_genetic_evaluation_timeouts_total {}\n", self.timeouts()));
+
+        out.push_str("# HELP wasi_genetic_job_queue_depth Jobs taken from the queue and not yet replied to.\n");
+        out.push_str("# TYPE wasi_genetic_job_queue_depth gauge\n");
+        out.push_str(&format!("wasi_genetic_job_queue_depth {}\n", self.job_queue_depth()));
+
+        out.push_str("# HELP wasi_genetic_best_length Length of the best tour found so far.\n");
+        out.push_str("# TYPE wasi_genetic_best_length gauge\n");
+        if let Some(best_length) = self.best_length() {
+            out.push_str(&format!("wasi_genetic_best_length {}\n", best_length));
+        }
+
+        let diversity = self.diversity();
+        out.push_str("# HELP wasi_genetic_unique_tour_ratio Distinct tours over the population size.\n");
+        out.push_str("# TYPE wasi_genetic_unique_tour_ratio gauge\n");
+        out.push_str(&format!("wasi_genetic_unique_tour_ratio {}\n", diversity.unique_ratio));
+
+        out.push_str("# HELP wasi_genetic_edge_overlap Mean share of edges two tours of the population have in common.\n");
+        out.push_str("# TYPE wasi_genetic_edge_overlap gauge\n");
+        out.push_str(&format!("wasi_genetic_edge_overlap {}\n", diversity.edge_overlap));
+
+        out.push_str("# HELP wasi_genetic_position_entropy Mean normalised entropy of the city at each tour position.\n");
+        out.push_str("# TYPE wasi_genetic_position_entropy gauge\n");
+        out.push_str(&format!("wasi_genetic_position_entropy {}\n", diversity.position_entropy));
+
+        out.push_str("# HELP wasi_genetic_evaluation_duration_seconds Time spent evaluating a single tour.\n");
+        out.push_str("# TYPE wasi_genetic_evaluation_duration_seconds histogram\n");
+        let mut cumulative = 0;
+        for (upper, count) in LATENCY_BUCKETS.iter().zip(self.latency_buckets.iter()) {
+            cumulative += count.load(Ordering::Relaxed);
+            out.push_str(&format!(
+                "wasi_genetic_evaluation_duration_seconds_bucket{{le=\"{}\"}} {}\n",
+                upper, cumulative
+            ));
+        }
+        out.push_str(&format!(
+            "wasi_genetic_evaluation_duration_seconds_bucket{{le=\"+Inf\"}} {}\n",
+            evaluations
+        ));
+        out.push_str(&format!(
+            "wasi_genetic_evaluation_duration_seconds_sum {}\n",
+            self.latency_sum_ns.load(Ordering::Relaxed) as f64 / 1e9
+        ));
+        out.push_str(&format!("wasi_genetic_evaluation_duration_seconds_count {}\n", evaluations));
+
+        out
+    }
+}
+
+/// Serves the metrics in the Prometheus text format on `addr` from a background thread.
+pub fn serve(addr: &str) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    METRICS.enable();
+
+    thread::Builder::new().name("metrics".to_string()).spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A misbehaving scraper shouldn't take the endpoint down
+            let _ = respond(stream);
+        }
+    })?;
+
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", METRICS.render())
+    } else {
+        ("404 Not Found", String::new())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_render_in_the_prometheus_text_format() {
+        let metrics = Metrics::new();
+        assert!(!metrics.render().lines().any(|l| l.starts_with("wasi_genetic_best_length ")));
+        metrics.record_generation(12.5);
+        metrics.record_generation(10.0);
+        // A later run that does worse doesn't make the best any longer
+        metrics.record_generation(11.0);
+        metrics.record_timeout();
+        metrics.record_job_taken();
+        metrics.record_evaluation(Duration::from_nanos(500));
+        metrics.record_evaluation(Duration::from_micros(30));
+        metrics.record_evaluation(Duration::from_micros(40));
+        metrics.record_evaluation(Duration::from_secs(1));
+
+        let text = metrics.render();
+        for line in [
+            "# TYPE wasi_genetic_generations_total counter",
+            "wasi_genetic_generations_total 3",
+            "wasi_genetic_evaluations_total 4",
+            "wasi_genetic_evaluation_timeouts_total 1",
+            "# TYPE wasi_genetic_job_queue_depth gauge",
+            "wasi_genetic_job_queue_depth 1",
+            "wasi_genetic_best_length 10",
+            "# TYPE wasi_genetic_evaluation_duration_seconds histogram",
+            "wasi_genetic_evaluation_duration_seconds_bucket{le=\"0.000001\"} 1",
+            "wasi_genetic_evaluation_duration_seconds_bucket{le=\"0.00001\"} 1",
+            "wasi_genetic_evaluation_duration_seconds_bucket{le=\"0.00005\"} 3",
+            "wasi_genetic_evaluation_duration_seconds_bucket{le=\"0.01\"} 3",
+            "wasi_genetic_evaluation_duration_seconds_bucket{le=\"+Inf\"} 4",
+            "wasi_genetic_evaluation_duration_seconds_sum 1.0000705",
+            "wasi_genetic_evaluation_duration_seconds_count 4",
+        ] {
+            assert!(text.lines().any(|l| l == line), "no {:?} in\n{}", line, text);
+        }
+
+        // Every sample is preceded by the HELP and TYPE of its metric
+        for sample in text.lines().filter(|l| !l.starts_with('#')) {
+            let name = sample.split(['{', ' ']).next().unwrap();
+            let family = name.trim_end_matches("_bucket").trim_end_matches("_sum").trim_end_matches("_count");
+            let family = if text.contains(&format!("# TYPE {} ", family)) { family } else { name };
+            assert!(text.contains(&format!("# HELP {} ", family)), "no HELP for {}", sample);
+            assert!(text.contains(&format!("# TYPE {} ", family)), "no TYPE for {}", sample);
+        }
+
+        metrics.record_job_done();
+        assert_eq!(metrics.job_queue_depth(), 0);
+    }
+}
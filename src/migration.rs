@@ -0,0 +1,263 @@
+//! Island-model migration through a shared Redis stream.
+//!
+//! Every instance pointed at the same stream periodically appends its best tours to it and reads
+//! back the tours the other instances appended since it last looked, so separate machines evolve
+//! as islands that occasionally swap elites.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{thread_rng, Rng};
+
+// Keeps the stream from growing without bound on long runs
+const STREAM_LENGTH: &str = "1000";
+const READ_COUNT: &str = "64";
+// Replies past these come from something other than the stream this module writes, and reading
+// them would only allocate or recurse on the server's say-so
+const MAX_BULK_LENGTH: i64 = 16 << 20;
+const MAX_DEPTH: usize = 8;
+
+enum Reply {
+    Nil,
+    Integer(i64),
+    Text(String),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn into_array(self) -> Vec<Reply> {
+        match self {
+            Reply::Array(items) => items,
+            _ => Vec::new(),
+        }
+    }
+
+    fn into_text(self) -> String {
+        match self {
+            Reply::Text(text) => text,
+            Reply::Integer(i) => i.to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+pub struct Migration {
+    connection: Connection,
+    key: String,
+    island: String,
+    last_id: String,
+    interval: usize,
+    migrants: usize,
+}
+
+impl Migration {
+    /// Connects to the Redis server at `url` (e.g. `redis://localhost:6379`) and exchanges
+    /// `migrants` tours through the stream `key` every `interval` generations.
+    pub fn connect(url: &str, key: &str, interval: usize, migrants: usize) -> std::io::Result<Migration> {
+        let addr = url.trim_start_matches("redis://").trim_end_matches('/');
+        let writer = TcpStream::connect(addr)?;
+        let reader = BufReader::new(writer.try_clone()?);
+
+        // Stream ids start with a millisecond timestamp, so this skips tours from earlier runs
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+
+        Ok(Migration {
+            connection: Connection { reader, writer },
+            key: key.to_string(),
+            island: format!("{:016x}", thread_rng().gen::<u64>()),
+            last_id: format!("{}-0", now_ms),
+            interval: interval.max(1),
+            migrants,
+        })
+    }
+
+    pub fn due(&self, generation: usize) -> bool {
+        (generation + 1).is_multiple_of(self.interval)
+    }
+
+    pub fn migrants(&self) -> usize {
+        self.migrants
+    }
+
+    /// Publishes `emigrants` and returns the tours other islands published since the last call.
+    pub fn exchange(&mut self, emigrants: &[&[usize]]) -> std::io::Result<Vec<Vec<usize>>> {
+        let reply = self.connection.command(&["XREAD", "COUNT", READ_COUNT, "STREAMS", &self.key, &self.last_id])?;
+
+        // [[key, [[id, [field, value, ...]], ...]]] or nil when nothing is new
+        let mut immigrants = Vec::new();
+        for stream in reply.into_array() {
+            let entries = stream.into_array().into_iter().nth(1).map(Reply::into_array).unwrap_or_default();
+            for entry in entries {
+                let mut entry = entry.into_array().into_iter();
+                if let Some(id) = entry.next() {
+                    self.last_id = id.into_text();
+                }
+
+                let fields: Vec<String> = entry.next().map(Reply::into_array).unwrap_or_default()
+                    .into_iter()
+                    .map(Reply::into_text)
+                    .collect();
+                let field = |name: &str| fields.chunks(2)
+                    .find(|pair| pair[0] == name)
+                    .and_then(|pair| pair.get(1));
+
+                if field("island") == Some(&self.island) {
+                    continue;
+                }
+                if let Some(order) = field("order").and_then(|o| parse_order(o)) {
+                    immigrants.push(order);
+                }
+            }
+        }
+
+        for order in emigrants {
+            let order: Vec<String> = order.iter().map(|o| o.to_string()).collect();
+            self.connection.command(&[
+                "XADD", &self.key, "MAXLEN", "~", STREAM_LENGTH, "*",
+                "island", &self.island, "order", &order.join(","),
+            ])?;
+        }
+
+        Ok(immigrants)
+    }
+}
+
+impl Connection {
+    fn command(&mut self, args: &[&str]) -> std::io::Result<Reply> {
+        let mut request = format!("*{}\r\n", args.len());
+        for arg in args {
+            request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        self.writer.write_all(request.as_bytes())?;
+
+        self.read_reply()
+    }
+
+    fn read_reply(&mut self) -> std::io::Result<Reply> {
+        read_reply(&mut self.reader, 0)
+    }
+}
+
+fn read_reply(reader: &mut impl BufRead, depth: usize) -> std::io::Result<Reply> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "redis closed the connection"));
+    }
+    let line = line.trim_end();
+    if line.is_empty() {
+        return Err(resp_error("empty reply".to_string()));
+    }
+    let (kind, rest) = line.split_at(1);
+
+    match kind {
+        "+" => Ok(Reply::Text(rest.to_string())),
+        "-" => Err(resp_error(rest.to_string())),
+        ":" => Ok(Reply::Integer(parse_length(rest)?)),
+        "$" => {
+            let length = parse_length(rest)?;
+            if length < 0 {
+                return Ok(Reply::Nil);
+            }
+            if length > MAX_BULK_LENGTH {
+                return Err(resp_error(format!("bulk reply of {} bytes is over the {} byte limit", length, MAX_BULK_LENGTH)));
+            }
+            let mut data = vec![0; length as usize + 2];
+            reader.read_exact(&mut data)?;
+            data.truncate(length as usize);
+            Ok(Reply::Text(String::from_utf8_lossy(&data).into_owned()))
+        }
+        "*" => {
+            let length = parse_length(rest)?;
+            if length < 0 {
+                return Ok(Reply::Nil);
+            }
+            if depth >= MAX_DEPTH {
+                return Err(resp_error(format!("reply nests arrays more than {} deep", MAX_DEPTH)));
+            }
+            let mut items = Vec::new();
+            for _ in 0..length {
+                items.push(read_reply(reader, depth + 1)?);
+            }
+            Ok(Reply::Array(items))
+        }
+        _ => Err(resp_error(format!("unexpected reply {:?}", line))),
+    }
+}
+
+fn parse_length(s: &str) -> std::io::Result<i64> {
+    s.parse().map_err(|_| resp_error(format!("bad length {:?}", s)))
+}
+
+fn parse_order(s: &str) -> Option<Vec<usize>> {
+    s.split(',').map(|o| o.parse().ok()).collect()
+}
+
+fn resp_error(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(reply: &str) -> std::io::Result<Reply> {
+        read_reply(&mut reply.as_bytes(), 0)
+    }
+
+    #[test]
+    fn replies_parse_into_their_kinds() {
+        assert_eq!(parse("+OK\r\n").unwrap().into_text(), "OK");
+        assert_eq!(parse(":42\r\n").unwrap().into_text(), "42");
+        assert_eq!(parse("$5\r\nhello\r\n").unwrap().into_text(), "hello");
+        assert!(matches!(parse("$-1\r\n").unwrap(), Reply::Nil));
+        assert!(matches!(parse("*-1\r\n").unwrap(), Reply::Nil));
+
+        let items: Vec<String> = parse("*3\r\n$2\r\nid\r\n:7\r\n+x\r\n").unwrap()
+            .into_array()
+            .into_iter()
+            .map(Reply::into_text)
+            .collect();
+        assert_eq!(items, ["id", "7", "x"]);
+    }
+
+    #[test]
+    fn an_error_reply_is_an_error() {
+        let error = parse("-ERR unknown command 'XREAD'\r\n").err().expect("an error reply");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "ERR unknown command 'XREAD'");
+    }
+
+    #[test]
+    fn malformed_and_truncated_replies_are_errors() {
+        assert_eq!(parse("").err().map(|e| e.kind()), Some(std::io::ErrorKind::UnexpectedEof));
+        assert_eq!(parse("\r\n").err().map(|e| e.kind()), Some(std::io::ErrorKind::InvalidData));
+        assert_eq!(parse("?what\r\n").err().map(|e| e.kind()), Some(std::io::ErrorKind::InvalidData));
+        assert_eq!(parse("$x\r\n").err().map(|e| e.kind()), Some(std::io::ErrorKind::InvalidData));
+        assert_eq!(parse("$10\r\nshort\r\n").err().map(|e| e.kind()), Some(std::io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn oversized_replies_are_refused_before_reading_them() {
+        let error = parse(&format!("${}\r\n", i64::MAX)).err().expect("an oversized bulk reply");
+        assert!(error.to_string().contains("over the"), "{}", error);
+
+        let nested = "*1\r\n".repeat(MAX_DEPTH + 1);
+        let error = parse(&nested).err().expect("a too deeply nested reply");
+        assert!(error.to_string().contains("more than"), "{}", error);
+
+        let nested = format!("{}:1\r\n", "*1\r\n".repeat(MAX_DEPTH));
+        assert!(parse(&nested).is_ok());
+    }
+
+    #[test]
+    fn orders_parse_from_the_stream_field() {
+        assert_eq!(parse_order("2,0,1"), Some(vec![2, 0, 1]));
+        assert_eq!(parse_order("2,x,1"), None);
+    }
+}
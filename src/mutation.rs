@@ -0,0 +1,356 @@
+//! How a path is changed when it mutates. Mutations only see the part of the tour after any
+//! prefix, so the prefix stays in place.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::distributions::{Distribution, Uniform};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+
+use crate::population::Gene;
+
+/// Makes a small random change to a tour.
+pub trait Mutation: Send + Sync {
+    /// The change to make to `len` cities of a tour that may move, or `None` if there are none.
+    fn draw(&self, len: usize, rng: &mut dyn RngCore) -> Option<Move>;
+}
+
+impl dyn Mutation {
+    /// Changes `free`, the cities of a tour that may move, leaving them a permutation of what
+    /// they were.
+    pub fn mutate<G: Gene>(&self, free: &mut [G], rng: &mut dyn RngCore) {
+        if let Some(change) = self.draw(free.len(), rng) {
+            change.apply(free, rng);
+        }
+    }
+}
+
+/// A change to the positions of a tour's cities, whatever the type of index its order stores
+/// them as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+    /// The cities at the two positions trade places.
+    Swap(usize, usize),
+    /// The cities from the first position to the second are reversed.
+    Reverse(usize, usize),
+    /// The cities from the first position to the second are shuffled, with the generator the
+    /// move is applied with.
+    Shuffle(usize, usize),
+    /// The city at the first position is taken out and put back at the second, shifting the
+    /// cities in between along by one.
+    Shift(usize, usize),
+}
+
+impl Move {
+    pub fn apply<G, R: Rng + ?Sized>(self, free: &mut [G], rng: &mut R) {
+        match self {
+            Move::Swap(a, b) => free.swap(a, b),
+            Move::Reverse(a, b) => free[a..=b].reverse(),
+            Move::Shuffle(a, b) => free[a..=b].shuffle(rng),
+            Move::Shift(from, to) if from < to => free[from..=to].rotate_left(1),
+            Move::Shift(from, to) => free[to..=from].rotate_right(1),
+        }
+    }
+
+    /// The legs of a tour of `len` cities that the move, applied to its cities from `offset` on,
+    /// can change, each by the position it leaves from, the last of a `closed` tour going back
+    /// to the first. A swap changes the four legs around the cities it swaps and a reversal the
+    /// two at its ends, unless the tour is `asymmetric`, when the legs it reverses cost
+    /// something else too. The others change every leg of the stretch they move.
+    pub fn legs(self, offset: usize, len: usize, closed: bool, asymmetric: bool) -> Vec<usize> {
+        let (Move::Swap(a, b) | Move::Reverse(a, b) | Move::Shuffle(a, b) | Move::Shift(a, b)) = self;
+        let (first, last) = (offset + a.min(b), offset + a.max(b));
+        if first == last {
+            return Vec::new();
+        }
+
+        let into = |position: usize| match position.checked_sub(1) {
+            Some(previous) => Some(previous),
+            None => Some(len - 1).filter(|_| closed),
+        };
+        let out_of = |position: usize| Some(position).filter(|&position| position + 1 < len || closed);
+        let mut legs: Vec<usize> = match self {
+            Move::Swap(..) => vec![into(first), out_of(first), into(last), out_of(last)].into_iter().flatten().collect(),
+            Move::Reverse(..) if !asymmetric => into(first).into_iter().chain(out_of(last)).collect(),
+            _ => into(first).into_iter().chain((first..=last).filter_map(out_of)).collect(),
+        };
+        legs.sort_unstable();
+        legs.dedup();
+        legs
+    }
+}
+
+/// Two random positions among `len`, possibly the same one.
+fn positions<R: Rng + ?Sized>(len: usize, rng: &mut R) -> (usize, usize) {
+    let position = Uniform::new(0, len);
+    (position.sample(rng), position.sample(rng))
+}
+
+/// Swaps two random cities of `free`.
+pub(crate) fn swap<T, R: Rng + ?Sized>(free: &mut [T], rng: &mut R) {
+    if free.is_empty() {
+        return;
+    }
+
+    let (a, b) = positions(free.len(), rng);
+    free.swap(a, b);
+}
+
+/// Swaps two random cities.
+pub struct Swap;
+
+impl Mutation for Swap {
+    fn draw(&self, len: usize, rng: &mut dyn RngCore) -> Option<Move> {
+        if len == 0 {
+            return None;
+        }
+
+        let (a, b) = positions(len, rng);
+        Some(Move::Swap(a, b))
+    }
+}
+
+/// Reverses the cities between two random positions, which replaces the two legs at its ends
+/// as a 2-opt move does, untangling them if they crossed. A swap replaces four.
+pub struct Inversion;
+
+impl Mutation for Inversion {
+    fn draw(&self, len: usize, rng: &mut dyn RngCore) -> Option<Move> {
+        if len == 0 {
+            return None;
+        }
+
+        let (a, b) = positions(len, rng);
+        Some(Move::Reverse(a.min(b), a.max(b)))
+    }
+}
+
+/// Shuffles the cities between two random positions.
+pub struct Scramble;
+
+impl Mutation for Scramble {
+    fn draw(&self, len: usize, rng: &mut dyn RngCore) -> Option<Move> {
+        if len == 0 {
+            return None;
+        }
+
+        let (a, b) = positions(len, rng);
+        Some(Move::Shuffle(a.min(b), a.max(b)))
+    }
+}
+
+/// Takes a random city out and puts it back at another random position, shifting the cities
+/// in between along by one.
+pub struct Insertion;
+
+impl Mutation for Insertion {
+    fn draw(&self, len: usize, rng: &mut dyn RngCore) -> Option<Move> {
+        if len == 0 {
+            return None;
+        }
+
+        let (from, to) = positions(len, rng);
+        Some(Move::Shift(from, to))
+    }
+}
+
+/// Share of distinct tours below which an adaptive mutation rate rises, unless set otherwise.
+pub const DEFAULT_ADAPTIVE_THRESHOLD: f64 = 0.5;
+
+// How far an adaptive rate moves in a generation, as fractions of its range. It rises faster
+// than it falls so that a collapsing population is shaken up before it has fully converged
+const RAISE_STEP: f64 = 0.1;
+const LOWER_STEP: f64 = 0.05;
+
+/// A mutation rate that follows the population's diversity, from `min` to `max`: raised every
+/// generation the share of distinct tours is below `threshold` and lowered every one it isn't.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adaptive {
+    pub min: f64,
+    pub max: f64,
+    pub threshold: f64,
+}
+
+impl Adaptive {
+    /// The rate to mutate at next, after mutating at `rate` left a population with
+    /// `unique_ratio` distinct tours.
+    pub fn adjust(&self, rate: f64, unique_ratio: f64) -> f64 {
+        let range = self.max - self.min;
+        let rate = if unique_ratio < self.threshold { rate + range * RAISE_STEP } else { rate - range * LOWER_STEP };
+        self.clamp(rate)
+    }
+
+    /// `rate` moved within the bounds.
+    pub fn clamp(&self, rate: f64) -> f64 {
+        rate.max(self.min).min(self.max)
+    }
+}
+
+impl FromStr for Adaptive {
+    type Err = String;
+
+    /// Parses `<min>:<max>`, e.g. `0.01:0.3`, with the default threshold.
+    fn from_str(s: &str) -> Result<Adaptive, String> {
+        let (min, max) = s.split_once(':')
+            .and_then(|(min, max)| min.trim().parse::<f64>().ok().zip(max.trim().parse::<f64>().ok()))
+            .ok_or_else(|| format!("{:?} isn't <min>:<max> mutation rates, e.g. 0.01:0.3", s))?;
+        if !(0.0..=1.0).contains(&min) || !(0.0..=1.0).contains(&max) {
+            return Err(format!("mutation rates must be between 0 and 1, not {}", s));
+        }
+        if min > max {
+            return Err(format!("the lowest mutation rate is above the highest in {}", s));
+        }
+
+        Ok(Adaptive { min, max, threshold: DEFAULT_ADAPTIVE_THRESHOLD })
+    }
+}
+
+impl fmt::Display for Adaptive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.min, self.max)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Swap,
+    Inversion,
+    Scramble,
+    Insertion,
+}
+
+impl Kind {
+    pub fn build(self) -> Box<dyn Mutation> {
+        match self {
+            Kind::Swap => Box::new(Swap),
+            Kind::Inversion => Box::new(Inversion),
+            Kind::Scramble => Box::new(Scramble),
+            Kind::Insertion => Box::new(Insertion),
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        match s {
+            "swap" => Ok(Kind::Swap),
+            "inversion" => Ok(Kind::Inversion),
+            "scramble" => Ok(Kind::Scramble),
+            "insertion" => Ok(Kind::Insertion),
+            _ => Err(format!("unknown mutation {:?}, expected swap, inversion, scramble or insertion", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Swap => "swap",
+            Kind::Inversion => "inversion",
+            Kind::Scramble => "scramble",
+            Kind::Insertion => "insertion",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::testing::{genes, is_permutation, permutation};
+    use crate::Path;
+
+    #[test]
+    fn adaptive_mutation_rates_are_parsed_and_kept_in_bounds() {
+        let adaptive: Adaptive = "0.1:0.5".parse().unwrap();
+        assert_eq!(adaptive.threshold, DEFAULT_ADAPTIVE_THRESHOLD);
+        assert!((adaptive.adjust(0.2, 0.1) - 0.24).abs() < 1e-12);
+        assert!((adaptive.adjust(0.2, 0.9) - 0.18).abs() < 1e-12);
+        assert_eq!(adaptive.adjust(0.5, 0.0), 0.5);
+        assert_eq!(adaptive.adjust(0.1, 1.0), 0.1);
+        assert!("0.3:0.1".parse::<Adaptive>().is_err());
+        assert!("0.1:2".parse::<Adaptive>().is_err());
+        assert!("0.1".parse::<Adaptive>().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn mutation_produces_a_permutation(order in permutation(50)) {
+            let len = order.len();
+            let mut path = Path { fitness: 0.0, order };
+            path.mutate(0, &mut rand::thread_rng());
+            prop_assert!(is_permutation(&path.order, len), "{:?}", path.order);
+        }
+
+        #[test]
+        fn inversion_reverses_a_stretch_of_the_tour(order in permutation(50)) {
+            let order = genes(&order);
+            let mut inverted = order.clone();
+            Kind::Inversion.build().mutate(&mut inverted, &mut rand::thread_rng());
+            let start = order.iter().zip(&inverted).take_while(|(a, b)| a == b).count();
+            let end = order.len() - order.iter().rev().zip(inverted.iter().rev()).take_while(|(a, b)| a == b).count();
+            if start < end {
+                let reversed: Vec<u16> = order[start..end].iter().rev().copied().collect();
+                prop_assert_eq!(&inverted[start..end], &reversed[..]);
+            }
+        }
+
+        #[test]
+        fn mutations_produce_a_permutation(order in permutation(50)) {
+            for kind in [Kind::Swap, Kind::Inversion, Kind::Scramble, Kind::Insertion] {
+                let mut mutated = genes(&order);
+                kind.build().mutate(&mut mutated, &mut rand::thread_rng());
+                prop_assert!(is_permutation(&mutated, order.len()), "{} gave {:?}", kind, mutated);
+            }
+        }
+
+        #[test]
+        fn insertion_moves_a_single_city(order in permutation(50)) {
+            let order = genes(&order);
+            let mut mutated = order.clone();
+            Kind::Insertion.build().mutate(&mut mutated, &mut rand::thread_rng());
+            let moved = (0..order.len()).any(|i| {
+                let mut removed = order.clone();
+                let city = removed.remove(i);
+                let at = mutated.iter().position(|&c| c == city).expect("the city is still there");
+                removed.insert(at, city);
+                removed == mutated
+            });
+            prop_assert!(moved, "{:?} to {:?}", order, mutated);
+        }
+
+        #[test]
+        fn a_mutation_changes_no_legs_but_the_ones_it_names(
+            costs in prop::collection::vec(0.0..100.0f64, 15 * 15),
+            symmetric in any::<bool>(),
+            closed in any::<bool>(),
+            fixed in 0..4usize,
+            kind in prop_oneof![
+                Just(Kind::Swap), Just(Kind::Inversion),
+                Just(Kind::Scramble), Just(Kind::Insertion),
+            ],
+            seed in any::<u64>(),
+        ) {
+            let cost = |a: usize, b: usize| if symmetric { costs[a.min(b) * 15 + a.max(b)] } else { costs[a * 15 + b] };
+            let length = |order: &[usize], legs: &mut dyn Iterator<Item = usize>| legs.map(|leg| cost(order[leg], order[(leg + 1) % 15])).sum::<f64>();
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let mut order: Vec<usize> = (0..15).collect();
+            order.shuffle(&mut rng);
+            let before = order.clone();
+
+            if let Some(change) = kind.build().draw(15 - fixed, &mut rng) {
+                let legs = change.legs(fixed, 15, closed, !symmetric);
+                change.apply(&mut order[fixed..], &mut rng);
+                let changed = length(&order, &mut legs.iter().copied()) - length(&before, &mut legs.iter().copied());
+                let whole = |order: &[usize]| length(order, &mut (0..if closed { 15 } else { 14 }));
+                prop_assert!((whole(&order) - whole(&before) - changed).abs() < 1e-9);
+            }
+        }
+    }
+}
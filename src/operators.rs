@@ -0,0 +1,166 @@
+//! Adaptive operator selection, for `--adaptive-operators`: rather than one crossover and one
+//! mutation picked up front, every child is bred and every mutation made with one drawn from
+//! all of them, and each is drawn more often the more it has improved tours lately.
+
+use std::fmt;
+
+use rand::Rng;
+
+use crate::crossover::Crossover;
+use crate::mutation::{Kind, Mutation};
+
+// How much of an operator's quality a generation's rewards replace, and the share of the chances
+// kept back to be split evenly, so that an operator that's fallen behind is still tried now and
+// then and can catch up if it's what the run needs later
+const ADAPTATION_RATE: f64 = 0.3;
+const EXPLORATION: f64 = 0.2;
+
+/// Probability matching among a number of operators: each is drawn with a chance in proportion
+/// to its quality, a moving average of the rewards it earned a generation, on top of an even
+/// share of `EXPLORATION`. Until any has earned something they're drawn evenly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matching {
+    quality: Vec<f64>,
+    // What each operator has earned this generation, and how often it was used
+    rewards: Vec<f64>,
+    uses: Vec<usize>,
+}
+
+impl Matching {
+    pub fn new(operators: usize) -> Matching {
+        Matching { quality: vec![0.0; operators], rewards: vec![0.0; operators], uses: vec![0; operators] }
+    }
+
+    /// The chance of drawing each operator.
+    pub fn probabilities(&self) -> Vec<f64> {
+        let count = self.quality.len() as f64;
+        let total: f64 = self.quality.iter().sum();
+        if total <= 0.0 {
+            return vec![1.0 / count; self.quality.len()];
+        }
+        self.quality.iter().map(|quality| EXPLORATION / count + (1.0 - EXPLORATION) * quality / total).collect()
+    }
+
+    /// Draws an operator by its chance.
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        let mut spin = rng.gen::<f64>();
+        let probabilities = self.probabilities();
+        for (operator, probability) in probabilities.iter().enumerate() {
+            if spin < *probability {
+                return operator;
+            }
+            spin -= probability;
+        }
+        probabilities.len() - 1
+    }
+
+    /// Notes that `operator` was used and earned `reward`.
+    pub fn credit(&mut self, operator: usize, reward: f64) {
+        self.rewards[operator] += reward;
+        self.uses[operator] += 1;
+    }
+
+    /// Moves the quality of every operator used this generation towards the mean reward it
+    /// earned, and starts the next generation's.
+    pub fn adapt(&mut self) {
+        for operator in 0..self.quality.len() {
+            if self.uses[operator] > 0 {
+                let reward = self.rewards[operator] / self.uses[operator] as f64;
+                self.quality[operator] += ADAPTATION_RATE * (reward - self.quality[operator]);
+            }
+        }
+        self.rewards.iter_mut().for_each(|reward| *reward = 0.0);
+        self.uses.iter_mut().for_each(|uses| *uses = 0);
+    }
+}
+
+/// How much fitter a child or a mutated tour is than what it came from, relative to it, or
+/// nothing if it isn't.
+fn improvement(before: f64, after: f64) -> f64 {
+    if before > 0.0 && before.is_finite() && after.is_finite() && after > before {
+        after / before - 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Every crossover and mutation, with how often each is drawn.
+pub struct Operators {
+    pub crossovers: Vec<Crossover>,
+    pub crossover: Matching,
+    pub mutation_kinds: Vec<Kind>,
+    pub mutations: Vec<Box<dyn Mutation>>,
+    pub mutation: Matching,
+}
+
+impl Operators {
+    /// Every operator there is, each as likely as the others to begin with.
+    pub fn all() -> Operators {
+        let crossovers = vec![Crossover::OnePoint, Crossover::Ordered];
+        let mutation_kinds = vec![Kind::Swap, Kind::Inversion, Kind::Scramble, Kind::Insertion];
+        Operators {
+            crossover: Matching::new(crossovers.len()),
+            mutation: Matching::new(mutation_kinds.len()),
+            mutations: mutation_kinds.iter().map(|kind| kind.build()).collect(),
+            crossovers,
+            mutation_kinds,
+        }
+    }
+
+    /// Credits the crossover drawn as `operator` with how much fitter the `child` it bred is
+    /// than the fitter of its parents, of fitness `parent`.
+    pub fn credit_crossover(&mut self, operator: Option<usize>, parent: f64, child: f64) {
+        if let Some(operator) = operator {
+            self.crossover.credit(operator, improvement(parent, child));
+        }
+    }
+
+    /// Credits the mutation drawn as `operator` with how much fitter it left a tour, from
+    /// `before` to `after`.
+    pub fn credit_mutation(&mut self, operator: usize, before: f64, after: f64) {
+        self.mutation.credit(operator, improvement(before, after));
+    }
+
+    /// Ends a generation, adapting the chances of both kinds of operator.
+    pub fn adapt(&mut self) {
+        self.crossover.adapt();
+        self.mutation.adapt();
+    }
+}
+
+impl fmt::Display for Operators {
+    /// The chance of each operator, e.g. `one-point 0.62, ox 0.38; swap 0.10, ...`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |names: Vec<String>, matching: &Matching| {
+            names.iter().zip(matching.probabilities()).map(|(name, p)| format!("{} {:.2}", name, p)).collect::<Vec<String>>().join(", ")
+        };
+        write!(
+            f,
+            "{}; {}",
+            list(self.crossovers.iter().map(Crossover::to_string).collect(), &self.crossover),
+            list(self.mutation_kinds.iter().map(Kind::to_string).collect(), &self.mutation),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{RngAlgorithm, SeededRng};
+
+    #[test]
+    fn operators_are_drawn_more_the_more_they_improve() {
+        let mut matching = Matching::new(2);
+        assert_eq!(matching.probabilities(), vec![0.5, 0.5]);
+        for _ in 0..10 {
+            matching.credit(0, 0.1);
+            matching.credit(1, 0.0);
+            matching.adapt();
+        }
+        // The one that never improved anything keeps its even share of what's kept back
+        let probabilities = matching.probabilities();
+        assert!((probabilities[0] - 0.9).abs() < 1e-12 && (probabilities[1] - 0.1).abs() < 1e-12);
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        assert!((0..1000).filter(|_| matching.pick(&mut rng) == 0).count() > 850);
+    }
+}
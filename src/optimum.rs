@@ -0,0 +1,91 @@
+//! Known optimal tour lengths of standard TSPLIB instances.
+//!
+//! Instances are recognised by name (the file name without its extension), city count and metric.
+//! TSPLIB rounds every leg to the nearest integer before adding them up, while tours here are
+//! measured exactly, so a gap of a fraction of a percent either way is within rounding.
+
+use crate::geo::Metric;
+
+// Name, city count, how legs are measured and the optimal length
+const KNOWN: &[(&str, usize, Metric, f64)] = &[
+    ("eil51", 51, Metric::Euclidean, 426.0),
+    ("berlin52", 52, Metric::Euclidean, 7542.0),
+    ("st70", 70, Metric::Euclidean, 675.0),
+    ("eil76", 76, Metric::Euclidean, 538.0),
+    ("pr76", 76, Metric::Euclidean, 108159.0),
+    ("rat99", 99, Metric::Euclidean, 1211.0),
+    ("kroA100", 100, Metric::Euclidean, 21282.0),
+    ("kroB100", 100, Metric::Euclidean, 22141.0),
+    ("kroC100", 100, Metric::Euclidean, 20749.0),
+    ("kroD100", 100, Metric::Euclidean, 21294.0),
+    ("kroE100", 100, Metric::Euclidean, 22068.0),
+    ("rd100", 100, Metric::Euclidean, 7910.0),
+    ("eil101", 101, Metric::Euclidean, 629.0),
+    ("lin105", 105, Metric::Euclidean, 14379.0),
+    ("pr107", 107, Metric::Euclidean, 44303.0),
+    ("pr124", 124, Metric::Euclidean, 59030.0),
+    ("bier127", 127, Metric::Euclidean, 118282.0),
+    ("ch130", 130, Metric::Euclidean, 6110.0),
+    ("pr136", 136, Metric::Euclidean, 96772.0),
+    ("pr144", 144, Metric::Euclidean, 58537.0),
+    ("ch150", 150, Metric::Euclidean, 6528.0),
+    ("kroA150", 150, Metric::Euclidean, 26524.0),
+    ("kroB150", 150, Metric::Euclidean, 26130.0),
+    ("pr152", 152, Metric::Euclidean, 73682.0),
+    ("u159", 159, Metric::Euclidean, 42080.0),
+    ("rat195", 195, Metric::Euclidean, 2323.0),
+    ("d198", 198, Metric::Euclidean, 15780.0),
+    ("kroA200", 200, Metric::Euclidean, 29368.0),
+    ("kroB200", 200, Metric::Euclidean, 29437.0),
+    ("ts225", 225, Metric::Euclidean, 126643.0),
+    ("tsp225", 225, Metric::Euclidean, 3916.0),
+    ("pr226", 226, Metric::Euclidean, 80369.0),
+    ("gil262", 262, Metric::Euclidean, 2378.0),
+    ("pr264", 264, Metric::Euclidean, 49135.0),
+    ("a280", 280, Metric::Euclidean, 2579.0),
+    ("pr299", 299, Metric::Euclidean, 48191.0),
+    ("lin318", 318, Metric::Euclidean, 42029.0),
+    ("rd400", 400, Metric::Euclidean, 15281.0),
+    ("fl417", 417, Metric::Euclidean, 11861.0),
+    ("pr439", 439, Metric::Euclidean, 107217.0),
+    ("pcb442", 442, Metric::Euclidean, 50778.0),
+    ("d493", 493, Metric::Euclidean, 35002.0),
+    ("rat575", 575, Metric::Euclidean, 6773.0),
+    ("d657", 657, Metric::Euclidean, 48912.0),
+    ("u724", 724, Metric::Euclidean, 41910.0),
+    ("rat783", 783, Metric::Euclidean, 8806.0),
+    ("pr1002", 1002, Metric::Euclidean, 259045.0),
+    ("ulysses16", 16, Metric::Geo, 6859.0),
+    ("ulysses22", 22, Metric::Geo, 7013.0),
+    ("att48", 48, Metric::Att, 10628.0),
+    ("gr96", 96, Metric::Geo, 55209.0),
+    ("gr137", 137, Metric::Geo, 69853.0),
+    ("gr202", 202, Metric::Geo, 40160.0),
+    ("gr229", 229, Metric::Geo, 134602.0),
+    ("gr431", 431, Metric::Geo, 171414.0),
+    ("att532", 532, Metric::Att, 27686.0),
+    ("gr666", 666, Metric::Geo, 294358.0),
+];
+
+/// The optimal tour length of the TSPLIB instance called `name`, if it's a known one with
+/// `city_count` cities measured with `metric`. Names are matched ignoring case.
+pub fn known(name: &str, city_count: usize, metric: Metric) -> Option<f64> {
+    KNOWN.iter()
+        .find(|(known, cities, measured, _)| known.eq_ignore_ascii_case(name) && *cities == city_count && *measured == metric)
+        .map(|&(_, _, _, optimum)| optimum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_instances_need_the_right_name_and_size() {
+        assert_eq!(known("berlin52", 52, Metric::Euclidean), Some(7542.0));
+        assert_eq!(known("KROA200", 200, Metric::Euclidean), Some(29368.0));
+        assert_eq!(known("berlin52", 51, Metric::Euclidean), None);
+        assert_eq!(known("cities", 52, Metric::Euclidean), None);
+        assert_eq!(known("berlin52", 52, Metric::Haversine), None);
+        assert_eq!(known("att48", 48, Metric::Att), Some(10628.0));
+    }
+}
@@ -0,0 +1,505 @@
+//! Where the results of a run are written. Every `--out kind:path` becomes a sink: all of them
+//! are created before the run starts, see every generation and write out the best tour at the
+//! end, so one run can leave a solution, a drawing and statistics behind at once.
+//!
+//! | kind      | contents                                                            |
+//! |-----------|---------------------------------------------------------------------|
+//! | `json`    | the best tour's order, length, legs and the run's summary           |
+//! | `svg`     | a drawing of the cities and the best tour                           |
+//! | `stats`   | a csv of the best length after every generation                     |
+//! | `history` | a csv of the best, mean and worst length and their spread, likewise |
+//! | `route`   | the best tour leg by leg, as csv or, for a `.json` path, as JSON    |
+//! | `tour`    | the best tour's cities in order with their coordinates, as csv      |
+//! | `geojson` | the best tour as a GeoJSON LineString, for QGIS, kepler.gl and such |
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path as FilePath, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+use crate::events::Notice;
+use crate::geo::{DistanceMatrix, Metric};
+use crate::input::City;
+use crate::summary::Summary;
+use crate::tour::TourType;
+use crate::{route, Path, Report};
+
+/// How a run prints its result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The solution and summary, for people.
+    Text,
+    /// A single JSON object, for other programs.
+    Json,
+    /// The best tour as a GeoJSON LineString, for mapping tools.
+    Geojson,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "geojson" => Ok(Format::Geojson),
+            _ => Err(format!("unknown output format {:?}, expected text, json or geojson", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Geojson => "geojson",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Json,
+    Svg,
+    Stats,
+    History,
+    Route,
+    Tour,
+    Geojson,
+}
+
+/// A sink to create: what to write and where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub kind: Kind,
+    pub path: PathBuf,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        let (kind, path) = s.split_once(':')
+            .filter(|(_, path)| !path.is_empty())
+            .ok_or_else(|| format!("{:?} isn't kind:path, e.g. json:solution.json", s))?;
+        let kind = match kind {
+            "json" => Kind::Json,
+            "svg" => Kind::Svg,
+            "stats" => Kind::Stats,
+            "history" => Kind::History,
+            "route" => Kind::Route,
+            "tour" => Kind::Tour,
+            "geojson" => Kind::Geojson,
+            _ => return Err(format!("unknown output {:?}, expected json, svg, stats, history, route, tour or geojson", kind)),
+        };
+
+        Ok(Target { kind, path: PathBuf::from(path) })
+    }
+}
+
+/// What a run leaves behind, for the sinks to write out.
+pub struct Outcome<'a> {
+    pub fittest: &'a Path,
+    /// The cities as solved, possibly projected.
+    pub cities: &'a [City],
+    /// The cities as given.
+    pub given: &'a [City],
+    pub metric: Metric,
+    pub tour_type: TourType,
+    /// The distances between the cities, when they were given rather than measured.
+    pub distances: Option<&'a DistanceMatrix>,
+    pub summary: Option<&'a Summary>,
+    /// The fittest distinct tours of the run, fittest first, if it kept them.
+    pub hall_of_fame: &'a [Path],
+}
+
+impl Outcome<'_> {
+    fn legs(&self) -> Vec<route::Leg> {
+        match self.distances {
+            Some(distances) => route::legs_measured(&self.fittest.order, self.given, self.tour_type, |a, b| distances.get(a, b)),
+            None => route::legs(&self.fittest.order, self.cities, self.given, self.metric, self.tour_type),
+        }
+    }
+
+    /// The result as `--output-format json` prints it, for a run seeded with `seed`.
+    pub fn result(&self, seed: u64) -> Value {
+        let mut result = json!({
+            "tour": self.fittest.order,
+            "tour_length": 1.0 / self.fittest.fitness,
+            "fitness": self.fittest.fitness,
+            "generations": self.summary.map(|summary| summary.generations),
+            "elapsed_ms": self.summary.map(|summary| summary.wall_time.as_millis() as u64),
+            "seed": seed,
+        });
+        if !self.hall_of_fame.is_empty() {
+            result["hall_of_fame"] = self.hall_of_fame_json();
+        }
+        result
+    }
+
+    /// The hall of fame's tours, as objects with the order and length of each.
+    fn hall_of_fame_json(&self) -> Value {
+        self.hall_of_fame.iter().map(|path| json!({ "tour": path.order, "tour_length": path.length() })).collect()
+    }
+
+    /// The best tour as a GeoJSON feature: a LineString through the cities as given, which for
+    /// geographic cities are longitudes and latitudes as GeoJSON expects, back to the first if
+    /// the tour is closed, with the order and length as properties.
+    pub fn geojson(&self) -> Value {
+        let order = &self.fittest.order;
+        let back = order.first().filter(|_| self.tour_type == TourType::Closed && order.len() > 1);
+        let coordinates: Vec<[f64; 2]> = order.iter().chain(back).map(|&i| [self.given[i].x, self.given[i].y]).collect();
+        json!({
+            "type": "Feature",
+            "geometry": { "type": "LineString", "coordinates": coordinates },
+            "properties": { "order": self.fittest.order, "length": 1.0 / self.fittest.fitness },
+        })
+    }
+}
+
+pub trait Sink {
+    /// Called after every generation.
+    fn generation(&mut self, _report: &Report) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the run is over.
+    fn finish(&mut self, outcome: &Outcome) -> io::Result<()>;
+}
+
+/// All the sinks of a run.
+#[derive(Default)]
+pub struct Outputs {
+    sinks: Vec<(PathBuf, Box<dyn Sink>)>,
+}
+
+impl Outputs {
+    /// Creates the files of every target, failing on the first that can't be.
+    pub fn open(targets: &[Target]) -> Result<Outputs, (PathBuf, io::Error)> {
+        let mut sinks = Vec::new();
+        for target in targets {
+            let file = File::create(&target.path).map_err(|e| (target.path.clone(), e))?;
+            let sink: Box<dyn Sink> = match target.kind {
+                Kind::Json => Box::new(JsonSink { file }),
+                Kind::Svg => Box::new(SvgSink { file }),
+                Kind::Stats => Box::new(StatsSink::new(file).map_err(|e| (target.path.clone(), e))?),
+                Kind::History => Box::new(HistorySink::new(file).map_err(|e| (target.path.clone(), e))?),
+                Kind::Route => Box::new(RouteSink { file, json: is_json(&target.path) }),
+                Kind::Tour => Box::new(TourSink { file }),
+                Kind::Geojson => Box::new(GeojsonSink { file }),
+            };
+            sinks.push((target.path.clone(), sink));
+        }
+
+        Ok(Outputs { sinks })
+    }
+
+    /// Passes `report` to every sink. A sink that fails to write is dropped rather than ending
+    /// the run, with the warning about it returned.
+    pub fn generation(&mut self, report: &Report) -> Vec<Notice> {
+        let mut warnings = Vec::new();
+        self.sinks.retain_mut(|(path, sink)| match sink.generation(report) {
+            Ok(()) => true,
+            Err(e) => {
+                warnings.push(Notice::Warning(format!("could not write to {}, no more will be: {}", path.display(), e)));
+                false
+            }
+        });
+        warnings
+    }
+
+    /// Writes `outcome` to every sink, returning the ones that failed.
+    pub fn finish(mut self, outcome: &Outcome) -> Vec<(PathBuf, io::Error)> {
+        self.sinks.iter_mut()
+            .filter_map(|(path, sink)| sink.finish(outcome).err().map(|e| (path.clone(), e)))
+            .collect()
+    }
+}
+
+fn is_json(path: &FilePath) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+struct JsonSink {
+    file: File,
+}
+
+impl Sink for JsonSink {
+    fn finish(&mut self, outcome: &Outcome) -> io::Result<()> {
+        let mut solution = json!({
+            "order": outcome.fittest.order,
+            "length": 1.0 / outcome.fittest.fitness,
+            "legs": outcome.legs(),
+            "summary": outcome.summary.map(Summary::to_json),
+        });
+        if !outcome.hall_of_fame.is_empty() {
+            solution["hall_of_fame"] = outcome.hall_of_fame_json();
+        }
+        serde_json::to_writer_pretty(&mut self.file, &solution)?;
+        self.file.write_all(b"\n")
+    }
+}
+
+struct RouteSink {
+    file: File,
+    json: bool,
+}
+
+impl Sink for RouteSink {
+    fn finish(&mut self, outcome: &Outcome) -> io::Result<()> {
+        route::write(&mut self.file, self.json, &outcome.legs())
+    }
+}
+
+struct TourSink {
+    file: File,
+}
+
+impl Sink for TourSink {
+    fn finish(&mut self, outcome: &Outcome) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(&mut self.file);
+        writer.write_record(["position", "city", "x", "y"])?;
+        for (position, &i) in outcome.fittest.order.iter().enumerate() {
+            let city = &outcome.given[i];
+            writer.write_record(&[position.to_string(), i.to_string(), city.x.to_string(), city.y.to_string()])?;
+        }
+        writer.flush()
+    }
+}
+
+struct GeojsonSink {
+    file: File,
+}
+
+impl Sink for GeojsonSink {
+    fn finish(&mut self, outcome: &Outcome) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut self.file, &outcome.geojson())?;
+        self.file.write_all(b"\n")
+    }
+}
+
+struct StatsSink {
+    writer: csv::Writer<File>,
+    started: Instant,
+}
+
+impl StatsSink {
+    fn new(file: File) -> io::Result<StatsSink> {
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record(["generation", "seconds", "best_length"])?;
+        Ok(StatsSink { writer, started: Instant::now() })
+    }
+}
+
+impl Sink for StatsSink {
+    fn generation(&mut self, report: &Report) -> io::Result<()> {
+        self.writer.write_record(&[
+            report.generation.to_string(),
+            self.started.elapsed().as_secs_f64().to_string(),
+            (1.0 / report.fittest.fitness).to_string(),
+        ])?;
+        Ok(())
+    }
+
+    fn finish(&mut self, _outcome: &Outcome) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+struct HistorySink {
+    writer: csv::Writer<File>,
+    started: Instant,
+}
+
+impl HistorySink {
+    fn new(file: File) -> io::Result<HistorySink> {
+        let mut writer = csv::Writer::from_writer(file);
+        writer.write_record(["generation", "best", "mean", "worst", "std_dev", "elapsed"])?;
+        Ok(HistorySink { writer, started: Instant::now() })
+    }
+}
+
+impl Sink for HistorySink {
+    fn generation(&mut self, report: &Report) -> io::Result<()> {
+        let stats = report.population_stats();
+        self.writer.write_record(&[
+            report.generation.to_string(),
+            stats.best.to_string(),
+            stats.mean.to_string(),
+            stats.worst.to_string(),
+            stats.std_dev.to_string(),
+            self.started.elapsed().as_secs_f64().to_string(),
+        ])?;
+        Ok(())
+    }
+
+    fn finish(&mut self, _outcome: &Outcome) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// How wide drawings are, in pixels. Their height follows the cities' aspect ratio.
+const SVG_WIDTH: f64 = 800.0;
+
+struct SvgSink {
+    file: File,
+}
+
+impl Sink for SvgSink {
+    fn finish(&mut self, outcome: &Outcome) -> io::Result<()> {
+        // Drawn as solved, so projected cities come out in proportion; y points up on maps
+        let cities = outcome.cities;
+        let bound = |axis: fn(&City) -> f64, pick: fn(f64, f64) -> f64| cities.iter().map(axis).reduce(pick).unwrap_or(0.0);
+        let (min_x, max_x) = (bound(|c| c.x, f64::min), bound(|c| c.x, f64::max));
+        let (min_y, max_y) = (bound(|c| -c.y, f64::min), bound(|c| -c.y, f64::max));
+        let extent = (max_x - min_x).max(max_y - min_y).max(f64::MIN_POSITIVE);
+        let margin = extent * 0.05;
+        let (width, height) = (max_x - min_x + 2.0 * margin, max_y - min_y + 2.0 * margin);
+
+        let mut svg = BufWriter::new(&mut self.file);
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{:.0}">"#,
+            min_x - margin, min_y - margin, width, height, SVG_WIDTH, SVG_WIDTH * height / width
+        )?;
+        let points: Vec<String> = outcome.fittest.order.iter()
+            .map(|&i| format!("{},{}", cities[i].x, -cities[i].y))
+            .collect();
+        let shape = match outcome.tour_type {
+            TourType::Closed => "polygon",
+            TourType::Open => "polyline",
+        };
+        writeln!(
+            svg,
+            r##"<{} points="{}" fill="none" stroke="#1f77b4" stroke-width="{}" stroke-linejoin="round"/>"##,
+            shape, points.join(" "), extent / 400.0
+        )?;
+        for (position, &i) in outcome.fittest.order.iter().enumerate() {
+            let fill = if position == 0 { "#d62728" } else { "#333" };
+            writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}"><title>{}</title></circle>"#,
+                cities[i].x, -cities[i].y, extent / 250.0, fill, i
+            )?;
+        }
+        writeln!(svg, "</svg>")?;
+        svg.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::testing::{simulation, twelve_cities};
+
+    #[test]
+    fn outputs_are_a_kind_and_a_path() {
+        assert_eq!(
+            "svg:maps/route:1.svg".parse(),
+            Ok(Target { kind: Kind::Svg, path: PathBuf::from("maps/route:1.svg") })
+        );
+        assert!("pdf:route.pdf".parse::<Target>().is_err());
+        assert!("json".parse::<Target>().is_err());
+        assert!("json:".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn the_json_result_has_the_tour_and_how_it_was_found() {
+        let cities = twelve_cities();
+        let mut sim = simulation(cities.clone(), 40);
+        let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let outcome = Outcome {
+            fittest: &fittest,
+            cities: &cities,
+            given: &cities,
+            metric: Metric::Euclidean,
+            tour_type: TourType::Closed,
+            distances: None,
+            summary: sim.summary(),
+            hall_of_fame: &[],
+        };
+        let result = outcome.result(42);
+        assert_eq!(result["tour"], json!(fittest.order()));
+        assert_eq!(result["tour_length"], json!(fittest.length()));
+        assert_eq!(result["fitness"], json!(fittest.fitness));
+        assert_eq!(result["generations"], json!(40));
+        assert!(result["elapsed_ms"].is_u64());
+        assert_eq!(result["seed"], json!(42));
+    }
+
+    #[test]
+    fn the_tour_is_written_as_csv_and_geojson() {
+        let cities = twelve_cities();
+        let mut sim = simulation(cities.clone(), 10);
+        let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("wasi-genetic-tour-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let targets: Vec<Target> = ["tour:tour.csv", "geojson:tour.geojson"].iter()
+            .map(|target| {
+                let Target { kind, path } = target.parse().unwrap();
+                Target { kind, path: dir.join(path) }
+            })
+            .collect();
+        let outputs = Outputs::open(&targets).unwrap();
+        let outcome = Outcome {
+            fittest: &fittest,
+            cities: &cities,
+            given: &cities,
+            metric: Metric::Euclidean,
+            tour_type: TourType::Closed,
+            distances: None,
+            summary: sim.summary(),
+            hall_of_fame: &[],
+        };
+        assert!(outputs.finish(&outcome).is_empty());
+
+        let csv = std::fs::read_to_string(dir.join("tour.csv")).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("position,city,x,y"));
+        let first = fittest.order()[0];
+        assert_eq!(lines.next(), Some(format!("0,{},{},{}", first, cities[first].x, cities[first].y).as_str()));
+        assert_eq!(lines.count(), 11);
+
+        let geojson: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("tour.geojson")).unwrap()).unwrap();
+        assert_eq!(geojson["geometry"]["type"], "LineString");
+        assert_eq!(geojson["geometry"]["coordinates"][0], json!([cities[first].x, cities[first].y]));
+        assert_eq!(geojson["geometry"]["coordinates"][12], json!([cities[first].x, cities[first].y]));
+        assert_eq!(geojson["properties"]["order"], json!(fittest.order()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_history_has_a_row_for_every_generation() {
+        let mut sim = simulation(twelve_cities(), 40);
+
+        let path = std::env::temp_dir().join(format!("wasi-genetic-history-{}.csv", std::process::id()));
+        let mut outputs = Outputs::open(&[format!("history:{}", path.display()).parse().unwrap()]).unwrap();
+        let mut bests = Vec::new();
+        sim.solve(|report| {
+            assert_eq!(outputs.generation(report), []);
+            bests.push((report.generation, 1.0 / report.fittest.fitness));
+            ControlFlow::Continue(())
+        }).unwrap();
+        drop(outputs);
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(reader.headers().unwrap(), vec!["generation", "best", "mean", "worst", "std_dev", "elapsed"]);
+        let rows: Vec<Vec<f64>> = reader.records().map(|row| row.unwrap().iter().map(|v| v.parse().unwrap()).collect()).collect();
+        assert_eq!(rows.len(), 40);
+        for (row, &(generation, best)) in rows.iter().zip(&bests) {
+            let [number, low, mean, high, spread, _] = row[..] else { panic!("{:?}", row) };
+            assert_eq!(number as usize, generation);
+            assert!(best - 1e-9 <= low && low <= mean && mean <= high && spread >= 0.0 && spread <= high - low);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
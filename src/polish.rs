@@ -0,0 +1,224 @@
+//! Local search, 2-opt to polish the final tour and, in a memetic run, 2-opt, Or-opt or 3-opt
+//! on tours of every generation. Each search weighs up moves until none of them makes the tour
+//! shorter or it runs out of budget, and returns how many it weighed up.
+
+use crate::required::RequiredEdges;
+use crate::tour::TourType;
+
+// Smallest shortening that counts, so rounding errors can't keep the search going forever
+const EPSILON: f64 = 1e-9;
+
+// Most cities an Or-opt move relocates at once
+const OR_OPT_SEGMENT: usize = 3;
+
+/// Reverses stretches of `order` after its first `fixed` cities for as long as that makes it
+/// shorter without dropping a `required` edge, leaving it at a 2-opt local optimum. An open
+/// `tour` doesn't return to its start, so a stretch at either end can be reversed too, changing
+/// only the one leg that joins it to the rest; a closed one wraps round, the leg back to the
+/// start joining its ends. Reversing a stretch doesn't change its own length as long as the
+/// `distance` between cities is symmetric; if it's `asymmetric`, the stretch's legs are measured
+/// the other way round too, which makes each move take as long as the stretch. The search
+/// gives up short of the optimum once it has weighed up `budget` moves.
+pub fn two_opt(
+    order: &mut [usize],
+    fixed: usize,
+    required: &RequiredEdges,
+    asymmetric: bool,
+    tour: TourType,
+    budget: usize,
+    distance: impl Fn(usize, usize) -> f64,
+) -> usize {
+    let n = order.len();
+    let closed = tour == TourType::Closed;
+    let leg = |order: &[usize], a: usize, b: usize| distance(order[a], order[b]);
+
+    let mut moves = 0;
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for start in fixed..n {
+            for end in start + 1..n {
+                if moves == budget {
+                    return moves;
+                }
+                moves += 1;
+
+                // The cities either side of order[start..=end], if it doesn't take the whole tour
+                let before = if start > 0 { Some(start - 1) } else if closed && end + 1 < n { Some(n - 1) } else { None };
+                let after = if end + 1 < n { Some(end + 1) } else if closed && start > 0 { Some(0) } else { None };
+
+                // Reversing the stretch swaps the legs joining it to the rest
+                let mut delta = 0.0;
+                if let Some(before) = before {
+                    delta += leg(order, before, end) - leg(order, before, start);
+                }
+                if let Some(after) = after {
+                    delta += leg(order, start, after) - leg(order, end, after);
+                }
+                if asymmetric {
+                    delta += (start..end).map(|i| leg(order, i + 1, i) - leg(order, i, i + 1)).sum::<f64>();
+                    // Reversing all of a closed tour turns the leg back to the start round too
+                    if closed && before.is_none() {
+                        delta += leg(order, start, end) - leg(order, end, start);
+                    }
+                }
+
+                let drops_required = before.is_some_and(|before| required.contains(order[before], order[start]))
+                    || after.is_some_and(|after| required.contains(order[end], order[after]));
+                if delta < -EPSILON && !drops_required {
+                    order[start..=end].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    moves
+}
+
+/// Moves stretches of up to three cities of `order` after its first `fixed` elsewhere in the
+/// tour, in the same direction, for as long as that makes it shorter without dropping a
+/// `required` edge. Since no stretch is reversed, moves are measured as quickly whether the
+/// `distance` between cities is symmetric or not. Gives up once it has weighed up `budget`
+/// moves.
+pub fn or_opt(order: &mut [usize], fixed: usize, required: &RequiredEdges, tour: TourType, budget: usize, distance: impl Fn(usize, usize) -> f64) -> usize {
+    let n = order.len();
+    let closed = tour == TourType::Closed;
+    let before = |p: usize| if p > 0 { Some(p - 1) } else if closed { Some(n - 1) } else { None };
+    let after = |p: usize| if p + 1 < n { Some(p + 1) } else if closed { Some(0) } else { None };
+    // Legs to or from the end of an open tour, where there's no city, cost nothing
+    let leg = |order: &[usize], a: Option<usize>, b: Option<usize>| a.zip(b).map_or(0.0, |(a, b)| distance(order[a], order[b]));
+    let is_required = |order: &[usize], a: Option<usize>, b: Option<usize>| a.zip(b).is_some_and(|(a, b)| required.contains(order[a], order[b]));
+
+    let mut moves = 0;
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for len in 1..=OR_OPT_SEGMENT.min(n.saturating_sub(2)) {
+            for start in fixed..=n - len {
+                let (first, last) = (Some(start), Some(start + len - 1));
+                let (prev, next) = (before(start), after(start + len - 1));
+                // Taking the stretch out joins the cities either side of it
+                let removal = leg(order, prev, next) - leg(order, prev, first) - leg(order, last, next);
+                if is_required(order, prev, first) || is_required(order, last, next) {
+                    continue;
+                }
+
+                // The stretch goes back in after `at`, or at the very start of an open tour
+                let front = if closed || fixed > 0 { None } else { Some(None) };
+                let targets = front.into_iter().chain((fixed.saturating_sub(1)..n).map(Some));
+                for at in targets {
+                    if at == prev || at.is_some_and(|at| (start..start + len).contains(&at)) {
+                        continue;
+                    }
+                    if moves == budget {
+                        return moves;
+                    }
+                    moves += 1;
+
+                    let to = at.map_or(Some(0), after);
+                    let delta = removal + leg(order, at, first) + leg(order, last, to) - leg(order, at, to);
+                    if delta < -EPSILON && !is_required(order, at, to) {
+                        match at {
+                            Some(at) if at > start => order[start..=at].rotate_left(len),
+                            Some(at) => order[at + 1..start + len].rotate_right(len),
+                            None => order[..start + len].rotate_right(len),
+                        }
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    moves
+}
+
+/// Takes `order` to a 3-opt local optimum: 2-opt moves, and then the moves that replace three
+/// legs, cutting the tour after its first `fixed` cities into stretches A, B, C and D and
+/// joining them up as A C B D, A B' C' D, A C B' D or A C' B D, where ' is a stretch reversed,
+/// for as long as that makes it shorter without dropping a `required` edge. Gives up once it
+/// has weighed up `budget` moves, of either kind. As with `two_opt`, reversed stretches are
+/// measured both ways if the `distance` between cities is `asymmetric`.
+pub fn three_opt(
+    order: &mut [usize],
+    fixed: usize,
+    required: &RequiredEdges,
+    asymmetric: bool,
+    tour: TourType,
+    budget: usize,
+    distance: impl Fn(usize, usize) -> f64,
+) -> usize {
+    let n = order.len();
+    let closed = tour == TourType::Closed;
+    let leg = |order: &[usize], a: usize, b: Option<usize>| b.map_or(0.0, |b| distance(order[a], order[b]));
+    // How much longer order[from..=to] is backwards than forwards
+    let reversal = |order: &[usize], from: usize, to: usize| {
+        if asymmetric {
+            (from..to).map(|p| distance(order[p + 1], order[p]) - distance(order[p], order[p + 1])).sum()
+        } else {
+            0.0
+        }
+    };
+
+    let mut moves = 0;
+    loop {
+        moves += two_opt(order, fixed, required, asymmetric, tour, budget - moves, &distance);
+        let mut improved = false;
+        // A is order[..=i], B order[i + 1..=j], C order[j + 1..=k] and D the rest
+        for i in fixed.max(1) - 1..n {
+            for j in i + 1..n {
+                for k in j + 1..n {
+                    // What D starts with, or A if D is empty and the tour closed
+                    let d = if k + 1 < n { Some(k + 1) } else if closed { Some(0) } else { None };
+                    if required.contains(order[i], order[i + 1]) || required.contains(order[j], order[j + 1])
+                        || d.is_some_and(|d| required.contains(order[k], order[d]))
+                    {
+                        continue;
+                    }
+
+                    let (a, b1, b2, c1, c2) = (i, i + 1, j, j + 1, k);
+                    let removed = leg(order, a, Some(b1)) + leg(order, b2, Some(c1)) + leg(order, c2, d);
+                    let candidates = [
+                        leg(order, a, Some(c1)) + leg(order, c2, Some(b1)) + leg(order, b2, d),
+                        leg(order, a, Some(b2)) + leg(order, b1, Some(c2)) + leg(order, c1, d) + reversal(order, b1, b2) + reversal(order, c1, c2),
+                        leg(order, a, Some(c1)) + leg(order, c2, Some(b2)) + leg(order, b1, d) + reversal(order, b1, b2),
+                        leg(order, a, Some(c2)) + leg(order, c1, Some(b1)) + leg(order, b2, d) + reversal(order, c1, c2),
+                    ];
+                    for (reconnection, added) in candidates.iter().enumerate() {
+                        if moves >= budget {
+                            return moves;
+                        }
+                        moves += 1;
+                        if added - removed >= -EPSILON {
+                            continue;
+                        }
+
+                        let (b_len, c_len) = (j - i, k - j);
+                        match reconnection {
+                            0 => order[b1..=c2].rotate_left(b_len),
+                            1 => {
+                                order[b1..=b2].reverse();
+                                order[c1..=c2].reverse();
+                            }
+                            2 => {
+                                order[b1..=c2].rotate_left(b_len);
+                                order[b1 + c_len..=c2].reverse();
+                            }
+                            _ => {
+                                order[b1..=c2].rotate_left(b_len);
+                                order[b1..b1 + c_len].reverse();
+                            }
+                        }
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if !improved {
+            return moves;
+        }
+    }
+}
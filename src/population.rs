@@ -0,0 +1,292 @@
+//! The paths of a generation, stored flat: their orders one after another in a single buffer of
+//! city indices, as the spill file stores them, with their fitness alongside. A population of
+//! paths that each owned their order allocated a vector for every child bred; a flat one is bred
+//! into a buffer kept from the generation before, and the orders it reads one after another lie
+//! one after another in memory.
+//!
+//! The indices are as narrow as the number of cities allows: `u16` for up to 65,536 cities,
+//! which halves the memory a population takes and the bandwidth breeding it takes, and `u32`
+//! beyond. Code that reads or writes orders is generic over the two, and is called with the one
+//! `Population::width` says.
+
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Range;
+
+use crate::Path;
+
+/// A city's index as an order stores it: `usize` for a `Path`, `u16` or `u32` in a population.
+pub trait Gene: Copy + Eq + Ord + Hash + Send + Sync + fmt::Debug + fmt::Display + 'static {
+    fn index(self) -> usize;
+    /// `index`, which has to fit.
+    fn from_index(index: usize) -> Self;
+}
+
+impl Gene for usize {
+    fn index(self) -> usize {
+        self
+    }
+
+    fn from_index(index: usize) -> usize {
+        index
+    }
+}
+
+impl Gene for u16 {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn from_index(index: usize) -> u16 {
+        debug_assert!(index <= u16::MAX as usize, "city {} doesn't fit in a u16", index);
+        index as u16
+    }
+}
+
+impl Gene for u32 {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn from_index(index: usize) -> u32 {
+        debug_assert!(index <= u32::MAX as usize, "city {} doesn't fit in a u32", index);
+        index as u32
+    }
+}
+
+/// The type of city index a population stores its orders in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Width {
+    U16,
+    U32,
+}
+
+impl Width {
+    /// The narrowest width that indexes `cities` cities.
+    pub fn of(cities: usize) -> Width {
+        if cities <= u16::MAX as usize + 1 {
+            Width::U16
+        } else {
+            Width::U32
+        }
+    }
+
+    /// The memory a city index takes.
+    pub fn bytes(self) -> usize {
+        match self {
+            Width::U16 => std::mem::size_of::<u16>(),
+            Width::U32 => std::mem::size_of::<u32>(),
+        }
+    }
+}
+
+/// A city index a population can store its orders in.
+pub trait Stored: Gene {
+    /// The buffer of `population`, which has to be of this width.
+    fn genes(population: &Population) -> &[Self];
+    fn genes_mut(population: &mut Population) -> &mut [Self];
+}
+
+impl Stored for u16 {
+    fn genes(population: &Population) -> &[u16] {
+        match &population.genes {
+            Genes::U16(genes) => genes,
+            Genes::U32(_) => panic!("the population stores u32 city indices, not u16"),
+        }
+    }
+
+    fn genes_mut(population: &mut Population) -> &mut [u16] {
+        match &mut population.genes {
+            Genes::U16(genes) => genes,
+            Genes::U32(_) => panic!("the population stores u32 city indices, not u16"),
+        }
+    }
+}
+
+impl Stored for u32 {
+    fn genes(population: &Population) -> &[u32] {
+        match &population.genes {
+            Genes::U32(genes) => genes,
+            Genes::U16(_) => panic!("the population stores u16 city indices, not u32"),
+        }
+    }
+
+    fn genes_mut(population: &mut Population) -> &mut [u32] {
+        match &mut population.genes {
+            Genes::U32(genes) => genes,
+            Genes::U16(_) => panic!("the population stores u16 city indices, not u32"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Genes {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Genes {
+    fn new(width: Width) -> Genes {
+        match width {
+            Width::U16 => Genes::U16(Vec::new()),
+            Width::U32 => Genes::U32(Vec::new()),
+        }
+    }
+}
+
+impl Default for Genes {
+    fn default() -> Genes {
+        Genes::U32(Vec::new())
+    }
+}
+
+/// Paths of `cities` cities each.
+#[derive(Clone, Debug, Default)]
+pub struct Population {
+    cities: usize,
+    genes: Genes,
+    fitness: Vec<f64>,
+}
+
+impl Population {
+    /// An empty population whose orders take as little memory as `cities` cities allow.
+    pub fn new(cities: usize) -> Population {
+        Population { cities, genes: Genes::new(Width::of(cities)), fitness: Vec::new() }
+    }
+
+    pub fn width(&self) -> Width {
+        match self.genes {
+            Genes::U16(_) => Width::U16,
+            Genes::U32(_) => Width::U32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.fitness.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fitness.is_empty()
+    }
+
+    /// Forgets every path, keeping the buffers for the next ones.
+    pub fn clear(&mut self) {
+        match &mut self.genes {
+            Genes::U16(genes) => genes.clear(),
+            Genes::U32(genes) => genes.clear(),
+        }
+        self.fitness.clear();
+    }
+
+    /// The order of the path at `index`.
+    pub fn order<G: Stored>(&self, index: usize) -> &[G] {
+        &G::genes(self)[index * self.cities..(index + 1) * self.cities]
+    }
+
+    pub fn order_mut<G: Stored>(&mut self, index: usize) -> &mut [G] {
+        let cities = self.cities;
+        &mut G::genes_mut(self)[index * cities..(index + 1) * cities]
+    }
+
+    /// The orders of the paths in `range`, one after another.
+    pub fn orders_mut<G: Stored>(&mut self, range: Range<usize>) -> &mut [G] {
+        let cities = self.cities;
+        &mut G::genes_mut(self)[range.start * cities..range.end * cities]
+    }
+
+    pub fn fitness(&self, index: usize) -> f64 {
+        self.fitness[index]
+    }
+
+    /// The fitness of every path, in order.
+    pub fn fitness_values(&self) -> &[f64] {
+        &self.fitness
+    }
+
+    pub fn set_fitness(&mut self, index: usize, fitness: f64) {
+        self.fitness[index] = fitness;
+    }
+
+    /// The orders and fitness of the paths, in order.
+    pub fn iter<G: Stored>(&self) -> impl Iterator<Item = (&[G], f64)> + '_ {
+        (0..self.len()).map(move |i| (self.order(i), self.fitness[i]))
+    }
+
+    /// The path at `index`, as one that owns its order.
+    pub fn path(&self, index: usize) -> Path {
+        let order = match self.width() {
+            Width::U16 => self.order::<u16>(index).iter().map(|&city| city.index()).collect(),
+            Width::U32 => self.order::<u32>(index).iter().map(|&city| city.index()).collect(),
+        };
+        Path { fitness: self.fitness[index], order }
+    }
+
+    /// The index of the fittest path, the first of them if several are.
+    pub fn fittest(&self) -> usize {
+        (1..self.len()).fold(0, |fittest, i| if self.fitness[i] > self.fitness[fittest] { i } else { fittest })
+    }
+
+    /// Adds a path after the others.
+    pub fn push<G: Gene>(&mut self, order: &[G], fitness: f64) {
+        debug_assert_eq!(order.len(), self.cities);
+        match &mut self.genes {
+            Genes::U16(genes) => genes.extend(order.iter().map(|city| u16::from_index(city.index()))),
+            Genes::U32(genes) => genes.extend(order.iter().map(|city| u32::from_index(city.index()))),
+        }
+        self.fitness.push(fitness);
+    }
+
+    /// Adds `count` paths of no fitness whose orders are to be written with `orders_mut`.
+    pub fn grow(&mut self, count: usize) {
+        let added = count * self.cities;
+        match &mut self.genes {
+            Genes::U16(genes) => genes.resize(genes.len() + added, 0),
+            Genes::U32(genes) => genes.resize(genes.len() + added, 0),
+        }
+        self.fitness.resize(self.fitness.len() + count, 0.0);
+    }
+
+    /// Adds copies of the paths of `other` in `range` after the others.
+    pub fn extend_from(&mut self, other: &Population, range: Range<usize>) {
+        debug_assert_eq!(other.cities, self.cities);
+        let genes = range.start * self.cities..range.end * self.cities;
+        match (&mut self.genes, &other.genes) {
+            (Genes::U16(to), Genes::U16(from)) => to.extend_from_slice(&from[genes]),
+            (Genes::U32(to), Genes::U32(from)) => to.extend_from_slice(&from[genes]),
+            _ => panic!("populations of different widths can't share paths"),
+        }
+        self.fitness.extend_from_slice(&other.fitness[range]);
+    }
+
+    /// Puts `order` and its `fitness` in place of the path at `index`.
+    pub fn replace<G: Gene>(&mut self, index: usize, order: &[G], fitness: f64) {
+        match self.width() {
+            Width::U16 => replace(self.order_mut::<u16>(index), order),
+            Width::U32 => replace(self.order_mut::<u32>(index), order),
+        }
+        self.fitness[index] = fitness;
+    }
+
+    /// Orders the paths fittest first, paths of equal fitness keeping their order, gathering
+    /// them through `scratch`, which is left with no paths worth keeping.
+    pub fn sort(&mut self, scratch: &mut Population) {
+        let mut ranked: Vec<usize> = (0..self.len()).collect();
+        ranked.sort_by(|&a, &b| self.fitness[b].total_cmp(&self.fitness[a]));
+
+        scratch.cities = self.cities;
+        if scratch.width() != self.width() {
+            scratch.genes = Genes::new(self.width());
+        }
+        scratch.clear();
+        for i in ranked {
+            scratch.extend_from(self, i..i + 1);
+        }
+        std::mem::swap(self, scratch);
+    }
+}
+
+fn replace<S: Gene, G: Gene>(slots: &mut [S], order: &[G]) {
+    for (slot, city) in slots.iter_mut().zip(order) {
+        *slot = S::from_index(city.index());
+    }
+}
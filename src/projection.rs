@@ -0,0 +1,193 @@
+//! Map projections from longitudes and latitudes onto the plane, so geographic instances can be
+//! solved with plain Euclidean distances in metres: UTM, which is accurate to within a metre or
+//! so per kilometre across a zone, and Web Mercator, which is only true along the equator.
+
+use std::f64::consts::FRAC_PI_4;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::events::Notice;
+use crate::input::City;
+
+// The WGS84 ellipsoid
+const SEMI_MAJOR_AXIS: f64 = 6378137.0;
+const FLATTENING: f64 = 1.0 / 298.257223563;
+
+// The scale factor on UTM central meridians, and the offsets keeping coordinates positive
+const UTM_SCALE: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500000.0;
+const FALSE_NORTHING: f64 = 10000000.0;
+
+/// How far from a zone's central meridian UTM stays reasonably accurate, in degrees.
+const UTM_REACH: f64 = 9.0;
+
+/// The latitude Web Mercator stops at, north and south, making the map square.
+const MERCATOR_LIMIT: f64 = 85.05112878;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Projection {
+    /// UTM in the zone and hemisphere of the middle of the cities.
+    UtmAuto,
+    /// UTM `zone` (1 to 60), north or south of the equator.
+    Utm { zone: u32, north: bool },
+    /// Spherical Web Mercator, EPSG:3857.
+    WebMercator,
+}
+
+impl Projection {
+    /// Projects `cities`, longitudes (x) and latitudes (y) in degrees, onto the plane in metres.
+    pub fn project(self, cities: &[City]) -> Result<Vec<City>, String> {
+        if let Some(city) = cities.iter().find(|c| c.x.abs() > 180.0 || c.y.abs() > 90.0) {
+            return Err(format!("({}, {}) isn't a longitude and latitude", city.x, city.y));
+        }
+
+        let (zone, north) = match self.resolve(cities) {
+            Projection::WebMercator => return Ok(cities.iter().map(web_mercator).collect()),
+            Projection::Utm { zone, north } => (zone, north),
+            // Only when there are no cities to pick a zone for
+            Projection::UtmAuto => return Ok(Vec::new()),
+        };
+
+        let central_meridian = zone as f64 * 6.0 - 183.0;
+        Ok(cities.iter().map(|c| utm(c, central_meridian, north)).collect())
+    }
+
+    /// A warning if some of `cities` are far enough from the middle of the UTM zone they're
+    /// projected onto to come out distorted.
+    pub fn distortion(self, cities: &[City]) -> Option<Notice> {
+        let Projection::Utm { zone, .. } = self.resolve(cities) else { return None };
+        let central_meridian = zone as f64 * 6.0 - 183.0;
+        cities.iter().any(|c| (c.x - central_meridian).abs() > UTM_REACH).then(|| Notice::Warning(format!(
+            "some cities are more than {} degrees from the middle of UTM zone {}, where it gets distorted",
+            UTM_REACH, zone
+        )))
+    }
+
+    /// The EPSG code of the projection, once the zone is known.
+    pub fn epsg(self) -> Option<u32> {
+        match self {
+            Projection::UtmAuto => None,
+            Projection::Utm { zone, north: true } => Some(32600 + zone),
+            Projection::Utm { zone, north: false } => Some(32700 + zone),
+            Projection::WebMercator => Some(3857),
+        }
+    }
+
+    /// The UTM zone `cities` would be projected onto by `UtmAuto`, or this projection otherwise.
+    pub fn resolve(self, cities: &[City]) -> Projection {
+        if self != Projection::UtmAuto || cities.is_empty() {
+            return self;
+        }
+
+        let count = cities.len() as f64;
+        let longitude = cities.iter().map(|c| c.x).sum::<f64>() / count;
+        let latitude = cities.iter().map(|c| c.y).sum::<f64>() / count;
+        Projection::Utm {
+            zone: ((longitude + 180.0) / 6.0).floor().clamp(0.0, 59.0) as u32 + 1,
+            north: latitude >= 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Projection::UtmAuto => write!(f, "utm"),
+            Projection::Utm { zone, north } => {
+                write!(f, "UTM zone {}{} (EPSG:{})", zone, if *north { "N" } else { "S" }, self.epsg().unwrap_or_default())
+            }
+            Projection::WebMercator => write!(f, "Web Mercator (EPSG:3857)"),
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    /// Parses `utm` (picking the zone from the cities), `web-mercator`, or an EPSG code of one
+    /// of those, e.g. `EPSG:32633` for UTM zone 33N.
+    fn from_str(s: &str) -> Result<Projection, String> {
+        let lower = s.to_lowercase();
+        match lower.as_str() {
+            "utm" => return Ok(Projection::UtmAuto),
+            "web-mercator" | "webmercator" => return Ok(Projection::WebMercator),
+            _ => (),
+        }
+
+        let code: u32 = lower.strip_prefix("epsg:").unwrap_or(&lower).parse()
+            .map_err(|_| format!("unknown projection {:?}, expected utm, web-mercator or an EPSG code", s))?;
+        match code {
+            3857 => Ok(Projection::WebMercator),
+            32601..=32660 => Ok(Projection::Utm { zone: code - 32600, north: true }),
+            32701..=32760 => Ok(Projection::Utm { zone: code - 32700, north: false }),
+            _ => Err(format!("EPSG:{} isn't supported, only UTM on WGS84 (32601-32660, 32701-32760) and 3857", code)),
+        }
+    }
+}
+
+fn web_mercator(city: &City) -> City {
+    let latitude = city.y.clamp(-MERCATOR_LIMIT, MERCATOR_LIMIT).to_radians();
+    City::new(SEMI_MAJOR_AXIS * city.x.to_radians(), SEMI_MAJOR_AXIS * (FRAC_PI_4 + latitude / 2.0).tan().ln())
+}
+
+/// The transverse Mercator projection onto the WGS84 ellipsoid, after Snyder's "Map
+/// Projections: A Working Manual" (USGS, 1987), equations 8-9 to 8-15.
+fn utm(city: &City, central_meridian: f64, north: bool) -> City {
+    let e2 = FLATTENING * (2.0 - FLATTENING);
+    let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+    let ep2 = e2 / (1.0 - e2);
+
+    let latitude = city.y.to_radians();
+    let (sin, cos, tan) = (latitude.sin(), latitude.cos(), latitude.tan());
+    let n = SEMI_MAJOR_AXIS / (1.0 - e2 * sin * sin).sqrt();
+    let t = tan * tan;
+    let c = ep2 * cos * cos;
+    let a = cos * (city.x - central_meridian).to_radians();
+    let m = SEMI_MAJOR_AXIS * (
+        (1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * latitude
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * latitude).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * latitude).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * latitude).sin()
+    );
+
+    let easting = UTM_SCALE * n * (
+        a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0
+    );
+    let northing = UTM_SCALE * (m + n * tan * (
+        a * a / 2.0
+            + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+            + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0
+    ));
+
+    City::new(FALSE_EASTING + easting, if north { northing } else { FALSE_NORTHING + northing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Metric;
+
+    #[test]
+    fn utm_keeps_distances_close_to_great_circles() {
+        assert_eq!("EPSG:32733".parse(), Ok(Projection::Utm { zone: 33, north: false }));
+        assert_eq!("3857".parse(), Ok(Projection::WebMercator));
+        assert!("EPSG:4326".parse::<Projection>().is_err());
+
+        let equator = Projection::Utm { zone: 31, north: true }.project(&[City::new(3.0, 0.0)]).unwrap();
+        assert!((equator[0].x - 500000.0).abs() < 1e-6 && equator[0].y.abs() < 1e-6);
+
+        // Across Berlin, which is in zone 33N
+        let cities = vec![City::new(13.2846, 52.5163), City::new(13.4501, 52.5106)];
+        assert_eq!(Projection::UtmAuto.resolve(&cities), Projection::Utm { zone: 33, north: true });
+        let projected = Projection::UtmAuto.project(&cities).unwrap();
+        let planar = Metric::Euclidean.distance(&projected[0], &projected[1]) / 1000.0;
+        let great_circle = Metric::Haversine.distance(&cities[0], &cities[1]);
+        assert!((planar / great_circle - 1.0).abs() < 0.005, "{} km vs {} km", planar, great_circle);
+        assert_eq!(Projection::UtmAuto.distortion(&cities), None);
+
+        let wide = vec![City::new(13.0, 52.0), City::new(30.0, 52.0)];
+        assert!(Projection::Utm { zone: 33, north: true }.distortion(&wide).is_some());
+        assert_eq!(Projection::WebMercator.distortion(&wide), None);
+    }
+}
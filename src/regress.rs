@@ -0,0 +1,156 @@
+//! Solution-quality regression gate.
+//!
+//! Solves a few reference instances built into the binary from fixed seeds and compares the
+//! median tour length of each with a baseline file, a JSON object of instance name to median
+//! length. Runs are fully determined by the seeds, so any change in the results comes from a
+//! change in the algorithm.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::Path as FilePath;
+
+use crate::input;
+use crate::rng::{RngAlgorithm, SeededRng};
+use crate::Simulation;
+
+const INSTANCES: &[(&str, &str)] = &[
+    ("circle32", include_str!("instances/circle32.csv")),
+    ("grid49", include_str!("instances/grid49.csv")),
+    ("random64", include_str!("instances/random64.csv")),
+];
+
+const SEEDS: &[u64] = &[1, 2, 3, 4, 5];
+const GENERATIONS: usize = 1000;
+const POPULATION_SIZE: usize = 200;
+const CROSSOVER_RATE: f64 = 0.4;
+const MUTATION_RATE: f64 = 0.1;
+const SURVIVAL_RATE: f64 = 0.3;
+
+/// Median tour length over `SEEDS` for each reference instance.
+pub fn measure() -> BTreeMap<String, f64> {
+    INSTANCES.iter().map(|(name, csv)| {
+        let cities = input::read_cities(csv.as_bytes()).expect("reference instances are valid");
+
+        let mut lengths: Vec<f64> = SEEDS.iter().map(|&seed| {
+            let mut sim = Simulation::new(
+                POPULATION_SIZE,
+                cities.clone(),
+                GENERATIONS,
+                CROSSOVER_RATE,
+                MUTATION_RATE,
+                SURVIVAL_RATE,
+                SeededRng::new(RngAlgorithm::ChaCha, seed),
+            ).expect("reference runs are valid");
+            1.0 / sim.solve(|_| ControlFlow::Continue(())).expect("reference runs are valid").fitness
+        }).collect();
+        lengths.sort_by(f64::total_cmp);
+
+        (name.to_string(), lengths[lengths.len() / 2])
+    }).collect()
+}
+
+/// The reference instances measured against a baseline, displayed as a line per instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// Each instance's median length, with its baseline if it has one.
+    pub instances: Vec<(String, f64, Option<f64>)>,
+    /// How much longer than its baseline an instance may come out, as a fraction.
+    pub tolerance: f64,
+}
+
+impl Comparison {
+    /// Whether every instance is in the baseline and within the tolerance of it.
+    pub fn passed(&self) -> bool {
+        self.instances.iter().all(|&(_, length, expected)| expected.is_some_and(|expected| !self.regressed(length, expected)))
+    }
+
+    fn regressed(&self, length: f64, expected: f64) -> bool {
+        length / expected - 1.0 > self.tolerance
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (name, length, expected)) in self.instances.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            match *expected {
+                Some(expected) => {
+                    let verdict = if self.regressed(*length, expected) { "REGRESSED" } else { "ok" };
+                    let change = length / expected - 1.0;
+                    write!(f, "{}: median length {:.3}, baseline {:.3} ({:+.2}%) {}", name, length, expected, change * 100.0, verdict)?;
+                }
+                None => write!(f, "{}: median length {:.3}, not in the baseline", name, length)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The median lengths measured for a new baseline, displayed as a line per instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Baseline {
+    pub lengths: BTreeMap<String, f64>,
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.lengths.iter().map(|(name, length)| format!("{}: median length {:.3}", name, length)).collect();
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// Measures the reference instances and compares them with the baseline at `path`, each
+/// allowed to come out `tolerance` (a fraction, so 0.02 allows tours 2% longer) longer than
+/// its baseline.
+pub fn check(path: &FilePath, tolerance: f64) -> Result<Comparison, Box<dyn Error>> {
+    let baseline: BTreeMap<String, f64> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let instances = measure().into_iter()
+        .map(|(name, length)| {
+            let expected = baseline.get(&name).copied();
+            (name, length, expected)
+        })
+        .collect();
+
+    Ok(Comparison { instances, tolerance })
+}
+
+/// Measures the reference instances and writes the results to `path` as the new baseline,
+/// returning them.
+pub fn update(path: &FilePath) -> Result<Baseline, Box<dyn Error>> {
+    let lengths = measure();
+    fs::write(path, serde_json::to_string_pretty(&lengths)? + "\n")?;
+    Ok(Baseline { lengths })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_comparison_fails_on_an_instance_too_long_or_missing_from_the_baseline() {
+        let compare = |instances: Vec<(&str, f64, Option<f64>)>| Comparison {
+            instances: instances.into_iter().map(|(name, length, expected)| (name.to_string(), length, expected)).collect(),
+            tolerance: 0.02,
+        };
+
+        let within = compare(vec![("circle32", 101.0, Some(100.0)), ("grid49", 90.0, Some(100.0))]);
+        assert!(within.passed());
+        assert_eq!(
+            within.to_string(),
+            "circle32: median length 101.000, baseline 100.000 (+1.00%) ok\ngrid49: median length 90.000, baseline 100.000 (-10.00%) ok"
+        );
+
+        let longer = compare(vec![("circle32", 103.0, Some(100.0))]);
+        assert!(!longer.passed());
+        assert_eq!(longer.to_string(), "circle32: median length 103.000, baseline 100.000 (+3.00%) REGRESSED");
+
+        let missing = compare(vec![("random64", 50.0, None)]);
+        assert!(!missing.passed());
+        assert_eq!(missing.to_string(), "random64: median length 50.000, not in the baseline");
+    }
+}
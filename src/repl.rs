@@ -0,0 +1,167 @@
+//! Commands typed on stdin while a run goes on, to see how it's doing and change its
+//! parameters without killing and restarting it. They take effect after the generation in
+//! progress.
+//!
+//! A run can also be paused, with `pause` or by sending the process SIGUSR1, and resumed with
+//! `resume` or another SIGUSR1. Unlike stopping and restarting from a checkpoint, the process
+//! and everything it holds stay as they are in between.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::Path;
+
+pub const HELP: &str = "\
+commands:
+  stats                 the best length, diversity, estimate and current parameters
+  mutation <rate>       mutate paths at this rate, 0 to 1
+  elitism <rate>        carry this share of the breeding pool over unchanged, 0 to 1
+  generations <count>   end the run after this many generations
+  stop-at <length>      end the run once the best tour is this short
+  pause                 wait after this generation, still taking commands
+  resume                go on after a pause
+  export <file>         write the best tour so far to file, as a list of city indices
+  stop                  end the run now
+  help                  this list";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Stats,
+    Mutation(f64),
+    Elitism(f64),
+    Generations(usize),
+    StopAt(f64),
+    Pause,
+    Resume,
+    Export(PathBuf),
+    Stop,
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Command, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let rate = |value: &str| value.parse().ok()
+            .filter(|rate| (0.0..=1.0).contains(rate))
+            .ok_or_else(|| format!("{:?} isn't a rate between 0 and 1", value));
+
+        match words[..] {
+            ["stats"] => Ok(Command::Stats),
+            ["mutation", value] => rate(value).map(Command::Mutation),
+            ["elitism", value] => rate(value).map(Command::Elitism),
+            ["generations", value] => value.parse().map(Command::Generations)
+                .map_err(|_| format!("{:?} isn't a number of generations", value)),
+            ["stop-at", value] => value.parse().ok()
+                .filter(|length: &f64| length.is_finite() && *length > 0.0)
+                .map(Command::StopAt)
+                .ok_or_else(|| format!("{:?} isn't a tour length", value)),
+            ["pause"] => Ok(Command::Pause),
+            ["resume"] => Ok(Command::Resume),
+            ["export", file] => Ok(Command::Export(PathBuf::from(file))),
+            ["stop"] => Ok(Command::Stop),
+            ["help"] => Ok(Command::Help),
+            _ => Err(format!("unknown command {:?}, type help for the list", s.trim())),
+        }
+    }
+}
+
+pub struct Repl {
+    // Behind a lock only so the simulation holding it stays Sync
+    lines: Mutex<Receiver<String>>,
+}
+
+impl Repl {
+    /// Reads commands from stdin on a thread of its own.
+    pub fn stdin() -> io::Result<Repl> {
+        Repl::reading(BufReader::new(io::stdin()))
+    }
+
+    /// Reads commands from `input` on a thread of its own.
+    pub fn reading<R: BufRead + Send + 'static>(input: R) -> io::Result<Repl> {
+        let (sender, lines) = channel();
+        thread::Builder::new().name("repl".to_string()).spawn(move || {
+            for line in input.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        })?;
+
+        Ok(Repl { lines: Mutex::new(lines) })
+    }
+
+    /// The commands typed since the last call, or why each line that isn't one isn't.
+    pub fn commands(&self) -> Vec<Result<Command, String>> {
+        let lines = self.lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        parse(lines.try_iter())
+    }
+
+    /// Like `commands`, but waits up to `timeout` for the first line if none has been typed.
+    pub fn wait(&self, timeout: Duration) -> Vec<Result<Command, String>> {
+        let lines = self.lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match lines.recv_timeout(timeout) {
+            Ok(first) => parse(std::iter::once(first).chain(lines.try_iter())),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+fn parse(lines: impl Iterator<Item = String>) -> Vec<Result<Command, String>> {
+    lines.filter(|line| !line.trim().is_empty()).map(|line| line.parse()).collect()
+}
+
+/// Writes the order of `fittest` to `path` for `export`, as `diff` and `--hints` read it.
+pub fn export(path: &std::path::Path, fittest: &Path) -> io::Result<()> {
+    let order = serde_json::to_string(&fittest.order).expect("a list of indices serializes");
+    fs::write(path, order + "\n")
+}
+
+/// Set by SIGUSR1, until `pause_signalled` sees it.
+static PAUSE_SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+/// Makes SIGUSR1 pause a run, or resume it if paused, instead of killing the process.
+#[cfg(unix)]
+pub fn pause_on_signal() {
+    extern "C" fn signalled(_: libc::c_int) {
+        PAUSE_SIGNALLED.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        libc::signal(libc::SIGUSR1, signalled as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+}
+
+/// Whether SIGUSR1 was sent since the last call.
+pub fn pause_signalled() -> bool {
+    PAUSE_SIGNALLED.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BreedingCounts;
+
+    #[test]
+    fn repl_commands_parse_and_reject_bad_values() {
+        assert_eq!("mutation 0.2".parse(), Ok(Command::Mutation(0.2)));
+        assert_eq!("  elitism   0.5 ".parse(), Ok(Command::Elitism(0.5)));
+        assert_eq!("generations 900".parse(), Ok(Command::Generations(900)));
+        assert_eq!("stop-at 7600".parse(), Ok(Command::StopAt(7600.0)));
+        assert!("mutation 1.5".parse::<Command>().is_err());
+        assert!("stop-at -1".parse::<Command>().is_err());
+        assert!("crossover 0.4".parse::<Command>().is_err());
+
+        let counts = BreedingCounts::new(100, 0.4, 0.3);
+        let tuned = counts.with_survival_rate(0.5);
+        assert_eq!((tuned.surviving_parent_count, tuned.population_size()), (20, 100));
+    }
+}
@@ -0,0 +1,126 @@
+//! Edges that every tour has to take, e.g. a ferry crossing that's already booked.
+//!
+//! The required edges join up into chains of cities that have to be visited one after the
+//! other, in either direction. Rather than penalising tours that break a chain, breeding and
+//! mutation are followed by a repair that gathers the chain back together where it starts.
+
+use crate::population::Gene;
+
+#[derive(Clone, Debug, Default)]
+pub struct RequiredEdges {
+    edges: Vec<(usize, usize)>,
+    chains: Vec<Vec<usize>>,
+    /// The index of the chain each city is in, if any.
+    chain_of: Vec<Option<usize>>,
+}
+
+impl RequiredEdges {
+    /// Requires `edges` in tours of `city_count` cities that start with `prefix`. Edges can
+    /// touch the prefix only if they join consecutive cities of it, which it already does.
+    pub fn new(edges: Vec<(usize, usize)>, city_count: usize, prefix: &[usize]) -> Result<RequiredEdges, String> {
+        let mut neighbours = vec![Vec::new(); city_count];
+        for &(a, b) in &edges {
+            if a >= city_count || b >= city_count {
+                return Err(format!("edge {}-{} needs a city that doesn't exist, there are only {}", a, b, city_count));
+            }
+            if a == b {
+                return Err(format!("edge {}-{} joins a city to itself", a, b));
+            }
+            if prefix.contains(&a) || prefix.contains(&b) {
+                if !prefix.windows(2).any(|w| (w[0], w[1]) == (a, b) || (w[0], w[1]) == (b, a)) {
+                    return Err(format!("edge {}-{} touches the prefix without joining two consecutive cities of it", a, b));
+                }
+                continue;
+            }
+            if neighbours[a].contains(&b) {
+                continue;
+            }
+            for city in [a, b] {
+                if neighbours[city].len() == 2 {
+                    return Err(format!("city {} is in more than two edges, but a tour can only enter and leave it once", city));
+                }
+            }
+            neighbours[a].push(b);
+            neighbours[b].push(a);
+        }
+
+        // Walk each chain from one of its ends, so whatever's left over must be a cycle
+        let mut chains = Vec::new();
+        let mut chain_of = vec![None; city_count];
+        for end in 0..city_count {
+            if neighbours[end].len() != 1 || chain_of[end].is_some() {
+                continue;
+            }
+
+            let mut chain = vec![end];
+            chain_of[end] = Some(chains.len());
+            while let Some(&next) = neighbours[*chain.last().unwrap()].iter().find(|&&c| chain_of[c].is_none()) {
+                chain_of[next] = Some(chains.len());
+                chain.push(next);
+            }
+            chains.push(chain);
+        }
+        if let Some(city) = (0..city_count).find(|&c| !neighbours[c].is_empty() && chain_of[c].is_none()) {
+            return Err(format!("the edges through city {} form a loop, which a tour can't take", city));
+        }
+
+        Ok(RequiredEdges { edges, chains, chain_of })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    /// Whether the edge between `a` and `b`, either way, is required.
+    pub fn contains(&self, a: usize, b: usize) -> bool {
+        self.edges.iter().any(|&edge| edge == (a, b) || edge == (b, a))
+    }
+
+    /// The required edges `order` doesn't take.
+    pub fn missing(&self, order: &[usize]) -> Vec<(usize, usize)> {
+        let taken = |a: usize, b: usize| order.windows(2).any(|w| (w[0], w[1]) == (a, b) || (w[0], w[1]) == (b, a));
+        self.edges.iter().copied().filter(|&(a, b)| !taken(a, b)).collect()
+    }
+
+    /// Moves the cities of every broken chain together to where its first city is, keeping
+    /// the direction the chain's ends are visited in. Chains that are whole stay where they
+    /// are, and nothing moves before the first city of a chain, so a prefix is left alone.
+    pub fn repair<G: Gene>(&self, order: &mut [G]) {
+        if self.chains.is_empty() {
+            return;
+        }
+
+        let mut position = vec![0; order.len()];
+        for (i, &city) in order.iter().enumerate() {
+            position[city.index()] = i;
+        }
+
+        for (index, chain) in self.chains.iter().enumerate() {
+            if chain.windows(2).all(|w| position[w[0]].abs_diff(position[w[1]]) == 1) {
+                continue;
+            }
+
+            let start = chain.iter().map(|&c| position[c]).min().expect("chains have cities");
+            let forward = position[chain[0]] < position[chain[chain.len() - 1]];
+            let rest: Vec<G> = order.iter().copied().filter(|c| self.chain_of[c.index()] != Some(index)).collect();
+
+            let mut repaired = Vec::with_capacity(order.len());
+            repaired.extend_from_slice(&rest[..start]);
+            if forward {
+                repaired.extend(chain.iter().map(|&c| G::from_index(c)));
+            } else {
+                repaired.extend(chain.iter().rev().map(|&c| G::from_index(c)));
+            }
+            repaired.extend_from_slice(&rest[start..]);
+
+            order.copy_from_slice(&repaired);
+            for (i, &city) in order.iter().enumerate().skip(start) {
+                position[city.index()] = i;
+            }
+        }
+    }
+}
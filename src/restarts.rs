@@ -0,0 +1,142 @@
+//! Several runs of one problem from different seeds, for `--restarts`. A single run's length
+//! says little about the settings it was made with; the spread over a few says how far to trust
+//! it. Natively the runs go on threads of their own, as many at a time as there are cores, and
+//! in wasm one after another.
+
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::rng::random_seed;
+use crate::{Error, PopulationStats, Simulation, SolutionReport};
+
+/// One of the runs, and the seed it was made with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Restart {
+    pub seed: u64,
+    pub report: SolutionReport,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Restarts {
+    /// In the order of their seeds.
+    pub runs: Vec<Restart>,
+}
+
+impl Restarts {
+    /// Runs the simulation `build` makes from each of `seeds`, which mustn't be empty. The
+    /// first run to fail, by its seed, fails them all.
+    pub fn run(seeds: &[u64], build: impl Fn(u64) -> Simulation + Sync) -> Result<Restarts, Error> {
+        assert!(!seeds.is_empty(), "there's no restarting without a seed");
+        let runs = map(seeds, |seed| build(seed).run().map(|report| Restart { seed, report }));
+        Ok(Restarts { runs: runs.into_iter().collect::<Result<_, _>>()? })
+    }
+
+    /// The seeds of `count` runs: `seed` and the ones after it if it's given, so the runs can be
+    /// made again, otherwise a random seed for each.
+    pub fn seeds(seed: Option<u64>, count: usize) -> Vec<u64> {
+        match seed {
+            Some(first) => (0..count as u64).map(|i| first.wrapping_add(i)).collect(),
+            None => (0..count).map(|_| random_seed()).collect(),
+        }
+    }
+
+    /// The run that found the shortest tour, the first of them if several did.
+    pub fn best(&self) -> &Restart {
+        self.runs.iter()
+            .reduce(|best, run| if run.report.length < best.report.length { run } else { best })
+            .expect("there's a run")
+    }
+
+    /// How long the runs' best tours are: the shortest, their mean, the longest and the standard
+    /// deviation.
+    pub fn stats(&self) -> PopulationStats {
+        PopulationStats::of(self.runs.iter().map(|run| run.report.length))
+    }
+
+    /// The runs and their stats as JSON, with the best tour.
+    pub fn result(&self) -> Value {
+        let best = self.best();
+        json!({
+            "tour": best.report.best_tour,
+            "tour_length": best.report.length,
+            "seed": best.seed,
+            "stats": self.stats(),
+            "runs": self.runs.iter()
+                .map(|run| json!({ "seed": run.seed, "tour_length": run.report.length, "generations": run.report.generations_run }))
+                .collect::<Vec<Value>>(),
+        })
+    }
+}
+
+impl fmt::Display for Restarts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Restarts:")?;
+        for run in &self.runs {
+            writeln!(f, "  seed {:<20} {:.3} after {} generations", run.seed, run.report.length, run.report.generations_run)?;
+        }
+        let stats = self.stats();
+        writeln!(f, "  best              {:.3} (seed {})", stats.best, self.best().seed)?;
+        writeln!(f, "  mean              {:.3}", stats.mean)?;
+        writeln!(f, "  worst             {:.3}", stats.worst)?;
+        write!(f, "  std dev           {:.3}", stats.std_dev)
+    }
+}
+
+/// `f` of each seed, in order.
+#[cfg(not(target_family = "wasm"))]
+fn map<T: Send>(seeds: &[u64], f: impl Fn(u64) -> T + Sync) -> Vec<T> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let threads = std::thread::available_parallelism().map_or(1, usize::from).min(seeds.len());
+    let next = AtomicUsize::new(0);
+    let done = Mutex::new(Vec::with_capacity(seeds.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&seed) = seeds.get(i) else { break };
+                    let result = f(seed);
+                    done.lock().expect("no run panicked holding the lock").push((i, result));
+                }
+            });
+        }
+    });
+
+    let mut done = done.into_inner().expect("no run panicked holding the lock");
+    done.sort_by_key(|&(i, _)| i);
+    done.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(target_family = "wasm")]
+fn map<T: Send>(seeds: &[u64], f: impl Fn(u64) -> T + Sync) -> Vec<T> {
+    seeds.iter().map(|&seed| f(seed)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{RngAlgorithm, SeededRng};
+    use crate::testing::twelve_cities;
+
+    #[test]
+    fn restarts_are_the_runs_of_their_seeds() {
+        let build = |seed| Simulation::new(30, twelve_cities(), 40, 0.4, 0.05, 0.3, SeededRng::new(RngAlgorithm::ChaCha, seed)).unwrap();
+        let restarts = Restarts::run(&[3, 1, 2], build).unwrap();
+
+        assert_eq!(restarts.runs.iter().map(|run| run.seed).collect::<Vec<_>>(), [3, 1, 2]);
+        for run in &restarts.runs {
+            assert_eq!(run.report, build(run.seed).run().unwrap());
+        }
+        let lengths: Vec<f64> = restarts.runs.iter().map(|run| run.report.length).collect();
+        let stats = restarts.stats();
+        assert_eq!(restarts.best().report.length, stats.best);
+        assert_eq!(stats.best, lengths.iter().copied().fold(f64::INFINITY, f64::min));
+        assert!((stats.mean - lengths.iter().sum::<f64>() / 3.0).abs() < 1e-9);
+
+        assert_eq!(Restarts::seeds(Some(7), 3), [7, 8, 9]);
+        assert_eq!(Restarts::seeds(None, 4).len(), 4);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::str::FromStr;
+#[cfg(not(feature = "wasm"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::rngs::SmallRng;
+use rand::{Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rand_pcg::Pcg64;
+// std's clock panics in the browser
+#[cfg(feature = "wasm")]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// The random number generators a run can draw from. ChaCha gives the same stream on every
+/// platform and rand release; the others trade some of that for speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RngAlgorithm {
+    ChaCha,
+    Pcg64,
+    Small,
+}
+
+impl FromStr for RngAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RngAlgorithm, String> {
+        match s {
+            "chacha" => Ok(RngAlgorithm::ChaCha),
+            "pcg64" => Ok(RngAlgorithm::Pcg64),
+            "small" => Ok(RngAlgorithm::Small),
+            _ => Err(format!("unknown rng {:?}, expected chacha, pcg64 or small", s)),
+        }
+    }
+}
+
+impl fmt::Display for RngAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            RngAlgorithm::ChaCha => "chacha",
+            RngAlgorithm::Pcg64 => "pcg64",
+            RngAlgorithm::Small => "small",
+        })
+    }
+}
+
+// There's one per simulation, so the size is no concern and boxing would only add indirection
+#[allow(clippy::large_enum_variant)]
+enum Inner {
+    ChaCha(ChaCha20Rng),
+    Pcg64(Pcg64),
+    Small(SmallRng),
+}
+
+impl Inner {
+    fn new(algorithm: RngAlgorithm, seed: u64) -> Inner {
+        match algorithm {
+            RngAlgorithm::ChaCha => Inner::ChaCha(ChaCha20Rng::seed_from_u64(seed)),
+            RngAlgorithm::Pcg64 => Inner::Pcg64(Pcg64::seed_from_u64(seed)),
+            RngAlgorithm::Small => Inner::Small(SmallRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+/// A generator of the chosen algorithm that remembers the seed it started from.
+pub struct SeededRng {
+    algorithm: RngAlgorithm,
+    seed: u64,
+    inner: Inner,
+}
+
+impl SeededRng {
+    pub fn new(algorithm: RngAlgorithm, seed: u64) -> SeededRng {
+        SeededRng { algorithm, seed, inner: Inner::new(algorithm, seed) }
+    }
+
+    /// A generator for a run that started from `seed`, carrying on from the `state` that
+    /// `reseed` returned.
+    pub fn resumed(algorithm: RngAlgorithm, seed: u64, state: u64) -> SeededRng {
+        SeededRng { algorithm, seed, inner: Inner::new(algorithm, state) }
+    }
+
+    /// Carries on with a stream seeded from this one, and returns that seed, which fits in a
+    /// checkpoint where the generator's own state wouldn't. The seed the run started from stays
+    /// as it was.
+    pub fn reseed(&mut self) -> u64 {
+        let state = self.next_u64();
+        self.inner = Inner::new(self.algorithm, state);
+        state
+    }
+
+    pub fn algorithm(&self) -> RngAlgorithm {
+        self.algorithm
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        match &mut self.inner {
+            Inner::ChaCha(rng) => rng.next_u32(),
+            Inner::Pcg64(rng) => rng.next_u32(),
+            Inner::Small(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match &mut self.inner {
+            Inner::ChaCha(rng) => rng.next_u64(),
+            Inner::Pcg64(rng) => rng.next_u64(),
+            Inner::Small(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match &mut self.inner {
+            Inner::ChaCha(rng) => rng.fill_bytes(dest),
+            Inner::Pcg64(rng) => rng.fill_bytes(dest),
+            Inner::Small(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        match &mut self.inner {
+            Inner::ChaCha(rng) => rng.try_fill_bytes(dest),
+            Inner::Pcg64(rng) => rng.try_fill_bytes(dest),
+            Inner::Small(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+/// A seed for a run not given one, straight from the operating system rather than a
+/// thread-local generator, so it works the same under WASI. Should the system have no
+/// randomness to give, the clock stands in for it.
+pub fn random_seed() -> u64 {
+    let mut bytes = [0; 8];
+    match getrandom::getrandom(&mut bytes) {
+        Ok(()) => u64::from_le_bytes(bytes),
+        Err(_) => SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64),
+    }
+}
@@ -0,0 +1,87 @@
+//! A tour broken down leg by leg, so the long hops stand out without recomputing anything.
+
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::geo::Metric;
+use crate::input::City;
+use crate::tour::TourType;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Leg {
+    /// The leg's place in the tour, from 1.
+    pub leg: usize,
+    pub from: usize,
+    pub to: usize,
+    pub from_x: f64,
+    pub from_y: f64,
+    pub to_x: f64,
+    pub to_y: f64,
+    pub distance: f64,
+    /// The length of the tour up to the end of this leg.
+    pub cumulative: f64,
+}
+
+/// The legs of `order`, measured between `cities` with `metric` and located at the coordinates
+/// of `shown`, which are the cities as given when they were projected to be measured. A closed
+/// `tour` ends with the leg back to the start.
+pub fn legs(order: &[usize], cities: &[City], shown: &[City], metric: Metric, tour: TourType) -> Vec<Leg> {
+    legs_measured(order, shown, tour, |a, b| metric.distance(&cities[a], &cities[b]))
+}
+
+/// The legs of `order`, measured with `distance` between cities by index, e.g. looked up in a
+/// matrix of given distances, and located at the coordinates of `shown`.
+pub fn legs_measured(order: &[usize], shown: &[City], tour: TourType, distance: impl Fn(usize, usize) -> f64) -> Vec<Leg> {
+    let mut cumulative = 0.0;
+    tour.legs(order).enumerate().map(|(i, (from, to))| {
+        let distance = distance(from, to);
+        cumulative += distance;
+        Leg {
+            leg: i + 1,
+            from,
+            to,
+            from_x: shown[from].x,
+            from_y: shown[from].y,
+            to_x: shown[to].x,
+            to_y: shown[to].y,
+            distance,
+            cumulative,
+        }
+    }).collect()
+}
+
+/// Writes `legs` to `writer` as JSON, or as csv if not `json`.
+pub fn write<W: Write>(mut writer: W, json: bool, legs: &[Leg]) -> std::io::Result<()> {
+    if json {
+        let length = legs.last().map_or(0.0, |leg| leg.cumulative);
+        serde_json::to_writer_pretty(&mut writer, &json!({ "length": length, "legs": legs }))?;
+        return writer.write_all(b"\n");
+    }
+
+    let mut writer = csv::Writer::from_writer(writer);
+    for leg in legs {
+        writer.serialize(leg)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Path;
+
+    #[test]
+    fn route_legs_add_up_to_the_tour() {
+        let cities = vec![City::new(0.0, 0.0), City::new(3.0, 4.0), City::new(3.0, 0.0)];
+        let closed = legs(&[0, 2, 1], &cities, &cities, Metric::Euclidean, TourType::Closed);
+        let mut csv = Vec::new();
+        write(&mut csv, false, &closed).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().nth(2), Some("2,2,1,3.0,0.0,3.0,4.0,4.0,7.0"));
+
+        assert_eq!(closed.iter().map(|leg| (leg.from, leg.to, leg.distance)).collect::<Vec<_>>(), vec![(0, 2, 3.0), (2, 1, 4.0), (1, 0, 5.0)]);
+        assert_eq!(closed.last().unwrap().cumulative, 1.0 / Path::calculate_fitness(&[0usize, 2, 1], &cities, Metric::Euclidean, TourType::Closed));
+        assert_eq!(legs(&[0, 2, 1], &cities, &cities, Metric::Euclidean, TourType::Open).len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+//! Rhai scripts supplying a custom objective and per-generation hooks.
+//!
+//! A script may define `fitness(order, cities)`, returning a number where higher is better, and
+//! `on_generation(generation, best_length, order)`, which can return `false` to end the run.
+//! `order` is an array of city indices and `cities` a read-only list of the cities, each with an
+//! `x` and a `y`, that can be indexed, iterated over and measured with `len()`.
+//!
+//! A call that runs longer than the timeout is terminated; a `fitness` call that times out gives
+//! the tour the worst possible fitness.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Position, Scope, AST, INT};
+
+use crate::evaluator::{Evaluator, WORST_FITNESS};
+use crate::metrics::METRICS;
+use crate::{City, Path};
+
+// Checking the clock on every operation would slow scripts down noticeably
+const OPERATIONS_PER_CLOCK_CHECK: u64 = 1024;
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    cities: Cities,
+    timeout: Duration,
+    call_started: Arc<Mutex<Instant>>,
+}
+
+impl Script {
+    /// Compiles the script at `path`, which may spend up to `timeout` on each call.
+    pub fn load(path: PathBuf, cities: &[City], timeout: Duration) -> Result<Script, Box<EvalAltResult>> {
+        let call_started = Arc::new(Mutex::new(Instant::now()));
+        let engine = Script::engine(timeout, call_started.clone());
+        let ast = engine.compile_file(path)?;
+
+        Ok(Script { engine, ast, cities: Cities(Arc::new(cities.to_vec())), timeout, call_started })
+    }
+
+    fn engine(timeout: Duration, call_started: Arc<Mutex<Instant>>) -> Engine {
+        let mut engine = Engine::new();
+        engine.register_type_with_name::<City>("City")
+            .register_get("x", |city: &mut City| city.x)
+            .register_get("y", |city: &mut City| city.y)
+            .register_type_with_name::<Cities>("Cities")
+            .register_indexer_get(|cities: &mut Cities, index: INT| {
+                usize::try_from(index).ok()
+                    .and_then(|i| cities.0.get(i).cloned())
+                    .ok_or_else(|| Box::new(EvalAltResult::ErrorArrayBounds(cities.0.len(), index, Position::NONE)))
+            })
+            .register_fn("len", |cities: &mut Cities| cities.0.len() as INT)
+            .register_iterator::<Cities>();
+        engine.on_progress(move |operations| {
+            let overdue = operations % OPERATIONS_PER_CLOCK_CHECK == 0 && call_started.lock().unwrap_or_else(PoisonError::into_inner).elapsed() > timeout;
+            overdue.then_some(Dynamic::UNIT)
+        });
+        engine
+    }
+
+    pub fn defines(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    fn start_call(&self) {
+        *self.call_started.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    /// Calls the script's `on_generation` hook, returning whether it wants the run to go on or
+    /// the error it failed with, which the caller can carry on past.
+    pub fn on_generation(&mut self, generation: usize, fittest: &Path) -> Result<ControlFlow<()>, Box<EvalAltResult>> {
+        self.start_call();
+        let result = self.engine.call_fn::<Dynamic>(
+            &mut Scope::new(),
+            &self.ast,
+            "on_generation",
+            (generation as i64, 1.0 / fittest.fitness, order_array(&fittest.order)),
+        );
+
+        Ok(match result? {
+            keep_going if keep_going.as_bool() == Ok(false) => ControlFlow::Break(()),
+            _ => ControlFlow::Continue(()),
+        })
+    }
+}
+
+impl Clone for Script {
+    fn clone(&self) -> Script {
+        let call_started = Arc::new(Mutex::new(Instant::now()));
+        Script {
+            engine: Script::engine(self.timeout, call_started.clone()),
+            ast: self.ast.clone(),
+            cities: self.cities.clone(),
+            timeout: self.timeout,
+            call_started,
+        }
+    }
+}
+
+impl Evaluator for Script {
+    fn evaluate(&mut self, orders: &[&[usize]]) -> Result<Vec<f64>, Box<dyn Error>> {
+        let mut scope = Scope::new();
+
+        orders.iter().map(|order| {
+            self.start_call();
+            let fitness = match self.engine.call_fn::<Dynamic>(
+                &mut scope,
+                &self.ast,
+                "fitness",
+                (order_array(order), self.cities.clone()),
+            ) {
+                Ok(fitness) => fitness,
+                Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => {
+                    METRICS.record_timeout();
+                    return Ok(WORST_FITNESS);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            fitness.as_float()
+                .or_else(|_| fitness.as_int().map(|i| i as f64))
+                .map_err(|t| format!("fitness returned {} instead of a number", t).into())
+        }).collect()
+    }
+}
+
+/// The cities as scripts see them: passed to every fitness call without being copied, and with
+/// nothing to change them through.
+#[derive(Clone)]
+struct Cities(Arc<Vec<City>>);
+
+impl IntoIterator for Cities {
+    type Item = City;
+    type IntoIter = std::vec::IntoIter<City>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Vec::clone(&self.0).into_iter()
+    }
+}
+
+fn order_array(order: &[usize]) -> Array {
+    order.iter().map(|&o| Dynamic::from_int(o as i64)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(name: &str, source: &str, timeout: Duration) -> Script {
+        let path = std::env::temp_dir().join(format!("wasi-genetic-{}-{}.rhai", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        let cities = [City::new(0.0, 0.0), City::new(3.0, 4.0), City::new(3.0, 0.0)];
+        let script = Script::load(path.clone(), &cities, timeout).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        script
+    }
+
+    #[test]
+    fn fitness_scores_tours_from_their_order_and_the_cities() {
+        let source = "fn fitness(order, cities) { cities[order[0]].x + order.len() }\n";
+        let mut script = script("fitness", source, Duration::from_secs(5));
+        assert!(script.defines("fitness") && !script.defines("on_generation"));
+        assert_eq!(script.evaluate(&[&[1, 0, 2], &[2, 1, 0], &[0, 2, 1]]).unwrap(), vec![6.0, 6.0, 3.0]);
+
+        let source = "fn fitness(order, cities) { let sum = 0.0; for city in cities { sum += city.y } sum + cities.len() }\n";
+        assert_eq!(self::script("iterating", source, Duration::from_secs(5)).evaluate(&[&[0, 1, 2]]).unwrap(), vec![7.0]);
+
+        // Every call gets the same cities, so no script can change them
+        let mut changing = self::script("changing", "fn fitness(order, cities) { cities[0].x = 100.0; 1.0 }\n", Duration::from_secs(5));
+        assert!(changing.evaluate(&[&[0, 1, 2]]).is_err());
+        assert!(self::script("beyond", "fn fitness(order, cities) { cities[3].x }\n", Duration::from_secs(5)).evaluate(&[&[0, 1, 2]]).is_err());
+
+        let mut wrong = self::script("wrong", "fn fitness(order, cities) { \"short\" }\n", Duration::from_secs(5));
+        assert!(wrong.evaluate(&[&[0, 1, 2]]).is_err());
+    }
+
+    #[test]
+    fn on_generation_can_end_the_run() {
+        let source = "fn on_generation(generation, best_length, order) { generation < 3 && order.len() == 3 }\n";
+        let mut script = script("hook", source, Duration::from_secs(5));
+        let fittest = Path { fitness: 0.1, order: vec![0, 1, 2] };
+        assert_eq!(script.on_generation(2, &fittest).unwrap(), ControlFlow::Continue(()));
+        assert_eq!(script.on_generation(3, &fittest).unwrap(), ControlFlow::Break(()));
+    }
+
+    #[test]
+    fn a_call_that_runs_too_long_gives_the_worst_fitness() {
+        let source = "fn fitness(order, cities) { loop {} }\nfn on_generation(generation, best_length, order) { loop {} }\n";
+        let mut script = script("timeout", source, Duration::from_millis(50));
+        let timeouts = METRICS.timeouts();
+        let started = Instant::now();
+        assert_eq!(script.evaluate(&[&[0, 1, 2]]).unwrap(), vec![WORST_FITNESS]);
+        assert!(METRICS.timeouts() > timeouts);
+        // A hook that times out fails, for the caller to carry on past
+        assert!(script.clone().on_generation(1, &Path { fitness: 0.1, order: vec![0, 1, 2] }).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,328 @@
+//! How the breeding pool is picked from a generation. Truncation, the default, takes the
+//! fittest outright; the other schemes give weaker paths some chance to breed, trading speed
+//! of convergence for diversity.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{Rng, RngCore};
+
+/// Picks the paths that breed.
+pub trait Selection: Send + Sync {
+    /// The ranks of `count` paths to breed out of a generation whose fitness, fittest first, is
+    /// `fitness`. A rank may come up more than once.
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize>;
+
+    /// Told before each generation how far through the run it is, from 0 at the start to 1
+    /// at the last generation, for schemes that change as the run goes on.
+    fn set_progress(&mut self, _progress: f64) {}
+}
+
+/// The `count` fittest, drawing nothing from the generator.
+pub struct Truncation;
+
+impl Selection for Truncation {
+    fn select(&self, _: &[f64], count: usize, _: &mut dyn RngCore) -> Vec<usize> {
+        (0..count).collect()
+    }
+}
+
+/// The fittest of `size` paths drawn at random, once for each path to breed. The larger the
+/// tournament, the harder the pressure towards the fittest; a size of 1 picks at random.
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Selection for Tournament {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        (0..count)
+            .map(|_| (0..self.size.max(1)).map(|_| rng.gen_range(0, fitness.len())).min().expect("at least one entrant"))
+            .collect()
+    }
+}
+
+/// Paths drawn with chances in proportion to their fitness, so for tours the shorter, the
+/// likelier. If no path has a positive fitness, e.g. all were quarantined, they're equally
+/// likely instead.
+pub struct Roulette;
+
+impl Selection for Roulette {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        match proportional(fitness) {
+            Some(cumulative) => spin(&cumulative, count, rng),
+            None => (0..count).map(|_| rng.gen_range(0, fitness.len())).collect(),
+        }
+    }
+}
+
+/// Stochastic universal sampling: the chances of roulette, but from a single spin of a wheel
+/// with `count` evenly spaced pointers rather than `count` spins of one. Every path is picked
+/// within one of its expected number of times, where roulette's luck could pick the fittest
+/// every time or never.
+pub struct Universal;
+
+impl Selection for Universal {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let cumulative = proportional(fitness)
+            .unwrap_or_else(|| (1..=fitness.len()).map(|n| n as f64).collect());
+        let total = cumulative[cumulative.len() - 1];
+        let step = total / count.max(1) as f64;
+        let start = rng.gen::<f64>() * step;
+
+        let mut rank = 0;
+        (0..count)
+            .map(|i| {
+                let pointer = start + i as f64 * step;
+                while rank < cumulative.len() - 1 && cumulative[rank] <= pointer {
+                    rank += 1;
+                }
+                rank
+            })
+            .collect()
+    }
+}
+
+/// Paths drawn with chances that fall in a straight line with their rank, whatever their
+/// fitness, from `pressure` times the average for the fittest down to 2 - `pressure` times it
+/// for the least fit. However little the lengths of good tours differ, the better ones keep
+/// their edge. A pressure of 1 picks at random, and 2, the most, never picks the least fit.
+pub struct Rank {
+    pub pressure: f64,
+}
+
+impl Selection for Rank {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let last = fitness.len().saturating_sub(1).max(1) as f64;
+        let pressure = self.pressure.clamp(1.0, 2.0);
+        let mut total = 0.0;
+        let cumulative: Vec<f64> = (0..fitness.len())
+            .map(|rank| {
+                total += pressure - 2.0 * (pressure - 1.0) * rank as f64 / last;
+                total
+            })
+            .collect();
+
+        spin(&cumulative, count, rng)
+    }
+}
+
+/// Paths drawn with chances that fall exponentially with how much less fit than the fittest
+/// they are, a path whose fitness is `1 - t` of the fittest's being `1 / e` times as likely at
+/// a temperature of `t`. The temperature cools geometrically from `start` at the start of the
+/// run to `end` at its end: hot, every path breeds about as often, for exploration, and cold,
+/// the fittest all but always do, for exploitation. If no path has a positive fitness, they're
+/// equally likely.
+pub struct Boltzmann {
+    pub start: f64,
+    pub end: f64,
+    progress: f64,
+}
+
+impl Boltzmann {
+    /// Cooling from `start` to `end`, which have to be positive.
+    pub fn new(start: f64, end: f64) -> Boltzmann {
+        Boltzmann { start, end, progress: 0.0 }
+    }
+
+    /// The temperature so far through the run.
+    pub fn temperature(&self) -> f64 {
+        self.start * (self.end / self.start).powf(self.progress)
+    }
+}
+
+impl Selection for Boltzmann {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        let fittest = fitness.iter().copied().filter(|f| f.is_finite()).fold(0.0, f64::max);
+        if fittest <= 0.0 {
+            return (0..count).map(|_| rng.gen_range(0, fitness.len())).collect();
+        }
+
+        let temperature = self.temperature();
+        let mut total = 0.0;
+        let cumulative: Vec<f64> = fitness.iter()
+            .map(|&f| {
+                if f.is_finite() && f > 0.0 {
+                    total += ((f / fittest - 1.0) / temperature).exp();
+                }
+                total
+            })
+            .collect();
+
+        spin(&cumulative, count, rng)
+    }
+
+    fn set_progress(&mut self, progress: f64) {
+        self.progress = progress.clamp(0.0, 1.0);
+    }
+}
+
+/// The running totals of `fitness`, counting what isn't positive and finite as none, unless
+/// that leaves no total.
+fn proportional(fitness: &[f64]) -> Option<Vec<f64>> {
+    let mut total = 0.0;
+    let cumulative: Vec<f64> = fitness.iter()
+        .map(|&f| {
+            total += if f.is_finite() { f.max(0.0) } else { 0.0 };
+            total
+        })
+        .collect();
+
+    (total > 0.0 && total.is_finite()).then_some(cumulative)
+}
+
+/// `count` ranks drawn with chances in proportion to their share of the running totals
+/// `cumulative`, the last of which has to be positive.
+fn spin(cumulative: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+    let total = cumulative[cumulative.len() - 1];
+    (0..count)
+        .map(|_| {
+            let spin = rng.gen::<f64>() * total;
+            cumulative.partition_point(|&sum| sum <= spin).min(cumulative.len() - 1)
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Truncation,
+    Tournament,
+    Roulette,
+    Rank,
+    Universal,
+    Boltzmann,
+}
+
+impl Kind {
+    /// The scheme, with the parameters it takes.
+    pub fn build(self, tournament_size: usize, pressure: f64, temperatures: (f64, f64)) -> Box<dyn Selection> {
+        match self {
+            Kind::Truncation => Box::new(Truncation),
+            Kind::Tournament => Box::new(Tournament { size: tournament_size }),
+            Kind::Roulette => Box::new(Roulette),
+            Kind::Rank => Box::new(Rank { pressure }),
+            Kind::Universal => Box::new(Universal),
+            Kind::Boltzmann => Box::new(Boltzmann::new(temperatures.0, temperatures.1)),
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kind, String> {
+        match s {
+            "truncation" => Ok(Kind::Truncation),
+            "tournament" => Ok(Kind::Tournament),
+            "roulette" => Ok(Kind::Roulette),
+            "rank" => Ok(Kind::Rank),
+            "sus" => Ok(Kind::Universal),
+            "boltzmann" => Ok(Kind::Boltzmann),
+            _ => Err(format!("unknown selection {:?}, expected truncation, tournament, roulette, sus, rank or boltzmann", s)),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::Truncation => "truncation",
+            Kind::Tournament => "tournament",
+            Kind::Roulette => "roulette",
+            Kind::Rank => "rank",
+            Kind::Universal => "sus",
+            Kind::Boltzmann => "boltzmann",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::rng::{RngAlgorithm, SeededRng};
+    use crate::testing::{is_permutation, simulation, twelve_cities};
+
+    #[test]
+    fn tournament_selection_breeds_the_same_whether_spilled_or_not() {
+        let fitness = [5.0, 4.0, 3.0, 2.0, 1.0];
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let picks = Tournament { size: 3 }.select(&fitness, 1000, &mut rng);
+        let wins = |rank| picks.iter().filter(|&&pick| pick == rank).count();
+        assert_eq!((0..5).map(wins).sum::<usize>(), 1000);
+        assert!(wins(0) > wins(2) && wins(2) > wins(4) && wins(4) > 0);
+
+        let solve = |max_memory| {
+            let mut sim = simulation(twelve_cities(), 40);
+            sim.set_selection(Kind::Tournament.build(2, 1.5, (1.0, 0.01)));
+            sim.set_max_memory(max_memory);
+            sim.set_verify(true);
+            let fittest = sim.solve(|_| ControlFlow::Continue(())).unwrap().order;
+            (fittest, sim.elites(30).unwrap())
+        };
+
+        let (fittest, elites) = solve(usize::MAX);
+        assert!(is_permutation(&fittest, 12));
+        assert_eq!((fittest, elites), solve(1 << 10));
+    }
+
+    #[test]
+    fn roulette_selection_favours_the_fittest_in_proportion() {
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let picks = Roulette.select(&[3.0, 1.0, 0.0], 4000, &mut rng);
+        let wins = |rank| picks.iter().filter(|&&pick| pick == rank).count();
+        assert_eq!(wins(2), 0, "no fitness, no chance");
+        assert!((2800..3200).contains(&wins(0)), "{} of 4000 instead of about 3000", wins(0));
+
+        let picks = Roulette.select(&[0.0, 0.0], 100, &mut rng);
+        assert!(picks.contains(&0) && picks.contains(&1));
+    }
+
+    #[test]
+    fn universal_sampling_picks_each_path_within_one_of_its_share() {
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        for _ in 0..100 {
+            let picks = Universal.select(&[3.0, 1.5, 1.0, 0.5, 0.0], 12, &mut rng);
+            let wins = |rank| picks.iter().filter(|&&pick| pick == rank).count();
+            assert!((6..=7).contains(&wins(0)), "{:?}", picks);
+            assert!((3..=4).contains(&wins(1)), "{:?}", picks);
+            assert!((2..=3).contains(&wins(2)), "{:?}", picks);
+            assert!((0..=1).contains(&wins(3)), "{:?}", picks);
+            assert_eq!(wins(4), 0, "no fitness, no chance");
+        }
+
+        assert_eq!(Universal.select(&[0.0, 0.0], 4, &mut rng), [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn boltzmann_selection_narrows_to_the_fittest_as_it_cools() {
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let fitness = [1.0, 0.9, 0.8, 0.0];
+        let mut boltzmann = Boltzmann::new(10.0, 0.01);
+        let picks = boltzmann.select(&fitness, 3000, &mut rng);
+        let wins = |picks: &[usize], rank| picks.iter().filter(|&&pick| pick == rank).count();
+        assert_eq!(wins(&picks, 3), 0, "no fitness, no chance");
+        assert!((0..3).all(|rank| (900..1100).contains(&wins(&picks, rank))), "hot, about as likely");
+
+        boltzmann.set_progress(0.5);
+        assert!((boltzmann.temperature() - 0.316).abs() < 0.001);
+        boltzmann.set_progress(1.0);
+        let picks = boltzmann.select(&fitness, 3000, &mut rng);
+        assert!(wins(&picks, 0) > 2990, "cold, all but always the fittest");
+    }
+
+    #[test]
+    fn rank_selection_favours_the_fittest_however_close_their_fitness() {
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let fitness = [1.002, 1.001, 1.0];
+        let picks = Rank { pressure: 2.0 }.select(&fitness, 4000, &mut rng);
+        let wins = |rank| picks.iter().filter(|&&pick| pick == rank).count();
+        assert_eq!(wins(2), 0, "the most pressure never picks the least fit");
+        assert!((2500..2833).contains(&wins(0)), "{} of 4000 instead of about 2667", wins(0));
+
+        let picks = Rank { pressure: 1.0 }.select(&fitness, 3000, &mut rng);
+        let wins = |rank| picks.iter().filter(|&&pick| pick == rank).count();
+        assert!((0..3).all(|rank| (900..1100).contains(&wins(rank))));
+        assert_eq!(Rank { pressure: 1.5 }.select(&[1.0], 3, &mut rng), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,91 @@
+//! A single run to its end, as the command line makes it: the best tour found and what's worth
+//! saying about how it was found.
+
+use std::ops::ControlFlow;
+
+use crate::barrier::Barriers;
+use crate::events::Notice;
+use crate::metrics::METRICS;
+use crate::{Error, Path, Report, Simulation};
+
+/// The best tour a run found, and what went wrong or is worth knowing about it.
+#[derive(Clone)]
+pub struct Solved {
+    pub fittest: Path,
+    /// Paths given the worst fitness for a NaN or infinite one.
+    pub quarantined: usize,
+    /// Evaluations that timed out, over the whole process.
+    pub timeouts: u64,
+    /// Legs of the best tour that cost the penalty for legs without a cost, if there was one.
+    pub missing_legs: Option<usize>,
+    /// Times the best tour enters an avoided zone, if there were any.
+    pub crossings: Option<usize>,
+}
+
+/// Runs `sim` to its end with `run_with`, calling `on_generation` after each generation, and
+/// counts the legs of the best tour that cost `penalty` or more and its crossings into
+/// `barriers`, for whichever is given. A run that fails ends there, with its error.
+pub fn solve(
+    sim: &mut Simulation,
+    penalty: Option<f64>,
+    barriers: Option<&Barriers>,
+    on_generation: impl FnMut(&Report) -> ControlFlow<()>,
+) -> Result<Solved, Error> {
+    sim.run_with(on_generation)?;
+    let fittest = sim.best().cloned().expect("a run has a best path");
+    let tour_type = sim.tour_type();
+    Ok(Solved {
+        quarantined: sim.quarantined(),
+        timeouts: METRICS.timeouts(),
+        missing_legs: penalty.map(|penalty| tour_type.legs(fittest.order()).filter(|&(a, b)| sim.distance(a, b) >= penalty).count()),
+        crossings: barriers.map(|barriers| barriers.tour_crossings(fittest.order(), sim.cities(), tour_type)),
+        fittest,
+    })
+}
+
+impl Solved {
+    /// What the run has to say about its best tour: warnings of paths quarantined and
+    /// evaluations timed out, then how many legs without a cost it takes and how often it enters
+    /// avoided zones.
+    pub fn notices(&self) -> Vec<Notice> {
+        let mut notices = Vec::new();
+        if self.quarantined > 0 {
+            notices.push(Notice::Warning(format!("quarantined {} paths with a non-finite fitness", self.quarantined)));
+        }
+        if self.timeouts > 0 {
+            notices.push(Notice::Warning(format!("{} evaluations timed out and were given the worst fitness", self.timeouts)));
+        }
+        if let Some(missing) = self.missing_legs {
+            notices.push(Notice::Info(format!("the best tour takes {} legs that the costs leave out", missing)));
+        }
+        notices.extend(self.crossings.map(|crossings| Notice::Info(match crossings {
+            0 => "the best tour keeps out of every avoided zone".to_string(),
+            crossings => format!("the best tour enters avoided zones {} times", crossings),
+        })));
+        notices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{is_permutation, simulation, twelve_cities};
+
+    #[test]
+    fn a_solved_run_counts_the_legs_its_best_tour_takes_at_the_penalty() {
+        let mut sim = simulation(twelve_cities(), 10);
+        let mut generations = 0;
+        let solved = solve(&mut sim, None, None, |_| {
+            generations += 1;
+            ControlFlow::Continue(())
+        }).unwrap();
+        assert_eq!(generations, 10);
+        assert!(is_permutation(solved.fittest.order(), 12));
+        assert_eq!((solved.missing_legs, solved.crossings), (None, None));
+
+        let mut sim = simulation(twelve_cities(), 10);
+        let solved = solve(&mut sim, Some(f64::MAX), None, |_| ControlFlow::Continue(())).unwrap();
+        assert_eq!(solved.missing_legs, Some(0));
+        assert!(solved.notices().contains(&Notice::Info("the best tour takes 0 legs that the costs leave out".to_string())));
+    }
+}
@@ -0,0 +1,154 @@
+//! Population storage on disk, for populations too big to keep in memory.
+//!
+//! Paths are kept in a temporary file as fixed-size records of little-endian city indices, as
+//! wide as the population's, in the order their generation produced them. Only their fitness
+//! stays in memory.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::population::{Population, Stored, Width};
+use crate::Path;
+
+static SPILLS: AtomicUsize = AtomicUsize::new(0);
+
+pub struct Spill {
+    path: PathBuf,
+    file: File,
+    city_count: usize,
+    width: Width,
+    chunk_size: usize,
+    fitness: Vec<f64>,
+}
+
+impl Spill {
+    /// Creates an empty spill file for tours of `city_count` cities stored as `width` indices in
+    /// the temporary directory, to be filled and worked through `chunk_size` paths at a time.
+    pub fn create(city_count: usize, width: Width, chunk_size: usize) -> std::io::Result<Spill> {
+        let path = std::env::temp_dir().join(format!(
+            "wasi-genetic-{}-{}.spill",
+            std::process::id(),
+            SPILLS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+
+        Ok(Spill { path, file, city_count, width, chunk_size, fitness: Vec::new() })
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.fitness.len()
+    }
+
+    pub fn fitness(&self, index: usize) -> f64 {
+        self.fitness[index]
+    }
+
+    /// Forgets every stored path, ready for the next generation.
+    pub fn clear(&mut self) {
+        self.fitness.clear();
+    }
+
+    /// Stores `paths` after the ones already stored.
+    pub fn push(&mut self, paths: &Population) -> std::io::Result<()> {
+        debug_assert_eq!(paths.width(), self.width);
+        let mut records = Vec::with_capacity(paths.len() * self.record_size());
+        match paths.width() {
+            Width::U16 => encode_all::<u16>(paths, &mut records),
+            Width::U32 => encode_all::<u32>(paths, &mut records),
+        }
+
+        self.file.seek(SeekFrom::Start((self.len() * self.record_size()) as u64))?;
+        self.file.write_all(&records)?;
+        self.fitness.extend_from_slice(paths.fitness_values());
+
+        Ok(())
+    }
+
+    pub fn read(&mut self, index: usize) -> std::io::Result<Path> {
+        let mut record = vec![0; self.record_size()];
+        self.file.seek(SeekFrom::Start((index * self.record_size()) as u64))?;
+        self.file.read_exact(&mut record)?;
+
+        let order = record.chunks_exact(self.width.bytes()).map(decode).collect();
+        Ok(Path { fitness: self.fitness[index], order })
+    }
+
+    /// Overwrites the path stored at `index`.
+    pub fn replace(&mut self, index: usize, path: &Path) -> std::io::Result<()> {
+        let record: Vec<u8> = path.order.iter().flat_map(|&city| encode(city, self.width)).collect();
+        self.file.seek(SeekFrom::Start((index * self.record_size()) as u64))?;
+        self.file.write_all(&record)?;
+        self.fitness[index] = path.fitness;
+
+        Ok(())
+    }
+
+    /// Indices of the stored paths, fittest first. Paths of equal fitness keep their order.
+    pub fn ranked(&self) -> Vec<usize> {
+        let mut ranked: Vec<usize> = (0..self.len()).collect();
+        ranked.sort_by(|&a, &b| self.fitness[b].total_cmp(&self.fitness[a]));
+        ranked
+    }
+
+    /// Reopens the file for reading only, so that storing paths fails as it does on a disk
+    /// that's full or read-only.
+    #[cfg(test)]
+    pub fn make_read_only(&mut self) -> std::io::Result<()> {
+        self.file = File::open(&self.path)?;
+        Ok(())
+    }
+
+        fn record_size(&self) -> usize {
+        self.city_count * self.width.bytes()
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Appends the orders of `paths` to `records`.
+fn encode_all<G: Stored>(paths: &Population, records: &mut Vec<u8>) {
+    for (order, _) in paths.iter::<G>() {
+        records.extend(order.iter().flat_map(|&city| encode(city.index(), paths.width())));
+    }
+}
+
+/// `city` as the `width` little-endian bytes a record stores it in, which are the low bytes of
+/// its `u32`.
+fn encode(city: usize, width: Width) -> impl Iterator<Item = u8> {
+    IntoIterator::into_iter((city as u32).to_le_bytes()).take(width.bytes())
+}
+
+fn decode(bytes: &[u8]) -> usize {
+    let mut city = [0; 4];
+    city[..bytes.len()].copy_from_slice(bytes);
+    u32::from_le_bytes(city) as usize
+}
+
+/// Parses a size in bytes, optionally suffixed with K, M or G (powers of 1024).
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let (digits, unit) = match s.char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm')) | Some((i, 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g')) | Some((i, 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+
+    digits.trim().parse::<usize>().ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("{:?} isn't a size like 512M or 2G", s))
+}
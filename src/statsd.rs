@@ -0,0 +1,93 @@
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{Metrics, METRICS};
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sends best-length, generation-rate, evaluation-timeout and diversity gauges to a StatsD (or DogStatsD) agent at `addr`
+/// once a second from a background thread.
+pub fn start(addr: &str) -> std::io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(addr)?;
+    METRICS.enable();
+
+    thread::Builder::new().name("statsd".to_string()).spawn(move || {
+        let mut last_generations = METRICS.generations();
+        let mut last_tick = Instant::now();
+
+        loop {
+            thread::sleep(INTERVAL);
+
+            let generations = METRICS.generations();
+            let elapsed = last_tick.elapsed().as_secs_f64();
+            let rate = (generations - last_generations) as f64 / elapsed;
+            last_generations = generations;
+            last_tick = Instant::now();
+
+            let packet = packet(&METRICS, rate);
+            // Nothing may be listening yet; UDP is fire-and-forget anyway
+            let _ = socket.send(packet.as_bytes());
+        }
+    })?;
+
+    Ok(())
+}
+
+/// The gauges of `metrics` as one StatsD packet, a line each, with `rate` generations a second.
+/// The best length is left out until there is one.
+fn packet(metrics: &Metrics, rate: f64) -> String {
+    let diversity = metrics.diversity();
+    let mut gauges: Vec<(&str, f64)> = metrics.best_length().map(|best| ("best_length", best)).into_iter().collect();
+    gauges.extend([
+        ("generations_per_second", rate),
+        ("evaluation_timeouts", metrics.timeouts() as f64),
+        ("unique_tour_ratio", diversity.unique_ratio),
+        ("edge_overlap", diversity.edge_overlap),
+        ("position_entropy", diversity.position_entropy),
+    ]);
+    gauges.iter()
+        .map(|(name, value)| format!("wasi_genetic.{}:{}|g", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diversity::Diversity;
+
+    #[test]
+    fn gauges_are_sent_a_line_each_in_the_statsd_format() {
+        let metrics = Metrics::new();
+        assert!(packet(&metrics, 2.5).starts_with("wasi_genetic.generations_per_second:2.5|g\n"));
+
+        metrics.record_generation(12.5);
+        metrics.record_generation(13.0);
+        metrics.record_timeout();
+        metrics.record_diversity(&Diversity { unique_ratio: 0.5, edge_overlap: 0.25, position_entropy: 0.75 });
+        assert_eq!(
+            packet(&metrics, 40.0),
+            "wasi_genetic.best_length:12.5|g\n\
+             wasi_genetic.generations_per_second:40|g\n\
+             wasi_genetic.evaluation_timeouts:1|g\n\
+             wasi_genetic.unique_tour_ratio:0.5|g\n\
+             wasi_genetic.edge_overlap:0.25|g\n\
+             wasi_genetic.position_entropy:0.75|g"
+        );
+    }
+
+    #[test]
+    fn packets_reach_the_agent() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent.set_read_timeout(Some(INTERVAL * 5)).unwrap();
+        start(&agent.local_addr().unwrap().to_string()).unwrap();
+
+        let mut packet = [0; 1024];
+        let size = agent.recv(&mut packet).unwrap();
+        let packet = String::from_utf8_lossy(&packet[..size]);
+        assert!(packet.lines().all(|line| line.starts_with("wasi_genetic.") && line.ends_with("|g")), "{}", packet);
+        assert!(packet.contains("wasi_genetic.generations_per_second:"), "{}", packet);
+    }
+}
@@ -0,0 +1,141 @@
+//! Statistics describing a finished run.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// What ended a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    /// It ran for all of its generations.
+    Completed,
+    /// The best tour hadn't improved for this many generations.
+    Stagnated(usize),
+    /// A tour no longer than the target `length` was found in `generation`.
+    TargetReached { length: f64, generation: usize },
+    /// It ran for as long as it was allowed.
+    TimeLimit(Duration),
+    /// The caller, the REPL or a command stopped it.
+    Stopped,
+}
+
+impl Termination {
+    /// A word for it, as the event log records it.
+    pub fn reason(self) -> &'static str {
+        match self {
+            Termination::Completed => "completed",
+            Termination::Stagnated(_) => "stagnated",
+            Termination::TargetReached { .. } => "target_reached",
+            Termination::TimeLimit(_) => "time_limit",
+            Termination::Stopped => "stopped",
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Termination::Completed => write!(f, "completed every generation"),
+            Termination::Stagnated(generations) => write!(f, "stagnated, no better tour in {} generations", generations),
+            Termination::TargetReached { length, generation } => {
+                write!(f, "reached the target length of {} in generation {}", length, generation)
+            }
+            Termination::TimeLimit(limit) => write!(f, "ran out of its {:.3}s", limit.as_secs_f64()),
+            Termination::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub generations: usize,
+    pub termination: Termination,
+    pub evaluations: usize,
+    pub wall_time: Duration,
+    pub initial_length: f64,
+    pub final_length: f64,
+    /// Generation after which the final best path was first found, or `None` if it was already
+    /// in the initial population.
+    pub best_generation: Option<usize>,
+    pub best_found_after: Duration,
+    /// Length of the optimal tour, when known.
+    pub optimum: Option<f64>,
+    /// Length of the best tour before it was polished, if it was.
+    pub polished_from: Option<f64>,
+    /// How many of the required edges the final best tour takes, and how many there are, if
+    /// any were required.
+    pub required_edges: Option<(usize, usize)>,
+}
+
+impl Summary {
+    pub fn evaluations_per_second(&self) -> f64 {
+        self.evaluations as f64 / self.wall_time.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    /// How much shorter the final best tour is than the best initial one, as a fraction.
+    pub fn improvement(&self) -> f64 {
+        1.0 - self.final_length / self.initial_length
+    }
+
+    /// How much longer the final best tour is than the optimal one, as a fraction.
+    pub fn gap(&self) -> Option<f64> {
+        self.optimum.map(|optimum| self.final_length / optimum - 1.0)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "generations": self.generations,
+            "termination": self.termination.reason(),
+            "evaluations": self.evaluations,
+            "evaluations_per_second": self.evaluations_per_second(),
+            "wall_time_seconds": self.wall_time.as_secs_f64(),
+            "initial_length": self.initial_length,
+            "final_length": self.final_length,
+            "improvement": self.improvement(),
+            "best_generation": self.best_generation,
+            "best_found_after_seconds": self.best_found_after.as_secs_f64(),
+            "polished_from": self.polished_from,
+            "optimum": self.optimum,
+            "gap": self.gap(),
+            "required_edges": self.required_edges.map(|(taken, required)| json!({ "taken": taken, "required": required })),
+        })
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Summary:")?;
+        writeln!(f, "  generations       {}", self.generations)?;
+        writeln!(f, "  ended             {}", self.termination)?;
+        writeln!(f, "  evaluations       {} ({:.0}/s)", self.evaluations, self.evaluations_per_second())?;
+        writeln!(f, "  wall time         {:.3}s", self.wall_time.as_secs_f64())?;
+        writeln!(
+            f,
+            "  improvement       {:.2}% (from {:.3} to {:.3})",
+            self.improvement() * 100.0, self.initial_length, self.final_length
+        )?;
+        match self.best_generation {
+            Some(generation) => write!(
+                f,
+                "  best found after  {:.3}s (generation {})",
+                self.best_found_after.as_secs_f64(), generation
+            )?,
+            None => write!(f, "  best found after  {:.3}s (initial population)", self.best_found_after.as_secs_f64())?,
+        }
+        if let Some(polished_from) = self.polished_from {
+            write!(
+                f,
+                "\n  polishing         {:.2}% shorter (from {:.3})",
+                (1.0 - self.final_length / polished_from) * 100.0, polished_from
+            )?;
+        }
+        if let Some((taken, required)) = self.required_edges {
+            write!(f, "\n  required edges    {} of {} taken", taken, required)?;
+        }
+        match (self.optimum, self.gap()) {
+            (Some(optimum), Some(gap)) => write!(f, "\n  gap to optimum    {:.2}% (optimum {})", gap * 100.0, optimum),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Fixtures shared by the tests of more than one module.
+
+use proptest::prelude::*;
+
+use crate::population::Gene;
+use crate::rng::{RngAlgorithm, SeededRng};
+use crate::{City, Path, Simulation};
+
+/// Twelve cities scattered over a 5 by 11 grid, few enough for a test to solve them many times
+/// over and enough for a run to have something to do.
+pub fn twelve_cities() -> Vec<City> {
+    (0..12).map(|i| City::new((i * 7 % 5) as f64, (i * 3 % 11) as f64)).collect()
+}
+
+/// The run most tests start from: 30 paths through `cities` for `generations` generations at
+/// middling rates, drawn from ChaCha seeded with 42.
+pub fn simulation(cities: Vec<City>, generations: usize) -> Simulation {
+    Simulation::new(30, cities, generations, 0.4, 0.05, 0.3, SeededRng::new(RngAlgorithm::ChaCha, 42)).unwrap()
+}
+
+pub fn is_permutation<G: Gene>(order: &[G], len: usize) -> bool {
+    Path::is_tour(order, len) && order.len() == len
+}
+
+pub fn permutation(max_len: usize) -> impl Strategy<Value = Vec<usize>> {
+    (1..max_len).prop_flat_map(|len| Just((0..len).collect::<Vec<usize>>()).prop_shuffle())
+}
+
+/// A permutation as a population stores it.
+pub fn genes(order: &[usize]) -> Vec<u16> {
+    order.iter().map(|&city| city as u16).collect()
+}
@@ -0,0 +1,73 @@
+//! Whether a tour returns to where it started. A closed tour, the travelling salesman's, pays
+//! for the leg from its last city back to its first; an open one is a path that ends at its
+//! last city.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::population::Gene;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TourType {
+    #[default]
+    Closed,
+    Open,
+}
+
+impl TourType {
+    /// The legs of `order` as the cities they join, ending with the one back to the start if
+    /// the tour is closed.
+    pub fn legs<G: Gene>(self, order: &[G]) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let back = match (self, order.first(), order.last()) {
+            (TourType::Closed, Some(&first), Some(&last)) if order.len() > 1 => Some((last.index(), first.index())),
+            _ => None,
+        };
+        order.windows(2).map(|leg| (leg[0].index(), leg[1].index())).chain(back)
+    }
+
+    /// `order` written the same way as every other order that's the same tour, taking the same
+    /// legs: a closed one from its lowest city, and in the direction that makes it lowest unless
+    /// the tour is `asymmetric`, when the direction matters.
+    pub fn canonical<G: Gene>(self, order: &[G], asymmetric: bool) -> Vec<usize> {
+        let mut forward: Vec<usize> = order.iter().map(|city| city.index()).collect();
+        if self == TourType::Closed {
+            let lowest = forward.iter().enumerate().min_by_key(|&(_, &city)| city).map_or(0, |(i, _)| i);
+            forward.rotate_left(lowest);
+        }
+        if asymmetric {
+            return forward;
+        }
+
+        let mut backward = forward.clone();
+        match self {
+            // Still from the lowest city
+            TourType::Closed if !backward.is_empty() => backward[1..].reverse(),
+            _ => backward.reverse(),
+        }
+        forward.min(backward)
+    }
+}
+
+impl FromStr for TourType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TourType, String> {
+        match s {
+            "closed" => Ok(TourType::Closed),
+            "open" => Ok(TourType::Open),
+            _ => Err(format!("unknown tour type {:?}, expected closed or open", s)),
+        }
+    }
+}
+
+impl fmt::Display for TourType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TourType::Closed => "closed",
+            TourType::Open => "open",
+        })
+    }
+}
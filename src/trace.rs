@@ -0,0 +1,314 @@
+//! Span recording for runs, generations and their phases.
+//!
+//! With the `otel` feature the spans are exported in batches to an OpenTelemetry
+//! collector over OTLP/HTTP (JSON encoding). Without it every call compiles down
+//! to a no-op, so the simulation can be instrumented unconditionally.
+
+#[cfg(feature = "otel")]
+pub use self::otlp::{Span, Tracer};
+
+#[cfg(not(feature = "otel"))]
+use crate::events::Notice;
+
+#[cfg(not(feature = "otel"))]
+pub struct Span;
+
+#[cfg(not(feature = "otel"))]
+pub struct Tracer;
+
+#[cfg(not(feature = "otel"))]
+impl Tracer {
+    pub fn disabled() -> Tracer {
+        Tracer
+    }
+
+    pub fn start(&self, _name: &'static str, _parent: Option<&Span>) -> Span {
+        Span
+    }
+
+    pub fn end(&mut self, _span: Span) {}
+
+    pub fn enabled(&self) -> bool {
+        false
+    }
+
+    pub fn shutdown(&mut self) {}
+
+    pub fn take_warnings(&mut self) -> Vec<Notice> {
+        Vec::new()
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+impl Span {
+    pub fn set_attribute(&mut self, _key: &'static str, _value: f64) {}
+}
+
+#[cfg(feature = "otel")]
+mod otlp {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::mpsc::{channel, Sender};
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::thread::{self, JoinHandle};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use rand::{thread_rng, Rng};
+    use serde_json::{json, Value};
+
+    use crate::events::Notice;
+    use crate::Error;
+
+    const BATCH_SIZE: usize = 512;
+
+    pub struct Span {
+        trace_id: u128,
+        span_id: u64,
+        parent_id: Option<u64>,
+        name: &'static str,
+        start_ns: u128,
+        attributes: Vec<(&'static str, f64)>,
+    }
+
+    impl Span {
+        pub fn set_attribute(&mut self, key: &'static str, value: f64) {
+            self.attributes.push((key, value));
+        }
+
+        fn to_json(&self, end_ns: u128) -> Value {
+            let attributes: Vec<Value> = self.attributes.iter()
+                .map(|(key, value)| json!({ "key": key, "value": { "doubleValue": value } }))
+                .collect();
+
+            let mut span = json!({
+                "traceId": format!("{:032x}", self.trace_id),
+                "spanId": format!("{:016x}", self.span_id),
+                "name": self.name,
+                "kind": 1,
+                "startTimeUnixNano": self.start_ns.to_string(),
+                "endTimeUnixNano": end_ns.to_string(),
+                "attributes": attributes,
+            });
+            if let Some(parent) = self.parent_id {
+                span["parentSpanId"] = json!(format!("{:016x}", parent));
+            }
+
+            span
+        }
+    }
+
+    pub struct Tracer {
+        batch: Vec<Value>,
+        exporter: Option<(Sender<Vec<Value>>, JoinHandle<()>)>,
+        /// Warnings of batches the exporter couldn't send, until they're taken.
+        warnings: Arc<Mutex<Vec<Notice>>>,
+    }
+
+    impl Tracer {
+        pub fn disabled() -> Tracer {
+            Tracer { batch: Vec::new(), exporter: None, warnings: Arc::default() }
+        }
+
+        /// Creates a tracer exporting to an OTLP/HTTP collector, e.g. `http://localhost:4318`.
+        /// Spans are sent in plain text, so an `https://` endpoint is an error.
+        pub fn otlp(endpoint: &str) -> Result<Tracer, Error> {
+            if let Some((scheme, _)) = endpoint.split_once("://").filter(|(scheme, _)| *scheme != "http") {
+                return Err(Error::Parameter {
+                    name: "OTLP endpoint",
+                    reason: format!("{} needs an http:// collector, spans can't be exported over {}", endpoint, scheme),
+                });
+            }
+            let endpoint = endpoint.trim_start_matches("http://").trim_end_matches('/').to_string();
+            let (sender, receiver) = channel::<Vec<Value>>();
+            let warnings: Arc<Mutex<Vec<Notice>>> = Arc::default();
+
+            let failed = warnings.clone();
+            let handle = thread::spawn(move || {
+                for spans in receiver {
+                    if let Err(e) = export(&endpoint, spans) {
+                        let warning = Notice::Warning(format!("failed to export spans to {}: {}", endpoint, e));
+                        failed.lock().unwrap_or_else(PoisonError::into_inner).push(warning);
+                    }
+                }
+            });
+
+            Ok(Tracer { batch: Vec::new(), exporter: Some((sender, handle)), warnings })
+        }
+
+        /// The warnings of spans that couldn't be exported since they were last taken.
+        pub fn take_warnings(&mut self) -> Vec<Notice> {
+            std::mem::take(&mut *self.warnings.lock().unwrap_or_else(PoisonError::into_inner))
+        }
+
+        pub fn start(&self, name: &'static str, parent: Option<&Span>) -> Span {
+            let mut rng = thread_rng();
+            Span {
+                trace_id: parent.map(|p| p.trace_id).unwrap_or_else(|| rng.gen()),
+                span_id: rng.gen(),
+                parent_id: parent.map(|p| p.span_id),
+                name,
+                start_ns: now_ns(),
+                attributes: Vec::new(),
+            }
+        }
+
+        pub fn enabled(&self) -> bool {
+            self.exporter.is_some()
+        }
+
+        pub fn end(&mut self, span: Span) {
+            if self.exporter.is_none() {
+                return;
+            }
+
+            self.batch.push(span.to_json(now_ns()));
+            if self.batch.len() >= BATCH_SIZE {
+                self.flush();
+            }
+        }
+
+        /// Exports any buffered spans and waits for the exporter to finish.
+        pub fn shutdown(&mut self) {
+            self.flush();
+            if let Some((sender, handle)) = self.exporter.take() {
+                drop(sender);
+                let _ = handle.join();
+            }
+        }
+
+        fn flush(&mut self) {
+            if let Some((sender, _)) = &self.exporter {
+                if !self.batch.is_empty() {
+                    let _ = sender.send(std::mem::take(&mut self.batch));
+                }
+            }
+        }
+    }
+
+    fn now_ns() -> u128 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0)
+    }
+
+    fn export(host: &str, spans: Vec<Value>) -> std::io::Result<()> {
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": "wasi-genetic" } }
+                    ]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "wasi-genetic", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }]
+            }]
+        }).to_string();
+
+        let mut stream = TcpStream::connect(host)?;
+        write!(
+            stream,
+            "POST /v1/traces HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            host,
+            body.len(),
+            body
+        )?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let status = response.split_whitespace().nth(1).unwrap_or("");
+        if !status.starts_with('2') {
+            return Err(std::io::Error::other(format!("collector responded with {}", status)));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        /// Answers one request on a local port with `status`, returning the address and the
+        /// request's line and body.
+        fn collector(status: &'static str) -> (String, JoinHandle<(String, String)>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            let handle = thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = header.strip_prefix("Content-Length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+                (request_line, String::from_utf8(body).unwrap())
+            });
+            (addr, handle)
+        }
+
+        #[test]
+        fn spans_are_posted_to_the_collector_as_otlp_json() {
+            let (addr, collector) = collector("200 OK");
+            let mut tracer = Tracer::otlp(&format!("http://{}/", addr)).unwrap();
+            let run = tracer.start("run", None);
+            let mut generation = tracer.start("generation", Some(&run));
+            generation.set_attribute("best_length", 12.5);
+            let (trace_id, run_id) = (run.trace_id, run.span_id);
+            tracer.end(generation);
+            tracer.end(run);
+            tracer.shutdown();
+
+            let (request_line, body) = collector.join().unwrap();
+            assert_eq!(request_line, "POST /v1/traces HTTP/1.1\r\n");
+            let body: Value = serde_json::from_str(&body).unwrap();
+            let resource = &body["resourceSpans"][0];
+            assert_eq!(resource["resource"]["attributes"][0]["value"]["stringValue"], "wasi-genetic");
+
+            let spans = resource["scopeSpans"][0]["spans"].as_array().unwrap();
+            assert_eq!(spans.len(), 2);
+            let (generation, run) = (&spans[0], &spans[1]);
+            assert_eq!((&generation["name"], &run["name"]), (&json!("generation"), &json!("run")));
+            assert_eq!(generation["traceId"], format!("{:032x}", trace_id));
+            assert_eq!(run["traceId"], generation["traceId"]);
+            assert_eq!(generation["parentSpanId"], format!("{:016x}", run_id));
+            assert!(run.get("parentSpanId").is_none());
+            assert_eq!(generation["attributes"][0], json!({ "key": "best_length", "value": { "doubleValue": 12.5 } }));
+            assert!(tracer.take_warnings().is_empty());
+        }
+
+        #[test]
+        fn a_collector_refusing_spans_leaves_a_warning() {
+            let (addr, collector) = collector("500 Internal Server Error");
+            let mut tracer = Tracer::otlp(&addr).unwrap();
+            let span = tracer.start("run", None);
+            tracer.end(span);
+            tracer.shutdown();
+            collector.join().unwrap();
+
+            let warnings = tracer.take_warnings();
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].to_string().contains("collector responded with 500"), "{}", warnings[0]);
+        }
+
+        #[test]
+        fn only_plain_http_endpoints_are_taken() {
+            let error = Tracer::otlp("https://collector:4318").err().expect("an https endpoint");
+            assert!(matches!(error, Error::Parameter { name: "OTLP endpoint", .. }));
+            assert!(error.to_string().contains("https"), "{}", error);
+        }
+    }
+}
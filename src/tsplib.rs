@@ -0,0 +1,343 @@
+//! TSPLIB `.tsp` and `.atsp` files, the format the standard benchmark instances come in.
+//! Symmetric problems can be read if given by the coordinates of their nodes, with the EUC_2D,
+//! CEIL_2D, ATT or GEO edge weight types, or by an EXPLICIT matrix of edge weights in any of its
+//! formats, and asymmetric ones by a full matrix; the coordinates of an explicit problem's
+//! display data, if it has any, are kept for drawing.
+//!
+//! GEO coordinates are latitudes and longitudes in degrees and minutes, written as DDD.MM; they're
+//! read into longitudes (`x`) and latitudes (`y`) in degrees, as csvs give them. Legs are measured
+//! exactly rather than rounded to integers as TSPLIB does, so lengths differ from published ones
+//! by a fraction of a percent.
+
+use std::fmt;
+
+use crate::geo::{DistanceMatrix, Metric};
+use crate::input::{self, City};
+
+/// What a `.tsp` file describes.
+#[derive(Clone, Debug)]
+pub struct Problem {
+    pub name: Option<String>,
+    /// How the edge weight type says to measure legs between the cities' coordinates, unless
+    /// the `distances` are given.
+    pub metric: Metric,
+    /// In the order of their node numbers. Placeholders for explicit problems without
+    /// coordinates.
+    pub cities: Vec<City>,
+    /// The edge weights of an explicit problem.
+    pub distances: Option<DistanceMatrix>,
+}
+
+/// Why a `.tsp` file can't be read, and on which line, from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+    pub help: Option<&'static str>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+const SUPPORTED: &str = "only EUC_2D, CEIL_2D, ATT, GEO and EXPLICIT instances can be read";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Specification,
+    NodeCoords,
+    EdgeWeights,
+    DisplayData,
+    /// A section of data not needed to solve the problem, e.g. FIXED_EDGES_SECTION.
+    Skipped,
+}
+
+/// How a problem gives the weights of its edges.
+#[derive(Clone, Copy)]
+enum Weights {
+    Measured(Metric),
+    Explicit,
+}
+
+// The layouts of explicit edge weights: which of them are given (the full matrix, or the upper
+// or lower triangle with or without the diagonal) and whether row by row or column by column
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Triangle {
+    Full,
+    Upper,
+    Lower,
+}
+
+const FORMATS: &[(&str, Triangle, bool, bool)] = &[
+    ("FULL_MATRIX", Triangle::Full, true, true),
+    ("UPPER_ROW", Triangle::Upper, true, false),
+    ("LOWER_ROW", Triangle::Lower, true, false),
+    ("UPPER_DIAG_ROW", Triangle::Upper, true, true),
+    ("LOWER_DIAG_ROW", Triangle::Lower, true, true),
+    ("UPPER_COL", Triangle::Upper, false, false),
+    ("LOWER_COL", Triangle::Lower, false, false),
+    ("UPPER_DIAG_COL", Triangle::Upper, false, true),
+    ("LOWER_DIAG_COL", Triangle::Lower, false, true),
+];
+
+/// Reads a `.tsp` file.
+pub fn parse(source: &str) -> Result<Problem, ParseError> {
+    let mut name = None;
+    let mut dimension = None;
+    let mut edge_weight_type = None;
+    let mut edge_weight_format = None;
+    let mut nodes: Vec<(usize, usize, f64, f64)> = Vec::new();
+    let mut display: Vec<(usize, usize, f64, f64)> = Vec::new();
+    let mut weights: Vec<f64> = Vec::new();
+    let mut weights_line = 0;
+    // Where the file says it's an ATSP, whose weights are a full matrix
+    let mut asymmetric = None;
+    let mut section = Section::Specification;
+    let mut last = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message: String, help: Option<&'static str>| ParseError { line: line_number, message, help };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        last = line_number;
+
+        let numeric = line.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.');
+        if numeric && section == Section::EdgeWeights {
+            for weight in line.split_whitespace() {
+                let weight = weight.parse::<f64>().ok()
+                    .filter(|weight| weight.is_finite())
+                    .ok_or_else(|| error(format!("{:?} isn't an edge weight", weight), None))?;
+                weights.push(weight);
+            }
+            continue;
+        }
+        if numeric && matches!(section, Section::NodeCoords | Section::DisplayData) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let node = match fields[..] {
+                [number, x, y] => number.parse::<usize>().ok().zip(x.parse::<f64>().ok()).zip(y.parse::<f64>().ok()),
+                _ => None,
+            };
+            let ((number, x), y) = node
+                .filter(|((_, x), y)| x.is_finite() && y.is_finite())
+                .ok_or_else(|| error(format!("{:?} isn't a node", line), Some("a node is its number and two coordinates, e.g. 1 565.0 575.0")))?;
+            let node = (number, line_number, x, y);
+            if section == Section::NodeCoords { nodes.push(node) } else { display.push(node) }
+            continue;
+        }
+        if numeric && section == Section::Skipped {
+            continue;
+        }
+
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (line, ""),
+        };
+        match key {
+            "NAME" => name = Some(value.to_string()),
+            "TYPE" if value == "TSP" => {}
+            "TYPE" if value == "ATSP" => asymmetric = Some(line_number),
+            "TYPE" => return Err(error(format!("{} problems can't be solved, only TSP and ATSP ones", value), None)),
+            "COMMENT" | "NODE_COORD_TYPE" | "DISPLAY_DATA_TYPE" | "EDGE_DATA_FORMAT" => {}
+            "DIMENSION" => {
+                dimension = Some(value.parse::<usize>().map_err(|_| error(format!("{:?} isn't a number of nodes", value), None))?);
+            }
+            "EDGE_WEIGHT_TYPE" => {
+                edge_weight_type = Some(match value {
+                    "EUC_2D" | "CEIL_2D" => Weights::Measured(Metric::Euclidean),
+                    "ATT" => Weights::Measured(Metric::Att),
+                    "GEO" => Weights::Measured(Metric::Geo),
+                    "EXPLICIT" => Weights::Explicit,
+                    _ => return Err(error(format!("{} edge weights aren't supported", value), Some(SUPPORTED))),
+                });
+            }
+            "EDGE_WEIGHT_FORMAT" => {
+                let format = FORMATS.iter().find(|(name, ..)| *name == value).ok_or_else(|| {
+                    error(format!("{} edge weights can't be read", value), Some("explicit edge weights can be read as a full matrix or a triangle"))
+                })?;
+                edge_weight_format = Some(*format);
+            }
+            "NODE_COORD_SECTION" => section = Section::NodeCoords,
+            "EDGE_WEIGHT_SECTION" => {
+                section = Section::EdgeWeights;
+                weights_line = line_number;
+            }
+            "DISPLAY_DATA_SECTION" => section = Section::DisplayData,
+            "FIXED_EDGES_SECTION" | "TOUR_SECTION" => section = Section::Skipped,
+            "EOF" => break,
+            _ if key.ends_with("_SECTION") => return Err(error(format!("{} can't be read", key), Some(SUPPORTED))),
+            _ => return Err(error(format!("{:?} isn't a TSPLIB keyword", key), None)),
+        }
+    }
+
+    let weights_type = edge_weight_type.ok_or_else(|| ParseError { line: last, message: "there's no EDGE_WEIGHT_TYPE".to_string(), help: Some(SUPPORTED) })?;
+    let (metric, distances) = match weights_type {
+        Weights::Measured(_) if asymmetric.is_some() => {
+            return Err(ParseError { line: asymmetric.unwrap_or(last), message: "an ATSP needs EXPLICIT edge weights".to_string(), help: None });
+        }
+        Weights::Measured(metric) => (metric, None),
+        Weights::Explicit => {
+            let count = dimension.ok_or_else(|| ParseError { line: last, message: "there's no DIMENSION".to_string(), help: None })?;
+            let format = edge_weight_format.ok_or_else(|| ParseError {
+                line: weights_line,
+                message: "there's no EDGE_WEIGHT_FORMAT".to_string(),
+                help: Some("explicit edge weights can be read as a full matrix or a triangle"),
+            })?;
+            if let Some(line) = asymmetric.filter(|_| format.1 != Triangle::Full) {
+                return Err(ParseError { line, message: format!("an ATSP's edge weights can't be a {}", format.0), help: Some("only a FULL_MATRIX has the weights both ways") });
+            }
+            let rows = matrix(format, count, &weights).map_err(|message| ParseError { line: weights_line, message, help: None })?;
+            let distances = DistanceMatrix::from_rows(rows).map_err(|message| ParseError { line: weights_line, message, help: None })?;
+            (Metric::Euclidean, Some(distances))
+        }
+    };
+
+    if distances.is_some() && display.is_empty() {
+        let count = distances.as_ref().map_or(0, DistanceMatrix::len);
+        return Ok(Problem { name, metric, cities: input::placeholders(count), distances });
+    }
+    let nodes = if distances.is_some() { display } else { nodes };
+    let count = dimension.unwrap_or(nodes.len());
+    // More nodes than are given means one is missing, found without making room for as many as
+    // the DIMENSION says, which can be more than memory holds
+    if count > nodes.len() {
+        let mut numbers: Vec<usize> = nodes.iter().map(|&(number, ..)| number).filter(|&number| number > 0).collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        let missing = numbers.iter().zip(1..).find(|&(&number, expected)| number != expected).map_or(numbers.len() + 1, |(_, expected)| expected);
+        return Err(ParseError { line: last, message: format!("node {} is missing", missing), help: None });
+    }
+    let mut cities = vec![None; count];
+    for &(number, line, x, y) in &nodes {
+        let city = match metric {
+            Metric::Geo => City::new(degrees(y), degrees(x)),
+            _ => City::new(x, y),
+        };
+        match cities.get_mut(number.wrapping_sub(1)) {
+            Some(slot @ None) => *slot = Some(city),
+            Some(Some(_)) => return Err(ParseError { line, message: format!("node {} is given twice", number), help: None }),
+            None => {
+                return Err(ParseError {
+                    line,
+                    message: format!("node {} is outside 1 to {}", number, count),
+                    help: Some("nodes are numbered from 1 up to the DIMENSION"),
+                })
+            }
+        }
+    }
+
+    let cities = cities.into_iter()
+        .enumerate()
+        .map(|(i, city)| city.ok_or_else(|| ParseError { line: last, message: format!("node {} is missing", i + 1), help: None }))
+        .collect::<Result<_, _>>()?;
+    Ok(Problem { name, metric, cities, distances })
+}
+
+/// The rows of the full matrix of `count` cities that `weights` in `format` describe.
+fn matrix((name, triangle, by_row, diagonal): (&str, Triangle, bool, bool), count: usize, weights: &[f64]) -> Result<Vec<Vec<f64>>, String> {
+    // Column by column, the upper triangle is laid out as the lower one is row by row
+    let triangle = match (triangle, by_row) {
+        (Triangle::Upper, false) => Triangle::Lower,
+        (Triangle::Lower, false) => Triangle::Upper,
+        (triangle, _) => triangle,
+    };
+    // Counted rather than listed, so that a DIMENSION far beyond the weights given is caught
+    // before anything's made for it
+    let expected = match triangle {
+        Triangle::Full => count.checked_mul(count),
+        _ if diagonal => count.checked_add(1).and_then(|side| count.checked_mul(side)).map(|cells| cells / 2),
+        _ => count.checked_mul(count.saturating_sub(1)).map(|cells| cells / 2),
+    };
+    if expected != Some(weights.len()) {
+        let expected = expected.map_or_else(|| "more than can be counted".to_string(), |cells| cells.to_string());
+        return Err(format!("{} edge weights where a {} of {} nodes has {}", weights.len(), name, count, expected));
+    }
+
+    let mut rows = vec![vec![0.0; count]; count];
+    let cells = (0..count).flat_map(|a| {
+        let columns = match triangle {
+            Triangle::Full => 0..count,
+            Triangle::Upper => if diagonal { a..count } else { a + 1..count },
+            Triangle::Lower => if diagonal { 0..a + 1 } else { 0..a },
+        };
+        columns.map(move |b| (a, b))
+    });
+    for ((a, b), &weight) in cells.zip(weights) {
+        rows[a][b] = weight;
+        if triangle != Triangle::Full {
+            rows[b][a] = weight;
+        }
+    }
+    Ok(rows)
+}
+
+/// DDD.MM degrees and minutes in degrees.
+fn degrees(coordinate: f64) -> f64 {
+    let whole = coordinate.trunc();
+    whole + (coordinate - whole) * 100.0 / 60.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo::Metric;
+
+    #[test]
+    fn tsplib_files_are_read_with_their_metric() {
+        let ulysses16 = "NAME: ulysses16.tsp\nTYPE: TSP\nCOMMENT: Odyssey of Ulysses (Groetschel/Padberg)\n\
+                         DIMENSION: 16\nEDGE_WEIGHT_TYPE: GEO\nDISPLAY_DATA_TYPE: COORD_DISPLAY\nNODE_COORD_SECTION\n\
+                         1 38.24 20.42\n2 39.57 26.15\n3 40.56 25.32\n4 36.26 23.12\n5 33.48 10.54\n6 37.56 12.19\n\
+                         7 38.42 13.11\n8 37.52 20.44\n9 41.23 9.10\n10 41.17 13.05\n11 36.08 -5.21\n12 38.47 15.13\n\
+                         13 38.15 15.35\n14 37.51 15.17\n15 35.49 14.32\n16 39.36 19.56\nEOF\n";
+        let problem = parse(ulysses16).unwrap();
+        assert_eq!(problem.name.as_deref(), Some("ulysses16.tsp"));
+        assert_eq!(problem.metric, Metric::Geo);
+        assert_eq!(problem.cities.len(), 16);
+        // DDD.MM: 38 degrees 24 minutes north, 20 degrees 42 minutes east
+        assert!((problem.cities[0].x - 20.7).abs() < 1e-9 && (problem.cities[0].y - 38.4).abs() < 1e-9);
+
+        // TSPLIB rounds each of the optimal round trip's 16 legs up to the next integer
+        let optimal = [0, 13, 12, 11, 6, 5, 14, 4, 10, 8, 9, 15, 2, 1, 3, 7, 0];
+        let length: f64 = optimal.windows(2).map(|leg| Metric::Geo.distance(&problem.cities[leg[0]], &problem.cities[leg[1]])).sum();
+        assert!(length > 6859.0 - 16.0 && length <= 6859.0, "{}", length);
+
+        let att = parse("NAME : att2\nTYPE : TSP\nDIMENSION : 2\nEDGE_WEIGHT_TYPE : ATT\nNODE_COORD_SECTION\n2 30 40\n1 0 0\nEOF").unwrap();
+        assert_eq!(att.metric, Metric::Att);
+        assert_eq!(Metric::Att.distance(&att.cities[0], &att.cities[1]), (2500.0f64 / 10.0).sqrt());
+
+        let error = |source: &str| parse(source).unwrap_err();
+        assert_eq!(error("TYPE: TSP\nEDGE_WEIGHT_TYPE: EUC_3D\nNODE_COORD_SECTION\n1 2 3 4\n").line, 2);
+        assert_eq!(error("TYPE: HCP\n").message, "HCP problems can't be solved, only TSP and ATSP ones");
+        assert_eq!(error("DIMENSION: 3\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n2 1 1\n").message, "node 3 is missing");
+        assert_eq!(error("DIMENSION: 100000000000\nEDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n3 1 1\n").message, "node 2 is missing");
+        assert_eq!(error("EDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0 0\n1 1 1\n").line, 4);
+        assert_eq!(error("EDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_SECTION\n1 0\n").line, 3);
+    }
+
+    #[test]
+    fn explicit_edge_weights_are_read_as_distances() {
+        let upper = "NAME: tri\nTYPE: TSP\nDIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: UPPER_ROW\n\
+                     EDGE_WEIGHT_SECTION\n 4 9\n 2\nEOF\n";
+        let problem = parse(upper).unwrap();
+        let distances = problem.distances.unwrap();
+        assert_eq!((distances.get(0, 2), distances.get(2, 0), distances.get(1, 2), distances.get(1, 1)), (9.0, 9.0, 2.0, 0.0));
+        assert_eq!(problem.cities.len(), 3);
+        let lower_diagonal = parse(&upper.replace("UPPER_ROW", "LOWER_DIAG_ROW").replace(" 4 9\n 2", "0\n4 0\n9 2 0")).unwrap();
+        assert_eq!(lower_diagonal.distances.unwrap().get(2, 0), 9.0);
+        assert!(parse(&upper.replace(" 2\n", "")).unwrap_err().message.starts_with("2 edge weights"));
+        let huge = upper.replace("DIMENSION: 3", "DIMENSION: 200000").replace("UPPER_ROW", "FULL_MATRIX");
+        assert_eq!(parse(&huge).unwrap_err().message, "3 edge weights where a FULL_MATRIX of 200000 nodes has 40000000000");
+
+        let atsp = upper.replace("TYPE: TSP", "TYPE: ATSP").replace("UPPER_ROW", "FULL_MATRIX").replace(" 4 9\n 2", "0 4 9\n1 0 2\n9 9 0");
+        let distances = parse(&atsp).unwrap().distances.unwrap();
+        assert!(!distances.is_symmetric());
+        assert_eq!((distances.get(0, 1), distances.get(1, 0)), (4.0, 1.0));
+        assert_eq!(parse(&upper.replace("TYPE: TSP", "TYPE: ATSP")).unwrap_err().line, 2);
+    }
+}
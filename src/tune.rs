@@ -0,0 +1,417 @@
+//! A search for the GA parameters that solve an instance best, for `tune`. Each configuration
+//! tried is run from the same seeds, so configurations are compared on the same luck, and
+//! judged by its median length over them. The best one is written as a `--config` file.
+
+use std::fmt;
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::crossover::Crossover;
+use crate::mutation::Kind;
+use crate::{check_parameters, Error, Instant, Simulation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Search {
+    /// Configurations drawn at random from the space.
+    Random,
+    /// Every combination of the values given, in turn.
+    Grid,
+}
+
+impl FromStr for Search {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Search, String> {
+        match s {
+            "random" => Ok(Search::Random),
+            "grid" => Ok(Search::Grid),
+            _ => Err(format!("unknown search {:?}, expected random or grid", s)),
+        }
+    }
+}
+
+impl Search {
+    /// How many configurations the search tries unless told otherwise: 20 drawn at random, or
+    /// the whole grid.
+    pub fn trials(self, space: &Space) -> usize {
+        match self {
+            Search::Random => 20,
+            Search::Grid => space.grid().len(),
+        }
+    }
+}
+
+impl fmt::Display for Search {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Search::Random => "random",
+            Search::Grid => "grid",
+        })
+    }
+}
+
+/// The values each parameter is tried with. A random search takes numbers anywhere between
+/// the least and the greatest of them, and operators among them.
+#[derive(Clone, Debug)]
+pub struct Space {
+    pub population: Vec<usize>,
+    pub crossover_rate: Vec<f64>,
+    pub mutation_rate: Vec<f64>,
+    pub survival_rate: Vec<f64>,
+    pub crossover: Vec<Crossover>,
+    pub mutation: Vec<Kind>,
+}
+
+impl Space {
+    /// The values given for each parameter, or the default ones for a parameter given none.
+    pub fn given(
+        population: &[usize],
+        crossover_rate: &[f64],
+        mutation_rate: &[f64],
+        survival_rate: &[f64],
+        crossover: &[Crossover],
+        mutation: &[Kind],
+    ) -> Space {
+        fn given_or<T: Clone>(given: &[T], default: Vec<T>) -> Vec<T> {
+            if given.is_empty() {
+                default
+            } else {
+                given.to_vec()
+            }
+        }
+
+        let defaults = Space::default();
+        Space {
+            population: given_or(population, defaults.population),
+            crossover_rate: given_or(crossover_rate, defaults.crossover_rate),
+            mutation_rate: given_or(mutation_rate, defaults.mutation_rate),
+            survival_rate: given_or(survival_rate, defaults.survival_rate),
+            crossover: given_or(crossover, defaults.crossover),
+            mutation: given_or(mutation, defaults.mutation),
+        }
+    }
+
+    /// Fails on values no run can take, as [`check_parameters`] does: a population of 0, or a
+    /// rate outside 0 to 1. Combinations that can't run, such as a population too small to
+    /// breed at some crossover rate, are left to do worst of all.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.population.contains(&0) {
+            return Err(Error::Parameter { name: "population sizes to tune", reason: "a population of 0 can't evolve".to_string() });
+        }
+        let rates = [
+            ("crossover rates to tune", &self.crossover_rate),
+            ("mutation rates to tune", &self.mutation_rate),
+            ("survival rates to tune", &self.survival_rate),
+        ];
+        for (name, rates) in rates {
+            if let Some(rate) = rates.iter().find(|rate| !(0.0..=1.0).contains(*rate)) {
+                return Err(Error::Parameter { name, reason: format!("{} is not between 0 and 1", rate) });
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every parameter has a value to try.
+    pub fn is_complete(&self) -> bool {
+        !(self.population.is_empty()
+            || self.crossover_rate.is_empty()
+            || self.mutation_rate.is_empty()
+            || self.survival_rate.is_empty()
+            || self.crossover.is_empty()
+            || self.mutation.is_empty())
+    }
+
+    /// Every combination of the values, the operators varying fastest.
+    pub fn grid(&self) -> Vec<Config> {
+        let mut grid = Vec::new();
+        for &population in &self.population {
+            for &crossover_rate in &self.crossover_rate {
+                for &mutation_rate in &self.mutation_rate {
+                    for &survival_rate in &self.survival_rate {
+                        for &crossover in &self.crossover {
+                            for &mutation in &self.mutation {
+                                grid.push(Config { population, crossover_rate, mutation_rate, survival_rate, crossover, mutation });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// A configuration drawn at random.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Config {
+        let (least, greatest) = bounds(self.population.iter().map(|&population| population as f64));
+        let population = rng.gen_range(least as usize, greatest as usize + 1);
+        let mut rate = |values: &[f64]| {
+            let (least, greatest) = bounds(values.iter().copied());
+            if least < greatest { rng.gen_range(least, greatest) } else { least }
+        };
+        let (crossover_rate, mutation_rate, survival_rate) = (rate(&self.crossover_rate), rate(&self.mutation_rate), rate(&self.survival_rate));
+        Config {
+            population,
+            crossover_rate,
+            mutation_rate,
+            survival_rate,
+            crossover: *self.crossover.choose(rng).expect("a crossover to try"),
+            mutation: *self.mutation.choose(rng).expect("a mutation to try"),
+        }
+    }
+}
+
+impl Default for Space {
+    fn default() -> Space {
+        Space {
+            population: vec![100, 200, 500],
+            crossover_rate: vec![0.2, 0.4, 0.6],
+            mutation_rate: vec![0.01, 0.05, 0.1, 0.2],
+            survival_rate: vec![0.1, 0.3, 0.5],
+            crossover: vec![Crossover::OnePoint, Crossover::Ordered],
+            mutation: vec![Kind::Swap, Kind::Inversion, Kind::Scramble, Kind::Insertion],
+        }
+    }
+}
+
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values.fold((f64::INFINITY, f64::NEG_INFINITY), |(least, greatest), value| (least.min(value), greatest.max(value)))
+}
+
+/// The parameters of a run that a search varies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub population: usize,
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+    pub survival_rate: f64,
+    pub crossover: Crossover,
+    pub mutation: Kind,
+}
+
+impl fmt::Display for Config {
+    /// As the keys of a config file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "population = {}", self.population)?;
+        writeln!(f, "crossover_rate = {}", self.crossover_rate)?;
+        writeln!(f, "mutation_rate = {}", self.mutation_rate)?;
+        writeln!(f, "survival_rate = {}", self.survival_rate)?;
+        writeln!(f, "crossover = \"{}\"", self.crossover)?;
+        writeln!(f, "mutation = \"{}\"", self.mutation)
+    }
+}
+
+/// A configuration and the lengths of its runs, one per seed.
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub config: Config,
+    pub lengths: Vec<f64>,
+}
+
+impl Trial {
+    pub fn median(&self) -> f64 {
+        let mut lengths = self.lengths.clone();
+        lengths.sort_by(f64::total_cmp);
+        lengths[lengths.len() / 2]
+    }
+}
+
+/// Tries configurations from `space` as `search` says, `trials` of them at most, running each
+/// from every one of `seeds` with `solve`, which is given how long the run may take and returns
+/// the length of the best tour found. The search stops when `budget` has passed; a
+/// configuration whose runs were cut short by it isn't counted. Returns the configurations
+/// tried, the best first.
+pub fn tune<R: Rng + ?Sized>(
+    space: &Space,
+    search: Search,
+    trials: usize,
+    seeds: &[u64],
+    budget: Duration,
+    rng: &mut R,
+    mut solve: impl FnMut(&Config, u64, Duration) -> f64,
+) -> Vec<Trial> {
+    let start = Instant::now();
+    let mut grid = space.grid().into_iter();
+    let mut tried = Vec::new();
+
+    'trials: for _ in 0..trials {
+        let config = match search {
+            Search::Random => space.sample(rng),
+            Search::Grid => match grid.next() {
+                Some(config) => config,
+                None => break,
+            },
+        };
+
+        let mut lengths = Vec::with_capacity(seeds.len());
+        for &seed in seeds {
+            let left = match budget.checked_sub(start.elapsed()) {
+                Some(left) if !left.is_zero() => left,
+                _ => break 'trials,
+            };
+            lengths.push(solve(&config, seed, left));
+        }
+        if start.elapsed() >= budget {
+            break;
+        }
+        tried.push(Trial { config, lengths });
+    }
+
+    tried.sort_by(|a, b| a.median().total_cmp(&b.median()));
+    tried
+}
+
+/// `count` consecutive seeds, starting at `first`.
+pub fn seeds(first: u64, count: usize) -> Vec<u64> {
+    (0..count as u64).map(|i| first.wrapping_add(i)).collect()
+}
+
+/// The configurations a tuning run tried, the best first, and what it took to try them.
+#[derive(Clone, Debug)]
+pub struct Tuning {
+    pub search: Search,
+    pub seeds: Vec<u64>,
+    pub tried: Vec<Trial>,
+    pub elapsed: Duration,
+}
+
+/// Tunes the simulations `build` makes from a seed and a configuration, as [`tune`] does, each
+/// run solved until `time_limit` if there is one or the budget runs out. A configuration that
+/// can't run, or a run that fails, does worst of all.
+#[allow(clippy::too_many_arguments)]
+pub fn run<R: Rng + ?Sized>(
+    space: &Space,
+    search: Search,
+    trials: usize,
+    seeds: &[u64],
+    budget: Duration,
+    rng: &mut R,
+    time_limit: Option<Duration>,
+    build: impl Fn(u64, Config) -> Simulation,
+) -> Tuning {
+    let start = Instant::now();
+    let tried = tune(space, search, trials, seeds, budget, rng, |config, seed, left| {
+        if check_parameters(config.population, config.crossover_rate, config.mutation_rate, config.survival_rate).is_err() {
+            return f64::INFINITY;
+        }
+        let mut sim = build(seed, *config);
+        sim.set_time_limit(time_limit.map_or(left, |limit| limit.min(left)));
+        sim.solve(|_| ControlFlow::Continue(())).map_or(f64::INFINITY, |fittest| fittest.length())
+    });
+    Tuning { search, seeds: seeds.to_vec(), tried, elapsed: start.elapsed() }
+}
+
+impl Tuning {
+    /// The configuration with the shortest median, or `None` if none finished its runs.
+    pub fn best(&self) -> Option<&Trial> {
+        self.tried.first()
+    }
+
+    /// The best configuration as a configuration file for runs of `generations` generations,
+    /// headed by a comment saying where it came from.
+    pub fn config_file(&self, instance: impl fmt::Display, generations: usize) -> Option<String> {
+        let best = self.best()?;
+        Some(format!(
+            "# tuned on {} by a {} search: median length {:.3} over seeds {}\niterations = {}\n{}",
+            instance, self.search, best.median(), self.seeds.iter().map(u64::to_string).collect::<Vec<_>>().join(", "), generations, best.config,
+        ))
+    }
+}
+
+impl fmt::Display for Tuning {
+    /// How many configurations were tried and the best five of them.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tried {} configurations in {:.1}s", self.tried.len(), self.elapsed.as_secs_f64())?;
+        for trial in self.tried.iter().take(5) {
+            let config = trial.config;
+            write!(
+                f,
+                "\n  median {:.3}: population {}, crossover rate {:.3}, mutation rate {:.3}, survival rate {:.3}, {} crossover, {} mutation",
+                trial.median(), config.population, config.crossover_rate, config.mutation_rate, config.survival_rate, config.crossover, config.mutation,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{RngAlgorithm, SeededRng};
+    use crate::testing::twelve_cities;
+
+    #[test]
+    fn tuning_runs_every_configuration_from_every_seed_and_ranks_them() {
+        let space = Space {
+            population: vec![10, 20],
+            crossover_rate: vec![0.4],
+            mutation_rate: vec![0.1, 0.3],
+            survival_rate: vec![0.3],
+            crossover: vec![Crossover::OnePoint],
+            mutation: vec![Kind::Swap, Kind::Inversion],
+        };
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let mut runs = 0;
+        let tried = tune(&space, Search::Grid, usize::MAX, &[1, 2, 3], Duration::from_secs(60), &mut rng, |config, seed, _| {
+            runs += 1;
+            config.population as f64 * config.mutation_rate + seed as f64
+        });
+        assert_eq!((runs, tried.len()), (24, 8));
+        assert_eq!((tried[0].config.population, tried[0].config.mutation_rate, tried[0].median()), (10, 0.1, 3.0));
+        assert!(tried.windows(2).all(|pair| pair[0].median() <= pair[1].median()));
+
+        for _ in 0..100 {
+            let config = space.sample(&mut rng);
+            assert!((10..=20).contains(&config.population) && (0.1..0.3).contains(&config.mutation_rate));
+            assert_eq!(config.crossover_rate, 0.4);
+        }
+        assert_eq!(tune(&space, Search::Random, 5, &[1], Duration::from_secs(60), &mut rng, |_, _, _| 1.0).len(), 5);
+    }
+
+    #[test]
+    fn a_tuning_run_solves_simulations_and_writes_the_best_as_a_config_file() {
+        let space = Space {
+            population: vec![0, 20],
+            crossover_rate: vec![0.4],
+            mutation_rate: vec![0.05],
+            survival_rate: vec![0.3],
+            crossover: vec![Crossover::Ordered],
+            mutation: vec![Kind::Swap],
+        };
+        let mut rng = SeededRng::new(RngAlgorithm::ChaCha, 42);
+        let tuning = run(&space, Search::Grid, usize::MAX, &[1, 2], Duration::from_secs(60), &mut rng, None, |seed, config| {
+            let rng = SeededRng::new(RngAlgorithm::ChaCha, seed);
+            Simulation::new(config.population, twelve_cities(), 10, config.crossover_rate, config.mutation_rate, config.survival_rate, rng).unwrap()
+        });
+        // The empty population can't run, so it's tried but comes last
+        assert_eq!(tuning.tried.len(), 2);
+        assert_eq!(tuning.best().unwrap().config.population, 20);
+        assert!(tuning.best().unwrap().median().is_finite() && tuning.tried[1].median().is_infinite());
+
+        let file = tuning.config_file("twelve", 10).unwrap();
+        assert!(file.starts_with("# tuned on twelve by a grid search: median length "));
+        assert!(file.contains(" over seeds 1, 2\niterations = 10\npopulation = 20\n"));
+        assert!(tuning.to_string().starts_with("tried 2 configurations in "));
+    }
+
+    #[test]
+    fn a_space_takes_the_default_values_of_parameters_given_none_and_checks_them() {
+        let space = Space::given(&[10, 20], &[], &[0.1], &[], &[Crossover::Ordered], &[]);
+        assert_eq!((space.population, space.mutation_rate, space.crossover), (vec![10, 20], vec![0.1], vec![Crossover::Ordered]));
+        assert_eq!(space.crossover_rate, Space::default().crossover_rate);
+        assert_eq!(space.mutation.len(), 4);
+        assert_eq!(Search::Grid.trials(&Space::given(&[10, 20], &[0.4], &[0.1], &[0.3], &[Crossover::Ordered], &[])), 8);
+        assert_eq!(Search::Random.trials(&Space::default()), 20);
+        assert!(Space::default().check().is_ok());
+
+        assert!(matches!(Space::given(&[0], &[], &[], &[], &[], &[]).check(), Err(Error::Parameter { name: "population sizes to tune", .. })));
+        assert_eq!(
+            Space::given(&[], &[], &[], &[0.5, 1.5], &[], &[]).check(),
+            Err(Error::Parameter { name: "survival rates to tune", reason: "1.5 is not between 0 and 1".to_string() })
+        );
+        assert_eq!(seeds(u64::MAX, 3), vec![u64::MAX, 0, 1]);
+    }
+}
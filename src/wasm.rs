@@ -0,0 +1,130 @@
+//! The solver for JavaScript, built with the `wasm` feature for wasm-bindgen. A browser page
+//! creates a `Solver`, calls `step` from its animation loop and draws `best` each frame:
+//!
+//! ```js
+//! const solver = new Solver(JSON.stringify(cities), JSON.stringify({ population_size: 200, seed: 7 }));
+//! function frame() {
+//!     solver.step(10);
+//!     draw(JSON.parse(solver.best()));
+//!     requestAnimationFrame(frame);
+//! }
+//! ```
+
+use serde::Deserialize;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use crate::geo::Metric;
+use crate::rng::{random_seed, RngAlgorithm, SeededRng};
+use crate::tour::TourType;
+use crate::{City, Simulation};
+
+/// The GA parameters a solver takes when it isn't given them: the population size and the
+/// crossover, mutation and survival rates.
+const DEFAULTS: (usize, f64, f64, f64) = (200, 0.4, 0.05, 0.3);
+
+/// The parameters `Solver::new` reads, all optional.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Params {
+    population_size: Option<usize>,
+    crossover_rate: Option<f64>,
+    mutation_rate: Option<f64>,
+    survival_rate: Option<f64>,
+    metric: Option<Metric>,
+    tour_type: Option<TourType>,
+    seed: Option<u64>,
+}
+
+/// A run that goes on for as many generations at a time as it's asked to.
+#[wasm_bindgen]
+pub struct Solver {
+    sim: Simulation,
+}
+
+#[wasm_bindgen]
+impl Solver {
+    /// Cities as a JSON array of `{"x": ..., "y": ...}` (or `lon` and `lat`), and the
+    /// parameters as a JSON object with any of `population_size`, `crossover_rate`,
+    /// `mutation_rate`, `survival_rate`, `metric`, `tour_type` and `seed`; an empty string
+    /// takes the defaults.
+    #[wasm_bindgen(constructor)]
+    pub fn new(cities_json: &str, params: &str) -> Result<Solver, JsError> {
+        let cities: Vec<City> = serde_json::from_str(cities_json).map_err(|e| JsError::new(&format!("invalid cities: {}", e)))?;
+        let params: Params = match params.trim() {
+            "" => Params::default(),
+            params => serde_json::from_str(params).map_err(|e| JsError::new(&format!("invalid parameters: {}", e)))?,
+        };
+
+        let (population_size, crossover_rate, mutation_rate, survival_rate) = DEFAULTS;
+        let mut sim = Simulation::new(
+            params.population_size.unwrap_or(population_size),
+            cities,
+            // Steps run the generations, not solve
+            0,
+            params.crossover_rate.unwrap_or(crossover_rate),
+            params.mutation_rate.unwrap_or(mutation_rate),
+            params.survival_rate.unwrap_or(survival_rate),
+            SeededRng::new(RngAlgorithm::ChaCha, params.seed.unwrap_or_else(random_seed)),
+        )
+        .map_err(|e| JsError::new(&e.to_string()))?;
+        if let Some(metric) = params.metric {
+            sim.set_metric(metric);
+        }
+        sim.set_tour_type(params.tour_type.unwrap_or_default());
+
+        Ok(Solver { sim })
+    }
+
+    /// Evolves the population for `generations` more generations, and returns the best tour's
+    /// length so far. Throws if the tours can't be scored.
+    pub fn step(&mut self, generations: usize) -> Result<f64, JsError> {
+        for _ in 0..generations {
+            self.sim.step().map_err(|e| JsError::new(&e.to_string()))?;
+        }
+
+        Ok(self.sim.best().map_or(f64::INFINITY, |best| best.length()))
+    }
+
+    /// The best tour so far as JSON: `{"order": [...], "length": ..., "generation": ...}`, with
+    /// the cities' indices in the order visited, or null before the first step.
+    pub fn best(&self) -> String {
+        match self.sim.best() {
+            Some(best) => json!({ "order": best.order, "length": best.length(), "generation": self.sim.generation() }),
+            None => json!(null),
+        }
+        .to_string()
+    }
+
+    /// How many generations have been run.
+    pub fn generation(&self) -> usize {
+        self.sim.generation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Path;
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn the_wasm_solver_goes_on_a_step_at_a_time() {
+        let cities: Vec<serde_json::Value> = (0..12).map(|i| json!({ "x": i * 7 % 5, "y": i * 3 % 11 })).collect();
+        let Ok(mut solver) = Solver::new(&json!(cities).to_string(), r#"{"population_size": 30, "seed": 42}"#) else {
+            panic!("the cities and parameters are valid");
+        };
+        assert_eq!(solver.best(), "null");
+
+        let (Ok(first), Ok(second)) = (solver.step(10), solver.step(30)) else {
+            panic!("tours scored by their length can't fail");
+        };
+        assert!(second <= first);
+        assert_eq!(solver.generation(), 40);
+
+        let best: serde_json::Value = serde_json::from_str(&solver.best()).unwrap();
+        let order: Vec<usize> = serde_json::from_value(best["order"].clone()).unwrap();
+        assert!(Path::is_tour(&order, 12));
+        assert_eq!((best["length"].as_f64(), best["generation"].as_u64()), (Some(second), Some(40)));
+    }
+}
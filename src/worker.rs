@@ -0,0 +1,396 @@
+//! Worker mode: consumes solve jobs from a NATS subject and publishes progress and results to
+//! each job's reply subject.
+//!
+//! Workers subscribe as part of a queue group, so any number of them can share one subject and
+//! NATS hands every job to exactly one of them. On Ctrl-C/SIGTERM a worker stops taking jobs,
+//! finishes and replies to the one it is working on, then exits.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::ops::ControlFlow;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::events::Notice;
+use crate::rng::{random_seed, RngAlgorithm, SeededRng};
+use crate::route;
+use crate::summary::Summary;
+use crate::geo::Metric;
+use crate::metrics::METRICS;
+use crate::tour::TourType;
+use crate::{City, Simulation};
+
+const QUEUE_GROUP: &str = "wasi-genetic";
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// NATS servers can't be configured to pass on anything larger, so a bigger size is a broken
+// stream rather than a job
+const MAX_PAYLOAD: usize = 64 << 20;
+
+/// Cities and GA parameters used for jobs that don't specify their own.
+pub struct Defaults {
+    pub cities: Vec<City>,
+    pub metric: Metric,
+    pub tour_type: TourType,
+    pub iterations: usize,
+    pub population_size: usize,
+    pub crossover_rate: f64,
+    pub mutation_rate: f64,
+    pub survival_rate: f64,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    cities: Option<Vec<City>>,
+    metric: Option<Metric>,
+    tour_type: Option<TourType>,
+    iterations: Option<usize>,
+    population_size: Option<usize>,
+    crossover_rate: Option<f64>,
+    mutation_rate: Option<f64>,
+    survival_rate: Option<f64>,
+    seed: Option<u64>,
+}
+
+struct Message {
+    reply_to: Option<String>,
+    payload: Vec<u8>,
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn connect(url: &str) -> std::io::Result<Connection> {
+        if let Some((scheme, _)) = url.split_once("://").filter(|(scheme, _)| *scheme != "nats") {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("workers only speak NATS, not {}", scheme),
+            ));
+        }
+        let addr = url.trim_start_matches("nats://").trim_end_matches('/');
+        let writer = TcpStream::connect(addr)?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+
+        // The server greets with an INFO line before accepting commands
+        let mut info = String::new();
+        reader.read_line(&mut info)?;
+        if !info.starts_with("INFO") {
+            return Err(protocol_error(format!("unexpected greeting {:?}", info.trim_end())));
+        }
+
+        let mut connection = Connection { reader, writer };
+        connection.send(b"CONNECT {\"verbose\":false,\"pedantic\":false,\"name\":\"wasi-genetic\"}\r\n")?;
+
+        Ok(connection)
+    }
+
+    fn subscribe(&mut self, subject: &str) -> std::io::Result<()> {
+        self.send(format!("SUB {} {} 1\r\n", subject, QUEUE_GROUP).as_bytes())
+    }
+
+    fn publish(&mut self, subject: &str, payload: &str) -> std::io::Result<()> {
+        self.send(format!("PUB {} {}\r\n{}\r\n", subject, payload.len(), payload).as_bytes())
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()
+    }
+
+    /// Blocks until the next message arrives, answering keep-alives along the way. Returns
+    /// `None` once the connection is closed.
+    fn next_message(&mut self) -> std::io::Result<Option<Message>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.first().copied() {
+                Some("PING") => self.send(b"PONG\r\n")?,
+                Some("-ERR") => return Err(protocol_error(line.trim_end().to_string())),
+                Some("MSG") => {
+                    // MSG <subject> <sid> [reply-to] <#bytes>
+                    let (reply_to, size) = match fields.len() {
+                        4 => (None, fields[3]),
+                        5 => (Some(fields[3].to_string()), fields[4]),
+                        _ => return Err(protocol_error(format!("malformed {:?}", line.trim_end()))),
+                    };
+                    let size: usize = size.parse()
+                        .map_err(|_| protocol_error(format!("bad payload size {:?}", size)))?;
+                    if size > MAX_PAYLOAD {
+                        return Err(protocol_error(format!("payload of {} bytes is over the {} byte limit", size, MAX_PAYLOAD)));
+                    }
+
+                    let mut payload = vec![0; size + 2];
+                    self.reader.read_exact(&mut payload)?;
+                    payload.truncate(size);
+
+                    return Ok(Some(Message { reply_to, payload }));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn protocol_error(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Connects to the NATS server at `url` and solves jobs published on `subject` until shut down,
+/// telling `on_notice` when it's waiting for jobs, shutting down or passing over a job.
+pub fn run(url: &str, subject: &str, defaults: Defaults, on_notice: impl Fn(&Notice) + Send + Sync + 'static) -> std::io::Result<()> {
+    let on_notice = Arc::new(on_notice);
+    let mut connection = Connection::connect(url)?;
+    connection.subscribe(subject)?;
+
+    let stopping = Arc::new(AtomicBool::new(false));
+    {
+        let stopping = stopping.clone();
+        let stream = connection.writer.try_clone()?;
+        let on_notice = on_notice.clone();
+        ctrlc::set_handler(move || {
+            on_notice(&Notice::Info("shutting down after the current job".to_string()));
+            stopping.store(true, Ordering::SeqCst);
+            // Unblocks the wait for the next job; replies can still be written
+            let _ = stream.shutdown(Shutdown::Read);
+        }).map_err(|e| std::io::Error::other(e.to_string()))?;
+    }
+
+    on_notice(&Notice::Info(format!("waiting for jobs on {}", subject)));
+    serve(&mut connection, &defaults, &stopping, &*on_notice)
+}
+
+/// Solves the jobs arriving on `connection` until it closes or `stopping` is set.
+fn serve(connection: &mut Connection, defaults: &Defaults, stopping: &AtomicBool, on_notice: &dyn Fn(&Notice)) -> std::io::Result<()> {
+    while let Some(message) = connection.next_message()? {
+        if stopping.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let reply_to = match message.reply_to {
+            Some(reply_to) => reply_to,
+            None => {
+                on_notice(&Notice::Warning("ignoring job without a reply subject".to_string()));
+                continue;
+            }
+        };
+
+        METRICS.record_job_taken();
+        let reply = match serde_json::from_slice::<Job>(&message.payload) {
+            Ok(Job { cities: Some(cities), .. }) if cities.is_empty() => {
+                json!({ "type": "error", "message": "the job has no cities to visit" })
+            }
+            Ok(job) => solve(connection, &reply_to, job, defaults)?,
+            Err(e) => json!({ "type": "error", "message": format!("invalid job: {}", e) }),
+        };
+        let published = connection.publish(&reply_to, &reply.to_string());
+        METRICS.record_job_done();
+        published?;
+    }
+
+    Ok(())
+}
+
+fn solve(
+    connection: &mut Connection,
+    reply_to: &str,
+    job: Job,
+    defaults: &Defaults,
+) -> std::io::Result<serde_json::Value> {
+    let seed = job.seed.unwrap_or_else(random_seed);
+    let mut last_progress = Instant::now();
+    let mut progress_error = None;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| -> Result<_, crate::Error> {
+        let mut sim = Simulation::new(
+            job.population_size.unwrap_or(defaults.population_size),
+            job.cities.unwrap_or_else(|| defaults.cities.clone()),
+            job.iterations.unwrap_or(defaults.iterations),
+            job.crossover_rate.unwrap_or(defaults.crossover_rate),
+            job.mutation_rate.unwrap_or(defaults.mutation_rate),
+            job.survival_rate.unwrap_or(defaults.survival_rate),
+            SeededRng::new(RngAlgorithm::ChaCha, seed),
+        )?;
+        let metric = job.metric.unwrap_or(defaults.metric);
+        sim.set_metric(metric);
+        let tour_type = job.tour_type.unwrap_or(defaults.tour_type);
+        sim.set_tour_type(tour_type);
+
+        let fittest = sim.solve(|report| {
+            for event in &report.events {
+                if progress_error.is_some() {
+                    break;
+                }
+                let event = json!({ "type": "event", "generation": report.generation, "event": event });
+                if let Err(e) = connection.publish(reply_to, &event.to_string()) {
+                    progress_error = Some(e);
+                }
+            }
+
+            if progress_error.is_some() || last_progress.elapsed() < PROGRESS_INTERVAL {
+                return ControlFlow::Continue(());
+            }
+            last_progress = Instant::now();
+
+            let progress = json!({
+                "type": "progress",
+                "generation": report.generation,
+                "best_length": 1.0 / report.fittest.fitness,
+                "diversity": report.diversity(),
+                "estimate": report.estimate,
+            });
+            if let Err(e) = connection.publish(reply_to, &progress.to_string()) {
+                progress_error = Some(e);
+            }
+            ControlFlow::Continue(())
+        })?;
+        let legs = route::legs(&fittest.order, &sim.city_list, &sim.city_list, metric, tour_type);
+        Ok((fittest, legs, sim.summary().map(Summary::to_json)))
+    }));
+
+    if let Some(e) = progress_error {
+        return Err(e);
+    }
+
+    // A bad job (e.g. too few cities) mustn't take the worker down with it
+    Ok(match result {
+        Ok(Ok((fittest, legs, summary))) => json!({
+            "type": "result",
+            "order": fittest.order,
+            "length": 1.0 / fittest.fitness,
+            "legs": legs,
+            "seed": seed,
+            "summary": summary,
+        }),
+        Ok(Err(e)) => json!({ "type": "error", "message": e.to_string() }),
+        Err(_) => json!({ "type": "error", "message": "solver panicked on this job" }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+    use crate::testing::twelve_cities;
+
+    /// Accepts one connection on a local port, greets it as a NATS server would and hands it to
+    /// `server`, returning the address to connect to and what `server` returns.
+    fn fake_server<T: Send + 'static>(
+        server: impl FnOnce(BufReader<TcpStream>, TcpStream) -> T + Send + 'static,
+    ) -> (String, thread::JoinHandle<T>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"INFO {\"max_payload\":1048576}\r\n").unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut connect = String::new();
+            reader.read_line(&mut connect).unwrap();
+            assert!(connect.starts_with("CONNECT {"), "{:?}", connect);
+            server(reader, stream)
+        });
+        (format!("nats://{}", addr), handle)
+    }
+
+    fn read_line(reader: &mut BufReader<TcpStream>) -> String {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line
+    }
+
+    fn defaults() -> Defaults {
+        Defaults {
+            cities: twelve_cities(),
+            metric: Metric::Euclidean,
+            tour_type: TourType::Closed,
+            iterations: 5,
+            population_size: 20,
+            crossover_rate: 0.5,
+            mutation_rate: 0.1,
+            survival_rate: 0.5,
+        }
+    }
+
+    #[test]
+    fn messages_are_read_and_pings_answered() {
+        let (url, server) = fake_server(|mut reader, mut stream| {
+            stream.write_all(b"PING\r\nMSG jobs 1 inbox.1 5\r\nhello\r\nMSG jobs 1 3\r\nabc\r\n").unwrap();
+            let pong = read_line(&mut reader);
+            stream.shutdown(Shutdown::Write).unwrap();
+            pong
+        });
+
+        let mut connection = Connection::connect(&url).unwrap();
+        let message = connection.next_message().unwrap().expect("a message");
+        assert_eq!((message.reply_to.as_deref(), &message.payload[..]), (Some("inbox.1"), &b"hello"[..]));
+        let message = connection.next_message().unwrap().expect("a message");
+        assert_eq!((message.reply_to, &message.payload[..]), (None, &b"abc"[..]));
+        assert!(connection.next_message().unwrap().is_none());
+        assert_eq!(server.join().unwrap(), "PONG\r\n");
+    }
+
+    #[test]
+    fn errors_oversized_payloads_and_other_schemes_are_refused() {
+        let (url, server) = fake_server(|_, mut stream| {
+            stream.write_all(format!("MSG jobs 1 inbox.1 {}\r\n-ERR 'Authorization Violation'\r\n", MAX_PAYLOAD + 1).as_bytes()).unwrap();
+        });
+        let mut connection = Connection::connect(&url).unwrap();
+        let error = connection.next_message().err().expect("an oversized payload");
+        assert!(error.to_string().contains("over the"), "{}", error);
+        let error = connection.next_message().err().expect("an error from the server");
+        assert_eq!(error.to_string(), "-ERR 'Authorization Violation'");
+        server.join().unwrap();
+
+        let error = Connection::connect("amqp://localhost:5672").err().expect("not a NATS url");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn jobs_are_solved_and_replied_to() {
+        let (url, server) = fake_server(|mut reader, mut stream| {
+            let subscribe = read_line(&mut reader);
+            let jobs = ["{\"seed\": 7, \"iterations\": 3}", "{\"cities\": []}", "not json"];
+            for (i, job) in jobs.iter().enumerate() {
+                stream.write_all(format!("MSG jobs 1 inbox.{} {}\r\n{}\r\n", i, job.len(), job).as_bytes()).unwrap();
+            }
+
+            let mut replies = Vec::new();
+            while replies.len() < jobs.len() {
+                let publish = read_line(&mut reader);
+                let payload: serde_json::Value = serde_json::from_str(&read_line(&mut reader)).unwrap();
+                if payload["type"] != "event" && payload["type"] != "progress" {
+                    replies.push((publish.split_whitespace().nth(1).unwrap().to_string(), payload));
+                }
+            }
+            stream.shutdown(Shutdown::Write).unwrap();
+            (subscribe, replies)
+        });
+
+        let mut connection = Connection::connect(&url).unwrap();
+        connection.subscribe("jobs").unwrap();
+        serve(&mut connection, &defaults(), &AtomicBool::new(false), &|_| {}).unwrap();
+
+        let (subscribe, replies) = server.join().unwrap();
+        assert_eq!(subscribe, "SUB jobs wasi-genetic 1\r\n");
+
+        let (subject, result) = &replies[0];
+        assert_eq!((subject.as_str(), &result["type"], &result["seed"]), ("inbox.0", &json!("result"), &json!(7)));
+        let mut order: Vec<u64> = result["order"].as_array().unwrap().iter().map(|o| o.as_u64().unwrap()).collect();
+        order.sort_unstable();
+        assert_eq!(order, (0..12).collect::<Vec<_>>());
+        assert_eq!(result["summary"]["generations"], 3);
+
+        assert_eq!((replies[1].0.as_str(), &replies[1].1["message"]), ("inbox.1", &json!("the job has no cities to visit")));
+        assert_eq!((replies[2].0.as_str(), &replies[2].1["type"]), ("inbox.2", &json!("error")));
+    }
+}